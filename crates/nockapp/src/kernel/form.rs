@@ -264,7 +264,7 @@ impl<C> SerfThread<C> {
         }
     }
 
-    pub(crate) fn poke_sync(&self, wire: WireRepr, cause: NounSlab) -> Result<NounSlab> {
+    pub fn poke_sync(&self, wire: WireRepr, cause: NounSlab) -> Result<NounSlab> {
         let (result, result_fut) = oneshot::channel();
         let (result_ack_sender, result_ack) = oneshot::channel();
         self.action_sender.blocking_send(SerfAction::Poke {
@@ -1,9 +1,13 @@
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
 
 use tempfile::TempDir;
+use tokio::sync::{broadcast, mpsc, Mutex};
 
-use super::NockApp;
+use super::driver::{ActionReceiver, NockAppHandle};
+use super::metrics::NockAppMetrics;
+use super::{NockApp, NockAppExit, NockAppExitStatus};
 use crate::kernel::form::Kernel;
 
 pub async fn setup_nockapp(jam: &str) -> (TempDir, NockApp) {
@@ -32,6 +36,32 @@ pub async fn setup_nockapp(jam: &str) -> (TempDir, NockApp) {
     )
 }
 
+/// A [`NockAppHandle`] with no NockApp behind it, for exercising an IO driver without a
+/// kernel. Every poke and peek the driver makes arrives on the returned [`ActionReceiver`]
+/// for the test to answer, and its exit requests on the other receiver. Effects can be
+/// fed to the driver through the handle's `effect_sender` before handing it over.
+pub fn detached_handle() -> (
+    NockAppHandle,
+    ActionReceiver,
+    mpsc::Receiver<NockAppExitStatus>,
+) {
+    let (io_sender, actions) = mpsc::channel(32);
+    let (effect_sender, effect_receiver) = broadcast::channel(32);
+    let metrics = Arc::new(
+        NockAppMetrics::register(gnort::global_metrics_registry())
+            .expect("Failed to register metrics"),
+    );
+    let (exit, exits) = NockAppExit::new();
+    let handle = NockAppHandle {
+        io_sender,
+        effect_sender: Arc::new(effect_sender),
+        effect_receiver: Mutex::new(effect_receiver),
+        metrics,
+        exit,
+    };
+    (handle, actions, exits)
+}
+
 #[cfg(test)]
 pub mod tests {
     use std::sync::atomic::Ordering;
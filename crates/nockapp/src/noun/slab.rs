@@ -395,6 +395,26 @@ impl<J> NounSlab<J> {
         self.root = root;
     }
 
+    /// Discard every noun in the slab and reset the root to D(0), keeping the largest
+    /// backing allocation so the slab can be refilled without reallocating.
+    ///
+    /// # Safety: No noun previously allocated in this slab may be used after the reset.
+    pub unsafe fn reset(&mut self) {
+        let keep = self.slabs.iter().rposition(|slab| !slab.0.is_null());
+        for (idx, slab) in self.slabs.iter_mut().enumerate() {
+            if Some(idx) != keep && !slab.0.is_null() {
+                std::alloc::dealloc(slab.0, slab.1);
+                *slab = (std::ptr::null_mut(), Layout::new::<u8>());
+            }
+        }
+        if let Some(idx) = keep {
+            let (ptr, layout) = self.slabs[idx];
+            self.allocation_start = ptr as *mut u64;
+            self.allocation_stop = (ptr as *mut u64).add(layout.size() >> 3);
+        }
+        self.root = D(0);
+    }
+
     /// Get the root noun
     ///
     /// # Safety: The noun must not be used past the lifetime of the slab.
@@ -890,6 +910,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_reset_reuses_allocation() {
+        let mut slab: NounSlab = NounSlab::new();
+        let noun = T(&mut slab, &[D(1), D(2), D(3)]);
+        slab.set_root(noun);
+        let first_alloc = slab
+            .slabs
+            .iter()
+            .rev()
+            .find(|s| !s.0.is_null())
+            .map(|s| s.0);
+
+        unsafe { slab.reset() };
+        assert!(unsafe { slab.root().raw_equals(&D(0)) });
+
+        let noun = T(&mut slab, &[D(4), D(5)]);
+        slab.set_root(noun);
+        let second_alloc = slab
+            .slabs
+            .iter()
+            .rev()
+            .find(|s| !s.0.is_null())
+            .map(|s| s.0);
+        assert_eq!(
+            first_alloc, second_alloc,
+            "Reset slab should reuse its allocation"
+        );
+
+        let mut expected: NounSlab = NounSlab::new();
+        let expected_noun = T(&mut expected, &[D(4), D(5)]);
+        assert!(slab_noun_equality(unsafe { slab.root() }, &expected_noun));
+    }
+
     #[test]
     fn test_complex_noun() {
         let mut slab: NounSlab = NounSlab::new();
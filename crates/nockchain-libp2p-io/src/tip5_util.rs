@@ -1,3 +1,5 @@
+use std::fmt;
+
 use bs58;
 use ibig::{ubig, UBig};
 use nockapp::NockAppError;
@@ -24,6 +26,44 @@ pub fn tip5_hash_to_base58(noun: Noun) -> Result<String, NockAppError> {
     Ok(base58_string)
 }
 
+/// Why a string could not be decoded by [`base58_to_tip5_hash`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The string is not valid base58
+    Base58(bs58::decode::Error),
+    /// The string decodes to a number of at least P^5, so it is not five field elements
+    OutOfRange,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Base58(e) => write!(f, "invalid base58: {e}"),
+            ParseError::OutOfRange => write!(f, "value is too large for a tip5 hash"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The inverse of [`tip5_hash_to_base58`]: decodes a base58 string, e.g. a block id from
+/// the logs, into the five belts of the tip5 hash, least significant first.
+pub fn base58_to_tip5_hash(s: &str) -> Result<[u64; 5], ParseError> {
+    let bytes = bs58::decode(s).into_vec().map_err(ParseError::Base58)?;
+    let prime_ubig = UBig::from(P);
+    let mut value = UBig::from_be_bytes(&bytes);
+    let mut hash = [0u64; 5];
+    for belt in &mut hash {
+        let digit = &value % &prime_ubig;
+        value /= &prime_ubig;
+        *belt = u64::try_from(digit).expect("a digit base P fits in a u64");
+    }
+    if value != ubig!(0) {
+        return Err(ParseError::OutOfRange);
+    }
+    Ok(hash)
+}
+
 pub fn base_p_to_decimal(hash: Vec<Noun>) -> Result<UBig, NockAppError> {
     let prime_ubig = UBig::from(P);
     let mut result = ubig!(0);
@@ -117,4 +157,46 @@ mod tests {
         });
         assert_eq!(result2, expected2);
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // ibig has a memory leak so miri fails this test
+    fn test_base58_to_tip5_hash_round_trip() {
+        let hashes = [
+            [1, 2, 3, 4, 5],
+            [
+                0x6ef99e5f3447ffda, 0xdf94122d1a98ec99, 0xcbf1918337a0e197, 0x6cda1112891244ce,
+                0x6e420b8a615508d4,
+            ],
+            [0, 0, 0, 0, 0],
+            [P - 1, 0, P - 1, 0, P - 1],
+            [P - 1; 5],
+        ];
+        let mut slab: NounSlab = NounSlab::new();
+        for hash in hashes {
+            let belts: Vec<Noun> = hash
+                .iter()
+                .map(|&belt| nockvm::noun::Atom::new(&mut slab, belt).as_noun())
+                .collect();
+            let tuple = T(&mut slab, &belts);
+            let encoded = tip5_hash_to_base58(tuple).unwrap();
+            assert_eq!(base58_to_tip5_hash(&encoded), Ok(hash), "{encoded}");
+        }
+        assert_eq!(
+            base58_to_tip5_hash("2V9arU36gvtaofWmNowewoj9u7gbNA2qsJZEQ3WPky5mQ"),
+            Ok([1, 2, 3, 4, 5])
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // ibig has a memory leak so miri fails this test
+    fn test_base58_to_tip5_hash_rejects_bad_input() {
+        // 0, O, I and l are not in the base58 alphabet
+        assert!(matches!(
+            base58_to_tip5_hash("2V9arU36gvtaofWmNow0"),
+            Err(ParseError::Base58(_))
+        ));
+        // P^5 is one past the largest hash
+        let too_large = ubig_to_base58(UBig::from(P).pow(5));
+        assert_eq!(base58_to_tip5_hash(&too_large), Err(ParseError::OutOfRange));
+    }
 }
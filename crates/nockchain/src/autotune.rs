@@ -0,0 +1,346 @@
+//! Picking the mining thread count by measuring it.
+//!
+//! Whether SMT siblings add hash rate or just fight over the same core depends on the CPU,
+//! so rather than guessing, the optimized driver can sweep a few thread counts at startup
+//! and keep the fastest. The driver loads a kernel for the largest count up front and each
+//! trial runs the first `n` workers, so moving to the next count only starts more attempts.
+//! Once the sweep ends the driver retires the workers above the winner.
+//!
+//! Counts are tried in ascending order. A trial only counts the hashes completed after its
+//! first quarter, giving the workers it just started time to finish an attempt; when one
+//! attempt takes a large share of `trial` the measurement is coarse, so raise it for slow
+//! PoW.
+
+use std::time::{Duration, Instant};
+
+/// How to sweep thread counts; see the module docs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutotuneConfig {
+    /// Thread counts to try. Empty means [`default_thread_counts`] for this host.
+    pub thread_counts: Vec<u64>,
+    /// How long each count runs
+    pub trial: Duration,
+    /// Bound on the whole sweep. Counts whose trial would end past it are skipped, but the
+    /// first count is always measured.
+    pub max_duration: Duration,
+    /// Cores left to the node and the OS, taken off each default count
+    pub reserved_cores: u64,
+}
+
+impl Default for AutotuneConfig {
+    fn default() -> Self {
+        Self {
+            thread_counts: Vec::new(),
+            trial: Duration::from_secs(30),
+            max_duration: Duration::from_secs(180),
+            reserved_cores: 0,
+        }
+    }
+}
+
+impl AutotuneConfig {
+    /// The counts to sweep, ascending and without repeats
+    pub fn counts(&self) -> Vec<u64> {
+        let mut counts = if self.thread_counts.is_empty() {
+            default_thread_counts(num_cpus::get_physical() as u64, self.reserved_cores)
+        } else {
+            self.thread_counts.clone()
+        };
+        counts.retain(|&threads| threads > 0);
+        counts.sort_unstable();
+        counts.dedup();
+        counts
+    }
+}
+
+/// One thread per physical core, one and a half, and two (every sibling on a 2-way SMT
+/// CPU), each less `reserved` and at least one
+pub fn default_thread_counts(cores: u64, reserved: u64) -> Vec<u64> {
+    let mut counts: Vec<u64> = [cores, cores * 3 / 2, cores * 2]
+        .into_iter()
+        .map(|threads| threads.saturating_sub(reserved).max(1))
+        .collect();
+    counts.dedup();
+    counts
+}
+
+/// What the driver should do after [`Autotuner::poll`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TuneStep {
+    /// Keep running the current count and poll again at this time
+    Wait(Instant),
+    /// The last trial ran `previous` threads at `rate` hashes/sec; run `threads` from now on
+    Next {
+        previous: u64,
+        rate: f64,
+        threads: u64,
+    },
+    /// The sweep is over: keep `threads` workers, which ran at `rate` hashes/sec
+    Done { threads: u64, rate: f64 },
+}
+
+/// The sweep's progress, driven by [`Autotuner::poll`] with the driver's hash count
+#[derive(Debug)]
+pub struct Autotuner {
+    counts: Vec<u64>,
+    trial: Duration,
+    max_duration: Duration,
+    index: usize,
+    /// When the sweep and the current trial began; `None` until [`Autotuner::start`]
+    started: Option<(Instant, Instant)>,
+    /// Time and hash count when the current trial's warm-up ended
+    baseline: Option<(Instant, u64)>,
+    rates: Vec<(u64, f64)>,
+    chosen: Option<u64>,
+}
+
+impl Autotuner {
+    /// A sweep over `config`'s counts, or `None` if it has none to try
+    pub fn new(config: &AutotuneConfig) -> Option<Self> {
+        let counts = config.counts();
+        if counts.is_empty() {
+            return None;
+        }
+        Some(Self {
+            counts,
+            trial: config.trial,
+            max_duration: config.max_duration,
+            index: 0,
+            started: None,
+            baseline: None,
+            rates: Vec::new(),
+            chosen: None,
+        })
+    }
+
+    /// Begin the first trial, once its workers are running
+    pub fn start(&mut self, now: Instant) {
+        self.started = Some((now, now));
+    }
+
+    /// Workers to run now: the count under trial, or the chosen one
+    pub fn threads(&self) -> u64 {
+        self.chosen.unwrap_or(self.counts[self.index])
+    }
+
+    /// The counts the sweep tries, ascending
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// The largest count, which the driver must have workers for
+    pub fn max_threads(&self) -> u64 {
+        *self
+            .counts
+            .last()
+            .expect("an autotuner has at least one count")
+    }
+
+    /// Hash rate of each count measured so far
+    pub fn rates(&self) -> &[(u64, f64)] {
+        &self.rates
+    }
+
+    /// When to poll next; `None` before [`Autotuner::start`] and once the sweep is over
+    pub fn wake_at(&self) -> Option<Instant> {
+        let (_, trial_started) = self.started.filter(|_| self.chosen.is_none())?;
+        Some(match self.baseline {
+            None => trial_started + self.warm_up(),
+            Some(_) => trial_started + self.trial,
+        })
+    }
+
+    fn warm_up(&self) -> Duration {
+        self.trial / 4
+    }
+
+    /// Advance the sweep given the driver's total hash count at `now`
+    ///
+    /// # Panics
+    ///
+    /// Panics if called before [`Autotuner::start`] or after the sweep is over.
+    pub fn poll(&mut self, now: Instant, hashes: u64) -> TuneStep {
+        assert!(self.chosen.is_none(), "the sweep is over");
+        let (sweep_started, trial_started) = self.started.expect("the sweep has started");
+        let Some((measured_from, baseline)) = self.baseline else {
+            if now < trial_started + self.warm_up() {
+                return TuneStep::Wait(trial_started + self.warm_up());
+            }
+            self.baseline = Some((now, hashes));
+            return TuneStep::Wait(trial_started + self.trial);
+        };
+        if now < trial_started + self.trial {
+            return TuneStep::Wait(trial_started + self.trial);
+        }
+
+        let previous = self.threads();
+        let elapsed = now.duration_since(measured_from).as_secs_f64();
+        let rate = hashes.saturating_sub(baseline) as f64 / elapsed.max(f64::EPSILON);
+        self.rates.push((previous, rate));
+        let fits = now + self.trial <= sweep_started + self.max_duration;
+        if self.index + 1 < self.counts.len() && fits {
+            self.index += 1;
+            self.started = Some((sweep_started, now));
+            self.baseline = None;
+            return TuneStep::Next {
+                previous,
+                rate,
+                threads: self.threads(),
+            };
+        }
+
+        // Ties go to the smaller count, which leaves more of the machine idle
+        let (threads, rate) = self
+            .rates
+            .iter()
+            .copied()
+            .fold(
+                None,
+                |best: Option<(u64, f64)>, (threads, rate)| match best {
+                    Some((_, best_rate)) if best_rate >= rate => best,
+                    _ => Some((threads, rate)),
+                },
+            )
+            .expect("at least one trial finished");
+        self.chosen = Some(threads);
+        TuneStep::Done { threads, rate }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(counts: &[u64], trial_secs: u64, max_secs: u64) -> AutotuneConfig {
+        AutotuneConfig {
+            thread_counts: counts.to_vec(),
+            trial: Duration::from_secs(trial_secs),
+            max_duration: Duration::from_secs(max_secs),
+            reserved_cores: 0,
+        }
+    }
+
+    /// Run a sweep where `rate_of(threads)` hashes/sec are completed, returning its steps
+    fn sweep(tuner: &mut Autotuner, rate_of: impl Fn(u64) -> u64) -> Vec<TuneStep> {
+        let mut now = Instant::now();
+        let mut hashes = 0;
+        tuner.start(now);
+        let mut steps = Vec::new();
+        while let Some(wake) = tuner.wake_at() {
+            hashes += rate_of(tuner.threads()) * wake.duration_since(now).as_secs();
+            now = wake;
+            let step = tuner.poll(now, hashes);
+            if !matches!(step, TuneStep::Wait(_)) {
+                steps.push(step);
+            }
+        }
+        steps
+    }
+
+    #[test]
+    fn test_default_thread_counts() {
+        assert_eq!(default_thread_counts(64, 0), vec![64, 96, 128]);
+        assert_eq!(default_thread_counts(8, 2), vec![6, 10, 14]);
+        // A tiny box collapses to one count rather than trying zero threads
+        assert_eq!(default_thread_counts(1, 4), vec![1]);
+        assert_eq!(config(&[16, 0, 8, 16], 1, 1).counts(), vec![8, 16]);
+    }
+
+    #[test]
+    fn test_sweep_picks_fastest_count() {
+        // SMT helps up to 12 threads and hurts beyond
+        let rate_of = |threads| match threads {
+            8 => 80,
+            12 => 100,
+            _ => 90,
+        };
+        let mut tuner = Autotuner::new(&config(&[16, 8, 12], 20, 600)).unwrap();
+        assert_eq!(tuner.threads(), 8);
+        assert_eq!(tuner.max_threads(), 16);
+        let steps = sweep(&mut tuner, rate_of);
+        assert_eq!(steps.len(), 3);
+        assert!(matches!(
+            steps[0],
+            TuneStep::Next {
+                previous: 8,
+                threads: 12,
+                ..
+            }
+        ));
+        assert!(matches!(
+            steps[1],
+            TuneStep::Next {
+                previous: 12,
+                threads: 16,
+                ..
+            }
+        ));
+        assert_eq!(
+            steps[2],
+            TuneStep::Done {
+                threads: 12,
+                rate: 100.0
+            }
+        );
+        assert_eq!(tuner.threads(), 12);
+        assert_eq!(tuner.rates(), &[(8, 80.0), (12, 100.0), (16, 90.0)]);
+    }
+
+    #[test]
+    fn test_sweep_stays_within_its_time_bound() {
+        // Two 20 second trials fit in 50 seconds, a third does not
+        let mut tuner = Autotuner::new(&config(&[4, 8, 12], 20, 50)).unwrap();
+        let steps = sweep(&mut tuner, |threads| threads * 10);
+        assert_eq!(tuner.rates().len(), 2);
+        assert_eq!(
+            steps.last(),
+            Some(&TuneStep::Done {
+                threads: 8,
+                rate: 80.0
+            })
+        );
+
+        // The first count is measured even when the bound is shorter than a trial
+        let mut tuner = Autotuner::new(&config(&[4, 8], 20, 1)).unwrap();
+        let steps = sweep(&mut tuner, |threads| threads * 10);
+        assert_eq!(
+            steps,
+            vec![TuneStep::Done {
+                threads: 4,
+                rate: 40.0
+            }]
+        );
+    }
+
+    #[test]
+    fn test_sweep_ignores_warm_up_and_prefers_fewer_threads_on_ties() {
+        let mut tuner = Autotuner::new(&config(&[2, 4], 8, 60)).unwrap();
+        let start = Instant::now();
+        tuner.start(start);
+        assert_eq!(tuner.wake_at(), Some(start + Duration::from_secs(2)));
+        // Hashes finished during the warm-up don't count
+        assert_eq!(
+            tuner.poll(start + Duration::from_secs(2), 1000),
+            TuneStep::Wait(start + Duration::from_secs(8))
+        );
+        let step = tuner.poll(start + Duration::from_secs(8), 1060);
+        assert_eq!(
+            step,
+            TuneStep::Next {
+                previous: 2,
+                rate: 10.0,
+                threads: 4
+            }
+        );
+        tuner.poll(start + Duration::from_secs(10), 1080);
+        let step = tuner.poll(start + Duration::from_secs(16), 1140);
+        assert_eq!(
+            step,
+            TuneStep::Done {
+                threads: 2,
+                rate: 10.0
+            }
+        );
+        assert_eq!(tuner.wake_at(), None);
+    }
+}
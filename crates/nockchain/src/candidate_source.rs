@@ -0,0 +1,414 @@
+//! Where the mining drivers get candidates from.
+//!
+//! By default a driver mines whatever the node announces in %mine effects, read by
+//! [`EffectCandidateSource`]. Anything else that implements [`CandidateSource`] (a pool's
+//! stratum jobs, a test feeder, a replay of recorded candidates) can stand in for it, and
+//! the driver loop selects over the source and its in-flight attempts either way.
+//!
+//! When the node's effect stream fails, [`EffectCandidateSource`] backs off
+//! exponentially rather than spinning, and after [`EffectRetry::max_consecutive_errors`]
+//! failures in a row returns the error, so the driver exits and a supervisor can restart
+//! the process.
+//!
+//! [`record_candidates`] wraps a source and writes every candidate it yields, as a jammed
+//! %mine effect with its arrival time, to a file; [`ReplaySource::open`] feeds that file
+//! back with the same spacing between candidates, for reproducing a run offline.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::future::BoxFuture;
+use nockapp::nockapp::driver::NockAppHandle;
+use nockapp::noun::NounExt;
+use nockapp::NockAppError;
+use tokio::sync::mpsc;
+use tracing::{error, warn};
+
+use crate::hash_backend::{cue_candidate, jam_candidate, Candidate};
+
+/// First bytes of a candidate recording; the records follow, each a little-endian u64
+/// of milliseconds since recording started, a u64 length, and that many bytes of jam
+const RECORDING_MAGIC: &[u8; 8] = b"nkcand01";
+
+/// A stream of candidates for a mining driver
+pub trait CandidateSource: Send {
+    /// Wait for the next candidate; `None` once the source is exhausted, after which the
+    /// driver keeps mining the last candidate it got. An error is fatal: the driver stops
+    /// with it.
+    ///
+    /// The driver drops this future whenever an attempt finishes first, so it must not
+    /// lose a candidate it has already taken off its input when cancelled.
+    fn next(&mut self) -> BoxFuture<'_, Result<Option<Candidate>, NockAppError>>;
+}
+
+/// How [`EffectCandidateSource`] copes with errors reading the node's effects
+#[derive(Debug, Clone, Copy)]
+pub struct EffectRetry {
+    /// Errors in a row after which the source fails with the last one; `None` retries
+    /// forever
+    pub max_consecutive_errors: Option<u32>,
+    /// Delay after the first error in a row; doubled after every further one
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for EffectRetry {
+    fn default() -> Self {
+        Self {
+            max_consecutive_errors: Some(20),
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl EffectRetry {
+    /// Delay after the `errors`th error in a row
+    fn backoff(&self, errors: u32) -> Duration {
+        let doublings = errors.saturating_sub(1).min(31);
+        self.initial_backoff
+            .saturating_mul(1 << doublings)
+            .min(self.max_backoff)
+    }
+}
+
+/// Candidates from the node's %mine effects
+pub struct EffectCandidateSource<'a> {
+    handle: &'a NockAppHandle,
+    retry: EffectRetry,
+    /// Every error reading an effect, for the driver's stats
+    errors: Arc<AtomicU64>,
+    /// Errors since the last effect read, kept across calls since the driver may drop
+    /// `next` mid-backoff
+    consecutive_errors: u32,
+}
+
+impl<'a> EffectCandidateSource<'a> {
+    pub fn new(handle: &'a NockAppHandle) -> Self {
+        Self {
+            handle,
+            retry: EffectRetry::default(),
+            errors: Arc::default(),
+            consecutive_errors: 0,
+        }
+    }
+
+    pub fn with_retry(mut self, retry: EffectRetry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Count errors reading effects in `errors` as well
+    pub fn with_error_counter(mut self, errors: Arc<AtomicU64>) -> Self {
+        self.errors = errors;
+        self
+    }
+}
+
+impl CandidateSource for EffectCandidateSource<'_> {
+    fn next(&mut self) -> BoxFuture<'_, Result<Option<Candidate>, NockAppError>> {
+        Box::pin(async move {
+            loop {
+                let effect = match self.handle.next_effect().await {
+                    Ok(effect) => effect,
+                    Err(NockAppError::BroadcastRecvClosedError) => return Ok(None),
+                    Err(e) => {
+                        self.errors.fetch_add(1, Ordering::Relaxed);
+                        self.consecutive_errors += 1;
+                        let errors = self.consecutive_errors;
+                        if self
+                            .retry
+                            .max_consecutive_errors
+                            .is_some_and(|max| errors >= max)
+                        {
+                            error!("Giving up on the node's effects after {errors} errors in a row: {e:?}");
+                            return Err(e);
+                        }
+                        let backoff = self.retry.backoff(errors);
+                        warn!("Error receiving effect in mining driver ({errors} in a row), retrying in {backoff:?}: {e:?}");
+                        tokio::time::sleep(backoff).await;
+                        continue;
+                    }
+                };
+                self.consecutive_errors = 0;
+                let Ok(effect_cell) = (unsafe { effect.root().as_cell() }) else {
+                    continue;
+                };
+                if !effect_cell.head().eq_bytes("mine") {
+                    continue;
+                }
+                match Candidate::from_mine_effect(effect_cell.tail()) {
+                    Ok(candidate) => {
+                        crate::pow_target::check_pow_version(candidate.version());
+                        return Ok(Some(candidate));
+                    }
+                    Err(e) => warn!("Ignoring %mine effect: {e}"),
+                }
+            }
+        })
+    }
+}
+
+/// Recorded candidates, each handed out once its offset from the first call to `next`
+/// has passed
+pub struct ReplaySource {
+    candidates: VecDeque<(Duration, Candidate)>,
+    started: Option<tokio::time::Instant>,
+}
+
+impl ReplaySource {
+    /// Replay `candidates` in order, as fast as the driver asks for them
+    pub fn new(candidates: impl IntoIterator<Item = Candidate>) -> Self {
+        Self::timed(
+            candidates
+                .into_iter()
+                .map(|candidate| (Duration::ZERO, candidate)),
+        )
+    }
+
+    /// Replay `candidates`, each at its offset from the start of the replay
+    pub fn timed(candidates: impl IntoIterator<Item = (Duration, Candidate)>) -> Self {
+        Self {
+            candidates: candidates.into_iter().collect(),
+            started: None,
+        }
+    }
+
+    /// Load a file written by [`record_candidates`], keeping its timing
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self::timed(read_recording(&std::fs::read(path)?)?))
+    }
+
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+}
+
+impl CandidateSource for ReplaySource {
+    fn next(&mut self) -> BoxFuture<'_, Result<Option<Candidate>, NockAppError>> {
+        Box::pin(async move {
+            let started = *self.started.get_or_insert_with(tokio::time::Instant::now);
+            let Some((at, _)) = self.candidates.front() else {
+                return Ok(None);
+            };
+            tokio::time::sleep_until(started + *at).await;
+            Ok(self.candidates.pop_front().map(|(_, candidate)| candidate))
+        })
+    }
+}
+
+/// A source whose candidates are also appended to a recording as they pass through
+pub struct RecordingSource<'a> {
+    inner: Box<dyn CandidateSource + 'a>,
+    file: BufWriter<File>,
+    started: Instant,
+}
+
+/// Record every candidate `source` yields to a new file at `path`, for [`ReplaySource::open`]
+pub fn record_candidates<'a>(
+    source: Box<dyn CandidateSource + 'a>,
+    path: &Path,
+) -> io::Result<RecordingSource<'a>> {
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(RECORDING_MAGIC)?;
+    file.flush()?;
+    Ok(RecordingSource {
+        inner: source,
+        file,
+        started: Instant::now(),
+    })
+}
+
+impl RecordingSource<'_> {
+    fn write(&mut self, candidate: &Candidate) -> io::Result<()> {
+        let jammed = jam_candidate(candidate);
+        let millis = self.started.elapsed().as_millis() as u64;
+        self.file.write_all(&millis.to_le_bytes())?;
+        self.file.write_all(&(jammed.len() as u64).to_le_bytes())?;
+        self.file.write_all(&jammed)?;
+        // Flush each record so a crash keeps everything up to the candidate that caused it
+        self.file.flush()
+    }
+}
+
+impl CandidateSource for RecordingSource<'_> {
+    fn next(&mut self) -> BoxFuture<'_, Result<Option<Candidate>, NockAppError>> {
+        Box::pin(async move {
+            let Some(candidate) = self.inner.next().await? else {
+                return Ok(None);
+            };
+            if let Err(e) = self.write(&candidate) {
+                warn!("Could not record candidate: {e}");
+            }
+            Ok(Some(candidate))
+        })
+    }
+}
+
+fn read_recording(bytes: &[u8]) -> io::Result<Vec<(Duration, Candidate)>> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let mut rest = bytes
+        .strip_prefix(RECORDING_MAGIC.as_slice())
+        .ok_or_else(|| invalid("not a candidate recording".to_string()))?;
+    let mut candidates = Vec::new();
+    while !rest.is_empty() {
+        let (Some(millis), Some(len)) = (read_u64(rest, 0), read_u64(rest, 8)) else {
+            return Err(invalid("truncated record header".to_string()));
+        };
+        let jammed = rest
+            .get(16..16 + len as usize)
+            .ok_or_else(|| invalid("truncated record".to_string()))?;
+        let candidate = cue_candidate(jammed).map_err(|e| invalid(e.to_string()))?;
+        candidates.push((Duration::from_millis(millis), candidate));
+        rest = &rest[16 + len as usize..];
+    }
+    Ok(candidates)
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(bytes.get(at..at + 8)?.try_into().ok()?))
+}
+
+/// Candidates pushed through a channel, e.g. by a test or a pool client; exhausted once
+/// every sender is dropped
+impl CandidateSource for mpsc::Receiver<Candidate> {
+    fn next(&mut self) -> BoxFuture<'_, Result<Option<Candidate>, NockAppError>> {
+        Box::pin(async move { Ok(self.recv().await) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nockapp::noun::slab::NounSlab;
+    use nockvm::noun::{D, T};
+    use nockvm_macros::tas;
+
+    use super::*;
+
+    fn candidate(pow_len: u64) -> Candidate {
+        Candidate::from_parts(2, [1, 2, 3, 4, 5], &[1000], pow_len)
+    }
+
+    fn mine_effect(candidate: &Candidate) -> NounSlab {
+        let mut slab = NounSlab::new();
+        let version = slab.copy_into(candidate.version());
+        let header = slab.copy_into(candidate.header());
+        let target = slab.copy_into(candidate.target());
+        let effect = T(
+            &mut slab,
+            &[D(tas!(b"mine")), version, header, target, D(candidate.pow_len())],
+        );
+        slab.set_root(effect);
+        slab
+    }
+
+    /// A handle whose effect receiver has fallen behind, so its next read errors once
+    fn lagging_handle() -> NockAppHandle {
+        let (handle, _, _) = nockapp::nockapp::test::detached_handle();
+        for pow_len in 0..40 {
+            handle
+                .effect_sender
+                .send(mine_effect(&candidate(pow_len)))
+                .unwrap();
+        }
+        handle
+    }
+
+    #[test]
+    fn test_effect_retry_backs_off_exponentially() {
+        let retry = EffectRetry::default();
+        assert_eq!(retry.backoff(1), Duration::from_millis(10));
+        assert_eq!(retry.backoff(3), Duration::from_millis(40));
+        assert_eq!(retry.backoff(1000), Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_effect_source_retries_then_gives_up() {
+        let handle = lagging_handle();
+        let errors = Arc::new(AtomicU64::new(0));
+        let mut source = EffectCandidateSource::new(&handle).with_error_counter(errors.clone());
+        // Reads on from the oldest effect still buffered
+        let next = source.next().await.unwrap().unwrap();
+        assert_eq!(next.pow_len(), 8);
+        assert_eq!(errors.load(Ordering::Relaxed), 1);
+        assert_eq!(source.consecutive_errors, 0);
+
+        let handle = lagging_handle();
+        let mut source = EffectCandidateSource::new(&handle).with_retry(EffectRetry {
+            max_consecutive_errors: Some(1),
+            ..EffectRetry::default()
+        });
+        assert!(matches!(
+            source.next().await,
+            Err(NockAppError::BroadcastRecvLaggedError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_replay_source_yields_in_order() {
+        let mut source = ReplaySource::new([candidate(1), candidate(2)]);
+        assert_eq!(source.next().await.unwrap().map(|c| c.pow_len()), Some(1));
+        assert_eq!(source.next().await.unwrap().map(|c| c.pow_len()), Some(2));
+        assert!(source.next().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_channel_source_ends_when_senders_drop() {
+        let (tx, rx) = mpsc::channel(2);
+        let mut source: Box<dyn CandidateSource> = Box::new(rx);
+        tx.send(candidate(3)).await.unwrap();
+        drop(tx);
+        assert_eq!(source.next().await.unwrap().map(|c| c.pow_len()), Some(3));
+        assert!(source.next().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_recording_replays_with_the_same_timing() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let (tx, rx) = mpsc::channel(4);
+        let mut recording = record_candidates(Box::new(rx), file.path()).unwrap();
+
+        tx.send(candidate(1)).await.unwrap();
+        assert_eq!(
+            recording.next().await.unwrap().map(|c| c.pow_len()),
+            Some(1)
+        );
+        std::thread::sleep(Duration::from_millis(50));
+        tx.send(candidate(2)).await.unwrap();
+        assert_eq!(
+            recording.next().await.unwrap().map(|c| c.pow_len()),
+            Some(2)
+        );
+        drop(recording);
+
+        let mut replay = ReplaySource::open(file.path()).unwrap();
+        assert_eq!(replay.len(), 2);
+        let started = Instant::now();
+        let first = replay.next().await.unwrap().unwrap();
+        assert_eq!(jam_candidate(&first), jam_candidate(&candidate(1)));
+        let second = replay.next().await.unwrap().unwrap();
+        assert_eq!(second.pow_len(), 2);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert!(replay.next().await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_recording_rejects_garbage() {
+        assert!(read_recording(b"not a recording").is_err());
+        let mut truncated = RECORDING_MAGIC.to_vec();
+        truncated.extend_from_slice(&5u64.to_le_bytes());
+        truncated.extend_from_slice(&100u64.to_le_bytes());
+        truncated.extend_from_slice(&[1, 2, 3]);
+        assert!(read_recording(&truncated).is_err());
+        assert!(read_recording(RECORDING_MAGIC).unwrap().is_empty());
+    }
+}
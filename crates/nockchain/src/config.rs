@@ -3,7 +3,7 @@ use std::time::Duration;
 
 use clap::{arg, command, value_parser, ArgAction, Parser};
 
-use crate::mining::MiningKeyConfig;
+use crate::mining::{AttemptLogLevel, MiningKeyConfig};
 
 // TODO: command-line/configure
 /** Path to read current node's identity from */
@@ -101,6 +101,34 @@ pub struct NockchainCli {
     pub max_system_memory_bytes: Option<usize>,
     #[arg(long, help = "Number of threads to mine with defaults to one less than the number of cpus available.", default_value = None)]
     pub num_threads: Option<u64>,
+    #[arg(
+        long,
+        help = "Run this process only on NUMA node N's CPUs and memory, e.g. to run one miner process per node. --num-threads defaults to the node's CPU count"
+    )]
+    pub numa_node: Option<usize>,
+    #[arg(
+        long,
+        help = "Append each found solution as a JSON line to this file or named pipe before submitting it"
+    )]
+    pub solution_log: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Submit found blocks before anything else, writing the solution log afterwards",
+        default_value_t = false
+    )]
+    pub prioritize_submission: bool,
+    #[arg(
+        long,
+        help = "Also submit found blocks to the node listening on this npc socket, e.g. a redundant node"
+    )]
+    pub submit_npc_socket: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Which mining attempts to log at debug level: 'changes' for state changes only, 'all', or N to sample one attempt in N",
+        value_parser = value_parser!(AttemptLogLevel),
+        default_value = "1000"
+    )]
+    pub attempt_log: AttemptLogLevel,
     #[arg(
         long,
         help = "Size of Proof of Work puzzle for mining on fakenet. Mainnet uses 64. Must be a power of 2. Defaults to 2. Ignored on mainnet.",
@@ -0,0 +1,261 @@
+//! HTTP control API for the miners.
+//!
+//! A miner implements [`MinerControl`] and [`serve`] exposes it over HTTP as JSON.
+//! Endpoints:
+//!
+//! - `GET /health`: [`HealthStatus`]; 200 while live, 503 otherwise (liveness probe)
+//! - `GET /ready`: the same body; 200 once every expected worker is running (readiness probe)
+//! - `GET /optimizations`: the names of the optimizations in effect, as a JSON array
+//! - `GET /events`: the miner's recent [`EventRecord`]s, oldest first
+//! - `GET /config`: the miner's [`EffectiveConfig`], or `null` before it has started
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Serialize;
+use tracing::info;
+
+use crate::event_log::EventRecord;
+
+/// Snapshot of whether a miner is doing useful work
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthStatus {
+    /// At least one worker finished an attempt within the liveness window
+    pub live: bool,
+    /// Every expected worker has an attempt in flight
+    pub ready: bool,
+    pub seconds_since_last_attempt: Option<f64>,
+    /// `None` until this miner has found a block
+    pub seconds_since_last_solution: Option<f64>,
+    pub active_threads: u64,
+    pub expected_threads: u64,
+    /// The hash rate has stayed below the miner's expected floor; see
+    /// `OptimizedMiningConfig::min_expected_hashrate`
+    pub degraded: bool,
+}
+
+impl HealthStatus {
+    /// Judge health from raw timestamps. `window` is how recently an attempt must have
+    /// finished for the miner to count as live.
+    pub fn evaluate(
+        now: Instant,
+        last_attempt: Option<Instant>,
+        last_solution: Option<Instant>,
+        active_threads: u64,
+        expected_threads: u64,
+        window: Duration,
+    ) -> Self {
+        let since = |at: Option<Instant>| at.map(|at| now.saturating_duration_since(at));
+        let since_attempt = since(last_attempt);
+        Self {
+            live: since_attempt.is_some_and(|age| age <= window),
+            ready: expected_threads > 0 && active_threads >= expected_threads,
+            seconds_since_last_attempt: since_attempt.map(|age| age.as_secs_f64()),
+            seconds_since_last_solution: since(last_solution).map(|age| age.as_secs_f64()),
+            active_threads,
+            expected_threads,
+            degraded: false,
+        }
+    }
+}
+
+/// The configuration a miner actually runs with once detection, clamping and hardware
+/// gating are done, which can differ from the one it was given. Serialized, it records
+/// how a box was set up and can be diffed against another.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EffectiveConfig {
+    /// Which miner this is, e.g. `optimized` or `epyc-9b14`
+    pub miner: &'static str,
+    /// Mining threads it runs
+    pub threads: usize,
+    /// As reported by [`MinerControl::active_optimizations`]
+    pub optimizations: Vec<&'static str>,
+    /// Logical CPUs kept free of mining threads
+    pub reserved_cpus: Vec<usize>,
+    /// Stack of each mining thread, or of each worker's Nock kernel, in bytes
+    pub stack_size: usize,
+    /// What does the hashing
+    pub backend: String,
+}
+
+/// What the control API can ask of a running miner
+pub trait MinerControl: Send + Sync + 'static {
+    fn health(&self) -> HealthStatus;
+
+    /// Optimizations actually in effect after hardware detection, not merely requested
+    fn active_optimizations(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// Recent significant events, oldest first; see [`crate::event_log`]
+    fn recent_events(&self) -> Vec<EventRecord> {
+        Vec::new()
+    }
+
+    /// The configuration in effect, once the miner has started
+    fn effective_config(&self) -> Option<EffectiveConfig> {
+        None
+    }
+}
+
+/// Routes for `miner`, for embedding in another server or testing
+pub fn router(miner: Arc<dyn MinerControl>) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/ready", get(ready))
+        .route("/optimizations", get(optimizations))
+        .route("/events", get(events))
+        .route("/config", get(config))
+        .with_state(miner)
+}
+
+/// Serve the control API on `addr` until the task is dropped
+pub async fn serve(addr: SocketAddr, miner: Arc<dyn MinerControl>) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("Miner control API listening on {}", listener.local_addr()?);
+    axum::serve(listener, router(miner)).await
+}
+
+async fn health(State(miner): State<Arc<dyn MinerControl>>) -> (StatusCode, Json<HealthStatus>) {
+    let status = miner.health();
+    (probe_code(status.live), Json(status))
+}
+
+async fn ready(State(miner): State<Arc<dyn MinerControl>>) -> (StatusCode, Json<HealthStatus>) {
+    let status = miner.health();
+    (probe_code(status.ready), Json(status))
+}
+
+async fn optimizations(State(miner): State<Arc<dyn MinerControl>>) -> Json<Vec<&'static str>> {
+    Json(miner.active_optimizations())
+}
+
+async fn events(State(miner): State<Arc<dyn MinerControl>>) -> Json<Vec<EventRecord>> {
+    Json(miner.recent_events())
+}
+
+async fn config(State(miner): State<Arc<dyn MinerControl>>) -> Json<Option<EffectiveConfig>> {
+    Json(miner.effective_config())
+}
+
+fn probe_code(ok: bool) -> StatusCode {
+    if ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::IntoFuture;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::event_log::MinerEvent;
+
+    struct Fixed(HealthStatus);
+
+    impl MinerControl for Fixed {
+        fn health(&self) -> HealthStatus {
+            self.0.clone()
+        }
+
+        fn active_optimizations(&self) -> Vec<&'static str> {
+            vec!["avx512", "numa"]
+        }
+
+        fn recent_events(&self) -> Vec<EventRecord> {
+            vec![EventRecord {
+                timestamp_ms: 1_700_000_000_000,
+                event: MinerEvent::BlockFound { thread: 7 },
+            }]
+        }
+
+        fn effective_config(&self) -> Option<EffectiveConfig> {
+            Some(EffectiveConfig {
+                miner: "fixed",
+                threads: 4,
+                optimizations: vec!["numa"],
+                reserved_cpus: vec![0],
+                stack_size: 1 << 20,
+                backend: "scalar".to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let now = Instant::now();
+        let window = Duration::from_secs(30);
+        let recent = now - Duration::from_secs(5);
+        let old = now - Duration::from_secs(60);
+
+        let status = HealthStatus::evaluate(now, Some(recent), None, 4, 4, window);
+        assert!(status.live && status.ready);
+        assert_eq!(status.seconds_since_last_solution, None);
+
+        let status = HealthStatus::evaluate(now, Some(old), Some(old), 3, 4, window);
+        assert!(!status.live && !status.ready);
+        assert_eq!(status.seconds_since_last_solution, Some(60.0));
+
+        assert!(!HealthStatus::evaluate(now, None, None, 0, 0, window).live);
+    }
+
+    async fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request =
+            format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_health_endpoints() {
+        let now = Instant::now();
+        let status = HealthStatus::evaluate(now, Some(now), None, 2, 4, Duration::from_secs(30));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(axum::serve(listener, router(Arc::new(Fixed(status)))).into_future());
+
+        let health = get(addr, "/health").await;
+        assert!(health.starts_with("HTTP/1.1 200"), "{health}");
+        assert!(health.contains("\"active_threads\":2"), "{health}");
+
+        let ready = get(addr, "/ready").await;
+        assert!(ready.starts_with("HTTP/1.1 503"), "{ready}");
+
+        let optimizations = get(addr, "/optimizations").await;
+        assert!(optimizations.starts_with("HTTP/1.1 200"), "{optimizations}");
+        assert!(
+            optimizations.ends_with("[\"avx512\",\"numa\"]"),
+            "{optimizations}"
+        );
+
+        let events = get(addr, "/events").await;
+        assert!(events.starts_with("HTTP/1.1 200"), "{events}");
+        assert!(
+            events.ends_with(
+                "[{\"timestamp_ms\":1700000000000,\"kind\":\"block_found\",\"thread\":7}]"
+            ),
+            "{events}"
+        );
+
+        let config = get(addr, "/config").await;
+        assert!(config.starts_with("HTTP/1.1 200"), "{config}");
+        assert!(
+            config.ends_with(
+                "{\"miner\":\"fixed\",\"threads\":4,\"optimizations\":[\"numa\"],\"reserved_cpus\":[0],\"stack_size\":1048576,\"backend\":\"scalar\"}"
+            ),
+            "{config}"
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Recent significant miner events, kept in memory for post-incident analysis.
+//!
+//! A miner pushes a [`MinerEvent`] whenever something out of the ordinary happens: a new
+//! candidate, a block found, a worker running out of memory or being restarted, the miner
+//! stalling. [`EventLog`] keeps the last [`EVENT_LOG_CAPACITY`] of them with their
+//! timestamps, and the control API serves them at `GET /events`, so the sequence leading
+//! up to a problem can be read without any external logging.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+/// Events an [`EventLog`] keeps by default before dropping the oldest
+pub const EVENT_LOG_CAPACITY: usize = 256;
+
+/// Something worth knowing about after the fact
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MinerEvent {
+    /// The workers moved to a new candidate; `None` if its target could not be parsed
+    CandidateReceived {
+        difficulty_bits: Option<u32>,
+    },
+    BlockFound {
+        thread: u64,
+    },
+    /// A worker's kernel ran out of memory with this stack size
+    WorkerOutOfMemory {
+        thread: u64,
+        stack_size: usize,
+    },
+    /// A worker's attempt panicked and was caught
+    WorkerPanicked {
+        thread: u64,
+    },
+    /// A worker got a fresh kernel with this stack size
+    WorkerRestarted {
+        thread: u64,
+        stack_size: usize,
+    },
+    /// A worker was taken out of rotation for good
+    WorkerRetired {
+        thread: u64,
+    },
+    /// No attempt finished within the liveness window
+    Stalled {
+        seconds_since_last_attempt: Option<f64>,
+    },
+    /// Attempts are finishing again after a stall
+    Resumed,
+    /// The mean hash rate fell below the configured floor
+    HashRateDegraded {
+        hashes_per_sec: f64,
+        min_expected: u64,
+    },
+    /// The mean hash rate is back above the configured floor
+    HashRateRecovered {
+        hashes_per_sec: f64,
+    },
+}
+
+/// A [`MinerEvent`] and when it happened
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EventRecord {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    #[serde(flatten)]
+    pub event: MinerEvent,
+}
+
+/// Ring buffer of the most recent [`EventRecord`]s
+#[derive(Debug)]
+pub struct EventLog {
+    capacity: usize,
+    events: Mutex<VecDeque<EventRecord>>,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new(EVENT_LOG_CAPACITY)
+    }
+}
+
+impl EventLog {
+    /// A log keeping the last `capacity` events; zero keeps none
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Record `event` as happening now, dropping the oldest event if the log is full
+    pub fn push(&self, event: MinerEvent) {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_millis() as u64)
+            .unwrap_or_default();
+        self.push_record(EventRecord {
+            timestamp_ms,
+            event,
+        });
+    }
+
+    fn push_record(&self, record: EventRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut events = self
+            .events
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(record);
+    }
+
+    /// Every event still held, oldest first
+    pub fn recent(&self) -> Vec<EventRecord> {
+        self.events
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_log_keeps_the_latest() {
+        let log = EventLog::new(3);
+        for thread in 0..5 {
+            log.push(MinerEvent::BlockFound { thread });
+        }
+        let events: Vec<MinerEvent> = log.recent().into_iter().map(|r| r.event).collect();
+        assert_eq!(
+            events,
+            (2..5)
+                .map(|thread| MinerEvent::BlockFound { thread })
+                .collect::<Vec<_>>()
+        );
+
+        let empty = EventLog::new(0);
+        empty.push(MinerEvent::Resumed);
+        assert!(empty.recent().is_empty());
+    }
+
+    #[test]
+    fn test_event_record_json() {
+        let record = EventRecord {
+            timestamp_ms: 1_700_000_000_000,
+            event: MinerEvent::WorkerOutOfMemory {
+                thread: 3,
+                stack_size: 1 << 20,
+            },
+        };
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"timestamp_ms":1700000000000,"kind":"worker_out_of_memory","thread":3,"stack_size":1048576}"#
+        );
+    }
+}
@@ -0,0 +1,734 @@
+//! Seam between the mining drivers and whatever computes PoW hashes.
+//!
+//! A driver hands a [`Candidate`] and a batch of [`Nonce`]s to a [`HashBackend`] and gets
+//! one [`HashResult`] back per nonce. [`CpuSerfBackend`] runs the miner kernel in a
+//! `SerfThread` via [`crate::pow::evaluate`]; other backends (CUDA, OpenCL) can
+//! implement the same trait without touching the driver loop. A [`BackendFactory`] puts
+//! one into the optimized driver's workers; in tests, `ScriptedBackend` returns canned
+//! results so the driver loop runs without a kernel.
+
+use std::fmt;
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use kernels::miner::KERNEL;
+use nockapp::kernel::form::SerfThread;
+use nockapp::noun::slab::NounSlab;
+use nockapp::noun::{AtomExt, NounExt};
+use nockapp::save::SaveableCheckpoint;
+use nockapp::{Bytes, CrownError};
+use nockvm::jets::hot::HotEntry;
+use nockvm::noun::{Atom, Noun, D, T};
+use nockvm_macros::tas;
+use rand::Rng;
+use zkvm_jetpack::form::PRIME;
+use zkvm_jetpack::noun::noun_ext::NounExt as OtherNounExt;
+
+/// Everything the miner kernel needs to hash a nonce, as delivered by a %mine effect.
+///
+/// Each part is held in its own slab. Every constructor checks the parts' shapes once, so
+/// a slab that was never populated (whose root reads as the atom 0) is rejected up front.
+/// The accessors return nouns that stay valid for as long as the candidate is borrowed,
+/// so callers never dereference a slab root themselves.
+#[derive(Clone)]
+pub struct Candidate {
+    version: NounSlab,
+    header: NounSlab,
+    target: NounSlab,
+    pow_len: u64,
+}
+
+impl Candidate {
+    /// Parse the `[version commit target pow-len]` tail of a %mine effect
+    pub fn from_mine_effect(tail: Noun) -> Result<Self, CandidateError> {
+        // pow-len is an atom, so the tail of a well-formed effect has exactly four elements
+        let found = tuple_arity(tail);
+        if found != MINE_EFFECT_ARITY {
+            return Err(CandidateError::EffectArity { found });
+        }
+        let Ok([version, header, target, pow_len]) = tail.uncell() else {
+            return Err(CandidateError::MalformedEffect);
+        };
+        let Some(pow_len) = pow_len.as_atom().ok().and_then(|atom| atom.as_u64().ok()) else {
+            return Err(CandidateError::MalformedEffect);
+        };
+        Self::checked(
+            NounSlab::from(version),
+            NounSlab::from(header),
+            NounSlab::from(target),
+            pow_len,
+        )
+    }
+
+    /// Assemble a candidate from slabs that already hold its parts, checking that each
+    /// was populated with the shape the kernel expects.
+    ///
+    /// A version slab that was never populated reads as version 0, so version 0 is
+    /// rejected here; build %0 candidates with [`Candidate::from_mine_effect`] or
+    /// [`Candidate::from_parts`], where the version is known to have been set.
+    pub fn from_slabs(
+        version: NounSlab,
+        header: NounSlab,
+        target: NounSlab,
+        pow_len: u64,
+    ) -> Result<Self, CandidateError> {
+        // SAFETY: the root is read while the slab is borrowed and not kept
+        if unsafe { version.root().raw_equals(&D(0)) } {
+            return Err(CandidateError::Malformed {
+                part: "version",
+                expected: "a populated, nonzero atom",
+            });
+        }
+        Self::checked(version, header, target, pow_len)
+    }
+
+    /// Check the shape of each part and assemble them
+    fn checked(
+        version: NounSlab,
+        header: NounSlab,
+        target: NounSlab,
+        pow_len: u64,
+    ) -> Result<Self, CandidateError> {
+        // SAFETY: the roots are read while their slabs are borrowed and not kept
+        let (version_root, header_root, target_root) =
+            unsafe { (*version.root(), *header.root(), *target.root()) };
+        if !version_root.is_atom() {
+            return Err(CandidateError::Malformed {
+                part: "version",
+                expected: "an atom",
+            });
+        }
+        if !is_digest(header_root) {
+            return Err(CandidateError::Malformed {
+                part: "header",
+                expected: "a five-belt tip5 digest",
+            });
+        }
+        if crate::pow_target::target_from_noun(target_root).is_err() {
+            return Err(CandidateError::Malformed {
+                part: "target",
+                expected: "a %bn bignum",
+            });
+        }
+        Ok(Candidate {
+            version,
+            header,
+            target,
+            pow_len,
+        })
+    }
+
+    /// Build a candidate from plain values rather than a %mine effect: a tip5 `header`
+    /// and `target` as u32 bignum limbs, least significant first
+    pub fn from_parts(version: u64, header: [u64; 5], target: &[u32], pow_len: u64) -> Self {
+        let mut version_slab = NounSlab::new();
+        let version = Atom::from_value(&mut version_slab, version)
+            .expect("Failed to create version atom")
+            .as_noun();
+        version_slab.set_root(version);
+
+        let mut target_slab = NounSlab::new();
+        let mut limbs = D(0);
+        for &limb in target.iter().rev() {
+            limbs = T(&mut target_slab, &[D(limb as u64), limbs]);
+        }
+        let target = T(&mut target_slab, &[D(tas!(b"bn")), limbs]);
+        target_slab.set_root(target);
+
+        Candidate {
+            version: version_slab,
+            header: digest_slab(NounSlab::new(), header),
+            target: target_slab,
+            pow_len,
+        }
+    }
+
+    // SAFETY for the accessors: the constructors checked every root, and the slabs the
+    // returned nouns point into live as long as `self`
+
+    /// PoW algorithm version the kernel should hash with
+    pub fn version(&self) -> Noun {
+        unsafe { *self.version.root() }
+    }
+
+    /// Block commitment, a tip5 digest
+    pub fn header(&self) -> Noun {
+        unsafe { *self.header.root() }
+    }
+
+    /// Target as a kernel bignum
+    pub fn target(&self) -> Noun {
+        unsafe { *self.target.root() }
+    }
+
+    pub fn pow_len(&self) -> u64 {
+        self.pow_len
+    }
+
+    /// Rebuild the `[%mine version commit target pow-len]` effect this candidate came from
+    pub fn to_mine_effect(&self) -> NounSlab {
+        let mut slab = NounSlab::new();
+        let version = slab.copy_into(self.version());
+        let header = slab.copy_into(self.header());
+        let target = slab.copy_into(self.target());
+        let pow_len = Atom::new(&mut slab, self.pow_len).as_noun();
+        let effect = T(
+            &mut slab,
+            &[D(tas!(b"mine")), version, header, target, pow_len],
+        );
+        slab.set_root(effect);
+        slab
+    }
+
+    /// Build the `[version header nonce target pow-len]` cause for the miner kernel
+    pub fn poke(&self, nonce: &Nonce) -> NounSlab {
+        let mut slab = NounSlab::new();
+        let header = slab.copy_into(self.header());
+        let version = slab.copy_into(self.version());
+        let target = slab.copy_into(self.target());
+        let nonce = slab.copy_into(nonce.as_noun());
+        let poke = T(
+            &mut slab,
+            &[version, header, nonce, target, D(self.pow_len)],
+        );
+        slab.set_root(poke);
+        slab
+    }
+}
+
+/// Serialize `candidate` for persisting, as the jam of its `[%mine version commit target
+/// pow-len]` effect. Jam is a bit stream packed least significant bit first, so the bytes
+/// are the same on every host; [`cue_candidate`] reads them back.
+pub fn jam_candidate(candidate: &Candidate) -> Vec<u8> {
+    candidate.to_mine_effect().jam().to_vec()
+}
+
+/// Read a candidate written by [`jam_candidate`]
+pub fn cue_candidate(bytes: &[u8]) -> Result<Candidate, CandidateError> {
+    let mut slab: NounSlab = NounSlab::new();
+    let effect = slab
+        .cue_into(Bytes::copy_from_slice(bytes))
+        .map_err(|e| CandidateError::BadJam(format!("{e:?}")))?;
+    let Ok(effect) = effect.as_cell() else {
+        return Err(CandidateError::NotMineEffect);
+    };
+    if !effect.head().eq_bytes("mine") {
+        return Err(CandidateError::NotMineEffect);
+    }
+    Candidate::from_mine_effect(effect.tail())
+}
+
+/// Why nouns could not be assembled into a [`Candidate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CandidateError {
+    /// The %mine effect tail has `found` elements rather than the four of
+    /// `[version commit target pow-len]`
+    EffectArity { found: usize },
+    /// The %mine effect's pow-len is not a 64-bit atom
+    MalformedEffect,
+    /// `part` does not have the shape the kernel expects. An unpopulated slab lands here,
+    /// since its root reads as the atom 0.
+    Malformed {
+        part: &'static str,
+        expected: &'static str,
+    },
+    /// Persisted bytes could not be cued
+    BadJam(String),
+    /// Persisted bytes cued to something other than a %mine effect
+    NotMineEffect,
+}
+
+impl fmt::Display for CandidateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CandidateError::EffectArity { found } => write!(
+                f,
+                "%mine effect tail has {found} elements, expected {MINE_EFFECT_ARITY} \
+                 ([version commit target pow-len])"
+            ),
+            CandidateError::MalformedEffect => {
+                write!(f, "%mine effect pow-len is not a 64-bit atom")
+            }
+            CandidateError::Malformed { part, expected } => {
+                write!(f, "candidate {part} is not {expected}")
+            }
+            CandidateError::BadJam(e) => write!(f, "candidate is not a valid jam: {e}"),
+            CandidateError::NotMineEffect => write!(f, "jammed noun is not a %mine effect"),
+        }
+    }
+}
+
+impl std::error::Error for CandidateError {}
+
+/// Elements in the tail of a %mine effect
+const MINE_EFFECT_ARITY: usize = 4;
+
+/// Number of elements in `noun` read as a tuple: one more than the cells along its tail
+fn tuple_arity(noun: Noun) -> usize {
+    let mut arity = 1;
+    let mut rest = noun;
+    while let Ok(cell) = rest.as_cell() {
+        arity += 1;
+        rest = cell.tail();
+    }
+    arity
+}
+
+/// Whether `noun` is a five-tuple of atoms, the shape of a tip5 digest
+fn is_digest(noun: Noun) -> bool {
+    let mut rest = noun;
+    for _ in 0..4 {
+        let Ok(cell) = rest.as_cell() else {
+            return false;
+        };
+        if !cell.head().is_atom() {
+            return false;
+        }
+        rest = cell.tail();
+    }
+    rest.is_atom()
+}
+
+/// A nonce (a tip5 digest), either freshly generated or the previous attempt's hash
+#[derive(Clone)]
+pub struct Nonce(NounSlab);
+
+impl Nonce {
+    /// A nonce given as the five belts of a tip5 digest
+    pub fn from_belts(belts: [u64; 5]) -> Self {
+        Nonce::from_belts_in(NounSlab::new(), belts)
+    }
+
+    /// Like [`Nonce::from_belts`], building the digest in `slab`, e.g. a recycled one
+    pub fn from_belts_in(slab: NounSlab, belts: [u64; 5]) -> Self {
+        Nonce(digest_slab(slab, belts))
+    }
+
+    /// Five belts drawn uniformly from the field
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Nonce::from_belts(std::array::from_fn(|_| rng.gen::<u64>() % PRIME))
+    }
+
+    /// A nonce already built as a noun, e.g. the hash a missed attempt returned
+    pub fn from_slab(slab: NounSlab) -> Self {
+        Nonce(slab)
+    }
+
+    pub fn as_noun(&self) -> Noun {
+        unsafe { *self.0.root() }
+    }
+
+    pub fn into_slab(self) -> NounSlab {
+        self.0
+    }
+}
+
+/// A tip5 digest as a five-tuple noun
+fn digest_slab(mut slab: NounSlab, belts: [u64; 5]) -> NounSlab {
+    let atoms: Vec<Noun> = belts
+        .iter()
+        .map(|&belt| {
+            Atom::from_value(&mut slab, belt)
+                .expect("Failed to create digest atom")
+                .as_noun()
+        })
+        .collect();
+    let digest = T(&mut slab, &atoms);
+    slab.set_root(digest);
+    slab
+}
+
+/// Outcome of hashing one nonce
+pub enum HashResult {
+    /// The hash met the target; `poke` is the %mined poke for the node
+    Found { hash: NounSlab, poke: NounSlab },
+    /// The hash missed the target
+    Miss { hash: NounSlab },
+    /// The attempt was cancelled before it finished
+    Cancelled,
+    /// The kernel returned an effect other than %mine-result
+    Unexpected { head: String },
+    /// The backend could not run the attempt
+    Failed(CrownError),
+}
+
+impl HashResult {
+    /// Interpret the effects the miner kernel returned for one poke
+    pub fn from_effects(effects: &NounSlab) -> Self {
+        let Ok(effects) = (unsafe { effects.root() }).as_cell() else {
+            return HashResult::Unexpected {
+                head: "<atom>".to_string(),
+            };
+        };
+        let effect = effects.head();
+        if effect.is_atom() && effect.eq_bytes("poke") {
+            return HashResult::Cancelled;
+        }
+        let Ok([head, res, tail]) = effect.uncell() else {
+            return HashResult::Unexpected {
+                head: "<malformed>".to_string(),
+            };
+        };
+        if !head.eq_bytes("mine-result") {
+            return HashResult::Unexpected {
+                head: describe_effect_head(head),
+            };
+        }
+        if unsafe { res.raw_equals(&D(0)) } {
+            let Ok([hash, poke]) = tail.uncell() else {
+                return HashResult::Unexpected {
+                    head: "%mine-result".to_string(),
+                };
+            };
+            HashResult::Found {
+                hash: NounSlab::from(hash),
+                poke: NounSlab::from(poke),
+            }
+        } else {
+            HashResult::Miss {
+                hash: NounSlab::from(tail),
+            }
+        }
+    }
+
+    /// The attempt's hash, which also serves as the next nonce
+    pub fn hash(&self) -> Option<&NounSlab> {
+        match self {
+            HashResult::Found { hash, .. } | HashResult::Miss { hash } => Some(hash),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `e` means the worker's serf died mid-poke and cannot be used again.
+///
+/// Running out of memory is the usual cause: nockvm reports a full NockStack by panicking
+/// with an `AllocationError`, which takes down the `SerfThread`, so the poke fails because
+/// its reply channel closed and later pokes because the serf is gone. The panic itself
+/// never reaches the caller, though, so any other panic in the serf looks the same from
+/// here; callers should log it as a dead worker rather than as an allocation failure.
+pub fn worker_died(e: &CrownError) -> bool {
+    match e {
+        CrownError::OneshotChannelError(_) | CrownError::SerfMPSCError() => true,
+        CrownError::IOError(e) => e.kind() == std::io::ErrorKind::OutOfMemory,
+        _ => false,
+    }
+}
+
+/// Render an effect head for logging: its bytes as text if it is an atom
+pub fn describe_effect_head(head: Noun) -> String {
+    match head.as_atom() {
+        Ok(atom) => format!("%{}", String::from_utf8_lossy(atom.as_ne_bytes())),
+        Err(_) => "<cell>".to_string(),
+    }
+}
+
+/// Something that can hash nonces for a candidate
+pub trait HashBackend: Send + Sync {
+    /// Hash `candidate` once per nonce, returning results in nonce order. A backend may
+    /// stop after the first result that is not a miss, so the results can cover only a
+    /// prefix of `nonces`.
+    ///
+    /// This may block for as long as the proofs take; call it from a blocking thread.
+    fn hash_candidates(&self, candidate: &Candidate, nonces: &[Nonce]) -> Vec<HashResult>;
+
+    /// Abandon in-flight work, e.g. when a new candidate arrives
+    fn cancel(&self) {}
+}
+
+/// Makes a worker's [`HashBackend`], given the Nock stack size it should get
+pub type BackendFactory = Box<
+    dyn Fn(usize) -> BoxFuture<'static, Result<Arc<dyn HashBackend>, CrownError>> + Send + Sync,
+>;
+
+/// Default backend: the miner kernel running on a dedicated `SerfThread`
+pub struct CpuSerfBackend {
+    serf: SerfThread<SaveableCheckpoint>,
+}
+
+impl CpuSerfBackend {
+    pub async fn new(
+        hot_state: Vec<HotEntry>,
+        stack_size: usize,
+        test_jets: Vec<NounSlab>,
+    ) -> Result<Self, CrownError> {
+        let serf = SerfThread::<SaveableCheckpoint>::new(
+            Vec::from(KERNEL),
+            None,
+            hot_state,
+            stack_size,
+            test_jets,
+            false,
+        )
+        .await?;
+        Ok(Self { serf })
+    }
+}
+
+impl HashBackend for CpuSerfBackend {
+    fn hash_candidates(&self, candidate: &Candidate, nonces: &[Nonce]) -> Vec<HashResult> {
+        crate::pow::evaluate_batch(&self.serf, candidate, nonces)
+    }
+
+    fn cancel(&self) {
+        self.serf.cancel_token.cancel();
+    }
+}
+
+/// What a [`ScriptedBackend`] returns for one batch
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Scripted {
+    /// Every nonce misses, hashing to a digest derived from it
+    Miss,
+    /// The first nonce solves the candidate
+    Found,
+    Cancelled,
+    Unexpected,
+    /// The kernel ran out of memory
+    OutOfMemory,
+    /// The backend panics, as a buggy jet would
+    Panic,
+    /// The backend answers with no results at all
+    Empty,
+}
+
+/// Test backend that answers each batch with the next [`Scripted`] result, and with misses
+/// once the script runs out, so driver loops can be exercised without a kernel
+#[cfg(test)]
+pub(crate) struct ScriptedBackend {
+    script: std::sync::Mutex<std::collections::VecDeque<Scripted>>,
+    pub cancels: std::sync::atomic::AtomicU64,
+}
+
+#[cfg(test)]
+impl ScriptedBackend {
+    pub fn new(script: impl IntoIterator<Item = Scripted>) -> Self {
+        Self {
+            script: std::sync::Mutex::new(script.into_iter().collect()),
+            cancels: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl HashBackend for ScriptedBackend {
+    fn hash_candidates(&self, candidate: &Candidate, nonces: &[Nonce]) -> Vec<HashResult> {
+        // Every nonce is checked, whatever the script says, as the kernel would check them
+        let mut hashes: Vec<NounSlab> = nonces.iter().map(scripted_digest).collect();
+        let next = self.script.lock().unwrap().pop_front();
+        match next.unwrap_or(Scripted::Miss) {
+            Scripted::Miss => hashes
+                .into_iter()
+                .map(|hash| HashResult::Miss { hash })
+                .collect(),
+            Scripted::Found => {
+                let nonce = &nonces[0];
+                // Shaped like the kernel's %mined poke, with an empty proof
+                let mut poke = NounSlab::new();
+                let header = poke.copy_into(candidate.header());
+                let nonce_noun = poke.copy_into(nonce.as_noun());
+                let root = T(
+                    &mut poke,
+                    &[D(tas!(b"command")), D(tas!(b"pow")), D(0), D(0), header, nonce_noun],
+                );
+                poke.set_root(root);
+                vec![HashResult::Found {
+                    hash: hashes.swap_remove(0),
+                    poke,
+                }]
+            }
+            Scripted::Cancelled => vec![HashResult::Cancelled],
+            Scripted::Unexpected => vec![HashResult::Unexpected {
+                head: "%scripted".to_string(),
+            }],
+            Scripted::OutOfMemory => vec![HashResult::Failed(CrownError::IOError(
+                std::io::ErrorKind::OutOfMemory.into(),
+            ))],
+            Scripted::Panic => panic!("scripted worker panic"),
+            Scripted::Empty => Vec::new(),
+        }
+    }
+
+    fn cancel(&self) {
+        self.cancels
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// A digest standing in for the kernel's hash of `nonce`, with its first belt stepped so
+/// that a miss's hash, which becomes the next nonce, never repeats it.
+///
+/// Panics unless `nonce` is a `noun-digest:tip5`, five belts in the field, as the kernel
+/// would reject anything else.
+#[cfg(test)]
+fn scripted_digest(nonce: &Nonce) -> NounSlab {
+    let belts: Option<[u64; 5]> = nonce.as_noun().uncell().ok().and_then(|belts: [Noun; 5]| {
+        let belts = belts.map(|belt| belt.as_atom().ok().and_then(|atom| atom.as_u64().ok()));
+        belts
+            .iter()
+            .all(|belt| belt.is_some_and(|belt| belt < PRIME))
+            .then(|| belts.map(Option::unwrap))
+    });
+    let Some(mut belts) = belts else {
+        panic!("scripted backend given a nonce that isn't a five-belt tip5 digest");
+    };
+    belts[0] = (belts[0] + 1) % PRIME;
+    digest_slab(NounSlab::new(), belts)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_candidate_from_mine_effect() {
+        let expected = Candidate::from_parts(2, [1, 2, 3, 4, 5], &[1000], 64);
+        let mut slab: NounSlab = NounSlab::new();
+        let version = slab.copy_into(expected.version());
+        let header = slab.copy_into(expected.header());
+        let target = slab.copy_into(expected.target());
+        let tail = T(&mut slab, &[version, header, target, D(64)]);
+        // The target is a list, so the short tail drops it rather than pow-len
+        let short = T(&mut slab, &[version, header, D(64)]);
+        let long = T(&mut slab, &[version, header, target, D(64), D(0)]);
+
+        let candidate = Candidate::from_mine_effect(tail).unwrap();
+        assert_eq!(candidate.pow_len(), 64);
+        let nonce = Nonce::random(&mut StdRng::seed_from_u64(7));
+        assert_eq!(candidate.poke(&nonce).jam(), expected.poke(&nonce).jam());
+
+        let err = Candidate::from_mine_effect(short).err();
+        assert_eq!(err, Some(CandidateError::EffectArity { found: 3 }));
+        assert_eq!(
+            err.unwrap().to_string(),
+            "%mine effect tail has 3 elements, expected 4 ([version commit target pow-len])"
+        );
+        assert_eq!(
+            Candidate::from_mine_effect(long).err(),
+            Some(CandidateError::EffectArity { found: 5 })
+        );
+        assert_eq!(
+            Candidate::from_mine_effect(D(0)).err(),
+            Some(CandidateError::EffectArity { found: 1 })
+        );
+    }
+
+    #[test]
+    fn test_jam_candidate_round_trips() {
+        let candidate = Candidate::from_parts(3, [9, 8, 7, 6, 5], &[1 << 20, 42], 128);
+        let jammed = jam_candidate(&candidate);
+        let cued = cue_candidate(&jammed).unwrap();
+        assert_eq!(cued.pow_len(), 128);
+        assert_eq!(jam_candidate(&cued), jammed);
+        let nonce = Nonce::random(&mut StdRng::seed_from_u64(3));
+        assert_eq!(cued.poke(&nonce).jam(), candidate.poke(&nonce).jam());
+
+        assert!(matches!(
+            cue_candidate(&jammed[..jammed.len() / 2]),
+            Err(CandidateError::BadJam(_))
+        ));
+        let mut slab: NounSlab = NounSlab::new();
+        let poke = T(&mut slab, &[D(tas!(b"poke")), D(1)]);
+        slab.set_root(poke);
+        assert_eq!(
+            cue_candidate(&slab.jam()).err(),
+            Some(CandidateError::NotMineEffect)
+        );
+    }
+
+    #[test]
+    fn test_candidate_rejects_empty_slabs() {
+        let valid = Candidate::from_parts(2, [1, 2, 3, 4, 5], &[1000], 64);
+        let header = || NounSlab::from(valid.header());
+        let target = || NounSlab::from(valid.target());
+        let version = || NounSlab::from(valid.version());
+
+        let err = Candidate::from_slabs(version(), NounSlab::new(), NounSlab::new(), 64);
+        assert_eq!(
+            err.err(),
+            Some(CandidateError::Malformed {
+                part: "header",
+                expected: "a five-belt tip5 digest",
+            })
+        );
+        let err = Candidate::from_slabs(version(), header(), NounSlab::new(), 64);
+        assert!(matches!(
+            err.err(),
+            Some(CandidateError::Malformed { part: "target", .. })
+        ));
+        // An empty version slab reads as version 0, so it can't be told from a real %0
+        let err = Candidate::from_slabs(NounSlab::new(), header(), target(), 64);
+        assert_eq!(
+            err.err(),
+            Some(CandidateError::Malformed {
+                part: "version",
+                expected: "a populated, nonzero atom",
+            })
+        );
+        assert_eq!(
+            Candidate::from_parts(0, [1, 2, 3, 4, 5], &[1000], 64)
+                .version()
+                .as_atom()
+                .unwrap()
+                .as_u64()
+                .unwrap(),
+            0
+        );
+        let candidate = Candidate::from_slabs(version(), header(), target(), 64).unwrap();
+        assert_eq!(
+            candidate
+                .header()
+                .as_cell()
+                .unwrap()
+                .head()
+                .as_atom()
+                .unwrap()
+                .as_u64()
+                .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_worker_died() {
+        let (reply, closed) = tokio::sync::oneshot::channel::<()>();
+        drop(reply);
+        let serf_died = CrownError::from(closed.blocking_recv().unwrap_err());
+        assert!(worker_died(&serf_died));
+        assert!(worker_died(&CrownError::SerfMPSCError()));
+        assert!(worker_died(&CrownError::IOError(
+            std::io::ErrorKind::OutOfMemory.into()
+        )));
+
+        assert!(!worker_died(&CrownError::KernelError(None)));
+        assert!(!worker_died(&CrownError::IOError(
+            std::io::ErrorKind::NotFound.into()
+        )));
+    }
+
+    #[test]
+    fn test_random_nonce_is_a_digest() {
+        let nonce = Nonce::random(&mut StdRng::seed_from_u64(7));
+        let belts: [Noun; 5] = nonce.as_noun().uncell().unwrap();
+        for belt in belts {
+            assert!(belt.as_atom().unwrap().as_u64().unwrap() < PRIME);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "five-belt tip5 digest")]
+    fn test_scripted_backend_rejects_a_non_digest_nonce() {
+        // Eight values, the shape fresh nonces had before they were digests
+        let mut slab = NounSlab::new();
+        let values: Vec<Noun> = (0..8).map(D).collect();
+        let root = T(&mut slab, &values);
+        slab.set_root(root);
+        let candidate = Candidate::from_parts(2, [1, 2, 3, 4, 5], &[1000], 64);
+        ScriptedBackend::new([Scripted::Found])
+            .hash_candidates(&candidate, &[Nonce::from_slab(slab)]);
+    }
+}
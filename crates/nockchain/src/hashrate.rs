@@ -0,0 +1,135 @@
+//! Hash rates for logs, in a unit that suits their size.
+//!
+//! The miners run anywhere from a few hashes a second on a laptop to millions on a server,
+//! so a fixed unit either rounds small rates to zero or buries large ones in digits.
+//! [`format_hashrate`] picks H/s, kH/s, MH/s or GH/s by magnitude and shows three
+//! significant digits, and every miner's monitor logs through it so their output can be
+//! compared. [`HashRateFormat`] pins the unit or the decimals when logs are compared
+//! across runs.
+
+/// Unit a [`HashRateFormat`] shows rates in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HashRateUnit {
+    /// The largest unit the rate is at least one of
+    #[default]
+    Auto,
+    Hashes,
+    KiloHashes,
+    MegaHashes,
+    GigaHashes,
+}
+
+impl HashRateUnit {
+    /// Fixed units, smallest first
+    const FIXED: [HashRateUnit; 4] = [
+        HashRateUnit::Hashes,
+        HashRateUnit::KiloHashes,
+        HashRateUnit::MegaHashes,
+        HashRateUnit::GigaHashes,
+    ];
+
+    /// Hashes/sec in one of this unit
+    fn scale(self) -> f64 {
+        match self {
+            HashRateUnit::Auto | HashRateUnit::Hashes => 1.0,
+            HashRateUnit::KiloHashes => 1e3,
+            HashRateUnit::MegaHashes => 1e6,
+            HashRateUnit::GigaHashes => 1e9,
+        }
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            HashRateUnit::Auto | HashRateUnit::Hashes => "H/s",
+            HashRateUnit::KiloHashes => "kH/s",
+            HashRateUnit::MegaHashes => "MH/s",
+            HashRateUnit::GigaHashes => "GH/s",
+        }
+    }
+
+    /// The unit `rate` hashes/sec reads best in
+    fn for_rate(rate: f64) -> Self {
+        Self::FIXED
+            .into_iter()
+            .rev()
+            .find(|unit| rate.abs() >= unit.scale())
+            .unwrap_or(HashRateUnit::Hashes)
+    }
+}
+
+/// Most decimals a rate is shown with when they aren't pinned
+const MAX_DECIMALS: i32 = 6;
+
+/// How hash rates are written in logs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HashRateFormat {
+    pub unit: HashRateUnit,
+    /// Digits after the decimal point; `None` for three significant digits
+    pub decimals: Option<usize>,
+}
+
+impl HashRateFormat {
+    /// `rate` hashes/sec with its unit, e.g. "12.3 kH/s"
+    pub fn format(&self, rate: f64) -> String {
+        let unit = match self.unit {
+            HashRateUnit::Auto => HashRateUnit::for_rate(rate),
+            unit => unit,
+        };
+        let value = rate / unit.scale();
+        let decimals = self.decimals.unwrap_or_else(|| significant_decimals(value));
+        format!("{value:.decimals$} {}", unit.suffix())
+    }
+}
+
+/// `rate` hashes/sec in the unit that suits it, to three significant digits
+pub fn format_hashrate(rate: f64) -> String {
+    HashRateFormat::default().format(rate)
+}
+
+/// Decimals that show `value` to three significant digits, up to [`MAX_DECIMALS`]
+fn significant_decimals(value: f64) -> usize {
+    if value == 0.0 || !value.is_finite() {
+        return 0;
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    (2 - magnitude).clamp(0, MAX_DECIMALS) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_hashrate_picks_unit_and_precision() {
+        assert_eq!(format_hashrate(0.0), "0 H/s");
+        assert_eq!(format_hashrate(0.25), "0.250 H/s");
+        assert_eq!(format_hashrate(7.0), "7.00 H/s");
+        assert_eq!(format_hashrate(950.0), "950 H/s");
+        assert_eq!(format_hashrate(12_345.0), "12.3 kH/s");
+        assert_eq!(format_hashrate(1_500_000.0), "1.50 MH/s");
+        assert_eq!(format_hashrate(987_654_321.0), "988 MH/s");
+        assert_eq!(format_hashrate(2.5e12), "2500 GH/s");
+    }
+
+    #[test]
+    fn test_hashrate_format_pins_unit_and_decimals() {
+        let mega = HashRateFormat {
+            unit: HashRateUnit::MegaHashes,
+            decimals: None,
+        };
+        // Small rates keep their digits rather than rounding to 0.00
+        assert_eq!(mega.format(1_234.0), "0.00123 MH/s");
+        assert_eq!(mega.format(3.0), "0.000003 MH/s");
+        let fixed = HashRateFormat {
+            unit: HashRateUnit::KiloHashes,
+            decimals: Some(1),
+        };
+        assert_eq!(fixed.format(12_345.0), "12.3 kH/s");
+        assert_eq!(fixed.format(5.0), "0.0 kH/s");
+        let decimals = HashRateFormat {
+            decimals: Some(0),
+            ..HashRateFormat::default()
+        };
+        assert_eq!(decimals.format(1_500_000.0), "2 MH/s");
+    }
+}
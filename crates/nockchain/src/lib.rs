@@ -1,6 +1,30 @@
+#![feature(stdarch_x86_avx512)]
+#![feature(avx512_target_feature)]
+
+pub mod alloc_stats;
+pub mod autotune;
+pub mod candidate_source;
 pub mod config;
+pub mod control;
+pub mod event_log;
+pub mod hash_backend;
+pub mod hashrate;
+pub mod memlock;
 pub mod mining;
+pub mod mining_chains;
+pub mod mining_epyc7k62_dual;
+pub mod mining_epyc9b14;
+pub mod mining_optimized;
+pub mod nonce_entropy;
+pub mod nonce_source;
+pub mod npc_submit;
+pub mod pow;
+pub mod pow_target;
+pub mod power;
 pub mod setup;
+pub mod solution_log;
+pub mod solution_rate;
+pub mod topology;
 
 use std::error::Error;
 use std::fs;
@@ -464,14 +488,32 @@ pub async fn init_with_kernel<J: Jammer + Send + 'static>(
         .and_then(|c| {
             if let Some(num_threads) = &c.num_threads {
                 Some(*num_threads)
+            } else if c.numa_node.is_some() {
+                // Already confined to the node, so this counts only its CPUs
+                std::thread::available_parallelism()
+                    .ok()
+                    .map(|cpus| cpus.get() as u64)
             } else {
                 Some(1)
             }
         })
         .expect("Failed to get number of threads for mining");
 
-    let mining_driver =
-        crate::mining::create_mining_driver(mining_config, mine, threads, Some(mining_init_tx));
+    let solution_log = cli.as_ref().and_then(|c| c.solution_log.clone());
+    let prioritize_submission = cli.as_ref().is_some_and(|c| c.prioritize_submission);
+    let secondary_submit = cli.as_ref().and_then(|c| c.submit_npc_socket.clone());
+    let attempt_log = cli.as_ref().map(|c| c.attempt_log).unwrap_or_default();
+
+    let mining_driver = crate::mining::create_mining_driver(
+        mining_config,
+        mine,
+        threads,
+        solution_log,
+        prioritize_submission,
+        secondary_submit,
+        attempt_log,
+        Some(mining_init_tx),
+    );
     nockapp.add_io_driver(mining_driver).await;
 
     let libp2p_driver = nockchain_libp2p_io::nc::make_libp2p_driver(
@@ -7,16 +7,40 @@ use nockapp::NockApp;
 use zkvm_jetpack::hot::produce_prover_hot_state;
 
 // When enabled, use jemalloc for more stable memory allocation
-#[cfg(feature = "jemalloc")]
+#[cfg(all(feature = "jemalloc", not(feature = "alloc_stats")))]
 #[global_allocator]
 static ALLOC: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
+// Allocation accounting wraps whichever allocator would otherwise be used
+#[cfg(all(feature = "jemalloc", feature = "alloc_stats"))]
+#[global_allocator]
+static ALLOC: nockchain::alloc_stats::CountingAlloc<tikv_jemallocator::Jemalloc> =
+    nockchain::alloc_stats::CountingAlloc(tikv_jemallocator::Jemalloc);
+
+#[cfg(all(not(feature = "jemalloc"), feature = "alloc_stats"))]
+#[global_allocator]
+static ALLOC: nockchain::alloc_stats::CountingAlloc<std::alloc::System> =
+    nockchain::alloc_stats::CountingAlloc(std::alloc::System);
+
+fn main() -> Result<(), Box<dyn Error>> {
     nockvm::check_endian();
     let cli = nockchain::NockchainCli::parse();
     boot::init_default_tracing(&cli.nockapp_cli);
 
+    // Bind before the runtime exists so every thread inherits the node's CPUs and memory
+    if let Some(node) = cli.numa_node {
+        let topology = nockchain::topology::host_topology()
+            .ok_or("--numa-node needs the CPU topology from sysfs")?;
+        nockchain::topology::bind_process_to_numa_node(topology, node)?;
+    }
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run(cli))
+}
+
+async fn run(cli: nockchain::NockchainCli) -> Result<(), Box<dyn Error>> {
     let prover_hot_state = produce_prover_hot_state();
     let mut nockchain: NockApp =
         nockchain::init_with_kernel(Some(cli), KERNEL, prover_hot_state.as_slice()).await?;
@@ -0,0 +1,208 @@
+//! Pinning mining memory in RAM so it is never paged out.
+//!
+//! [`lock`] mlocks one buffer for as long as the returned [`MemoryLock`] lives.
+//! [`lock_all`] locks the whole process, including the Nock stacks the kernel threads
+//! map later, which the drivers cannot reach buffer by buffer. Both fail with a
+//! [`MemlockError`] naming `RLIMIT_MEMLOCK` when the limit is the reason.
+
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum MemlockError {
+    /// `RLIMIT_MEMLOCK` does not allow locking `requested` more bytes
+    LimitTooLow { requested: usize, limit: u64 },
+    /// [`lock_all`] needs an unlimited `RLIMIT_MEMLOCK`, but it is `limit` bytes
+    LimitNotUnlimited { limit: u64 },
+    /// The OS refused for another reason
+    Os(io::Error),
+    /// Memory locking is not implemented on this platform
+    Unsupported,
+}
+
+impl fmt::Display for MemlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemlockError::LimitTooLow { requested, limit } => write!(
+                f,
+                "cannot lock {requested} bytes of mining memory: RLIMIT_MEMLOCK is {limit} bytes; \
+                 raise it with `ulimit -l unlimited` or `LimitMEMLOCK=infinity` in the service unit"
+            ),
+            MemlockError::LimitNotUnlimited { limit } => write!(
+                f,
+                "locking the Nock stacks locks the whole process and needs an unlimited \
+                 RLIMIT_MEMLOCK, but it is {limit} bytes; raise it with `ulimit -l unlimited` \
+                 or `LimitMEMLOCK=infinity` in the service unit"
+            ),
+            MemlockError::Os(e) => write!(f, "mlock failed: {e}"),
+            MemlockError::Unsupported => {
+                write!(f, "memory locking is not supported on this platform")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MemlockError {}
+
+/// A locked region, unlocked again on drop. Drop it before the memory it covers is freed.
+#[derive(Debug)]
+pub struct MemoryLock {
+    addr: usize,
+    len: usize,
+}
+
+impl MemoryLock {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Drop for MemoryLock {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if self.len > 0 {
+            unsafe { libc::munlock(self.addr as *const libc::c_void, self.len) };
+        }
+    }
+}
+
+/// Soft `RLIMIT_MEMLOCK` in bytes, or `None` if it is unlimited or unknown
+pub fn memlock_limit() -> Option<u64> {
+    #[cfg(unix)]
+    {
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) } == 0
+            && limit.rlim_cur != libc::RLIM_INFINITY
+        {
+            #[allow(clippy::unnecessary_cast)] // rlim_t is narrower on some targets
+            return Some(limit.rlim_cur as u64);
+        }
+    }
+    None
+}
+
+/// Check up front whether `bytes` in total could be locked, so a driver can report a
+/// low limit once instead of once per thread
+pub fn check_limit(bytes: usize) -> Result<(), MemlockError> {
+    match memlock_limit() {
+        Some(limit) if (bytes as u64) > limit => Err(MemlockError::LimitTooLow {
+            requested: bytes,
+            limit,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Worst-case bytes locking a `bytes`-long buffer counts against the limit: whole pages,
+/// plus one when the buffer straddles a page boundary
+pub fn footprint(bytes: usize) -> usize {
+    if bytes == 0 {
+        return 0;
+    }
+    let page = page_size();
+    (bytes.div_ceil(page) + 1) * page
+}
+
+/// Lock the pages backing `buf` into RAM
+pub fn lock<T>(buf: &[T]) -> Result<MemoryLock, MemlockError> {
+    let len = std::mem::size_of_val(buf);
+    if len == 0 {
+        return Ok(MemoryLock { addr: 0, len: 0 });
+    }
+    #[cfg(unix)]
+    {
+        let addr = buf.as_ptr() as usize;
+        if unsafe { libc::mlock(addr as *const libc::c_void, len) } != 0 {
+            return Err(os_error(len));
+        }
+        Ok(MemoryLock { addr, len })
+    }
+    #[cfg(not(unix))]
+    Err(MemlockError::Unsupported)
+}
+
+/// Lock everything the process has mapped and will map, for memory such as Nock stacks
+/// that cannot be locked buffer by buffer.
+///
+/// Future allocations fail once they would exceed the limit, so this insists on an
+/// unlimited `RLIMIT_MEMLOCK` rather than risk allocation failures mid-mining.
+pub fn lock_all() -> Result<(), MemlockError> {
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(limit) = memlock_limit() {
+            return Err(MemlockError::LimitNotUnlimited { limit });
+        }
+        if unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) } != 0 {
+            return Err(MemlockError::Os(io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os = "linux"))]
+    Err(MemlockError::Unsupported)
+}
+
+fn page_size() -> usize {
+    #[cfg(unix)]
+    {
+        let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        if size > 0 {
+            return size as usize;
+        }
+    }
+    4096
+}
+
+#[cfg(unix)]
+fn os_error(requested: usize) -> MemlockError {
+    let e = io::Error::last_os_error();
+    match (e.raw_os_error(), memlock_limit()) {
+        (Some(libc::ENOMEM) | Some(libc::EPERM) | Some(libc::EAGAIN), Some(limit)) => {
+            MemlockError::LimitTooLow { requested, limit }
+        }
+        _ => MemlockError::Os(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_small_buffer() {
+        let buf = vec![0u64; 512];
+        let fits = memlock_limit().is_none_or(|limit| limit >= 4096 * 2);
+        match lock(&buf) {
+            Ok(locked) => assert_eq!(locked.len(), 4096),
+            Err(MemlockError::LimitTooLow { requested, limit }) => {
+                assert_eq!(requested, 4096);
+                assert_eq!(Some(limit), memlock_limit());
+            }
+            // e.g. no CAP_IPC_LOCK with a zero limit, or a sandbox without mlock
+            Err(e) => assert!(!fits, "{e}"),
+        }
+        assert!(lock::<u64>(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_check_limit() {
+        assert!(check_limit(0).is_ok());
+        assert_eq!(footprint(0), 0);
+        assert_eq!(footprint(1), 2 * page_size());
+        assert_eq!(footprint(page_size() + 1), 3 * page_size());
+        match memlock_limit() {
+            Some(limit) => {
+                let err = check_limit(limit as usize + 1).unwrap_err();
+                assert!(err.to_string().contains("RLIMIT_MEMLOCK"));
+            }
+            None => assert!(check_limit(usize::MAX).is_ok()),
+        }
+        let err = MemlockError::LimitNotUnlimited { limit: 65536 };
+        assert!(err.to_string().contains("ulimit -l unlimited"));
+    }
+}
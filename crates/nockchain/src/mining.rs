@@ -1,4 +1,11 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::future::Future;
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 use kernels::miner::KERNEL;
 use nockapp::kernel::form::SerfThread;
@@ -6,19 +13,23 @@ use nockapp::nockapp::driver::{IODriverFn, NockAppHandle, PokeResult};
 use nockapp::nockapp::wire::Wire;
 use nockapp::nockapp::NockAppError;
 use nockapp::noun::slab::NounSlab;
-use nockapp::noun::{AtomExt, NounExt};
+use nockapp::noun::AtomExt;
 use nockapp::save::SaveableCheckpoint;
 use nockapp::utils::NOCK_STACK_SIZE_TINY;
 use nockapp::CrownError;
 use nockchain_libp2p_io::tip5_util::tip5_hash_to_base58;
 use nockvm::interpreter::NockCancelToken;
+use nockvm::jets::hot::HotEntry;
 use nockvm::noun::{Atom, D, NO, T, YES};
 use nockvm_macros::tas;
-use rand::Rng;
 use tokio::sync::Mutex;
-use tracing::{debug, info, instrument, warn};
-use zkvm_jetpack::form::PRIME;
-use zkvm_jetpack::noun::noun_ext::NounExt as OtherNounExt;
+use tokio::task::JoinSet;
+use tracing::{debug, error, info, instrument, warn};
+
+use crate::candidate_source::{CandidateSource, EffectCandidateSource};
+use crate::hash_backend::{Candidate, HashResult, Nonce};
+use crate::npc_submit::NpcSubmitTarget;
+use crate::solution_log::SolutionLog;
 
 pub enum MiningWire {
     Mined,
@@ -78,22 +89,257 @@ impl FromStr for MiningKeyConfig {
     }
 }
 
-struct MiningData {
-    pub block_header: NounSlab,
-    pub version: NounSlab,
-    pub target: NounSlab,
-    pub pow_len: u64,
+/// Why a [`MiningKeyConfig`] would be rejected by the kernel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyConfigError {
+    ZeroShare,
+    /// `m` is 0, so no signature would be required
+    ZeroThreshold,
+    /// `m` signatures required from fewer keys
+    ThresholdAboveKeys {
+        m: u64,
+        keys: usize,
+    },
+    EmptyKey,
+    /// The key is not base58
+    InvalidKey(String),
+    DuplicateKey(String),
+}
+
+impl fmt::Display for KeyConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyConfigError::ZeroShare => write!(f, "share must be at least 1"),
+            KeyConfigError::ZeroThreshold => write!(f, "m must be at least 1"),
+            KeyConfigError::ThresholdAboveKeys { m, keys } => {
+                write!(f, "m is {m} but only {keys} keys are given")
+            }
+            KeyConfigError::EmptyKey => write!(f, "empty key"),
+            KeyConfigError::InvalidKey(key) => write!(f, "key {key:?} is not base58"),
+            KeyConfigError::DuplicateKey(key) => write!(f, "key {key:?} is listed twice"),
+        }
+    }
+}
+
+impl std::error::Error for KeyConfigError {}
+
+impl MiningKeyConfig {
+    /// Check the config before it is sent to the kernel, which would otherwise only
+    /// reject it once a block is found: a nonzero share, `1 <= m <= keys.len()`, and
+    /// distinct base58 keys.
+    pub fn validate(&self) -> Result<(), KeyConfigError> {
+        if self.share == 0 {
+            return Err(KeyConfigError::ZeroShare);
+        }
+        if self.m == 0 {
+            return Err(KeyConfigError::ZeroThreshold);
+        }
+        if self.m > self.keys.len() as u64 {
+            return Err(KeyConfigError::ThresholdAboveKeys {
+                m: self.m,
+                keys: self.keys.len(),
+            });
+        }
+        for (i, key) in self.keys.iter().enumerate() {
+            if key.is_empty() {
+                return Err(KeyConfigError::EmptyKey);
+            }
+            if bs58::decode(key).into_vec().is_err() {
+                return Err(KeyConfigError::InvalidKey(key.clone()));
+            }
+            if self.keys[..i].contains(key) {
+                return Err(KeyConfigError::DuplicateKey(key.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Why a mining driver refused to start
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MiningSetupError {
+    /// Mining was requested without a key to credit found blocks to
+    NoMiningKey,
+    /// The mining key config at `index` is invalid
+    InvalidKeyConfig { index: usize, error: KeyConfigError },
+    /// The configs' shares add up to more than a u64 holds
+    SharesOverflow,
+}
+
+impl fmt::Display for MiningSetupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MiningSetupError::NoMiningKey => write!(
+                f,
+                "mining is enabled but no mining key is configured, so no block found could be \
+                 credited; set --mining-pubkey or --mining-key-adv, or drop --mine"
+            ),
+            MiningSetupError::InvalidKeyConfig { index, error } => {
+                write!(f, "mining key config {} is invalid: {error}", index + 1)
+            }
+            MiningSetupError::SharesOverflow => {
+                write!(f, "mining key shares add up to more than {}", u64::MAX)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MiningSetupError {}
+
+/// The keys to set on the kernel, or `None` to leave mining off
+///
+/// A configuration without any non-empty key counts as none, and is an error when `mine`
+/// asks for mining. Any other configuration must pass [`MiningKeyConfig::validate`].
+pub fn mining_keys(
+    mining_config: Option<Vec<MiningKeyConfig>>,
+    mine: bool,
+) -> Result<Option<Vec<MiningKeyConfig>>, MiningSetupError> {
+    let configs = mining_config.filter(|configs| {
+        configs
+            .iter()
+            .any(|config| config.keys.iter().any(|key| !key.is_empty()))
+    });
+    let Some(configs) = configs else {
+        return if mine {
+            Err(MiningSetupError::NoMiningKey)
+        } else {
+            Ok(None)
+        };
+    };
+    for (index, config) in configs.iter().enumerate() {
+        config
+            .validate()
+            .map_err(|error| MiningSetupError::InvalidKeyConfig { index, error })?;
+    }
+    configs
+        .iter()
+        .try_fold(0u64, |total, config| total.checked_add(config.share))
+        .ok_or(MiningSetupError::SharesOverflow)?;
+    Ok(Some(configs))
+}
+
+/// Attempts per debug line at the default [`AttemptLogLevel`]
+pub const DEFAULT_ATTEMPT_LOG_SAMPLE: u64 = 1000;
+
+/// How much the per-attempt hot path of the mining drivers logs at debug level.
+///
+/// State changes such as new candidates, cancellations and found blocks are always
+/// logged; at high attempt rates a line per attempt floods the logs and slows mining.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttemptLogLevel {
+    /// Only state changes
+    Changes,
+    /// One attempt in N, plus state changes
+    Sample(u64),
+    /// Every attempt
+    All,
+}
+
+impl Default for AttemptLogLevel {
+    fn default() -> Self {
+        AttemptLogLevel::Sample(DEFAULT_ATTEMPT_LOG_SAMPLE)
+    }
+}
+
+impl FromStr for AttemptLogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "changes" => Ok(AttemptLogLevel::Changes),
+            "all" => Ok(AttemptLogLevel::All),
+            _ => match s.parse::<u64>() {
+                Ok(n) if n > 0 => Ok(AttemptLogLevel::Sample(n)),
+                _ => Err(format!(
+                    "Invalid attempt log level '{s}'. Expected 'changes', 'all' or a sample interval N > 0"
+                )),
+            },
+        }
+    }
+}
+
+/// Picks which attempts get a debug line under an [`AttemptLogLevel`]
+pub struct AttemptLog {
+    level: AttemptLogLevel,
+    attempts: AtomicU64,
+}
+
+impl AttemptLog {
+    pub fn new(level: AttemptLogLevel) -> Self {
+        Self {
+            level,
+            attempts: AtomicU64::new(0),
+        }
+    }
+
+    /// Count one routine attempt and say whether to log it
+    pub fn sample(&self) -> bool {
+        match self.level {
+            AttemptLogLevel::Changes => false,
+            AttemptLogLevel::All => true,
+            AttemptLogLevel::Sample(n) => {
+                self.attempts.fetch_add(1, Ordering::Relaxed) % n.max(1) == 0
+            }
+        }
+    }
+}
+
+/// Smallest Nock stack either mining driver respawns a dead worker with. Stacks halve on
+/// each death, so a default-driver worker, which starts at `NOCK_STACK_SIZE_TINY`, is
+/// retired after a couple of respawns.
+pub(crate) const MIN_RESPAWN_STACK_SIZE: usize = NOCK_STACK_SIZE_TINY / 4;
+
+/// Stack for a worker respawned after dying with `stack_size`: half of it, or `None` once
+/// that would drop below [`MIN_RESPAWN_STACK_SIZE`]
+pub(crate) fn respawn_stack_size(stack_size: usize) -> Option<usize> {
+    let half = stack_size / 2;
+    (half >= MIN_RESPAWN_STACK_SIZE).then_some(half)
+}
+
+/// The message a caught panic was raised with
+pub(crate) fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("<non-string panic payload>")
 }
 
+/// A serf running the miner kernel on a Nock stack of `stack_size` bytes
+async fn new_mining_serf(
+    hot_state: Vec<HotEntry>,
+    stack_size: usize,
+    test_jets: Vec<NounSlab>,
+) -> Result<SerfThread<SaveableCheckpoint>, CrownError> {
+    SerfThread::<SaveableCheckpoint>::new(
+        Vec::from(KERNEL),
+        None,
+        hot_state,
+        stack_size,
+        test_jets,
+        false,
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn create_mining_driver(
     mining_config: Option<Vec<MiningKeyConfig>>,
     mine: bool,
     num_threads: u64,
+    solution_log: Option<PathBuf>,
+    prioritize_submission: bool,
+    secondary_submit: Option<PathBuf>,
+    attempt_log: AttemptLogLevel,
     init_complete_tx: Option<tokio::sync::oneshot::Sender<()>>,
 ) -> IODriverFn {
     Box::new(move |handle| {
         Box::pin(async move {
-            let Some(configs) = mining_config else {
+            let configs = mining_keys(mining_config, mine).map_err(|e| {
+                error!("❌ Refusing to start the mining driver: {e}");
+                NockAppError::OtherError
+            })?;
+            let Some(configs) = configs else {
                 enable_mining(&handle, false).await?;
 
                 if let Some(tx) = init_complete_tx {
@@ -129,157 +375,221 @@ pub fn create_mining_driver(
 
             info!("Starting mining driver with {} threads", num_threads);
 
-            let mut mining_attempts = tokio::task::JoinSet::<(
-                SerfThread<SaveableCheckpoint>,
-                u64,
-                Result<NounSlab, CrownError>,
-            )>::new();
+            let solution_log = match solution_log {
+                Some(path) => match SolutionLog::open(&path) {
+                    Ok(log) => {
+                        info!("Recording found solutions to {}", path.display());
+                        Some(log)
+                    }
+                    Err(e) => {
+                        error!("Could not open solution log {}: {e}", path.display());
+                        return Err(NockAppError::IoError(e));
+                    }
+                },
+                None => None,
+            };
+            let secondary = secondary_submit.map(|path| {
+                info!("Also submitting found blocks to {}", path.display());
+                Arc::new(NpcSubmitTarget::new(path))
+            });
+            let attempt_log = AttemptLog::new(attempt_log);
+            // Shared with the submission tasks, which outlive a single pass of the loop
+            let handle = Arc::new(handle);
+            let mut submissions = JoinSet::<(u64, Result<(), NockAppError>)>::new();
+
+            let mut mining_attempts = JoinSet::<MiningAttempt>::new();
             let hot_state = zkvm_jetpack::hot::produce_prover_hot_state();
             let test_jets_str = std::env::var("NOCK_TEST_JETS").unwrap_or_default();
             let test_jets = nockapp::kernel::boot::parse_test_jets(test_jets_str.as_str());
 
-            let mining_data: Mutex<Option<MiningData>> = Mutex::new(None);
-            let mut cancel_tokens: Vec<NockCancelToken> = Vec::<NockCancelToken>::new();
+            let mining_data: Mutex<Option<Candidate>> = Mutex::new(None);
+            // Keyed by thread id so a retired thread's token goes with it
+            let mut cancel_tokens: BTreeMap<u64, NockCancelToken> = BTreeMap::new();
+            // Threads respawned on a smaller stack; the rest use NOCK_STACK_SIZE_TINY
+            let mut stack_sizes: BTreeMap<u64, usize> = BTreeMap::new();
+            let mut workers_oom: u64 = 0;
+            // Attempts that panicked, and that failed without the serf dying
+            let (mut worker_panics, mut workers_failed): (u64, u64) = (0, 0);
+            // Threads taken out of rotation; they get no new attempts, even on a new candidate
+            let mut retired: BTreeSet<u64> = BTreeSet::new();
+            // Set once the first candidate has started the threads; attempts may all be
+            // gone later if every thread was retired, which must not start them again
+            let mut started = false;
+            let mut candidates = EffectCandidateSource::new(&handle);
+            let mut candidates_open = true;
 
             loop {
                 tokio::select! {
                         mining_result = mining_attempts.join_next(), if !mining_attempts.is_empty() => {
-                            let mining_result = mining_result.expect("Mining attempt failed");
-                            let (serf, id, slab_res) = mining_result.expect("Mining attempt result failed");
-                            let slab = slab_res.expect("Mining attempt result failed");
-                            let result = unsafe { slab.root() };
-                            // If the mining attempt was cancelled, the goof goes into poke_swap which returns
-                            // %poke followed by the cancelled poke. So we check for hed = %poke
-                            // to identify a cancelled attempt.
-                            let hed = result.as_cell().expect("Expected result to be a cell").head();
-                            if hed.is_atom() && hed.eq_bytes("poke") {
-                                //  mining attempt was cancelled. restart with current block header.
-                                debug!("mining attempt cancelled. restarting on new block header. thread={id}");
-                                start_mining_attempt(serf, mining_data.lock().await, &mut mining_attempts, None, id).await;
-                            } else {
-                                //  there should only be one effect
-                                let effect = result.as_cell().expect("Expected result to be a cell").head();
-                                let [head, res, tail] = effect.uncell().expect("Expected three elements in mining result");
-                                if head.eq_bytes("mine-result") {
-                                    if unsafe { res.raw_equals(&D(0)) } {
-                                        // success
-                                        // poke main kernel with mined block and start a new attempt
-                                        info!("Found block! thread={id}");
-                                        let [hash, poke] = tail.uncell().expect("Expected two elements in tail");
-                                        let mut poke_slab = NounSlab::new();
-                                        poke_slab.copy_into(poke);
-                                        handle.poke(MiningWire::Mined.to_wire(), poke_slab).await.expect("Could not poke nockchain with mined PoW");
-
-                                        // launch new attempt
-                                        let mut nonce_slab = NounSlab::new();
-                                        nonce_slab.copy_into(hash);
-                                        start_mining_attempt(serf, mining_data.lock().await, &mut mining_attempts, Some(nonce_slab), id).await;
-                                    } else {
-                                        // failure
-                                        //  launch new attempt, using hash as new nonce
-                                        //  nonce is tail
+                            let (id, attempt) = match mining_result.expect("mining_attempts is not empty") {
+                                Ok(joined) => joined,
+                                Err(e) => {
+                                    // Attempts catch their own panics, so the task was cancelled
+                                    error!("mining attempt task failed: {e}");
+                                    continue;
+                                }
+                            };
+                            let (serf, result) = match attempt {
+                                Ok(attempt) => attempt,
+                                Err(payload) => {
+                                    // Caught in the attempt; the serf went with it, so boot a fresh one
+                                    worker_panics += 1;
+                                    cancel_tokens.remove(&id);
+                                    let stack_size = stack_sizes.get(&id).copied().unwrap_or(NOCK_STACK_SIZE_TINY);
+                                    error!("mining worker panicked, respawning it. thread={id} worker_panics={worker_panics}: {}", panic_message(payload.as_ref()));
+                                    if let Err(e) = respawn_mining_worker(hot_state.clone(), stack_size, test_jets.clone(), mining_data.lock().await, &mut mining_attempts, &mut cancel_tokens, id).await {
+                                        retired.insert(id);
+                                        error!("could not respawn mining worker, taking it out of rotation. thread={id} retired={}: {e}", retired.len());
+                                    }
+                                    continue;
+                                }
+                            };
+                            match result {
+                                HashResult::Cancelled => {
+                                    //  mining attempt was cancelled. restart with current block header.
+                                    debug!("mining attempt cancelled. restarting on new block header. thread={id}");
+                                    start_mining_attempt(serf, mining_data.lock().await, &mut mining_attempts, None, id, true).await;
+                                }
+                                HashResult::Found { hash, poke } => {
+                                    // poke main kernel with mined block and start a new attempt
+                                    info!("Found block! thread={id}");
+                                    if let (false, Some(log)) = (prioritize_submission, &solution_log) {
+                                        log_solution(log, mining_data.lock().await.as_ref(), id, &poke, &hash);
+                                    }
+                                    // Otherwise the submission is on its way before the mining_data
+                                    // lock is taken or the solution log written
+                                    let log_after = (prioritize_submission && solution_log.is_some()).then(|| poke.clone());
+                                    // Submitting retries for a while, so it runs on its own task
+                                    // rather than holding up the other threads' results
+                                    let (handle, secondary) = (handle.clone(), secondary.clone());
+                                    submissions.spawn(async move {
+                                        (id, submit_mined_block(&handle, secondary.as_deref(), poke).await)
+                                    });
+                                    if let (Some(poke), Some(log)) = (log_after, &solution_log) {
+                                        log_solution(log, mining_data.lock().await.as_ref(), id, &poke, &hash);
+                                    }
+
+                                    // launch new attempt
+                                    start_mining_attempt(serf, mining_data.lock().await, &mut mining_attempts, Some(Nonce::from_slab(hash)), id, true).await;
+                                }
+                                HashResult::Miss { hash } => {
+                                    //  launch new attempt, using hash as new nonce
+                                    let log_attempt = attempt_log.sample();
+                                    if log_attempt {
                                         debug!("didn't find block, starting new attempt. thread={id}");
-                                        let mut nonce_slab = NounSlab::new();
-                                        nonce_slab.copy_into(tail);
-                                        start_mining_attempt(serf, mining_data.lock().await, &mut mining_attempts, Some(nonce_slab), id).await;
+                                    }
+                                    start_mining_attempt(serf, mining_data.lock().await, &mut mining_attempts, Some(Nonce::from_slab(hash)), id, log_attempt).await;
+                                }
+                                HashResult::Unexpected { head } => {
+                                    warn!("unexpected mining result {head}, starting new attempt. thread={id}");
+                                    start_mining_attempt(serf, mining_data.lock().await, &mut mining_attempts, None, id, true).await;
+                                }
+                                HashResult::Failed(e) if crate::hash_backend::worker_died(&e) => {
+                                    // The serf is gone, most likely out of memory; the other threads keep mining
+                                    workers_oom += 1;
+                                    cancel_tokens.remove(&id);
+                                    let stack_size = stack_sizes.remove(&id).unwrap_or(NOCK_STACK_SIZE_TINY);
+                                    let Some(smaller) = respawn_stack_size(stack_size) else {
+                                        retired.insert(id);
+                                        error!("mining worker died with a {stack_size} byte stack (likely out of memory), taking it out of rotation. thread={id} workers_oom={workers_oom} retired={}: {e}", retired.len());
+                                        continue;
+                                    };
+                                    error!("mining worker died with a {stack_size} byte stack (likely out of memory), respawning it with {smaller} bytes. thread={id} workers_oom={workers_oom}: {e}");
+                                    match respawn_mining_worker(hot_state.clone(), smaller, test_jets.clone(), mining_data.lock().await, &mut mining_attempts, &mut cancel_tokens, id).await {
+                                        Ok(()) => {
+                                            stack_sizes.insert(id, smaller);
+                                        }
+                                        Err(e) => {
+                                            retired.insert(id);
+                                            error!("could not respawn mining worker, taking it out of rotation. thread={id} retired={}: {e}", retired.len());
+                                        }
+                                    }
+                                }
+                                HashResult::Failed(e) => {
+                                    // The serf survived, but whatever failed may have left it in a bad state
+                                    workers_failed += 1;
+                                    drop(serf);
+                                    cancel_tokens.remove(&id);
+                                    let stack_size = stack_sizes.get(&id).copied().unwrap_or(NOCK_STACK_SIZE_TINY);
+                                    error!("mining attempt failed, respawning the worker. thread={id} workers_failed={workers_failed}: {e:?}");
+                                    if let Err(e) = respawn_mining_worker(hot_state.clone(), stack_size, test_jets.clone(), mining_data.lock().await, &mut mining_attempts, &mut cancel_tokens, id).await {
+                                        retired.insert(id);
+                                        error!("could not respawn mining worker, taking it out of rotation. thread={id} retired={}: {e}", retired.len());
                                     }
                                 }
                             }
                         }
 
-                    effect_res = handle.next_effect() => {
-                        let Ok(effect) = effect_res else {
-                            warn!("Error receiving effect in mining driver: {effect_res:?}");
-                            continue;
+                    submitted = submissions.join_next(), if !submissions.is_empty() => {
+                        match submitted.expect("submissions is not empty") {
+                            Ok((_, Ok(()))) => {}
+                            Ok((id, Err(_))) => error!("Mined block from thread={id} was not submitted"),
+                            Err(e) => error!("Mined block submission task failed: {e}"),
+                        }
+                    }
+
+                    candidate = candidates.next(), if candidates_open => {
+                        let candidate = match candidate {
+                            Ok(candidate) => candidate,
+                            Err(e) => {
+                                error!("lost the node's effects, exiting so the node can be restarted: {e:?}");
+                                for token in cancel_tokens.values() {
+                                    token.cancel();
+                                }
+                                handle.exit.exit(1).await?;
+                                return Err(e);
+                            }
                         };
-                        let Ok(effect_cell) = (unsafe { effect.root().as_cell() }) else {
-                            drop(effect);
+                        let Some(candidate) = candidate else {
+                            debug!("effect stream closed, no more candidates");
+                            candidates_open = false;
                             continue;
                         };
+                        debug!("received new candidate block header: {:?}",
+                            tip5_hash_to_base58(candidate.header())
+                            .expect("Failed to convert header to Base58")
+                        );
+                        match crate::pow_target::difficulty_from_noun(candidate.target()) {
+                            Ok(bits) => info!("candidate difficulty: ~{bits} leading zero bits"),
+                            Err(e) => warn!("could not parse mining target: {e}"),
+                        }
+                        *(mining_data.lock().await) = Some(candidate);
 
-                        if effect_cell.head().eq_bytes("mine") {
-                            let (version_slab, header_slab, target_slab, pow_len) = {
-                                let [version, commit, target, pow_len_noun] = effect_cell.tail().uncell().expect(
-                                    "Expected three elements in %mine effect",
-                                );
-                                let mut version_slab = NounSlab::new();
-                                version_slab.copy_into(version);
-                                let mut header_slab = NounSlab::new();
-                                header_slab.copy_into(commit);
-                                let mut target_slab = NounSlab::new();
-                                target_slab.copy_into(target);
-                                let pow_len =
-                                    pow_len_noun
-                                        .as_atom()
-                                        .expect("Expected pow-len to be an atom")
-                                        .as_u64()
-                                        .expect("Expected pow-len to be a u64");
-                                (version_slab, header_slab, target_slab, pow_len)
-                            };
-                            debug!("received new candidate block header: {:?}",
-                                tip5_hash_to_base58(*unsafe { header_slab.root() })
-                                .expect("Failed to convert header to Base58")
-                            );
-                            *(mining_data.lock().await) = Some(MiningData {
-                                block_header: header_slab,
-                                version: version_slab,
-                                target: target_slab,
-                                pow_len: pow_len
-                            });
-
-                            // Mining hasn't started yet, so start it
-                            if mining_attempts.is_empty() {
-                                info!("starting mining threads");
-                                for i in 0..num_threads {
-                                    let kernel = Vec::from(KERNEL);
-                                    let serf = SerfThread::<SaveableCheckpoint>::new(
-                                        kernel,
-                                        None,
-                                        hot_state.clone(),
-                                        NOCK_STACK_SIZE_TINY,
-                                        test_jets.clone(),
-                                        false,
-                                    )
+                        // Mining hasn't started yet, so start it
+                        if !started {
+                            started = true;
+                            info!("starting mining threads");
+                            for i in 0..num_threads {
+                                let serf = new_mining_serf(hot_state.clone(), NOCK_STACK_SIZE_TINY, test_jets.clone())
                                     .await
                                     .expect("Could not load mining kernel");
 
-                                    cancel_tokens.push(serf.cancel_token.clone());
+                                cancel_tokens.insert(i, serf.cancel_token.clone());
 
-                                    start_mining_attempt(serf, mining_data.lock().await, &mut mining_attempts, None, i).await;
-                                }
-                                info!("mining threads started with {} threads", num_threads);
-                            } else {
-                                // Mining is already running so cancel all the running attemps
-                                // which are mining on the old block.
-                                debug!("restarting mining attempts with new block header.");
-                                for token in &cancel_tokens {
-                                    token.cancel();
-                                }
+                                start_mining_attempt(serf, mining_data.lock().await, &mut mining_attempts, None, i, true).await;
+                            }
+                            info!("mining threads started with {} threads", num_threads);
+                        } else if retired.len() as u64 >= num_threads {
+                            warn!("every mining thread has been retired, not mining on the new candidate");
+                        } else {
+                            // Mining is already running so cancel all the running attemps
+                            // which are mining on the old block.
+                            debug!("restarting mining attempts with new block header.");
+                            for token in cancel_tokens.values() {
+                                token.cancel();
                             }
                         }
                     }
+
+                    else => return Ok(()),
                 }
             }
         })
     })
 }
 
-fn create_poke(mining_data: &MiningData, nonce: &NounSlab) -> NounSlab {
-    let mut slab = NounSlab::new();
-    let header = slab.copy_into(unsafe { *(mining_data.block_header.root()) });
-    let version = slab.copy_into(unsafe { *(mining_data.version.root()) });
-    let target = slab.copy_into(unsafe { *(mining_data.target.root()) });
-    let nonce = slab.copy_into(unsafe { *(nonce.root()) });
-    let poke_noun = T(
-        &mut slab,
-        &[version, header, nonce, target, D(mining_data.pow_len)],
-    );
-    slab.set_root(poke_noun);
-    slab
-}
-
 #[instrument(skip(handle, pubkey))]
-async fn set_mining_key(
+pub(crate) async fn set_mining_key(
     handle: &NockAppHandle,
     pubkey: String,
 ) -> Result<PokeResult, NockAppError> {
@@ -299,7 +609,7 @@ async fn set_mining_key(
         .await
 }
 
-async fn set_mining_key_advanced(
+pub(crate) async fn set_mining_key_advanced(
     handle: &NockAppHandle,
     configs: Vec<MiningKeyConfig>,
 ) -> Result<PokeResult, NockAppError> {
@@ -340,7 +650,10 @@ async fn set_mining_key_advanced(
 
 //TODO add %set-mining-key-multisig poke
 #[instrument(skip(handle))]
-async fn enable_mining(handle: &NockAppHandle, enable: bool) -> Result<PokeResult, NockAppError> {
+pub(crate) async fn enable_mining(
+    handle: &NockAppHandle,
+    enable: bool,
+) -> Result<PokeResult, NockAppError> {
     let mut enable_mining_slab = NounSlab::new();
     let enable_mining = Atom::from_value(&mut enable_mining_slab, "enable-mining")
         .expect("Failed to create enable-mining atom");
@@ -354,45 +667,379 @@ async fn enable_mining(handle: &NockAppHandle, enable: bool) -> Result<PokeResul
         .await
 }
 
+/// How hard to try submitting a found block before giving up on it
+#[derive(Debug, Clone, Copy)]
+pub struct SubmitRetry {
+    pub max_attempts: u32,
+    /// Delay before the second attempt; doubled after every further failure
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for SubmitRetry {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Submit a %mined poke, retrying failed sends with exponential backoff.
+///
+/// The poke is kept until the node has answered, so a transient failure does not lose
+/// the block. A `Nack` is an answer and is returned as is. Errors only after
+/// `retry.max_attempts` failed sends.
+pub async fn submit_with_retry<F, Fut>(
+    poke_slab: NounSlab,
+    retry: SubmitRetry,
+    mut poke: F,
+) -> Result<PokeResult, NockAppError>
+where
+    F: FnMut(NounSlab) -> Fut,
+    Fut: Future<Output = Result<PokeResult, NockAppError>>,
+{
+    let mut backoff = retry.initial_backoff;
+    let mut attempt = 1;
+    loop {
+        match poke(poke_slab.clone()).await {
+            Ok(result) => return Ok(result),
+            Err(e) if attempt >= retry.max_attempts.max(1) => {
+                error!("Giving up on submitting mined block after {attempt} attempts: {e:?}");
+                return Err(e);
+            }
+            Err(e) => {
+                warn!("Submitting mined block failed (attempt {attempt}), retrying in {backoff:?}: {e:?}");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(retry.max_backoff);
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Submit a found block to the node, and at the same time to `secondary` if there is
+/// one, with the default [`SubmitRetry`] policy for each.
+///
+/// Each target retries on its own, so one being down neither delays nor loses the
+/// submission to the other. Errors only if no target answered.
+pub async fn submit_mined_block(
+    handle: &NockAppHandle,
+    secondary: Option<&NpcSubmitTarget>,
+    poke_slab: NounSlab,
+) -> Result<(), NockAppError> {
+    let primary = submit_with_retry(poke_slab.clone(), SubmitRetry::default(), |slab| {
+        handle.poke(MiningWire::Mined.to_wire(), slab)
+    });
+    let redundant = async move {
+        let target = secondary?;
+        let result =
+            submit_with_retry(poke_slab, SubmitRetry::default(), |slab| target.poke(slab)).await;
+        Some((target.path().display().to_string(), result))
+    };
+    let (primary, redundant) = tokio::join!(primary, redundant);
+    report_submissions(primary, redundant)
+}
+
+/// Log which targets accepted a block. Ok if any target answered, even with a nack.
+fn report_submissions(
+    primary: Result<PokeResult, NockAppError>,
+    secondary: Option<(String, Result<PokeResult, NockAppError>)>,
+) -> Result<(), NockAppError> {
+    let mut answered = false;
+    let mut last_error = None;
+    let results = std::iter::once(("node".to_string(), primary)).chain(secondary);
+    for (target, result) in results {
+        match result {
+            Ok(PokeResult::Ack) => {
+                info!("Mined block accepted by {target}");
+                answered = true;
+            }
+            Ok(PokeResult::Nack) => {
+                warn!("Mined block rejected by {target}");
+                answered = true;
+            }
+            Err(e) => {
+                error!("Mined block could not be submitted to {target}: {e:?}");
+                last_error = Some(e);
+            }
+        }
+    }
+    match last_error {
+        Some(e) if !answered => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Append a found solution to the solution log, if a candidate is being mined.
+///
+/// This doesn't await: a `&NounSlab` held across an await would make the driver's future
+/// not `Send`.
+fn log_solution(
+    log: &SolutionLog,
+    candidate: Option<&Candidate>,
+    id: u64,
+    poke: &NounSlab,
+    hash: &NounSlab,
+) {
+    if let Some(data) = candidate {
+        log.record(id, unsafe { *poke.root() }, data.target(), unsafe {
+            *hash.root()
+        });
+    }
+}
+
+/// A finished attempt of thread `id`: its serf and result, or what it panicked with
+type MiningAttempt = (
+    u64,
+    Result<(SerfThread<SaveableCheckpoint>, HashResult), Box<dyn std::any::Any + Send>>,
+);
+
+/// Boot a fresh serf on a `stack_size` byte stack for thread `id` and start it mining
+async fn respawn_mining_worker(
+    hot_state: Vec<HotEntry>,
+    stack_size: usize,
+    test_jets: Vec<NounSlab>,
+    mining_data: tokio::sync::MutexGuard<'_, Option<Candidate>>,
+    mining_attempts: &mut JoinSet<MiningAttempt>,
+    cancel_tokens: &mut BTreeMap<u64, NockCancelToken>,
+    id: u64,
+) -> Result<(), CrownError> {
+    let serf = new_mining_serf(hot_state, stack_size, test_jets).await?;
+    cancel_tokens.insert(id, serf.cancel_token.clone());
+    start_mining_attempt(serf, mining_data, mining_attempts, None, id, true).await;
+    Ok(())
+}
+
 async fn start_mining_attempt(
     serf: SerfThread<SaveableCheckpoint>,
-    mining_data: tokio::sync::MutexGuard<'_, Option<MiningData>>,
-    mining_attempts: &mut tokio::task::JoinSet<(
-        SerfThread<SaveableCheckpoint>,
-        u64,
-        Result<NounSlab, CrownError>,
-    )>,
-    nonce: Option<NounSlab>,
+    mining_data: tokio::sync::MutexGuard<'_, Option<Candidate>>,
+    mining_attempts: &mut JoinSet<MiningAttempt>,
+    nonce: Option<Nonce>,
     id: u64,
+    log_attempt: bool,
 ) {
-    let nonce = nonce.unwrap_or_else(|| {
-        let mut rng = rand::thread_rng();
-        let mut nonce_slab = NounSlab::new();
-        let mut nonce_cell = Atom::from_value(&mut nonce_slab, rng.gen::<u64>() % PRIME)
-            .expect("Failed to create nonce atom")
-            .as_noun();
-        for _ in 1..5 {
-            let nonce_atom = Atom::from_value(&mut nonce_slab, rng.gen::<u64>() % PRIME)
-                .expect("Failed to create nonce atom")
-                .as_noun();
-            nonce_cell = T(&mut nonce_slab, &[nonce_atom, nonce_cell]);
-        }
-        nonce_slab.set_root(nonce_cell);
-        nonce_slab
-    });
+    let nonce = nonce.unwrap_or_else(|| Nonce::random(&mut rand::thread_rng()));
     let mining_data_ref = mining_data
         .as_ref()
         .expect("Mining data should already be initialized");
-    debug!(
-        "starting mining attempt on thread {:?} on header {:?}with nonce: {:?}",
-        id,
-        tip5_hash_to_base58(*unsafe { mining_data_ref.block_header.root() })
-            .expect("Failed to convert block header to Base58"),
-        tip5_hash_to_base58(*unsafe { nonce.root() }).expect("Failed to convert nonce to Base58"),
-    );
-    let poke_slab = create_poke(mining_data_ref, &nonce);
-    mining_attempts.spawn(async move {
-        let result = serf.poke(MiningWire::Candidate.to_wire(), poke_slab).await;
-        (serf, id, result)
+    if log_attempt {
+        debug!(
+            "starting mining attempt on thread {:?} on header {:?}with nonce: {:?}",
+            id,
+            tip5_hash_to_base58(mining_data_ref.header())
+                .expect("Failed to convert block header to Base58"),
+            tip5_hash_to_base58(nonce.as_noun()).expect("Failed to convert nonce to Base58"),
+        );
+    }
+    let candidate = mining_data_ref.clone();
+    mining_attempts.spawn_blocking(move || {
+        // A panicking jet takes only this attempt down; the serf is dropped with it
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            crate::pow::evaluate(&serf, &candidate, &nonce)
+        }));
+        (id, result.map(|result| (serf, result)))
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn test_respawn_stack_size_halves_to_a_floor() {
+        assert_eq!(
+            respawn_stack_size(NOCK_STACK_SIZE_TINY),
+            Some(NOCK_STACK_SIZE_TINY / 2)
+        );
+        assert_eq!(
+            respawn_stack_size(NOCK_STACK_SIZE_TINY / 2),
+            Some(MIN_RESPAWN_STACK_SIZE)
+        );
+        assert_eq!(respawn_stack_size(MIN_RESPAWN_STACK_SIZE), None);
+    }
+
+    fn fast_retry(max_attempts: u32) -> SubmitRetry {
+        SubmitRetry {
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+        }
+    }
+
+    #[test]
+    fn test_mining_requires_a_key() {
+        let key = |keys: &[&str]| {
+            Some(vec![MiningKeyConfig {
+                share: 1,
+                m: 1,
+                keys: keys.iter().map(|key| key.to_string()).collect(),
+            }])
+        };
+        assert_eq!(
+            mining_keys(None, true).unwrap_err(),
+            MiningSetupError::NoMiningKey
+        );
+        assert_eq!(
+            mining_keys(Some(Vec::new()), true).unwrap_err(),
+            MiningSetupError::NoMiningKey
+        );
+        // "share,m:" parses to a single empty key
+        assert_eq!(
+            mining_keys(key(&[""]), true).unwrap_err(),
+            MiningSetupError::NoMiningKey
+        );
+        assert!(mining_keys(None, false).unwrap().is_none());
+        assert!(mining_keys(key(&[""]), false).unwrap().is_none());
+        assert_eq!(
+            mining_keys(key(&["abc"]), true).unwrap().unwrap()[0].keys,
+            vec!["abc"]
+        );
+        // Keys are still set on a node that isn't mining
+        assert!(mining_keys(key(&["abc"]), false).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_key_config_validation() {
+        let config = |share, m, keys: &[&str]| MiningKeyConfig {
+            share,
+            m,
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+        };
+        assert_eq!(config(3, 2, &["abc", "def"]).validate(), Ok(()));
+        assert_eq!(
+            config(0, 1, &["abc"]).validate(),
+            Err(KeyConfigError::ZeroShare)
+        );
+        assert_eq!(
+            config(1, 0, &["abc"]).validate(),
+            Err(KeyConfigError::ZeroThreshold)
+        );
+        assert_eq!(
+            config(1, 3, &["abc", "def"]).validate(),
+            Err(KeyConfigError::ThresholdAboveKeys { m: 3, keys: 2 })
+        );
+        assert_eq!(
+            config(1, 1, &["abc", ""]).validate(),
+            Err(KeyConfigError::EmptyKey)
+        );
+        // 0, O, I and l are not in the base58 alphabet
+        assert_eq!(
+            config(1, 1, &["abc0"]).validate(),
+            Err(KeyConfigError::InvalidKey("abc0".to_string()))
+        );
+        assert_eq!(
+            config(1, 1, &["abc", "def", "abc"]).validate(),
+            Err(KeyConfigError::DuplicateKey("abc".to_string()))
+        );
+
+        let err = mining_keys(
+            Some(vec![config(1, 1, &["abc"]), config(1, 2, &["def"])]),
+            true,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            MiningSetupError::InvalidKeyConfig {
+                index: 1,
+                error: KeyConfigError::ThresholdAboveKeys { m: 2, keys: 1 }
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "mining key config 2 is invalid: m is 2 but only 1 keys are given"
+        );
+        assert_eq!(
+            mining_keys(
+                Some(vec![config(u64::MAX, 1, &["abc"]), config(1, 1, &["def"])]),
+                true
+            )
+            .unwrap_err(),
+            MiningSetupError::SharesOverflow
+        );
+    }
+
+    #[tokio::test]
+    async fn test_submit_retries_until_poke_succeeds() {
+        let calls = AtomicU32::new(0);
+        let mut slab = NounSlab::new();
+        slab.set_root(D(42));
+
+        let result = submit_with_retry(slab, fast_retry(5), |slab| {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                // The same solution is offered on every attempt
+                assert!(unsafe { slab.root().raw_equals(&D(42)) });
+                if call < 2 {
+                    Err(NockAppError::OtherError)
+                } else {
+                    Ok(PokeResult::Ack)
+                }
+            }
+        })
+        .await;
+
+        assert!(matches!(result, Ok(PokeResult::Ack)));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_attempt_log_sampling() {
+        let sampled = AttemptLog::new(AttemptLogLevel::Sample(3));
+        let logged: Vec<bool> = (0..7).map(|_| sampled.sample()).collect();
+        assert_eq!(logged, [true, false, false, true, false, false, true]);
+        assert!(!AttemptLog::new(AttemptLogLevel::Changes).sample());
+        assert!(AttemptLog::new(AttemptLogLevel::All).sample());
+
+        assert_eq!(
+            "changes".parse::<AttemptLogLevel>(),
+            Ok(AttemptLogLevel::Changes)
+        );
+        assert_eq!("all".parse::<AttemptLogLevel>(), Ok(AttemptLogLevel::All));
+        assert_eq!(
+            "50".parse::<AttemptLogLevel>(),
+            Ok(AttemptLogLevel::Sample(50))
+        );
+        assert!("0".parse::<AttemptLogLevel>().is_err());
+        assert!("verbose".parse::<AttemptLogLevel>().is_err());
+    }
+
+    #[test]
+    fn test_report_submissions_needs_one_answer() {
+        let secondary = |result| Some(("npc.sock".to_string(), result));
+        assert!(report_submissions(Ok(PokeResult::Ack), None).is_ok());
+        assert!(report_submissions(Err(NockAppError::OtherError), None).is_err());
+        // Either node being down does not fail the submission
+        assert!(report_submissions(
+            Err(NockAppError::OtherError),
+            secondary(Ok(PokeResult::Ack))
+        )
+        .is_ok());
+        assert!(report_submissions(
+            Ok(PokeResult::Nack),
+            secondary(Err(NockAppError::OtherError))
+        )
+        .is_ok());
+        assert!(report_submissions(
+            Err(NockAppError::OtherError),
+            secondary(Err(NockAppError::OtherError))
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_submit_gives_up_after_max_attempts() {
+        let calls = AtomicU32::new(0);
+        let result = submit_with_retry(NounSlab::new(), fast_retry(3), |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(NockAppError::OtherError) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,209 @@
+//! Mining several chains from one rig.
+//!
+//! For merge-mining style setups a driver can take candidates from more than one
+//! [`CandidateSource`], one per chain, and split its workers between the chains by weight.
+//! [`assign_workers`] decides which chain each worker mines, [`ChainSources`] waits on
+//! every chain's source at once and says which one a candidate came from, and
+//! [`ChainStats`] counts each chain's candidates, hashes and solutions.
+//!
+//! Workers are interleaved rather than handed out in blocks, so every prefix of the
+//! worker ids is split close to the weights; a driver that only runs its first few
+//! workers, e.g. while auto-tuning the thread count, still mines every chain.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::future::{BoxFuture, FutureExt};
+use nockapp::NockAppError;
+use serde::Serialize;
+
+use crate::candidate_source::CandidateSource;
+use crate::hash_backend::Candidate;
+
+/// The chain each of `workers` workers mines, by index into `weights`, splitting them in
+/// proportion to the weights. `None` if no chain has a weight above zero.
+pub fn assign_workers(weights: &[u32], workers: u64) -> Option<Vec<usize>> {
+    let total: i64 = weights.iter().map(|&weight| weight as i64).sum();
+    if total == 0 {
+        return None;
+    }
+    // Smooth weighted round robin: each worker goes to the chain furthest behind its share
+    let mut credit = vec![0i64; weights.len()];
+    let assignment = (0..workers)
+        .map(|_| {
+            for (credit, &weight) in credit.iter_mut().zip(weights) {
+                *credit += weight as i64;
+            }
+            let chain = (0..weights.len())
+                .reduce(|best, chain| {
+                    if credit[chain] > credit[best] {
+                        chain
+                    } else {
+                        best
+                    }
+                })
+                .expect("some chain has a weight");
+            credit[chain] -= total;
+            chain
+        })
+        .collect();
+    Some(assignment)
+}
+
+/// Every chain's [`CandidateSource`], read as one stream of `(chain, candidate)`
+pub struct ChainSources<'a> {
+    /// By chain; `None` once a source is exhausted
+    sources: Vec<Option<Box<dyn CandidateSource + 'a>>>,
+}
+
+impl<'a> ChainSources<'a> {
+    /// Chain `i` reads from `sources[i]`
+    pub fn new(sources: Vec<Box<dyn CandidateSource + 'a>>) -> Self {
+        Self {
+            sources: sources.into_iter().map(Some).collect(),
+        }
+    }
+
+    /// Wait for the next candidate from any chain. A chain whose source is exhausted is
+    /// dropped and the rest are still read; `None` once all of them are. An error from any
+    /// source is fatal, as it is for a single source.
+    ///
+    /// Like [`CandidateSource::next`], this may be dropped whenever something else is
+    /// ready first, and only drops the sources' own futures.
+    pub fn next_candidate(
+        &mut self,
+    ) -> BoxFuture<'_, Result<Option<(usize, Candidate)>, NockAppError>> {
+        Box::pin(async move {
+            loop {
+                let pending: Vec<_> = self
+                    .sources
+                    .iter_mut()
+                    .enumerate()
+                    .filter_map(|(chain, source)| {
+                        let source = source.as_mut()?;
+                        Some(source.next().map(move |next| (chain, next)))
+                    })
+                    .collect();
+                if pending.is_empty() {
+                    return Ok(None);
+                }
+                let ((chain, next), _, _) = futures::future::select_all(pending).await;
+                match next? {
+                    Some(candidate) => return Ok(Some((chain, candidate))),
+                    None => self.sources[chain] = None,
+                }
+            }
+        })
+    }
+
+    /// Whether chain `chain`'s source can still yield candidates
+    pub fn is_open(&self, chain: usize) -> bool {
+        self.sources.get(chain).is_some_and(Option::is_some)
+    }
+}
+
+/// One chain's counters, shared with the driver's stats
+#[derive(Debug)]
+pub struct ChainStats {
+    pub name: String,
+    pub weight: u32,
+    /// Workers assigned to the chain
+    pub workers: u64,
+    /// Candidates the chain's source yielded
+    pub candidates: AtomicU64,
+    pub hashes: AtomicU64,
+    pub solutions: AtomicU64,
+    /// Leading zero bits the chain's current candidate demands
+    pub current_difficulty: AtomicU64,
+}
+
+impl ChainStats {
+    pub fn new(name: impl Into<String>, weight: u32, workers: u64) -> Self {
+        Self {
+            name: name.into(),
+            weight,
+            workers,
+            candidates: AtomicU64::new(0),
+            hashes: AtomicU64::new(0),
+            solutions: AtomicU64::new(0),
+            current_difficulty: AtomicU64::new(0),
+        }
+    }
+
+    pub fn snapshot(&self) -> ChainSnapshot {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        ChainSnapshot {
+            name: self.name.clone(),
+            weight: self.weight,
+            workers: self.workers,
+            candidates: load(&self.candidates),
+            hashes: load(&self.hashes),
+            solutions: load(&self.solutions),
+            current_difficulty: load(&self.current_difficulty),
+        }
+    }
+}
+
+/// Point-in-time copy of a [`ChainStats`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChainSnapshot {
+    pub name: String,
+    pub weight: u32,
+    pub workers: u64,
+    pub candidates: u64,
+    pub hashes: u64,
+    pub solutions: u64,
+    pub current_difficulty: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn candidate(pow_len: u64) -> Candidate {
+        Candidate::from_parts(2, [1, 2, 3, 4, 5], &[1000], pow_len)
+    }
+
+    #[test]
+    fn test_assign_workers_interleaves_by_weight() {
+        let assignment = assign_workers(&[3, 1], 8).unwrap();
+        assert_eq!(assignment, vec![0, 0, 1, 0, 0, 0, 1, 0]);
+        // Every prefix is within one worker of the weights
+        for len in 1..=assignment.len() {
+            let second = assignment[..len]
+                .iter()
+                .filter(|&&chain| chain == 1)
+                .count();
+            assert!(second.abs_diff(len / 4) <= 1, "first {len} workers");
+        }
+
+        assert_eq!(assign_workers(&[1, 0, 1], 4), Some(vec![0, 2, 0, 2]));
+        assert_eq!(assign_workers(&[5], 3), Some(vec![0, 0, 0]));
+        assert_eq!(assign_workers(&[0, 0], 4), None);
+        assert_eq!(assign_workers(&[], 4), None);
+    }
+
+    #[tokio::test]
+    async fn test_chain_sources_tag_and_outlive_exhausted_chains() {
+        let (first_tx, first) = mpsc::channel(4);
+        let (second_tx, second) = mpsc::channel(4);
+        let mut sources = ChainSources::new(vec![Box::new(first), Box::new(second)]);
+
+        second_tx.send(candidate(2)).await.unwrap();
+        let (chain, next) = sources.next_candidate().await.unwrap().unwrap();
+        assert_eq!((chain, next.pow_len()), (1, 2));
+
+        // The first chain runs dry; the second is still read
+        drop(first_tx);
+        second_tx.send(candidate(3)).await.unwrap();
+        let (chain, next) = sources.next_candidate().await.unwrap().unwrap();
+        assert_eq!((chain, next.pow_len()), (1, 3));
+        assert!(!sources.is_open(0));
+        assert!(sources.is_open(1));
+
+        drop(second_tx);
+        assert!(sources.next_candidate().await.unwrap().is_none());
+        assert!(!sources.is_open(1));
+    }
+}
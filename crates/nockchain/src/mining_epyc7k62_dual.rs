@@ -1,27 +1,30 @@
-use std::sync::{Arc, Mutex};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::thread;
 use std::time::{Duration, Instant};
+
 use libc::{cpu_set_t, sched_setaffinity, CPU_SET, CPU_ZERO};
+use rand::Rng;
+
+use crate::control::EffectiveConfig;
+use crate::hashrate::HashRateFormat;
+use crate::memlock;
+use crate::solution_rate::{SolutionRate, SOLUTION_RATE_WINDOW};
+use crate::topology::{cpu_in_domains, host_topology, interleave_memory, NumaNode, Topology};
 
 // EPYC 7K62*2双路专用优化常量
-const EPYC_7K62_CORES_PER_SOCKET: usize = 48;
 const EPYC_7K62_THREADS_PER_SOCKET: usize = 96;
 const TOTAL_SOCKETS: usize = 2;
-const TOTAL_CORES: usize = EPYC_7K62_CORES_PER_SOCKET * TOTAL_SOCKETS; // 96核
 const TOTAL_THREADS: usize = EPYC_7K62_THREADS_PER_SOCKET * TOTAL_SOCKETS; // 192线程
 const MINING_THREADS: usize = 188; // 保留4个线程给系统
 const STACK_SIZE_7K62: usize = 4 * 1024 * 1024; // 4MB栈，DDR4优化
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(10); // drain时检查线程是否结束的间隔
 const ZEN3_CACHE_LINE: usize = 64;
-
-// Zen 3架构双路NUMA优化
-const ZEN3_CCX_SIZE: usize = 8; // Zen 3每个CCX 8核
-const ZEN3_CCD_SIZE: usize = 8; // 每个CCD 8核
-const EPYC_7K62_CCDS_PER_SOCKET: usize = 6; // 每个插槽6个CCD
-const TOTAL_CCDS: usize = EPYC_7K62_CCDS_PER_SOCKET * TOTAL_SOCKETS; // 总共12个CCD
+const SOCKET_BUFFER_LEN: usize = 64; // Socket本地缓冲区的u64个数
 
 // NUMA节点配置
-const NUMA_NODES: usize = 2; // 双路系统2个NUMA节点
+const NUMA_NODES: usize = 2; // NPS1下双路系统2个NUMA节点，实际数量从sysfs读取
 
 #[repr(align(64))] // CPU缓存行对齐
 pub struct DualSocketMiningConfig {
@@ -32,6 +35,36 @@ pub struct DualSocketMiningConfig {
     pub cross_socket_balancing: bool,
     pub zen3_cache_optimization: bool,
     pub threads_per_socket: usize,
+    pub topology_report_path: Option<PathBuf>, // 启动时写入JSON拓扑报告的路径
+    pub lock_memory: bool,                     // 分配后用mlock锁定每个线程的挖矿缓冲区，避免被换出
+    pub restart_jitter_percent: u32, // 每个线程的重启间隔随机偏移±该百分比，避免所有线程同时重启
+    pub yield_policy: YieldPolicy,   // 挖矿循环定期让出CPU的方式，默认不让出
+    pub reserved_cpus: Vec<usize>,   // 不绑定挖矿线程的逻辑CPU，留给系统和网卡中断；需要已知拓扑
+    pub numa_interleave_weights: Vec<u8>, // 按NUMA节点编号的内存交错权重，空为均匀交错；权重不同时需Linux 6.9+，否则退回均匀交错
+    pub solution_rate_window: Duration,   // solutions_per_hour()统计的滑动窗口
+    pub hashrate_format: HashRateFormat,  // 性能日志中算力的单位和小数位数，默认按大小自动选择单位
+}
+
+/// 挖矿循环每隔固定迭代次数如何让出CPU
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum YieldPolicy {
+    /// 不让出，适合专用矿机
+    #[default]
+    None,
+    /// 休眠指定的纳秒数，适合与其他负载共用的机器
+    Sleep(u64),
+    /// 发出自旋等待提示（x86上为PAUSE），不进入调度器
+    SpinHint,
+}
+
+impl YieldPolicy {
+    fn apply(self) {
+        match self {
+            YieldPolicy::None => {}
+            YieldPolicy::Sleep(nanos) => thread::sleep(Duration::from_nanos(nanos)),
+            YieldPolicy::SpinHint => std::hint::spin_loop(),
+        }
+    }
 }
 
 impl Default for DualSocketMiningConfig {
@@ -44,38 +77,123 @@ impl Default for DualSocketMiningConfig {
             cross_socket_balancing: true,
             zen3_cache_optimization: true,
             threads_per_socket: MINING_THREADS / TOTAL_SOCKETS,
+            topology_report_path: None,
+            lock_memory: false,
+            restart_jitter_percent: 10,
+            yield_policy: YieldPolicy::None,
+            reserved_cpus: Vec::new(),
+            numa_interleave_weights: Vec::new(),
+            solution_rate_window: SOLUTION_RATE_WINDOW,
+            hashrate_format: HashRateFormat::default(),
         }
     }
 }
 
+impl DualSocketMiningConfig {
+    /// 挖矿线程是否把内存优先分配到本地NUMA域
+    ///
+    /// 设置了交错权重时线程沿用进程的加权交错策略，否则线程级的MPOL_PREFERRED会覆盖它，权重就不起作用了。
+    fn prefer_local_memory(&self) -> bool {
+        !self.numa_optimization || self.numa_interleave_weights.is_empty()
+    }
+}
+
+/// 独占一条缓存行的计数器，相邻分片不会共享缓存行
+#[repr(align(64))]
+#[derive(Default)]
+struct PaddedCounter(AtomicU64);
+
+/// 按NUMA节点分片的计数器
+///
+/// 单个原子计数器即使缓存行对齐，两路的线程每次`fetch_add`仍会让这条缓存行在插槽间来回传递。
+/// 分片后热路径上每个线程只写本节点的分片，读取时再求和。
+pub struct NodeShardedCounter {
+    shards: Vec<PaddedCounter>,
+}
+
+impl NodeShardedCounter {
+    /// `nodes`个分片，至少一个
+    pub fn new(nodes: usize) -> Self {
+        Self {
+            shards: (0..nodes.max(1))
+                .map(|_| PaddedCounter::default())
+                .collect(),
+        }
+    }
+
+    /// 计入`node`的分片；超出范围的节点号取模落到已有分片上
+    pub fn add(&self, node: usize, n: u64) {
+        self.shards[node % self.shards.len()]
+            .0
+            .fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// 所有分片之和
+    pub fn sum(&self) -> u64 {
+        self.shards
+            .iter()
+            .map(|shard| shard.0.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// 单个节点分片的值
+    pub fn node(&self, node: usize) -> u64 {
+        self.shards
+            .get(node)
+            .map_or(0, |shard| shard.0.load(Ordering::Relaxed))
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.shards.len()
+    }
+}
+
 #[repr(align(64))]
 pub struct DualSocketMiningStats {
     pub hash_rate_socket0: AtomicU64,
-    pub hash_rate_socket1: AtomicU64,
-    pub total_hash_rate: AtomicU64,
+    pub hash_rate_socket1: AtomicU64, // 总算力不单独存储，由computed_total()读取时求和
     pub solutions_found: AtomicU64,
     pub threads_active: AtomicU64,
     pub numa_balance_ratio: AtomicU64, // Socket0/Socket1的负载比例
     pub cross_socket_migrations: AtomicU64,
-    pub zen3_cache_hits: AtomicU64,
+    pub zen3_cache_hits: NodeShardedCounter, // 每次迭代都会计数，按NUMA节点分片
+    pub hashes: NodeShardedCounter,          // 累计哈希数，按NUMA节点分片
+    pub recent_solutions: SolutionRate,      // 最近找到解的时间，用于计算滑动窗口内的出块率
+}
+
+impl Default for DualSocketMiningStats {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DualSocketMiningStats {
     pub fn new() -> Self {
+        Self::with_nodes(NUMA_NODES)
+    }
+
+    /// 热点计数器按`nodes`个NUMA节点分片
+    pub fn with_nodes(nodes: usize) -> Self {
         Self {
             hash_rate_socket0: AtomicU64::new(0),
             hash_rate_socket1: AtomicU64::new(0),
-            total_hash_rate: AtomicU64::new(0),
             solutions_found: AtomicU64::new(0),
             threads_active: AtomicU64::new(0),
             numa_balance_ratio: AtomicU64::new(100), // 初始100%表示平衡
             cross_socket_migrations: AtomicU64::new(0),
-            zen3_cache_hits: AtomicU64::new(0),
+            zen3_cache_hits: NodeShardedCounter::new(nodes),
+            hashes: NodeShardedCounter::new(nodes),
+            recent_solutions: SolutionRate::default(),
         }
     }
 
-    pub fn get_total_hash_rate(&self) -> u64 {
-        self.total_hash_rate.load(Ordering::Relaxed)
+    /// 读取时对各Socket的当前算力求和
+    ///
+    /// 不存储总算力：只有一路更新时，存储的总和会与两路之和不一致。
+    pub fn computed_total(&self) -> u64 {
+        (0..TOTAL_SOCKETS)
+            .map(|socket| self.get_socket_hash_rate(socket))
+            .sum()
     }
 
     pub fn get_socket_hash_rate(&self, socket: usize) -> u64 {
@@ -92,85 +210,213 @@ impl DualSocketMiningStats {
             1 => self.hash_rate_socket1.store(rate, Ordering::Relaxed),
             _ => {}
         }
-        
-        let total = self.hash_rate_socket0.load(Ordering::Relaxed) + 
-                   self.hash_rate_socket1.load(Ordering::Relaxed);
-        self.total_hash_rate.store(total, Ordering::Relaxed);
     }
 
     pub fn increment_solutions(&self) {
         self.solutions_found.fetch_add(1, Ordering::Relaxed);
+        self.recent_solutions.record(Instant::now());
+    }
+
+    /// 滑动窗口内平均每小时找到的解数，反映最近的"运气"，`solutions_found`只会增长看不出来
+    pub fn solutions_per_hour(&self) -> f64 {
+        self.recent_solutions.per_hour(Instant::now())
     }
 
     pub fn get_numa_balance_ratio(&self) -> f64 {
         let socket0_rate = self.hash_rate_socket0.load(Ordering::Relaxed);
         let socket1_rate = self.hash_rate_socket1.load(Ordering::Relaxed);
-        
+
         if socket1_rate == 0 {
             return 0.0;
         }
-        
+
         (socket0_rate as f64 / socket1_rate as f64) * 100.0
     }
 }
 
+/// `drain`的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrainResult {
+    /// 所有线程都在超时前完成了当前批次
+    Completed,
+    /// 超时时仍有线程在运行
+    TimedOut { remaining: usize },
+}
+
 pub struct DualSocketMiner {
     config: DualSocketMiningConfig,
     stats: Arc<DualSocketMiningStats>,
     should_stop: Arc<AtomicBool>,
-    mining_handles: Vec<thread::JoinHandle<()>>,
+    stopped: AtomicBool, // stop()已执行过，之后的调用（包括Drop）直接返回
+    mining_handles: Mutex<Vec<thread::JoinHandle<()>>>,
     numa_topology: NumaTopology,
+    topology: Option<Topology>, // None表示无法读取sysfs，使用默认双路布局
+}
+
+/// 每路可用的逻辑CPU数，两路不同时取较少的一路；读不到拓扑或只有一路时按总CPU数平分
+fn cpus_per_socket(topology: Option<&Topology>) -> usize {
+    let Some(topology) = topology else {
+        return num_cpus::get() / TOTAL_SOCKETS;
+    };
+    let per_socket: Vec<usize> = (0..TOTAL_SOCKETS)
+        .map(|socket| {
+            topology
+                .socket_domains(socket)
+                .iter()
+                .map(|domain| domain.cpus.len())
+                .sum()
+        })
+        .collect();
+    if per_socket.contains(&0) {
+        return topology.logical_cpus() / TOTAL_SOCKETS;
+    }
+    per_socket.into_iter().min().unwrap_or(0)
 }
 
 #[derive(Debug, Clone)]
 struct NumaTopology {
-    socket_cpu_ranges: Vec<(usize, usize)>, // (start_cpu, end_cpu) for each socket
-    numa_memory_nodes: Vec<usize>,
+    socket_domains: Vec<Vec<NumaNode>>, // 每个Socket上的NUMA域（NPS2/NPS4下每个Socket有多个域）
+}
+
+impl NumaTopology {
+    /// 第`thread_id`个线程在该Socket上的(CPU, NUMA节点)，线程轮流分配到各NUMA域
+    fn placement(&self, socket: usize, thread_id: usize) -> (usize, usize) {
+        cpu_in_domains(&self.socket_domains[socket], thread_id)
+            .unwrap_or((socket * EPYC_7K62_THREADS_PER_SOCKET + thread_id, socket))
+    }
 }
 
 impl DualSocketMiner {
     pub fn new(config: DualSocketMiningConfig) -> Result<Self, Box<dyn std::error::Error>> {
-        let numa_topology = Self::detect_numa_topology()?;
-        
-        Ok(Self {
+        Ok(Self::with_topology(config, host_topology().cloned()))
+    }
+
+    /// 使用指定拓扑创建矿工，测试和CI可传入`Topology::synthetic`而不依赖真实sysfs
+    pub fn with_topology(mut config: DualSocketMiningConfig, topology: Option<Topology>) -> Self {
+        // 保留的CPU从拓扑中去掉，之后的线程分配都不会用到它们
+        let topology = topology.map(|topology| topology.without_cpus(&config.reserved_cpus));
+        // 每路线程数超过该路的CPU时，多出的线程会和其他线程挤在同一个CPU上
+        let available = cpus_per_socket(topology.as_ref());
+        if available > 0 && config.threads_per_socket > available {
+            eprintln!(
+                "警告: 每路线程数{}超过每路可用的{}个CPU，已缩减为{}以免超额占用",
+                config.threads_per_socket, available, available
+            );
+            config.threads_per_socket = available;
+        }
+        let numa_topology = Self::detect_numa_topology(topology.as_ref());
+        // NUMA节点号可能不连续，按最大节点号分片
+        let nodes = topology
+            .as_ref()
+            .and_then(|topology| topology.numa_nodes.iter().map(|node| node.id + 1).max())
+            .unwrap_or(NUMA_NODES);
+        let stats = DualSocketMiningStats {
+            recent_solutions: SolutionRate::new(config.solution_rate_window),
+            ..DualSocketMiningStats::with_nodes(nodes)
+        };
+
+        Self {
             config,
-            stats: Arc::new(DualSocketMiningStats::new()),
+            stats: Arc::new(stats),
             should_stop: Arc::new(AtomicBool::new(false)),
-            mining_handles: Vec::new(),
+            stopped: AtomicBool::new(false),
+            mining_handles: Mutex::new(Vec::new()),
             numa_topology,
-        })
+            topology,
+        }
+    }
+
+    /// 每个挖矿线程（按全局线程ID）将绑定的(逻辑CPU, NUMA节点)
+    pub fn cpu_assignment(&self) -> Vec<(usize, usize)> {
+        (0..TOTAL_SOCKETS)
+            .flat_map(|socket| {
+                (0..self.config.threads_per_socket)
+                    .map(move |thread_id| self.numa_topology.placement(socket, thread_id))
+            })
+            .collect()
     }
 
     /// 检测NUMA拓扑结构
-    fn detect_numa_topology() -> Result<NumaTopology, Box<dyn std::error::Error>> {
-        // 对于EPYC 7K62*2，通常的拓扑是：
+    fn detect_numa_topology(topology: Option<&Topology>) -> NumaTopology {
+        // 从sysfs读取实际的NUMA域，NPS设置决定每个Socket有几个域
+        if let Some(topology) = topology {
+            let socket_domains: Vec<Vec<NumaNode>> = (0..TOTAL_SOCKETS)
+                .map(|socket| topology.socket_domains(socket))
+                .collect();
+            if socket_domains.iter().all(|domains| !domains.is_empty()) {
+                println!("🔍 检测到双路NUMA拓扑 ({}):", topology.nps_mode());
+                for (socket, domains) in socket_domains.iter().enumerate() {
+                    let ids: Vec<usize> = domains.iter().map(|d| d.id).collect();
+                    println!("  Socket {}: NUMA域 {:?}", socket, ids);
+                }
+                return NumaTopology { socket_domains };
+            }
+        }
+
+        // 无法读取sysfs时按NPS1处理，EPYC 7K62*2通常的拓扑是：
         // Socket 0: CPU 0-95 (物理0-47, 逻辑48-95)
         // Socket 1: CPU 96-191 (物理48-95, 逻辑96-143)
-        
-        let socket_cpu_ranges = vec![
-            (0, 95),   // Socket 0
-            (96, 191), // Socket 1
-        ];
-        
-        let numa_memory_nodes = vec![0, 1];
-        
-        println!("🔍 检测到双路NUMA拓扑:");
+        let socket_domains = (0..TOTAL_SOCKETS)
+            .map(|socket| {
+                let start = socket * EPYC_7K62_THREADS_PER_SOCKET;
+                vec![NumaNode {
+                    id: socket,
+                    cpus: (start..start + EPYC_7K62_THREADS_PER_SOCKET).collect(),
+                }]
+            })
+            .collect();
+
+        println!("🔍 使用默认双路NUMA拓扑:");
         println!("  Socket 0: CPU 0-95");
         println!("  Socket 1: CPU 96-191");
-        
-        Ok(NumaTopology {
-            socket_cpu_ranges,
-            numa_memory_nodes,
-        })
+
+        NumaTopology { socket_domains }
+    }
+
+    /// 当前实际生效的优化名称：按运行时检测结果而非仅按配置，供日志和控制API报告
+    ///
+    /// NUMA和跨Socket优化只在sysfs确认有多个NUMA域或第二个Socket时报告，默认布局只是推测。
+    /// 内存锁定在`start_mining`检查RLIMIT_MEMLOCK之后才反映实际结果。
+    pub fn active_optimizations(&self) -> Vec<&'static str> {
+        let mut active = Vec::new();
+        if let Some(topology) = &self.topology {
+            if self.config.numa_optimization && topology.numa_nodes.len() > 1 {
+                active.push("numa");
+            }
+            if self.config.cross_socket_balancing && !topology.socket_domains(1).is_empty() {
+                active.push("cross-socket-balancing");
+            }
+        }
+        if self.config.zen3_cache_optimization && cfg!(target_arch = "x86_64") {
+            active.push("zen3-prefetch");
+        }
+        if self.config.lock_memory {
+            active.push("memlock");
+        }
+        active
+    }
+
+    /// 检测、裁剪和硬件检查之后实际生效的配置，可序列化后记录或与其他机器对比
+    ///
+    /// 每路线程数在创建时按每路CPU裁剪，`start_mining`还可能按总CPU数再缩减；内存锁定在其后才反映实际结果。
+    pub fn effective_config(&self) -> EffectiveConfig {
+        EffectiveConfig {
+            miner: "epyc-7k62-dual",
+            threads: self.config.threads_per_socket * TOTAL_SOCKETS,
+            optimizations: self.active_optimizations(),
+            reserved_cpus: self.config.reserved_cpus.clone(),
+            stack_size: STACK_SIZE_7K62,
+            backend: "zen3-scalar".to_string(),
+        }
     }
 
     /// 启动双路EPYC 7K62挖矿
     pub fn start_mining(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         println!("🚀 启动EPYC 7K62*2双路挖矿优化...");
-        
+
         // 检测双路配置
         self.verify_dual_socket_config()?;
-        
+
         // 设置NUMA内存策略
         if self.config.numa_optimization {
             self.setup_numa_memory_policy()?;
@@ -181,6 +427,51 @@ impl DualSocketMiner {
             self.start_dual_socket_monitor();
         }
 
+        // 检查RLIMIT_MEMLOCK是否足够锁定所有线程的缓冲区，不足时只警告一次并不锁定继续挖矿
+        if self.config.lock_memory {
+            let threads = self.config.threads_per_socket * TOTAL_SOCKETS;
+            match memlock::check_limit(threads * locked_bytes_per_thread()) {
+                Ok(()) => println!("✅ 挖矿缓冲区将锁定在内存中"),
+                Err(e) => {
+                    eprintln!("警告: 无法锁定挖矿内存，将不锁定继续: {}", e);
+                    self.config.lock_memory = false;
+                }
+            }
+        }
+
+        let mining_cpus: Vec<usize> = self
+            .cpu_assignment()
+            .into_iter()
+            .map(|(cpu, _)| cpu)
+            .collect();
+        // 检查CPU绑定是否真正生效，部分虚拟机会接受但忽略sched_setaffinity
+        let pinning = crate::topology::probe_pinning(&mining_cpus);
+        if !pinning.effective() {
+            eprintln!(
+                "警告: 线程绑定未生效，挖矿线程可能不会固定在指定CPU上: {}",
+                pinning
+            );
+        }
+
+        // 导出拓扑报告
+        if let Some(path) = &self.config.topology_report_path {
+            let result = match &self.topology {
+                Some(topology) => topology
+                    .report(&mining_cpus)
+                    .with_pinning(Some(&pinning))
+                    .write_to(path),
+                None => crate::topology::export_topology_report(path, &mining_cpus, Some(&pinning)),
+            };
+            if let Err(e) = result {
+                eprintln!("警告: 无法写入拓扑报告 {}: {}", path.display(), e);
+            }
+        }
+
+        println!(
+            "📌 挖矿CPU: {:?} (保留: {:?})",
+            mining_cpus, self.config.reserved_cpus
+        );
+
         // 为每个Socket启动挖矿线程组
         for socket in 0..TOTAL_SOCKETS {
             let threads_per_socket = self.config.threads_per_socket;
@@ -192,19 +483,48 @@ impl DualSocketMiner {
             self.start_cross_socket_balancer();
         }
 
-        println!("✅ 双路EPYC 7K62挖矿已启动 - {} 线程激活", MINING_THREADS);
+        println!(
+            "✅ 双路EPYC 7K62挖矿已启动 - {} 线程激活",
+            self.config.threads_per_socket * TOTAL_SOCKETS
+        );
+        println!(
+            "⚙️ 已启用的优化: {}",
+            self.active_optimizations().join(", ")
+        );
+        println!(
+            "⚙️ 实际生效的配置: {}",
+            serde_json::to_string(&self.effective_config()).unwrap_or_default()
+        );
         Ok(())
     }
 
     /// 验证双路配置
-    fn verify_dual_socket_config(&self) -> Result<(), Box<dyn std::error::Error>> {
+    ///
+    /// CPU不足以容纳`threads_per_socket * TOTAL_SOCKETS`个线程时，按可用CPU缩减每路线程数并警告，
+    /// 以便在小型开发机上也能运行双路代码路径。连每路一个线程都放不下时才报错。
+    fn verify_dual_socket_config(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         // 检查CPU数量
-        let cpu_count = num_cpus::get();
-        if cpu_count < TOTAL_THREADS {
-            return Err(format!(
-                "CPU数量不足: 检测到{}个CPU，需要{}个", 
-                cpu_count, TOTAL_THREADS
-            ).into());
+        let cpu_count = match &self.topology {
+            Some(topology) => topology.logical_cpus(),
+            None => num_cpus::get(),
+        };
+        let required = self.config.threads_per_socket * TOTAL_SOCKETS;
+        if cpu_count < required {
+            let threads_per_socket = cpu_count / TOTAL_SOCKETS;
+            if threads_per_socket == 0 {
+                return Err(
+                    format!("CPU数量不足: 检测到{}个CPU，每路至少需要1个", cpu_count).into(),
+                );
+            }
+            eprintln!(
+                "警告: 检测到{}个CPU，不足{}个线程 (完整双路7K62为{}线程)，每路线程数从{}缩减为{}",
+                cpu_count,
+                required,
+                TOTAL_THREADS,
+                self.config.threads_per_socket,
+                threads_per_socket
+            );
+            self.config.threads_per_socket = threads_per_socket;
         }
 
         println!("✅ 双路配置验证通过: {} CPU threads", cpu_count);
@@ -212,37 +532,36 @@ impl DualSocketMiner {
     }
 
     /// 设置NUMA内存策略
+    ///
+    /// 按`numa_interleave_weights`在所有NUMA节点间交错分配内存，两路内存容量或速度不同时可加权。
     fn setup_numa_memory_policy(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // 设置内存交错分配策略，充分利用双通道内存
-        unsafe {
-            // 在Linux上设置NUMA内存策略
-            #[cfg(target_os = "linux")]
-            {
-                // 设置内存交错策略
-                let ret = libc::syscall(libc::SYS_set_mempolicy, 
-                    libc::MPOL_INTERLEAVE, 
-                    std::ptr::null::<libc::c_ulong>(), 
-                    0);
-                    
-                if ret != 0 {
-                    eprintln!("警告: 无法设置NUMA内存策略");
-                }
-            }
+        let nodes: Vec<usize> = match &self.topology {
+            Some(topology) => topology.numa_nodes.iter().map(|node| node.id).collect(),
+            None => (0..NUMA_NODES).collect(),
+        };
+        // 设置内存交错分配策略，充分利用各节点的内存通道
+        match interleave_memory(&nodes, &self.config.numa_interleave_weights) {
+            Ok(policy) => println!("✅ NUMA内存策略: {}", policy),
+            Err(e) => eprintln!("警告: 无法设置NUMA内存策略: {}", e),
+        }
+        if self.config.prefer_local_memory() {
+            println!("   挖矿线程: 优先本地NUMA域 (MPOL_PREFERRED)");
+        } else {
+            println!("   挖矿线程: 沿用加权交错");
         }
-        
-        println!("✅ NUMA内存策略已优化");
         Ok(())
     }
 
     /// 启动Socket级别的挖矿线程组
-    fn start_socket_mining_group(&mut self, socket: usize, thread_count: usize) -> Result<(), Box<dyn std::error::Error>> {
-        let (cpu_start, cpu_end) = self.numa_topology.socket_cpu_ranges[socket];
-        let cpus_per_socket = cpu_end - cpu_start + 1;
-        
+    fn start_socket_mining_group(
+        &mut self,
+        socket: usize,
+        thread_count: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         for thread_id in 0..thread_count {
             let global_thread_id = socket * self.config.threads_per_socket + thread_id;
-            let cpu_id = cpu_start + (thread_id % cpus_per_socket);
-            
+            let (cpu_id, numa_node) = self.numa_topology.placement(socket, thread_id);
+
             let stats = self.stats.clone();
             let should_stop = self.should_stop.clone();
             let config = self.config.clone();
@@ -256,26 +575,26 @@ impl DualSocketMiner {
                         eprintln!("警告: 无法设置CPU亲和性 {}: {}", cpu_id, e);
                     });
 
-                    // 设置NUMA内存亲和性
-                    set_numa_memory_affinity(socket).unwrap_or_else(|e| {
-                        eprintln!("警告: 无法设置NUMA内存亲和性 socket {}: {}", socket, e);
-                    });
+                    // 设置NUMA内存亲和性到CPU所在的NUMA域，设置了交错权重时保留进程的加权交错
+                    if config.prefer_local_memory() {
+                        set_numa_memory_affinity(numa_node).unwrap_or_else(|e| {
+                            eprintln!("警告: 无法设置NUMA内存亲和性 node {}: {}", numa_node, e);
+                        });
+                    }
 
                     // 执行双路优化挖矿
                     dual_socket_mining_loop(
-                        global_thread_id,
-                        socket,
-                        cpu_id,
-                        stats,
-                        should_stop,
-                        config,
+                        global_thread_id, socket, numa_node, cpu_id, stats, should_stop, config,
                     );
                 })?;
 
-            self.mining_handles.push(handle);
+            self.handles().push(handle);
         }
 
-        println!("✅ Socket {} 挖矿线程组已启动 - {} 线程", socket, thread_count);
+        println!(
+            "✅ Socket {} 挖矿线程组已启动 - {} 线程",
+            socket, thread_count
+        );
         Ok(())
     }
 
@@ -283,39 +602,34 @@ impl DualSocketMiner {
     fn start_dual_socket_monitor(&self) {
         let stats = self.stats.clone();
         let should_stop = self.should_stop.clone();
+        let hashrate_format = self.config.hashrate_format;
 
         thread::spawn(move || {
-            let mut last_time = Instant::now();
-            let mut last_operations = [0u64; 2]; // 每个Socket的操作计数
-
             while !should_stop.load(Ordering::Relaxed) {
                 thread::sleep(Duration::from_secs(15));
 
-                let current_time = Instant::now();
-                let elapsed = current_time.duration_since(last_time).as_secs_f64();
-                
                 let socket0_rate = stats.get_socket_hash_rate(0);
                 let socket1_rate = stats.get_socket_hash_rate(1);
-                let total_rate = stats.get_total_hash_rate();
+                let total_rate = stats.computed_total();
                 let balance_ratio = stats.get_numa_balance_ratio();
 
                 println!(
                     "📊 双路EPYC 7K62性能报告:\n\
-                     ├─ 总算力: {:.2} MH/s\n\
-                     ├─ Socket 0: {:.2} MH/s\n\
-                     ├─ Socket 1: {:.2} MH/s\n\
+                     ├─ 总算力: {}\n\
+                     ├─ Socket 0: {}\n\
+                     ├─ Socket 1: {}\n\
                      ├─ 负载平衡: {:.1}%\n\
                      ├─ 活跃线程: {}\n\
-                     └─ 找到解: {}",
-                    total_rate as f64 / 1_000_000.0,
-                    socket0_rate as f64 / 1_000_000.0,
-                    socket1_rate as f64 / 1_000_000.0,
+                     └─ 找到解: {} (最近{:?}内 {:.2}/小时)",
+                    hashrate_format.format(total_rate as f64),
+                    hashrate_format.format(socket0_rate as f64),
+                    hashrate_format.format(socket1_rate as f64),
                     balance_ratio,
                     stats.threads_active.load(Ordering::Relaxed),
-                    stats.solutions_found.load(Ordering::Relaxed)
+                    stats.solutions_found.load(Ordering::Relaxed),
+                    stats.recent_solutions.window(),
+                    stats.solutions_per_hour()
                 );
-
-                last_time = current_time;
             }
         });
     }
@@ -330,29 +644,79 @@ impl DualSocketMiner {
                 thread::sleep(Duration::from_secs(30));
 
                 let balance_ratio = stats.get_numa_balance_ratio();
-                
+
                 // 如果负载不平衡（偏差超过20%），记录并可能调整
-                if balance_ratio < 80.0 || balance_ratio > 120.0 {
+                if !(80.0..=120.0).contains(&balance_ratio) {
                     println!("⚠️  NUMA负载不平衡检测: {:.1}%", balance_ratio);
-                    stats.cross_socket_migrations.fetch_add(1, Ordering::Relaxed);
-                    
+                    stats
+                        .cross_socket_migrations
+                        .fetch_add(1, Ordering::Relaxed);
+
                     // 在实际实现中，这里可以动态调整线程分配
                 }
             }
         });
     }
 
-    pub fn stop_mining(&mut self) {
+    /// 优雅停止：不再开始新的哈希批次，让正在进行的批次在`timeout`内完成后再回收线程
+    ///
+    /// 超时后仍在运行的线程保留在句柄列表中，之后的`stop`或Drop会继续等待它们。
+    pub fn drain(&self, timeout: Duration) -> DrainResult {
+        println!("🛑 排空双路EPYC 7K62挖矿线程 (最多等待 {:?})...", timeout);
+        self.should_stop.store(true, Ordering::Relaxed);
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let mut handles = self.handles();
+            let (finished, running): (Vec<_>, Vec<_>) =
+                handles.drain(..).partition(|handle| handle.is_finished());
+            for handle in finished {
+                let _ = handle.join();
+            }
+            *handles = running;
+
+            if handles.is_empty() {
+                println!("✅ 双路EPYC 7K62挖矿已排空");
+                return DrainResult::Completed;
+            }
+            if Instant::now() >= deadline {
+                println!("⚠️  排空超时: 仍有 {} 个线程未结束", handles.len());
+                return DrainResult::TimedOut {
+                    remaining: handles.len(),
+                };
+            }
+            drop(handles);
+            thread::sleep(DRAIN_POLL_INTERVAL);
+        }
+    }
+
+    /// 停止挖矿并回收所有线程
+    ///
+    /// 可重复调用，也可以与Drop或其他线程的调用并发：只有第一次调用会真正停止，
+    /// 之后的调用等到线程回收完毕后直接返回，不会重复join。
+    pub fn stop(&self) {
+        // 整个过程持有锁：并发的stop会等到线程全部回收后才看到stopped并返回
+        let mut handles = self.handles();
+        if self.stopped.swap(true, Ordering::AcqRel) {
+            return;
+        }
         println!("🛑 停止双路EPYC 7K62挖矿...");
         self.should_stop.store(true, Ordering::Relaxed);
 
-        for handle in self.mining_handles.drain(..) {
+        for handle in handles.drain(..) {
             let _ = handle.join();
         }
 
         println!("✅ 双路EPYC 7K62挖矿已停止");
     }
 
+    /// 线程句柄列表；锁中毒后仍可继续回收线程
+    fn handles(&self) -> MutexGuard<'_, Vec<thread::JoinHandle<()>>> {
+        self.mining_handles
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
     pub fn get_stats(&self) -> &Arc<DualSocketMiningStats> {
         &self.stats
     }
@@ -360,7 +724,7 @@ impl DualSocketMiner {
 
 impl Drop for DualSocketMiner {
     fn drop(&mut self) {
-        self.stop_mining();
+        self.stop();
     }
 }
 
@@ -368,19 +732,33 @@ impl Drop for DualSocketMiner {
 fn dual_socket_mining_loop(
     thread_id: usize,
     socket: usize,
-    cpu_id: usize,
+    numa_node: usize,
+    _cpu_id: usize,
     stats: Arc<DualSocketMiningStats>,
     should_stop: Arc<AtomicBool>,
     config: DualSocketMiningConfig,
 ) {
     stats.threads_active.fetch_add(1, Ordering::Relaxed);
-    
+
     // Zen 3 + 双路特定优化
     let mut zen3_cache_data = vec![0u8; ZEN3_CACHE_LINE * 32]; // 2KB缓存友好数据
-    let mut socket_local_buffer = vec![0u64; 64]; // Socket本地缓冲区
-    
+    let mut socket_local_buffer = vec![0u64; SOCKET_BUFFER_LEN]; // Socket本地缓冲区
+
+    // 锁定必须在缓冲区之后声明，保证先于缓冲区释放时解锁
+    let _locked = if config.lock_memory {
+        lock_buffers(thread_id, &socket_local_buffer, &zen3_cache_data)
+    } else {
+        Vec::new()
+    };
+
     let mut iteration_count = 0u64;
     let mut local_hash_count = 0u64;
+    // 各线程几乎同时启动，不加抖动会在同一时刻一起重启，造成算力周期性骤降
+    let restart_after = jittered_restart_interval(
+        config.candidate_update_interval,
+        config.restart_jitter_percent,
+        &mut rand::thread_rng(),
+    );
     let start_time = Instant::now();
     let mut last_report_time = start_time;
 
@@ -388,11 +766,15 @@ fn dual_socket_mining_loop(
         // 执行Zen 3优化的哈希计算
         zen3_dual_socket_hash(&mut socket_local_buffer, &mut zen3_cache_data, socket);
         local_hash_count += socket_local_buffer.len() as u64;
+        // 只写本节点的分片，避免跨插槽争用缓存行
+        stats
+            .hashes
+            .add(numa_node, socket_local_buffer.len() as u64);
 
         // Zen 3缓存优化
         if config.zen3_cache_optimization {
             zen3_cache_prefetch(&zen3_cache_data, iteration_count);
-            stats.zen3_cache_hits.fetch_add(1, Ordering::Relaxed);
+            stats.zen3_cache_hits.add(numa_node, 1);
         }
 
         iteration_count += 1;
@@ -401,11 +783,12 @@ fn dual_socket_mining_loop(
         if iteration_count % 50000 == 0 {
             let now = Instant::now();
             let elapsed = now.duration_since(last_report_time).as_secs_f64();
-            
-            if elapsed >= 5.0 { // 每5秒报告一次
+
+            if elapsed >= 5.0 {
+                // 每5秒报告一次
                 let hash_rate = (local_hash_count as f64 / elapsed) as u64;
                 stats.update_socket_hash_rate(socket, hash_rate);
-                
+
                 local_hash_count = 0;
                 last_report_time = now;
             }
@@ -414,30 +797,59 @@ fn dual_socket_mining_loop(
         // 定期检查是否需要重启线程
         if config.thread_restart_enabled && iteration_count % 100000 == 0 {
             let elapsed = start_time.elapsed();
-            if elapsed > config.candidate_update_interval {
+            if elapsed > restart_after {
                 break; // 重启线程以获取新的候选区块
             }
         }
 
-        // CPU友好的短暂休眠
+        // 按配置让出CPU，专用矿机默认不休眠
         if iteration_count % 20000 == 0 {
-            thread::sleep(Duration::from_nanos(50));
+            config.yield_policy.apply();
         }
     }
 
     stats.threads_active.fetch_sub(1, Ordering::Relaxed);
 }
 
+/// 在基准重启间隔上随机偏移±`jitter_percent`%（最多100%）
+fn jittered_restart_interval(base: Duration, jitter_percent: u32, rng: &mut impl Rng) -> Duration {
+    let jitter = jitter_percent.min(100) as f64 / 100.0;
+    if jitter == 0.0 {
+        return base;
+    }
+    base.mul_f64(1.0 + rng.gen_range(-jitter..=jitter))
+}
+
+/// 每个挖矿线程锁定的内存上限（按页计算）
+fn locked_bytes_per_thread() -> usize {
+    memlock::footprint(SOCKET_BUFFER_LEN * std::mem::size_of::<u64>())
+        + memlock::footprint(ZEN3_CACHE_LINE * 32)
+}
+
+/// 锁定挖矿缓冲区，失败时只警告，不影响挖矿
+fn lock_buffers(
+    thread_id: usize,
+    socket_buffer: &[u64],
+    cache_data: &[u8],
+) -> Vec<memlock::MemoryLock> {
+    let result =
+        memlock::lock(socket_buffer).and_then(|first| Ok(vec![first, memlock::lock(cache_data)?]));
+    result.unwrap_or_else(|e| {
+        eprintln!("警告: 线程 {} 无法锁定挖矿缓冲区: {}", thread_id, e);
+        Vec::new()
+    })
+}
+
 /// Zen 3双路优化的哈希计算
 fn zen3_dual_socket_hash(buffer: &mut [u64], cache_data: &mut [u8], socket: usize) {
     // 针对Zen 3架构和双路系统的优化哈希计算
     // 这里集成实际的Nockchain哈希算法
-    
+
     for (i, item) in buffer.iter_mut().enumerate() {
         // 使用Socket ID影响计算，确保不同Socket有不同的起始值
         let socket_offset = (socket as u64) << 32;
         *item = (*item).wrapping_add(0x123456789ABCDEF0 + socket_offset + i as u64);
-        
+
         // 模拟缓存友好的内存访问模式
         let cache_index = (i * 8) % cache_data.len();
         cache_data[cache_index] = (*item & 0xFF) as u8;
@@ -449,7 +861,7 @@ fn zen3_cache_prefetch(data: &[u8], iteration: u64) {
     #[cfg(target_arch = "x86_64")]
     unsafe {
         use std::arch::x86_64::*;
-        
+
         let prefetch_offset = (iteration % 32) as usize * ZEN3_CACHE_LINE;
         if prefetch_offset < data.len() {
             // Zen 3优化的预取策略
@@ -464,36 +876,34 @@ fn set_thread_affinity(cpu_id: usize) -> Result<(), Box<dyn std::error::Error>>
         let mut cpu_set: cpu_set_t = std::mem::zeroed();
         CPU_ZERO(&mut cpu_set);
         CPU_SET(cpu_id, &mut cpu_set);
-        
+
         let result = sched_setaffinity(0, std::mem::size_of::<cpu_set_t>(), &cpu_set);
-        
+
         if result != 0 {
             return Err(format!("设置CPU亲和性失败: {}", std::io::Error::last_os_error()).into());
         }
     }
-    
+
     Ok(())
 }
 
 /// 设置NUMA内存亲和性
-fn set_numa_memory_affinity(socket: usize) -> Result<(), Box<dyn std::error::Error>> {
+fn set_numa_memory_affinity(numa_node: usize) -> Result<(), Box<dyn std::error::Error>> {
     #[cfg(target_os = "linux")]
     unsafe {
-        // 设置内存分配优先使用本地Socket的内存
-        let numa_node = socket; // Socket 0 -> NUMA node 0, Socket 1 -> NUMA node 1
-        
+        // 设置内存分配优先使用本地NUMA域的内存
         let ret = libc::syscall(
             libc::SYS_set_mempolicy,
             libc::MPOL_PREFERRED,
             &(1u64 << numa_node) as *const u64,
             64, // max node + 1
         );
-        
+
         if ret != 0 {
-            return Err(format!("设置NUMA内存亲和性失败: socket {}", socket).into());
+            return Err(format!("设置NUMA内存亲和性失败: node {}", numa_node).into());
         }
     }
-    
+
     Ok(())
 }
 
@@ -516,6 +926,372 @@ impl Clone for DualSocketMiningConfig {
             cross_socket_balancing: self.cross_socket_balancing,
             zen3_cache_optimization: self.zen3_cache_optimization,
             threads_per_socket: self.threads_per_socket,
+            topology_report_path: self.topology_report_path.clone(),
+            lock_memory: self.lock_memory,
+            restart_jitter_percent: self.restart_jitter_percent,
+            yield_policy: self.yield_policy,
+            reserved_cpus: self.reserved_cpus.clone(),
+            numa_interleave_weights: self.numa_interleave_weights.clone(),
+            solution_rate_window: self.solution_rate_window,
+            hashrate_format: self.hashrate_format,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yield_policy_defaults_to_no_sleep() {
+        assert_eq!(
+            DualSocketMiningConfig::default().yield_policy,
+            YieldPolicy::None
+        );
+        let started = Instant::now();
+        YieldPolicy::Sleep(2_000_000).apply();
+        assert!(started.elapsed() >= Duration::from_millis(2));
+        YieldPolicy::None.apply();
+        YieldPolicy::SpinHint.apply();
+    }
+
+    #[test]
+    fn test_restart_jitter_stays_within_bounds() {
+        let base = Duration::from_secs(300);
+        let mut rng = rand::thread_rng();
+        assert_eq!(jittered_restart_interval(base, 0, &mut rng), base);
+        let intervals: Vec<Duration> = (0..1000)
+            .map(|_| jittered_restart_interval(base, 10, &mut rng))
+            .collect();
+        assert!(intervals
+            .iter()
+            .all(|d| *d >= Duration::from_secs(270) && *d <= Duration::from_secs(330)));
+        // 抖动必须真正把线程错开
+        assert!(intervals.iter().any(|d| *d != intervals[0]));
+    }
+
+    #[test]
+    fn test_node_sharded_counter_sums_shards() {
+        // 每个分片独占一条缓存行
+        assert_eq!(std::mem::align_of::<PaddedCounter>(), ZEN3_CACHE_LINE);
+        assert_eq!(std::mem::size_of::<PaddedCounter>(), ZEN3_CACHE_LINE);
+
+        let counter = Arc::new(NodeShardedCounter::new(4));
+        let workers: Vec<_> = (0..8)
+            .map(|thread| {
+                let counter = counter.clone();
+                thread::spawn(move || {
+                    for _ in 0..10_000 {
+                        counter.add(thread % 4, 1);
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert_eq!(counter.sum(), 80_000);
+        assert!((0..4).all(|node| counter.node(node) == 20_000));
+        assert_eq!(counter.node(4), 0);
+
+        // 超出范围的节点号不会越界
+        counter.add(6, 5);
+        assert_eq!(counter.node(2), 20_005);
+        assert_eq!(NodeShardedCounter::new(0).nodes(), 1);
+    }
+
+    #[test]
+    fn test_total_hash_rate_matches_sockets() {
+        let stats = Arc::new(DualSocketMiningStats::new());
+        let workers: Vec<_> = (0..4)
+            .map(|thread| {
+                let stats = stats.clone();
+                thread::spawn(move || {
+                    // 各线程交替更新两路，速率互不相同
+                    for i in 0..10_000u64 {
+                        stats.update_socket_hash_rate(
+                            ((thread + i) % 2) as usize,
+                            thread * 1_000_000 + i,
+                        );
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert_eq!(
+            stats.computed_total(),
+            stats.get_socket_hash_rate(0) + stats.get_socket_hash_rate(1)
+        );
+
+        // 只有一路更新时总和随之变化
+        stats.update_socket_hash_rate(0, 7);
+        assert_eq!(stats.computed_total(), 7 + stats.get_socket_hash_rate(1));
+        stats.update_socket_hash_rate(1, 5);
+        assert_eq!(stats.computed_total(), 12);
+        // 不存在的Socket不计入
+        stats.update_socket_hash_rate(2, 100);
+        assert_eq!(stats.computed_total(), 12);
+    }
+
+    #[test]
+    fn test_stats_sharded_by_topology_nodes() {
+        let miner = DualSocketMiner::with_topology(
+            DualSocketMiningConfig::default(),
+            Some(Topology::synthetic(2, 4, 4)),
+        );
+        assert_eq!(miner.get_stats().hashes.nodes(), 4);
+        assert_eq!(miner.get_stats().zen3_cache_hits.nodes(), 4);
+        let miner = DualSocketMiner::with_topology(DualSocketMiningConfig::default(), None);
+        assert_eq!(miner.get_stats().hashes.nodes(), NUMA_NODES);
+    }
+
+    #[test]
+    fn test_socket_groups_pinned_to_local_cpus() {
+        // 双路，每路1个NUMA域，每个域4核8线程
+        let topology = Topology::synthetic(2, 2, 4);
+        let config = DualSocketMiningConfig {
+            threads_per_socket: 8,
+            ..DualSocketMiningConfig::default()
+        };
+        let miner = DualSocketMiner::with_topology(config, Some(topology.clone()));
+        let assignment = miner.cpu_assignment();
+        assert_eq!(assignment.len(), 16);
+
+        for (thread, &(cpu, node)) in assignment.iter().enumerate() {
+            let socket = thread / 8;
+            assert_eq!(topology.cpus[cpu].package, socket);
+            assert_eq!(topology.cpus[cpu].numa_node, node);
+        }
+        let mut cpus: Vec<usize> = assignment.iter().map(|&(cpu, _)| cpu).collect();
+        cpus.sort_unstable();
+        assert_eq!(cpus, (0..16).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_reserved_cpus_excluded_from_sockets() {
+        // Socket 0的CPU 0和8留给网卡中断
+        let config = DualSocketMiningConfig {
+            threads_per_socket: 6,
+            reserved_cpus: vec![0, 8],
+            ..DualSocketMiningConfig::default()
+        };
+        let topology = Topology::synthetic(2, 2, 4);
+        let miner = DualSocketMiner::with_topology(config, Some(topology.clone()));
+        let assignment = miner.cpu_assignment();
+        assert_eq!(assignment.len(), 12);
+        for (thread, &(cpu, _)) in assignment.iter().enumerate() {
+            assert!(
+                cpu != 0 && cpu != 8,
+                "线程 {} 绑定到保留的CPU {}",
+                thread,
+                cpu
+            );
+            assert_eq!(topology.cpus[cpu].package, thread / 6);
+        }
+    }
+
+    #[test]
+    fn test_active_optimizations_follow_topology() {
+        let miner = DualSocketMiner::with_topology(
+            DualSocketMiningConfig::default(),
+            Some(Topology::synthetic(2, 2, 4)),
+        );
+        let active = miner.active_optimizations();
+        assert!(active.contains(&"numa") && active.contains(&"cross-socket-balancing"));
+        assert!(!active.contains(&"memlock"));
+
+        // 单路机器上即使请求了也没有NUMA和跨Socket可优化
+        let miner = DualSocketMiner::with_topology(
+            DualSocketMiningConfig::default(),
+            Some(Topology::synthetic(1, 1, 8)),
+        );
+        let active = miner.active_optimizations();
+        assert!(!active.contains(&"numa") && !active.contains(&"cross-socket-balancing"));
+    }
+
+    #[test]
+    fn test_threads_scaled_down_to_available_cpus() {
+        // 合成拓扑只有16个逻辑CPU，默认的每路94线程放不下
+        let topology = Topology::synthetic(2, 2, 4);
+        let mut miner = DualSocketMiner::with_topology(
+            DualSocketMiningConfig::default(),
+            Some(topology.clone()),
+        );
+        assert!(miner.verify_dual_socket_config().is_ok());
+        assert_eq!(miner.config.threads_per_socket, 8);
+        assert_eq!(miner.cpu_assignment().len(), 16);
+
+        // 放得下时不改动配置
+        let config = DualSocketMiningConfig {
+            threads_per_socket: 4,
+            ..DualSocketMiningConfig::default()
+        };
+        let mut miner = DualSocketMiner::with_topology(config, Some(topology));
+        assert!(miner.verify_dual_socket_config().is_ok());
+        assert_eq!(miner.config.threads_per_socket, 4);
+    }
+
+    #[test]
+    fn test_absurd_threads_per_socket_clamped_on_creation() {
+        let config = DualSocketMiningConfig {
+            threads_per_socket: 100_000,
+            ..DualSocketMiningConfig::default()
+        };
+        let miner = DualSocketMiner::with_topology(config, Some(Topology::synthetic(2, 2, 4)));
+        assert_eq!(miner.config.threads_per_socket, 8);
+        // 每个线程独占一个CPU
+        let mut cpus: Vec<usize> = miner
+            .cpu_assignment()
+            .into_iter()
+            .map(|(cpu, _)| cpu)
+            .collect();
+        cpus.sort_unstable();
+        cpus.dedup();
+        assert_eq!(cpus.len(), 16);
+
+        // 两路可用CPU不同时按较少的一路缩减
+        let config = DualSocketMiningConfig {
+            threads_per_socket: 100_000,
+            reserved_cpus: vec![0, 1, 2],
+            ..DualSocketMiningConfig::default()
+        };
+        let miner = DualSocketMiner::with_topology(config, Some(Topology::synthetic(2, 2, 4)));
+        assert_eq!(miner.config.threads_per_socket, 5);
+    }
+
+    #[test]
+    fn test_effective_config_reports_clamped_threads() {
+        let config = DualSocketMiningConfig {
+            threads_per_socket: 100_000,
+            reserved_cpus: vec![0],
+            ..DualSocketMiningConfig::default()
+        };
+        let miner = DualSocketMiner::with_topology(config, Some(Topology::synthetic(2, 2, 4)));
+        let effective = miner.effective_config();
+        assert_eq!(effective.threads, 14);
+        assert_eq!(effective.reserved_cpus, vec![0]);
+        assert_eq!(effective.stack_size, STACK_SIZE_7K62);
+        assert!(effective.optimizations.contains(&"numa"));
+        let json = serde_json::to_string(&effective).unwrap();
+        assert!(json.contains("\"miner\":\"epyc-7k62-dual\""), "{}", json);
+    }
+
+    #[test]
+    fn test_interleave_weights_keep_worker_policy() {
+        assert!(DualSocketMiningConfig::default().prefer_local_memory());
+        let weighted = DualSocketMiningConfig {
+            numa_interleave_weights: vec![3, 1],
+            ..DualSocketMiningConfig::default()
+        };
+        assert!(!weighted.prefer_local_memory());
+        // 没有启用NUMA优化时不会设置交错，线程仍优先本地内存
+        let weighted = DualSocketMiningConfig {
+            numa_optimization: false,
+            ..weighted
+        };
+        assert!(weighted.prefer_local_memory());
+    }
+
+    #[test]
+    fn test_stop_is_idempotent() {
+        let miner = Arc::new(DualSocketMiner::with_topology(
+            DualSocketMiningConfig::default(),
+            Some(Topology::synthetic(2, 2, 4)),
+        ));
+        let joined = Arc::new(AtomicU64::new(0));
+        for _ in 0..4 {
+            let should_stop = miner.should_stop.clone();
+            let joined = joined.clone();
+            miner.handles().push(thread::spawn(move || {
+                while !should_stop.load(Ordering::Relaxed) {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                joined.fetch_add(1, Ordering::Relaxed);
+            }));
+        }
+
+        // 并发调用都要等到线程回收完毕才返回
+        let stoppers: Vec<_> = (0..2)
+            .map(|_| {
+                let miner = miner.clone();
+                let joined = joined.clone();
+                thread::spawn(move || {
+                    miner.stop();
+                    assert_eq!(joined.load(Ordering::Relaxed), 4);
+                })
+            })
+            .collect();
+        for stopper in stoppers {
+            stopper.join().unwrap();
+        }
+        assert!(miner.handles().is_empty());
+
+        // 再次调用以及Drop中的调用都是空操作
+        miner.stop();
+        drop(miner);
+        assert_eq!(joined.load(Ordering::Relaxed), 4);
+    }
+
+    /// 模拟一个挖矿线程：收到停止信号后再用`batch`跑完当前批次，`release`置位之前不会结束
+    fn spawn_batch_worker(
+        should_stop: Arc<AtomicBool>,
+        batch: Duration,
+        release: Arc<AtomicBool>,
+    ) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            while !should_stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            thread::sleep(batch);
+            while !release.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+        })
+    }
+
+    #[test]
+    fn test_drain_waits_for_in_flight_batch() {
+        let miner = DualSocketMiner::with_topology(
+            DualSocketMiningConfig::default(),
+            Some(Topology::synthetic(2, 2, 4)),
+        );
+        let released = Arc::new(AtomicBool::new(true));
+        miner.handles().push(spawn_batch_worker(
+            miner.should_stop.clone(),
+            Duration::from_millis(50),
+            released,
+        ));
+
+        let started = Instant::now();
+        assert_eq!(miner.drain(Duration::from_secs(10)), DrainResult::Completed);
+        // 当前批次跑完才算排空，而且线程已被回收
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert!(miner.handles().is_empty());
+    }
+
+    #[test]
+    fn test_drain_times_out_on_stuck_batch() {
+        let miner = DualSocketMiner::with_topology(
+            DualSocketMiningConfig::default(),
+            Some(Topology::synthetic(2, 2, 4)),
+        );
+        let release = Arc::new(AtomicBool::new(false));
+        miner.handles().push(spawn_batch_worker(
+            miner.should_stop.clone(),
+            Duration::ZERO,
+            release.clone(),
+        ));
+
+        assert_eq!(
+            miner.drain(Duration::from_millis(50)),
+            DrainResult::TimedOut { remaining: 1 }
+        );
+        // 超时的线程留给stop回收
+        assert_eq!(miner.handles().len(), 1);
+        release.store(true, Ordering::Relaxed);
+        miner.stop();
+        assert!(miner.handles().is_empty());
+    }
+}
@@ -1,20 +1,27 @@
-use std::sync::{Arc, Mutex};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::thread;
 use std::time::{Duration, Instant};
-use tokio::time::interval;
+
 use libc::{cpu_set_t, sched_setaffinity, CPU_SET, CPU_ZERO};
+use rand::Rng;
+
+use crate::control::EffectiveConfig;
+use crate::hashrate::HashRateFormat;
+use crate::memlock;
+use crate::solution_rate::{SolutionRate, SOLUTION_RATE_WINDOW};
+use crate::topology::{assign_workers_strided, host_topology, strided_index, Topology};
 
 // EPYC 9B14专用优化常量
 const EPYC_9B14_CORES: usize = 32;
-const EPYC_9B14_THREADS: usize = 64;
 const MINING_THREADS: usize = 62; // 保留2个线程给系统
 const STACK_SIZE_9B14: usize = 8 * 1024 * 1024; // 8MB栈，利用DDR5高带宽
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(10); // drain时检查线程是否结束的间隔
 const ZEN4_CACHE_LINE: usize = 64;
 const AVX512_BATCH_SIZE: usize = 8; // AVX-512一次处理8个64位数
 
 // Zen 4架构NUMA优化
-const ZEN4_CCX_SIZE: usize = 8; // Zen 4每个CCX 8核
 const ZEN4_CCD_SIZE: usize = 8; // 每个CCD 8核
 const EPYC_9B14_CCDS: usize = 4; // 4个CCD
 
@@ -26,6 +33,36 @@ pub struct EpycMiningConfig {
     pub zen4_optimizations: bool,
     pub avx512_enabled: bool,
     pub ddr5_prefetch: bool,
+    pub topology_report_path: Option<PathBuf>, // 启动时写入JSON拓扑报告的路径
+    pub affinity_stride: usize, // 相邻线程之间的CPU间隔，1为紧密排列，ZEN4_CCX_SIZE可将线程分散到各CCX
+    pub lock_memory: bool,      // 分配后用mlock锁定每个线程的挖矿缓冲区，避免被换出
+    pub restart_jitter_percent: u32, // 每个线程的重启间隔随机偏移±该百分比，避免所有线程同时重启
+    pub yield_policy: YieldPolicy, // 挖矿循环定期让出CPU的方式，默认不让出
+    pub reserved_cpus: Vec<usize>, // 不绑定挖矿线程的逻辑CPU，留给系统和网卡中断；需要已知拓扑
+    pub solution_rate_window: Duration, // solutions_per_hour()统计的滑动窗口
+    pub hashrate_format: HashRateFormat, // 性能日志中算力的单位和小数位数，默认按大小自动选择单位
+}
+
+/// 挖矿循环每隔固定迭代次数如何让出CPU
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum YieldPolicy {
+    /// 不让出，适合专用矿机
+    #[default]
+    None,
+    /// 休眠指定的纳秒数，适合与其他负载共用的机器
+    Sleep(u64),
+    /// 发出自旋等待提示（x86上为PAUSE），不进入调度器
+    SpinHint,
+}
+
+impl YieldPolicy {
+    fn apply(self) {
+        match self {
+            YieldPolicy::None => {}
+            YieldPolicy::Sleep(nanos) => thread::sleep(Duration::from_nanos(nanos)),
+            YieldPolicy::SpinHint => std::hint::spin_loop(),
+        }
+    }
 }
 
 impl Default for EpycMiningConfig {
@@ -37,6 +74,14 @@ impl Default for EpycMiningConfig {
             zen4_optimizations: true,
             avx512_enabled: true,
             ddr5_prefetch: true,
+            topology_report_path: None,
+            affinity_stride: 1,
+            lock_memory: false,
+            restart_jitter_percent: 10,
+            yield_policy: YieldPolicy::None,
+            reserved_cpus: Vec::new(),
+            solution_rate_window: SOLUTION_RATE_WINDOW,
+            hashrate_format: HashRateFormat::default(),
         }
     }
 }
@@ -49,6 +94,13 @@ pub struct EpycMiningStats {
     pub avg_hash_time: AtomicU64,
     pub zen4_cache_hits: AtomicU64,
     pub avx512_operations: AtomicU64,
+    pub recent_solutions: SolutionRate, // 最近找到解的时间，用于计算滑动窗口内的出块率
+}
+
+impl Default for EpycMiningStats {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EpycMiningStats {
@@ -60,6 +112,7 @@ impl EpycMiningStats {
             avg_hash_time: AtomicU64::new(0),
             zen4_cache_hits: AtomicU64::new(0),
             avx512_operations: AtomicU64::new(0),
+            recent_solutions: SolutionRate::default(),
         }
     }
 
@@ -69,6 +122,12 @@ impl EpycMiningStats {
 
     pub fn increment_solutions(&self) {
         self.solutions_found.fetch_add(1, Ordering::Relaxed);
+        self.recent_solutions.record(Instant::now());
+    }
+
+    /// 滑动窗口内平均每小时找到的解数，反映最近的"运气"，`solutions_found`只会增长看不出来
+    pub fn solutions_per_hour(&self) -> f64 {
+        self.recent_solutions.per_hour(Instant::now())
     }
 
     pub fn update_hash_rate(&self, rate: u64) {
@@ -76,30 +135,132 @@ impl EpycMiningStats {
     }
 }
 
+/// `drain`的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrainResult {
+    /// 所有线程都在超时前完成了当前批次
+    Completed,
+    /// 超时时仍有线程在运行
+    TimedOut { remaining: usize },
+}
+
 pub struct EpycMiner {
     config: EpycMiningConfig,
     stats: Arc<EpycMiningStats>,
     should_stop: Arc<AtomicBool>,
-    mining_handles: Vec<thread::JoinHandle<()>>,
+    stopped: AtomicBool, // stop()已执行过，之后的调用（包括Drop）直接返回
+    mining_handles: Mutex<Vec<thread::JoinHandle<()>>>,
+    topology: Option<Topology>, // None表示无法读取sysfs，使用EPYC 9B14默认布局
 }
 
 impl EpycMiner {
     pub fn new(config: EpycMiningConfig) -> Self {
+        Self::with_topology(config, host_topology().cloned())
+    }
+
+    /// 使用指定拓扑创建矿工，测试和CI可传入`Topology::synthetic`而不依赖真实sysfs
+    pub fn with_topology(config: EpycMiningConfig, topology: Option<Topology>) -> Self {
+        // 保留的CPU从拓扑中去掉，之后的线程分配都不会用到它们
+        let topology = topology.map(|topology| topology.without_cpus(&config.reserved_cpus));
+        let stats = EpycMiningStats {
+            recent_solutions: SolutionRate::new(config.solution_rate_window),
+            ..EpycMiningStats::new()
+        };
         Self {
             config,
-            stats: Arc::new(EpycMiningStats::new()),
+            stats: Arc::new(stats),
             should_stop: Arc::new(AtomicBool::new(false)),
-            mining_handles: Vec::new(),
+            stopped: AtomicBool::new(false),
+            mining_handles: Mutex::new(Vec::new()),
+            topology,
+        }
+    }
+
+    /// 每个挖矿线程（按全局线程ID）将绑定的逻辑CPU
+    ///
+    /// 已知拓扑时按`assign_workers_strided`在NUMA域间轮流分配，各域线程数最多相差1。
+    pub fn cpu_assignment(&self) -> Vec<usize> {
+        let threads_per_ccd = MINING_THREADS / EPYC_9B14_CCDS;
+        if let Some(topology) = &self.topology {
+            if !topology.cpu_domains().is_empty() {
+                return assign_workers_strided(
+                    topology,
+                    threads_per_ccd * EPYC_9B14_CCDS,
+                    self.config.affinity_stride,
+                );
+            }
+        }
+        (0..EPYC_9B14_CCDS)
+            .flat_map(|ccd| (0..threads_per_ccd).map(move |t| (ccd, t)))
+            .map(|(ccd, t)| self.calculate_cpu_affinity(ccd, t))
+            .collect()
+    }
+
+    /// 当前实际生效的优化名称：按运行时检测结果而非仅按配置，供日志和控制API报告
+    ///
+    /// 内存锁定在`start_mining`检查RLIMIT_MEMLOCK之后才反映实际结果。
+    pub fn active_optimizations(&self) -> Vec<&'static str> {
+        let mut active = Vec::new();
+        if self.config.avx512_enabled && avx512_detected() {
+            active.push("avx512");
+        }
+        if self.config.zen4_optimizations {
+            active.push("zen4-prefetch");
+        }
+        if self.config.ddr5_prefetch {
+            active.push("ddr5-prefetch");
+        }
+        if self
+            .topology
+            .as_ref()
+            .is_some_and(|topology| topology.numa_nodes.len() > 1)
+        {
+            active.push("numa");
+        }
+        if self.config.lock_memory {
+            active.push("memlock");
+        }
+        active
+    }
+
+    /// 检测、裁剪和硬件检查之后实际生效的配置，可序列化后记录或与其他机器对比
+    ///
+    /// 与`active_optimizations`一样，AVX-512和内存锁定在`start_mining`之后才反映实际结果。
+    pub fn effective_config(&self) -> EffectiveConfig {
+        // 不支持AVX-512时挖矿循环不执行批量哈希
+        let avx512 = self.config.avx512_enabled && avx512_detected();
+        EffectiveConfig {
+            miner: "epyc-9b14",
+            threads: self.cpu_assignment().len(),
+            optimizations: self.active_optimizations(),
+            reserved_cpus: self.config.reserved_cpus.clone(),
+            stack_size: STACK_SIZE_9B14,
+            backend: if avx512 { "avx512" } else { "none" }.to_string(),
         }
     }
 
     /// 启动EPYC 9B14优化挖矿
     pub fn start_mining(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         println!("🚀 启动EPYC 9B14专用挖矿优化...");
-        
+
         // 检测Zen 4特性
         self.detect_zen4_features()?;
-        
+
+        // 没有AVX-512的CPU上执行AVX-512批量哈希会触发非法指令，改为不使用
+        if self.config.avx512_enabled && !avx512_detected() {
+            eprintln!("警告: CPU不支持AVX-512F/DQ/VL，将不使用AVX-512继续");
+            self.config.avx512_enabled = false;
+        }
+
+        // 检测NPS模式
+        if let Some(topology) = &self.topology {
+            println!(
+                "✅ NUMA配置: {} 个NUMA域 ({})",
+                topology.numa_nodes.len(),
+                topology.nps_mode()
+            );
+        }
+
         // 设置内存预取策略
         if self.config.ddr5_prefetch {
             self.setup_ddr5_prefetch()?;
@@ -110,6 +271,47 @@ impl EpycMiner {
             self.start_performance_monitor();
         }
 
+        // 检查RLIMIT_MEMLOCK是否足够锁定所有线程的缓冲区，不足时只警告一次并不锁定继续挖矿
+        if self.config.lock_memory {
+            match memlock::check_limit(MINING_THREADS * locked_bytes_per_thread()) {
+                Ok(()) => println!("✅ 挖矿缓冲区将锁定在内存中"),
+                Err(e) => {
+                    eprintln!("警告: 无法锁定挖矿内存，将不锁定继续: {}", e);
+                    self.config.lock_memory = false;
+                }
+            }
+        }
+
+        // 检查CPU绑定是否真正生效，部分虚拟机会接受但忽略sched_setaffinity
+        let pinning = crate::topology::probe_pinning(&self.cpu_assignment());
+        if !pinning.effective() {
+            eprintln!(
+                "警告: 线程绑定未生效，挖矿线程可能不会固定在指定CPU上: {}",
+                pinning
+            );
+        }
+
+        // 导出拓扑报告
+        if let Some(path) = &self.config.topology_report_path {
+            let assignment = self.cpu_assignment();
+            let result = match &self.topology {
+                Some(topology) => topology
+                    .report(&assignment)
+                    .with_pinning(Some(&pinning))
+                    .write_to(path),
+                None => crate::topology::export_topology_report(path, &assignment, Some(&pinning)),
+            };
+            if let Err(e) = result {
+                eprintln!("警告: 无法写入拓扑报告 {}: {}", path.display(), e);
+            }
+        }
+
+        println!(
+            "📌 挖矿CPU: {:?} (保留: {:?})",
+            self.cpu_assignment(),
+            self.config.reserved_cpus
+        );
+
         // 为每个CCD创建线程组
         for ccd in 0..EPYC_9B14_CCDS {
             let threads_per_ccd = MINING_THREADS / EPYC_9B14_CCDS;
@@ -117,29 +319,36 @@ impl EpycMiner {
         }
 
         println!("✅ EPYC 9B14挖矿已启动 - {} 线程激活", MINING_THREADS);
+        println!(
+            "⚙️ 已启用的优化: {}",
+            self.active_optimizations().join(", ")
+        );
+        println!(
+            "⚙️ 实际生效的配置: {}",
+            serde_json::to_string(&self.effective_config()).unwrap_or_default()
+        );
         Ok(())
     }
 
     /// 检测Zen 4特定功能
     fn detect_zen4_features(&self) -> Result<(), Box<dyn std::error::Error>> {
         // 检测AVX-512支持
-        let cpuid = raw_cpuid::CpuId::new();
-        
-        if let Some(features) = cpuid.get_extended_feature_info() {
-            if features.has_avx512f() {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if std::arch::is_x86_feature_detected!("avx512f") {
                 println!("✅ AVX-512 支持已检测");
             }
-            if features.has_avx512dq() {
+            if std::arch::is_x86_feature_detected!("avx512dq") {
                 println!("✅ AVX-512DQ 支持已检测");
             }
-            if features.has_avx512vl() {
+            if std::arch::is_x86_feature_detected!("avx512vl") {
                 println!("✅ AVX-512VL 支持已检测");
             }
         }
 
         // 检测DDR5内存
         println!("✅ DDR5-4800 内存支持确认");
-        
+
         Ok(())
     }
 
@@ -152,20 +361,25 @@ impl EpycMiner {
             libc::madvise(
                 std::ptr::null_mut(),
                 0,
-                libc::MADV_WILLNEED | libc::MADV_SEQUENTIAL
+                libc::MADV_WILLNEED | libc::MADV_SEQUENTIAL,
             );
         }
-        
+
         println!("✅ DDR5内存预取优化已启用");
         Ok(())
     }
 
     /// 启动CCD级别的挖矿线程组
-    fn start_ccd_mining_group(&mut self, ccd_id: usize, thread_count: usize) -> Result<(), Box<dyn std::error::Error>> {
+    fn start_ccd_mining_group(
+        &mut self,
+        ccd_id: usize,
+        thread_count: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let assignment = self.cpu_assignment();
         for thread_id in 0..thread_count {
             let global_thread_id = ccd_id * (MINING_THREADS / EPYC_9B14_CCDS) + thread_id;
-            let cpu_id = self.calculate_cpu_affinity(ccd_id, thread_id);
-            
+            let cpu_id = assignment[global_thread_id];
+
             let stats = self.stats.clone();
             let should_stop = self.should_stop.clone();
             let config = self.config.clone();
@@ -181,29 +395,29 @@ impl EpycMiner {
 
                     // 执行Zen 4优化挖矿
                     zen4_optimized_mining_loop(
-                        global_thread_id,
-                        ccd_id,
-                        stats,
-                        should_stop,
-                        config,
+                        global_thread_id, ccd_id, stats, should_stop, config,
                     );
                 })?;
 
-            self.mining_handles.push(handle);
+            self.handles().push(handle);
         }
 
         println!("✅ CCD {} 挖矿线程组已启动 - {} 线程", ccd_id, thread_count);
         Ok(())
     }
 
-    /// 计算Zen 4 CCD拓扑的CPU亲和性
+    /// 无法读取拓扑时，按默认Zen 4 CCD布局计算CPU亲和性
     fn calculate_cpu_affinity(&self, ccd_id: usize, thread_id: usize) -> usize {
         // Zen 4 EPYC 9B14拓扑：4个CCD，每个CCD 8核心
         // 物理核心映射：CCD0(0-7), CCD1(8-15), CCD2(16-23), CCD3(24-31)
         // 逻辑核心映射：每个物理核心对应两个逻辑核心
-        
-        let physical_core = ccd_id * ZEN4_CCD_SIZE + (thread_id % ZEN4_CCD_SIZE);
-        
+
+        let physical_core = strided_index(
+            ccd_id * ZEN4_CCD_SIZE + (thread_id % ZEN4_CCD_SIZE),
+            EPYC_9B14_CORES,
+            self.config.affinity_stride,
+        );
+
         // 优先使用物理核心，如果线程数超过物理核心则使用超线程
         if thread_id < ZEN4_CCD_SIZE {
             physical_core // 物理核心
@@ -216,6 +430,7 @@ impl EpycMiner {
     fn start_performance_monitor(&self) {
         let stats = self.stats.clone();
         let should_stop = self.should_stop.clone();
+        let hashrate_format = self.config.hashrate_format;
 
         thread::spawn(move || {
             let mut last_time = Instant::now();
@@ -226,7 +441,7 @@ impl EpycMiner {
 
                 let current_time = Instant::now();
                 let current_operations = stats.avx512_operations.load(Ordering::Relaxed);
-                
+
                 let elapsed = current_time.duration_since(last_time).as_secs_f64();
                 let operations_delta = current_operations.saturating_sub(last_operations);
                 let hash_rate = (operations_delta as f64 / elapsed) as u64;
@@ -234,10 +449,12 @@ impl EpycMiner {
                 stats.update_hash_rate(hash_rate);
 
                 println!(
-                    "📊 EPYC 9B14性能: {:.2} MH/s | AVX-512操作: {} | 活跃线程: {}",
-                    hash_rate as f64 / 1_000_000.0,
+                    "📊 EPYC 9B14性能: {} | AVX-512操作: {} | 活跃线程: {} | 找到解: {} ({:.2}/小时)",
+                    hashrate_format.format(hash_rate as f64),
                     current_operations,
-                    stats.threads_active.load(Ordering::Relaxed)
+                    stats.threads_active.load(Ordering::Relaxed),
+                    stats.solutions_found.load(Ordering::Relaxed),
+                    stats.solutions_per_hour()
                 );
 
                 last_time = current_time;
@@ -246,17 +463,65 @@ impl EpycMiner {
         });
     }
 
-    pub fn stop_mining(&mut self) {
+    /// 优雅停止：不再开始新的哈希批次，让正在进行的批次在`timeout`内完成后再回收线程
+    ///
+    /// 超时后仍在运行的线程保留在句柄列表中，之后的`stop`或Drop会继续等待它们。
+    pub fn drain(&self, timeout: Duration) -> DrainResult {
+        println!("🛑 排空EPYC 9B14挖矿线程 (最多等待 {:?})...", timeout);
+        self.should_stop.store(true, Ordering::Relaxed);
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let mut handles = self.handles();
+            let (finished, running): (Vec<_>, Vec<_>) =
+                handles.drain(..).partition(|handle| handle.is_finished());
+            for handle in finished {
+                let _ = handle.join();
+            }
+            *handles = running;
+
+            if handles.is_empty() {
+                println!("✅ EPYC 9B14挖矿已排空");
+                return DrainResult::Completed;
+            }
+            if Instant::now() >= deadline {
+                println!("⚠️  排空超时: 仍有 {} 个线程未结束", handles.len());
+                return DrainResult::TimedOut {
+                    remaining: handles.len(),
+                };
+            }
+            drop(handles);
+            thread::sleep(DRAIN_POLL_INTERVAL);
+        }
+    }
+
+    /// 停止挖矿并回收所有线程
+    ///
+    /// 可重复调用，也可以与Drop或其他线程的调用并发：只有第一次调用会真正停止，
+    /// 之后的调用等到线程回收完毕后直接返回，不会重复join。
+    pub fn stop(&self) {
+        // 整个过程持有锁：并发的stop会等到线程全部回收后才看到stopped并返回
+        let mut handles = self.handles();
+        if self.stopped.swap(true, Ordering::AcqRel) {
+            return;
+        }
         println!("🛑 停止EPYC 9B14挖矿...");
         self.should_stop.store(true, Ordering::Relaxed);
 
-        for handle in self.mining_handles.drain(..) {
+        for handle in handles.drain(..) {
             let _ = handle.join();
         }
 
         println!("✅ EPYC 9B14挖矿已停止");
     }
 
+    /// 线程句柄列表；锁中毒后仍可继续回收线程
+    fn handles(&self) -> MutexGuard<'_, Vec<thread::JoinHandle<()>>> {
+        self.mining_handles
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
     pub fn get_stats(&self) -> &Arc<EpycMiningStats> {
         &self.stats
     }
@@ -264,32 +529,48 @@ impl EpycMiner {
 
 impl Drop for EpycMiner {
     fn drop(&mut self) {
-        self.stop_mining();
+        self.stop();
     }
 }
 
 /// Zen 4优化的挖矿循环
 fn zen4_optimized_mining_loop(
     thread_id: usize,
-    ccd_id: usize,
+    _ccd_id: usize,
     stats: Arc<EpycMiningStats>,
     should_stop: Arc<AtomicBool>,
     config: EpycMiningConfig,
 ) {
     stats.threads_active.fetch_add(1, Ordering::Relaxed);
-    
+
     // Zen 4特定优化
     let mut avx512_buffer = vec![0u64; AVX512_BATCH_SIZE];
     let mut cache_aligned_data = vec![0u8; ZEN4_CACHE_LINE * 64]; // 4KB缓存友好数据
-    
+
+    // 锁定必须在缓冲区之后声明，保证先于缓冲区释放时解锁
+    let _locked = if config.lock_memory {
+        lock_buffers(thread_id, &avx512_buffer, &cache_aligned_data)
+    } else {
+        Vec::new()
+    };
+
     let mut iteration_count = 0u64;
+    // 各线程几乎同时启动，不加抖动会在同一时刻一起重启，造成算力周期性骤降
+    let restart_after = jittered_restart_interval(
+        config.candidate_update_interval,
+        config.restart_jitter_percent,
+        &mut rand::thread_rng(),
+    );
     let start_time = Instant::now();
 
     while !should_stop.load(Ordering::Relaxed) {
         // AVX-512优化的哈希计算
-        if config.avx512_enabled {
-            zen4_avx512_hash_batch(&mut avx512_buffer, &mut cache_aligned_data);
-            stats.avx512_operations.fetch_add(AVX512_BATCH_SIZE as u64, Ordering::Relaxed);
+        if config.avx512_enabled && avx512_detected() {
+            // SAFETY: 刚确认过CPU支持AVX-512F/DQ/VL
+            unsafe { zen4_avx512_hash_batch(&mut avx512_buffer, &mut cache_aligned_data) };
+            stats
+                .avx512_operations
+                .fetch_add(AVX512_BATCH_SIZE as u64, Ordering::Relaxed);
         }
 
         // Zen 4缓存优化：预取下一批数据
@@ -303,40 +584,82 @@ fn zen4_optimized_mining_loop(
         // 定期检查是否需要重启线程
         if config.thread_restart_enabled && iteration_count % 100000 == 0 {
             let elapsed = start_time.elapsed();
-            if elapsed > config.candidate_update_interval {
+            if elapsed > restart_after {
                 break; // 重启线程以获取新的候选区块
             }
         }
 
-        // CPU友好的短暂休眠
+        // 按配置让出CPU，专用矿机默认不休眠
         if iteration_count % 10000 == 0 {
-            thread::sleep(Duration::from_nanos(100));
+            config.yield_policy.apply();
         }
     }
 
     stats.threads_active.fetch_sub(1, Ordering::Relaxed);
 }
 
+/// 在基准重启间隔上随机偏移±`jitter_percent`%（最多100%）
+fn jittered_restart_interval(base: Duration, jitter_percent: u32, rng: &mut impl Rng) -> Duration {
+    let jitter = jitter_percent.min(100) as f64 / 100.0;
+    if jitter == 0.0 {
+        return base;
+    }
+    base.mul_f64(1.0 + rng.gen_range(-jitter..=jitter))
+}
+
+/// 每个挖矿线程锁定的内存上限（按页计算）
+fn locked_bytes_per_thread() -> usize {
+    memlock::footprint(AVX512_BATCH_SIZE * std::mem::size_of::<u64>())
+        + memlock::footprint(ZEN4_CACHE_LINE * 64)
+}
+
+/// 锁定挖矿缓冲区，失败时只警告，不影响挖矿
+fn lock_buffers(
+    thread_id: usize,
+    avx512_buffer: &[u64],
+    cache_data: &[u8],
+) -> Vec<memlock::MemoryLock> {
+    let result =
+        memlock::lock(avx512_buffer).and_then(|first| Ok(vec![first, memlock::lock(cache_data)?]));
+    result.unwrap_or_else(|e| {
+        eprintln!("警告: 线程 {} 无法锁定挖矿缓冲区: {}", thread_id, e);
+        Vec::new()
+    })
+}
+
+/// CPU是否支持`zen4_avx512_hash_batch`所需的AVX-512F/DQ/VL
+#[cfg(target_arch = "x86_64")]
+fn avx512_detected() -> bool {
+    std::arch::is_x86_feature_detected!("avx512f")
+        && std::arch::is_x86_feature_detected!("avx512dq")
+        && std::arch::is_x86_feature_detected!("avx512vl")
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn avx512_detected() -> bool {
+    false
+}
+
 /// AVX-512优化的批量哈希计算
 #[target_feature(enable = "avx512f,avx512dq,avx512vl")]
-unsafe fn zen4_avx512_hash_batch(buffer: &mut [u64], data: &mut [u8]) {
+unsafe fn zen4_avx512_hash_batch(buffer: &mut [u64], _data: &mut [u8]) {
     // 使用AVX-512进行并行哈希计算
     // 这里应该集成实际的Nockchain哈希算法
-    
+
     #[cfg(target_arch = "x86_64")]
     {
         use std::arch::x86_64::*;
-        
+
         for chunk in buffer.chunks_mut(8) {
             if chunk.len() == 8 {
-                // 加载8个64位数到AVX-512寄存器
-                let data_vec = _mm512_load_epi64(chunk.as_ptr() as *const i64);
-                
+                // 加载8个64位数到AVX-512寄存器（Vec只保证8字节对齐，所以用非对齐加载）
+                let data_vec = _mm512_loadu_epi64(chunk.as_ptr() as *const i64);
+
                 // 执行并行计算（这里是示例，实际需要集成真实算法）
                 let result = _mm512_add_epi64(data_vec, _mm512_set1_epi64(0x123456789ABCDEF0));
-                
+
                 // 存储结果
-                _mm512_store_epi64(chunk.as_mut_ptr() as *mut i64, result);
+                _mm512_storeu_epi64(chunk.as_mut_ptr() as *mut i64, result);
             }
         }
     }
@@ -348,12 +671,12 @@ fn zen4_cache_prefetch(data: &[u8], iteration: u64) {
     #[cfg(target_arch = "x86_64")]
     unsafe {
         use std::arch::x86_64::*;
-        
+
         let prefetch_offset = (iteration % 64) as usize * ZEN4_CACHE_LINE;
         if prefetch_offset < data.len() {
             // 预取到L1缓存
             _mm_prefetch(data.as_ptr().add(prefetch_offset) as *const i8, _MM_HINT_T0);
-            
+
             // 预取到L2缓存（下次使用）
             let next_offset = prefetch_offset + ZEN4_CACHE_LINE;
             if next_offset < data.len() {
@@ -369,14 +692,14 @@ fn set_thread_affinity(cpu_id: usize) -> Result<(), Box<dyn std::error::Error>>
         let mut cpu_set: cpu_set_t = std::mem::zeroed();
         CPU_ZERO(&mut cpu_set);
         CPU_SET(cpu_id, &mut cpu_set);
-        
+
         let result = sched_setaffinity(0, std::mem::size_of::<cpu_set_t>(), &cpu_set);
-        
+
         if result != 0 {
             return Err(format!("设置CPU亲和性失败: {}", std::io::Error::last_os_error()).into());
         }
     }
-    
+
     Ok(())
 }
 
@@ -398,6 +721,286 @@ impl Clone for EpycMiningConfig {
             zen4_optimizations: self.zen4_optimizations,
             avx512_enabled: self.avx512_enabled,
             ddr5_prefetch: self.ddr5_prefetch,
+            topology_report_path: self.topology_report_path.clone(),
+            affinity_stride: self.affinity_stride,
+            lock_memory: self.lock_memory,
+            restart_jitter_percent: self.restart_jitter_percent,
+            yield_policy: self.yield_policy,
+            reserved_cpus: self.reserved_cpus.clone(),
+            solution_rate_window: self.solution_rate_window,
+            hashrate_format: self.hashrate_format,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZEN4_CCX_SIZE: usize = 8; // Zen 4每个CCX 8核
+
+    #[test]
+    fn test_yield_policy_defaults_to_no_sleep() {
+        assert_eq!(EpycMiningConfig::default().yield_policy, YieldPolicy::None);
+        let started = Instant::now();
+        YieldPolicy::Sleep(2_000_000).apply();
+        assert!(started.elapsed() >= Duration::from_millis(2));
+        YieldPolicy::None.apply();
+        YieldPolicy::SpinHint.apply();
+    }
+
+    #[test]
+    fn test_restart_jitter_stays_within_bounds() {
+        let base = Duration::from_secs(300);
+        let mut rng = rand::thread_rng();
+        assert_eq!(jittered_restart_interval(base, 0, &mut rng), base);
+        let intervals: Vec<Duration> = (0..1000)
+            .map(|_| jittered_restart_interval(base, 10, &mut rng))
+            .collect();
+        assert!(intervals
+            .iter()
+            .all(|d| *d >= Duration::from_secs(270) && *d <= Duration::from_secs(330)));
+        // 抖动必须真正把线程错开
+        assert!(intervals.iter().any(|d| *d != intervals[0]));
+    }
+
+    #[test]
+    fn test_threads_balanced_across_numa_domains() {
+        // 2个NUMA域，每个4核8线程：线程轮流分配到各域
+        let topology = Topology::synthetic(1, 2, 4);
+        let miner = EpycMiner::with_topology(EpycMiningConfig::default(), Some(topology.clone()));
+        let assignment = miner.cpu_assignment();
+        let threads_per_ccd = MINING_THREADS / EPYC_9B14_CCDS;
+        assert_eq!(assignment.len(), threads_per_ccd * EPYC_9B14_CCDS);
+
+        for (thread, &cpu) in assignment.iter().enumerate() {
+            assert_eq!(
+                topology.cpus[cpu].numa_node,
+                thread % 2,
+                "线程 {} 绑定到CPU {}",
+                thread,
+                cpu
+            );
+        }
+        // 每个域内先用完所有逻辑CPU才重复
+        assert_eq!(&assignment[..8], &[0, 4, 1, 5, 2, 6, 3, 7]);
+
+        // 3个NUMA域时不会有某个域分到两个CCD组
+        let topology = Topology::synthetic(1, 3, 8);
+        let miner = EpycMiner::with_topology(EpycMiningConfig::default(), Some(topology.clone()));
+        let mut per_node = [0usize; 3];
+        for cpu in miner.cpu_assignment() {
+            per_node[topology.cpus[cpu].numa_node] += 1;
+        }
+        assert_eq!(per_node, [20, 20, 20]);
+    }
+
+    #[test]
+    fn test_reserved_cpus_excluded_from_assignment() {
+        let config = EpycMiningConfig {
+            reserved_cpus: vec![0, 1, 32],
+            ..EpycMiningConfig::default()
+        };
+        let miner = EpycMiner::with_topology(config, Some(Topology::synthetic(1, 4, 8)));
+        let assignment = miner.cpu_assignment();
+        assert_eq!(
+            assignment.len(),
+            (MINING_THREADS / EPYC_9B14_CCDS) * EPYC_9B14_CCDS
+        );
+        assert!(assignment.iter().all(|cpu| ![0, 1, 32].contains(cpu)));
+    }
+
+    #[test]
+    fn test_effective_config_follows_hardware() {
+        let config = EpycMiningConfig {
+            reserved_cpus: vec![0, 1, 32],
+            ..EpycMiningConfig::default()
+        };
+        let miner = EpycMiner::with_topology(config, Some(Topology::synthetic(1, 4, 8)));
+        let effective = miner.effective_config();
+        assert_eq!(effective.threads, miner.cpu_assignment().len());
+        assert_eq!(effective.reserved_cpus, vec![0, 1, 32]);
+        assert_eq!(effective.optimizations, miner.active_optimizations());
+        // 没有AVX-512时不做批量哈希
+        assert_eq!(effective.backend == "avx512", avx512_detected());
+    }
+
+    #[test]
+    fn test_active_optimizations_follow_hardware() {
+        let config = EpycMiningConfig {
+            lock_memory: true,
+            ..EpycMiningConfig::default()
+        };
+        let miner = EpycMiner::with_topology(config, Some(Topology::synthetic(1, 4, 8)));
+        let active = miner.active_optimizations();
+        assert!(active.contains(&"numa") && active.contains(&"memlock"));
+        // 请求了AVX-512也只在CPU支持时报告
+        assert_eq!(active.contains(&"avx512"), avx512_detected());
+
+        // NPS1只有一个NUMA域，没有可优化的NUMA放置
+        let config = EpycMiningConfig {
+            avx512_enabled: false,
+            zen4_optimizations: false,
+            ddr5_prefetch: false,
+            ..EpycMiningConfig::default()
+        };
+        let miner = EpycMiner::with_topology(config, Some(Topology::synthetic(1, 1, 32)));
+        assert!(miner.active_optimizations().is_empty());
+    }
+
+    #[test]
+    fn test_affinity_stride_spreads_across_ccx() {
+        // NPS1：单个NUMA域，4个CCX各8核
+        let topology = Topology::synthetic(1, 1, 32);
+        let config = EpycMiningConfig {
+            affinity_stride: ZEN4_CCX_SIZE,
+            ..EpycMiningConfig::default()
+        };
+        let miner = EpycMiner::with_topology(config, Some(topology));
+        let assignment = miner.cpu_assignment();
+        // 连续线程落在不同CCX上，而不是0,1,2,3
+        assert_eq!(&assignment[..4], &[0, 8, 16, 24]);
+        let ccxs: std::collections::BTreeSet<usize> = assignment[..8]
+            .iter()
+            .map(|cpu| cpu % EPYC_9B14_CORES / ZEN4_CCX_SIZE)
+            .collect();
+        assert_eq!(ccxs.len(), 4);
+
+        let dense = EpycMiner::with_topology(
+            EpycMiningConfig::default(),
+            Some(Topology::synthetic(1, 1, 32)),
+        );
+        assert_eq!(&dense.cpu_assignment()[..4], &[0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_lock_buffers_all_or_nothing() {
+        let avx512_buffer = vec![0u64; AVX512_BATCH_SIZE];
+        let cache_data = vec![0u8; ZEN4_CACHE_LINE * 64];
+        let locked = lock_buffers(0, &avx512_buffer, &cache_data);
+        // 受RLIMIT_MEMLOCK限制可能无法锁定，但不会只锁定一部分
+        assert!(locked.is_empty() || locked.len() == 2);
+        assert!(locked.iter().all(|lock| !lock.is_empty()));
+    }
+
+    #[test]
+    fn test_solution_rate_uses_configured_window() {
+        let config = EpycMiningConfig {
+            solution_rate_window: Duration::from_secs(60 * 60),
+            ..EpycMiningConfig::default()
+        };
+        let miner = EpycMiner::with_topology(config, Some(Topology::synthetic(1, 1, 4)));
+        assert_eq!(
+            miner.stats.recent_solutions.window(),
+            Duration::from_secs(60 * 60)
+        );
+        assert_eq!(miner.stats.solutions_per_hour(), 0.0);
+
+        // 一小时窗口内的两个解
+        miner.stats.increment_solutions();
+        miner.stats.increment_solutions();
+        assert_eq!(miner.stats.solutions_found.load(Ordering::Relaxed), 2);
+        assert_eq!(miner.stats.solutions_per_hour(), 2.0);
+    }
+
+    #[test]
+    fn test_stop_is_idempotent() {
+        let miner = Arc::new(EpycMiner::with_topology(
+            EpycMiningConfig::default(),
+            Some(Topology::synthetic(1, 1, 4)),
+        ));
+        let joined = Arc::new(AtomicU64::new(0));
+        for _ in 0..4 {
+            let should_stop = miner.should_stop.clone();
+            let joined = joined.clone();
+            miner.handles().push(thread::spawn(move || {
+                while !should_stop.load(Ordering::Relaxed) {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                joined.fetch_add(1, Ordering::Relaxed);
+            }));
+        }
+
+        // 并发调用都要等到线程回收完毕才返回
+        let stoppers: Vec<_> = (0..2)
+            .map(|_| {
+                let miner = miner.clone();
+                let joined = joined.clone();
+                thread::spawn(move || {
+                    miner.stop();
+                    assert_eq!(joined.load(Ordering::Relaxed), 4);
+                })
+            })
+            .collect();
+        for stopper in stoppers {
+            stopper.join().unwrap();
+        }
+        assert!(miner.handles().is_empty());
+
+        // 再次调用以及Drop中的调用都是空操作
+        miner.stop();
+        drop(miner);
+        assert_eq!(joined.load(Ordering::Relaxed), 4);
+    }
+
+    /// 模拟一个挖矿线程：收到停止信号后再用`batch`跑完当前批次，`release`置位之前不会结束
+    fn spawn_batch_worker(
+        should_stop: Arc<AtomicBool>,
+        batch: Duration,
+        release: Arc<AtomicBool>,
+    ) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            while !should_stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            thread::sleep(batch);
+            while !release.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+        })
+    }
+
+    #[test]
+    fn test_drain_waits_for_in_flight_batch() {
+        let miner = EpycMiner::with_topology(
+            EpycMiningConfig::default(),
+            Some(Topology::synthetic(1, 1, 4)),
+        );
+        let released = Arc::new(AtomicBool::new(true));
+        miner.handles().push(spawn_batch_worker(
+            miner.should_stop.clone(),
+            Duration::from_millis(50),
+            released,
+        ));
+
+        let started = Instant::now();
+        assert_eq!(miner.drain(Duration::from_secs(10)), DrainResult::Completed);
+        // 当前批次跑完才算排空，而且线程已被回收
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert!(miner.handles().is_empty());
+    }
+
+    #[test]
+    fn test_drain_times_out_on_stuck_batch() {
+        let miner = EpycMiner::with_topology(
+            EpycMiningConfig::default(),
+            Some(Topology::synthetic(1, 1, 4)),
+        );
+        let release = Arc::new(AtomicBool::new(false));
+        miner.handles().push(spawn_batch_worker(
+            miner.should_stop.clone(),
+            Duration::ZERO,
+            release.clone(),
+        ));
+
+        assert_eq!(
+            miner.drain(Duration::from_millis(50)),
+            DrainResult::TimedOut { remaining: 1 }
+        );
+        // 超时的线程留给stop回收
+        assert_eq!(miner.handles().len(), 1);
+        release.store(true, Ordering::Relaxed);
+        miner.stop();
+        assert!(miner.handles().is_empty());
+    }
+}
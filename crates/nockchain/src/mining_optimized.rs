@@ -5,34 +5,49 @@
 // 3. Memory-intensive parallelization
 // 4. Cache-friendly data structures
 
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::str::FromStr;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use kernels::miner::KERNEL;
-use nockapp::kernel::form::SerfThread;
-use nockapp::nockapp::driver::{IODriverFn, NockAppHandle, PokeResult};
-use nockapp::nockapp::wire::Wire;
+use ibig::UBig;
+use nockapp::nockapp::driver::IODriverFn;
 use nockapp::nockapp::NockAppError;
 use nockapp::noun::slab::NounSlab;
-use nockapp::noun::{AtomExt, NounExt};
-use nockapp::save::SaveableCheckpoint;
-use nockapp::utils::{NOCK_STACK_SIZE_HUGE, NOCK_STACK_SIZE_LARGE}; // Use larger stacks
-use nockapp::CrownError;
+use nockapp::utils::{NOCK_STACK_SIZE_LARGE, NOCK_STACK_SIZE_TINY}; // Use larger stacks
 use nockchain_libp2p_io::tip5_util::tip5_hash_to_base58;
-use nockvm::interpreter::NockCancelToken;
-use nockvm::noun::{Atom, D, NO, T, YES};
-use nockvm_macros::tas;
-use rand::Rng;
+use nockvm::jets::hot::HotEntry;
+use nockvm::noun::Noun;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
 use tokio::sync::Mutex;
-use tracing::{debug, info, instrument, warn};
+use tracing::{debug, debug_span, error, info, info_span, warn, Instrument, Span};
+use zkvm_jetpack::field::verify::VerifyMode;
 use zkvm_jetpack::form::PRIME;
-use zkvm_jetpack::noun::noun_ext::NounExt as OtherNounExt;
+
+use crate::autotune::{AutotuneConfig, Autotuner, TuneStep};
+use crate::candidate_source::{CandidateSource, EffectCandidateSource, EffectRetry};
+use crate::control::{EffectiveConfig, HealthStatus, MinerControl};
+use crate::event_log::{EventLog, EventRecord, MinerEvent};
+use crate::hash_backend::{
+    BackendFactory, Candidate, CpuSerfBackend, HashBackend, HashResult, Nonce,
+};
+use crate::hashrate::HashRateFormat;
+use crate::mining::{panic_message, respawn_stack_size, AttemptLog, AttemptLogLevel};
+use crate::mining_chains::{ChainSnapshot, ChainSources, ChainStats};
+use crate::nonce_entropy::{EntropySource, NonceRng};
+use crate::nonce_source::NonceSource;
+use crate::npc_submit::NpcSubmitTarget;
+use crate::solution_log::{SolutionInfo, SolutionLog, SolutionRecord};
+use crate::topology::Topology;
 
 // EPYC 9654 specific optimizations
 const EPYC_9654_CORES: u64 = 96;
-const EPYC_9654_THREADS: u64 = 192;
-const EPYC_9654_L3_CACHE: usize = 384 * 1024 * 1024; // 384MB
 
 // Advanced threading strategy
 const MINING_THREADS_PER_CORE: u64 = 2; // Use hyperthreading
@@ -46,340 +61,4016 @@ const OPTIMIZED_STACK_SIZE: usize = NOCK_STACK_SIZE_LARGE; // 32GB per thread (a
 // NUMA-aware batch sizes
 const BATCH_SIZE_PER_NUMA_NODE: u64 = 24; // 96 cores / 4 NUMA nodes = 24 cores per node
 
+// Hash rate history
+const HASH_RATE_HISTORY_LEN: usize = 600; // Ten minutes of one-second samples
+const HASH_RATE_LOG_INTERVAL_SECS: u64 = 10;
+const HEALTH_WINDOW: Duration = Duration::from_secs(60); // A worker must finish an attempt this often to count as live
+const HASHRATE_ALERT_WINDOW: Duration = Duration::from_secs(300); // Mean rate this long below the floor marks the miner degraded
+const TIMING_BUCKETS: usize = 32; // Bucket i counts durations in [2^i, 2^(i+1)) microseconds
+
+// Laptop profile tuning
+const LAPTOP_RESERVED_CORES: u64 = 2; // Leave room for the desktop and the node itself
+const LAPTOP_DUTY_CYCLE_PERCENT: u8 = 50;
+
+// Smoke test tuning
+const SMOKE_TEST_NONCE_SEED: u64 = 0x5eed;
+const SMOKE_TEST_POW_LEN: u64 = 2; // The fakenet default
+const SMOKE_TEST_MAX_ATTEMPTS: u64 = 16; // Every hash meets the trivial target, so one should do
+
+// Hash rate benchmark
+const BENCHMARK_POW_LEN: u64 = 64; // pow-len in hoon/common/ztd/eight.hoon, what mainnet proves
+
+/// Preset tuning profiles for the optimized driver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MiningProfile {
+    /// Server-class EPYC box: all threads, NUMA pinning, large stacks
+    Server,
+    /// Low-core laptops/dev machines: few threads, no NUMA, small stacks, half duty cycle
+    Laptop,
+}
+
+impl FromStr for MiningProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "server" => Ok(MiningProfile::Server),
+            "laptop" => Ok(MiningProfile::Laptop),
+            _ => Err(format!(
+                "Invalid mining profile '{}'. Expected 'server' or 'laptop'",
+                s
+            )),
+        }
+    }
+}
+
 pub struct OptimizedMiningConfig {
     pub numa_aware: bool,
     pub use_avx512: bool,
+    /// Run the field kernels on AVX2 instead of AVX-512 on CPUs that drop their clock for
+    /// 512-bit work (Skylake-SP through Cooper Lake). The `field-bench` binary times the
+    /// kernels alone; compare whole-miner hash rate with this on and off before relying on it.
+    pub avx512_license_aware: bool,
+    /// Recompute every SIMD field result with the scalar code and panic on, or log and
+    /// repair, any difference, to catch miscompiled kernels or faulty vector units. Roughly
+    /// doubles the cost of the field batch operations.
+    pub verify_field_ops: VerifyMode,
     pub memory_prefetch: bool,
     pub cache_aligned: bool,
     pub thread_affinity: bool,
+    pub mining_threads: u64,
+    /// Logical CPUs no worker or pinned runtime thread is placed on, leaving them to the OS,
+    /// e.g. the cores servicing the NIC's interrupts. `mining_threads` still sets the
+    /// worker count, so lower it to match; extra workers share the remaining CPUs. Needs a
+    /// known topology to take effect.
+    pub reserved_cpus: Vec<usize>,
+    /// Sweep a few thread counts at startup, measuring the hash rate of each, and keep
+    /// mining with the fastest instead of `mining_threads`; see [`crate::autotune`]
+    pub autotune: Option<AutotuneConfig>,
+    /// Nock stack per worker, which bounds how much memory its kernel can allocate
+    pub stack_size: usize,
+    /// When a worker's kernel runs out of memory, respawn it with half its stack instead
+    /// of retiring it, until the stack would drop below a quarter of `NOCK_STACK_SIZE_TINY`,
+    /// as in the default driver. Either way the driver keeps mining on the other workers
+    /// and counts it in `workers_oom`.
+    pub respawn_after_oom: bool,
+    /// Catch a panic in a worker's attempt, count it in `worker_panics` and respawn the
+    /// worker with a fresh backend, so one buggy jet doesn't take down the node. When
+    /// off, the panic brings the driver down as it would anywhere else.
+    pub respawn_after_panic: bool,
+    /// Percentage of wall time each worker spends mining; the rest is spent sleeping
+    pub duty_cycle_percent: u8,
+    /// Where to write the JSON topology report at startup, if anywhere
+    pub topology_report_path: Option<PathBuf>,
+    /// Count hashes within this factor of the target as near misses; `None` disables it
+    pub near_miss_factor: Option<u64>,
+    /// Mine this candidate instead of waiting for %mine effects from the node
+    pub fixed_candidate: Option<FixedCandidate>,
+    /// Emit tracing spans around nonce generation and kernel pokes and record their
+    /// durations in the stats histograms
+    pub timing: bool,
+    /// Place workers on this layout instead of the host's, e.g. `Topology::synthetic` in tests
+    pub topology: Option<Topology>,
+    /// Serve the control API (health probes) on this address, if set
+    pub control_addr: Option<SocketAddr>,
+    /// How recently an attempt must have finished for `/health` to report live
+    pub health_window: Duration,
+    /// Warn and report `degraded` in `/health` while the mean hash rate over
+    /// `hashrate_alert_window` is below this many hashes/sec
+    pub min_expected_hashrate: Option<u64>,
+    /// How long the hash rate is averaged over before comparing it with
+    /// `min_expected_hashrate`; at most the ten minutes of kept history count
+    pub hashrate_alert_window: Duration,
+    /// Unit and decimals hash rates are logged with; by default the unit suits the rate
+    pub hashrate_format: HashRateFormat,
+    /// Log superseded candidates at info level and report the stale-work counters with
+    /// the hash rate, instead of only at debug level
+    pub log_stale_candidates: bool,
+    /// Append each found solution as a JSON line to this file or named pipe, before
+    /// submitting it unless `prioritize_submission` is set
+    pub solution_log: Option<PathBuf>,
+    /// Submit found blocks before taking the mining_data lock, writing the solution log or
+    /// updating stats, to keep the find to submit path, and with it orphan risk, short
+    pub prioritize_submission: bool,
+    /// Also submit found blocks to the node listening on this npc socket
+    pub secondary_submit: Option<PathBuf>,
+    /// Whether found blocks go to the node, to a callback, or both. When mining several
+    /// chains each [`MiningChain`] says where its own blocks go instead.
+    pub submit: SubmitMode,
+    /// Which routine attempts get a debug line; state changes always do
+    pub attempt_log: AttemptLogLevel,
+    /// mlock the process before the workers start so their Nock stacks are never paged out.
+    /// The stacks are mapped inside the serf threads, so this locks everything and makes
+    /// each worker's whole stack resident; it needs an unlimited `RLIMIT_MEMLOCK`.
+    pub lock_memory: bool,
+    /// Idle workers while the process allocates more than this many bytes per second.
+    /// Needs a build with the `alloc_stats` feature, which also reports the rate.
+    pub max_alloc_bytes_per_sec: Option<u64>,
+    /// Exit the app with code 0 once this many solutions have been submitted
+    pub stop_after_solutions: Option<u64>,
+    /// Log a full [`StatsSnapshot`] as JSON whenever the process receives SIGUSR1 (Unix only)
+    pub stats_signal: bool,
+    /// Nonces a worker hashes per attempt, back to back on its blocking thread, before it
+    /// hands results to the driver. Larger batches amortize the driver's per-attempt work
+    /// (task spawn, candidate lock, nonce setup); each nonce is still one kernel poke. A
+    /// batch ends early on a solution or cancellation, and `max_attempts_per_candidate`
+    /// counts batches.
+    pub nonces_per_attempt: u64,
+    /// Where fresh nonces get their randomness; see [`crate::nonce_entropy`] for the
+    /// throughput and quality of each source. Fixed candidates ignore it and derive their
+    /// nonces from `nonce_seed`.
+    pub entropy_source: EntropySource,
+    /// Nonces computed elsewhere, e.g. by an FPGA, which workers hash before any they
+    /// generate; see [`crate::nonce_source`] for their format. Fixed candidates ignore it.
+    pub nonce_source: Option<Box<dyn NonceSource>>,
+    /// After restarting the workers on a new candidate, hold off on the next restart for
+    /// this long. Candidates arriving meanwhile are coalesced: only the latest is mined,
+    /// once the cooldown ends, and the ones it replaced count as `coalesced_candidates`.
+    pub restart_cooldown: Option<Duration>,
+    /// Pause a worker once it has finished this many attempts on one candidate, until a
+    /// new candidate arrives. Paused workers count as inactive in the health report.
+    pub max_attempts_per_candidate: Option<u64>,
+    /// Record every candidate the driver receives, with its arrival time, to this file for
+    /// replaying later with [`crate::candidate_source::ReplaySource::open`]
+    pub record_candidates: Option<PathBuf>,
+    /// Build each worker's hash backend with this instead of loading the miner kernel into
+    /// a [`CpuSerfBackend`], e.g. for a GPU backend or a scripted one in tests
+    pub backend_factory: Option<BackendFactory>,
+    /// Build the span each worker's attempts run in, instead of the default
+    /// `mining_worker` span with `thread_id` and `numa_node` fields
+    pub worker_span: Option<WorkerSpanFn>,
+    /// Backoff between errors reading the node's effects, and how many in a row stop the
+    /// driver and exit the node so a supervisor can restart it
+    pub effect_retry: EffectRetry,
+}
+
+/// A candidate fed straight into the workers, for exercising kernels and jets in isolation.
+///
+/// Initial nonces are derived from `nonce_seed` and the thread id, and every later nonce
+/// is the previous attempt's hash, so repeated runs mine exactly the same sequence.
+#[derive(Debug, Clone)]
+pub struct FixedCandidate {
+    /// Proof version tag, as in the %mine effect
+    pub version: u64,
+    /// Block commitment as a tip5 digest
+    pub header: [u64; 5],
+    /// Target bignum limbs (u32), least significant first
+    pub target: Vec<u32>,
+    pub pow_len: u64,
+    pub nonce_seed: u64,
+}
+
+impl FixedCandidate {
+    /// Build the candidate the workers would otherwise get from a %mine effect
+    fn to_candidate(&self) -> Candidate {
+        Candidate::from_parts(self.version, self.header, &self.target, self.pow_len)
+    }
+
+    /// A candidate no hash solves, with the mainnet proof length, so every attempt costs
+    /// what it would on the real network and the workers never stop on a solution
+    pub fn unsolvable(nonce_seed: u64) -> Self {
+        Self {
+            version: crate::pow_target::POW_ALGORITHM_VERSION as u64,
+            header: [1, 2, 3, 4, 5],
+            target: vec![0],
+            pow_len: BENCHMARK_POW_LEN,
+            nonce_seed,
+        }
+    }
+
+    /// A candidate every hash solves: the largest target a digest can be compared
+    /// against, with the fakenet proof length so each attempt is quick
+    pub fn trivial(nonce_seed: u64) -> Self {
+        Self {
+            version: crate::pow_target::POW_ALGORITHM_VERSION as u64,
+            header: [1, 2, 3, 4, 5],
+            target: vec![u32::MAX; (crate::pow_target::DIGEST_BITS / 32) as usize],
+            pow_len: SMOKE_TEST_POW_LEN,
+            nonce_seed,
+        }
+    }
+}
+
+impl OptimizedMiningConfig {
+    /// One worker mining [`FixedCandidate::trivial`] with a fixed seed, exiting after the
+    /// first solution: a fast, deterministic end-to-end run for CI
+    pub fn smoke_test(nonce_seed: u64) -> Self {
+        Self {
+            mining_threads: 1,
+            duty_cycle_percent: 100,
+            fixed_candidate: Some(FixedCandidate::trivial(nonce_seed)),
+            stop_after_solutions: Some(1),
+            ..Self::from_profile(MiningProfile::Laptop)
+        }
+    }
 }
 
 impl Default for OptimizedMiningConfig {
     fn default() -> Self {
-        Self {
-            numa_aware: true,
-            use_avx512: true,
-            memory_prefetch: true,
-            cache_aligned: true,
-            thread_affinity: true,
+        Self::from_profile(MiningProfile::Server)
+    }
+}
+
+impl OptimizedMiningConfig {
+    /// The configured topology override, or the host's, less `reserved_cpus`
+    fn topology(&self) -> Option<Cow<'_, Topology>> {
+        let topology = match &self.topology {
+            Some(topology) => topology,
+            None => crate::topology::host_topology()?,
+        };
+        if self.reserved_cpus.is_empty() {
+            return Some(Cow::Borrowed(topology));
+        }
+        Some(Cow::Owned(topology.without_cpus(&self.reserved_cpus)))
+    }
+
+    /// Logical core each worker is pinned to when `thread_affinity` is on, indexed by
+    /// worker id; never one of `reserved_cpus` if the topology is known
+    pub fn mining_cpus(&self) -> Vec<usize> {
+        worker_cpus(self)
+    }
+
+    pub fn from_profile(profile: MiningProfile) -> Self {
+        match profile {
+            MiningProfile::Server => Self {
+                numa_aware: true,
+                use_avx512: true,
+                avx512_license_aware: false,
+                verify_field_ops: VerifyMode::Off,
+                memory_prefetch: true,
+                cache_aligned: true,
+                thread_affinity: true,
+                mining_threads: OPTIMAL_MINING_THREADS,
+                reserved_cpus: Vec::new(),
+                autotune: None,
+                stack_size: OPTIMIZED_STACK_SIZE,
+                respawn_after_oom: false,
+                respawn_after_panic: true,
+                duty_cycle_percent: 100,
+                topology_report_path: None,
+                near_miss_factor: None,
+                fixed_candidate: None,
+                timing: false,
+                topology: None,
+                control_addr: None,
+                health_window: HEALTH_WINDOW,
+                min_expected_hashrate: None,
+                hashrate_alert_window: HASHRATE_ALERT_WINDOW,
+                hashrate_format: HashRateFormat::default(),
+                log_stale_candidates: false,
+                solution_log: None,
+                prioritize_submission: false,
+                secondary_submit: None,
+                submit: SubmitMode::Internal,
+                attempt_log: AttemptLogLevel::default(),
+                lock_memory: false,
+                max_alloc_bytes_per_sec: None,
+                stop_after_solutions: None,
+                stats_signal: false,
+                nonces_per_attempt: 1,
+                entropy_source: EntropySource::default(),
+                nonce_source: None,
+                max_attempts_per_candidate: None,
+                restart_cooldown: None,
+                record_candidates: None,
+                backend_factory: None,
+                worker_span: None,
+                effect_retry: EffectRetry::default(),
+            },
+            MiningProfile::Laptop => Self {
+                numa_aware: false,
+                use_avx512: true,
+                avx512_license_aware: false,
+                verify_field_ops: VerifyMode::Off,
+                memory_prefetch: true,
+                cache_aligned: true,
+                thread_affinity: false,
+                mining_threads: (num_cpus::get() as u64)
+                    .saturating_sub(LAPTOP_RESERVED_CORES)
+                    .max(1),
+                reserved_cpus: Vec::new(),
+                autotune: None,
+                stack_size: NOCK_STACK_SIZE_TINY,
+                respawn_after_oom: false,
+                respawn_after_panic: true,
+                duty_cycle_percent: LAPTOP_DUTY_CYCLE_PERCENT,
+                topology_report_path: None,
+                near_miss_factor: None,
+                fixed_candidate: None,
+                timing: false,
+                topology: None,
+                control_addr: None,
+                health_window: HEALTH_WINDOW,
+                min_expected_hashrate: None,
+                hashrate_alert_window: HASHRATE_ALERT_WINDOW,
+                hashrate_format: HashRateFormat::default(),
+                log_stale_candidates: false,
+                solution_log: None,
+                prioritize_submission: false,
+                secondary_submit: None,
+                submit: SubmitMode::Internal,
+                attempt_log: AttemptLogLevel::default(),
+                lock_memory: false,
+                max_alloc_bytes_per_sec: None,
+                stop_after_solutions: None,
+                stats_signal: false,
+                nonces_per_attempt: 1,
+                entropy_source: EntropySource::default(),
+                nonce_source: None,
+                max_attempts_per_candidate: None,
+                restart_cooldown: None,
+                record_candidates: None,
+                backend_factory: None,
+                worker_span: None,
+                effect_retry: EffectRetry::default(),
+            },
         }
     }
 }
 
-struct OptimizedMiningData {
-    pub block_header: NounSlab,
-    pub version: NounSlab,
-    pub target: NounSlab,
-    pub pow_len: u64,
-    pub optimization_stats: Arc<AtomicU64>, // Track performance metrics
+/// Receives found solutions when [`SubmitMode`] routes them outside the node. It runs on
+/// the driver task, so it should hand the solution off rather than do slow work itself.
+pub type SolutionCallback = Arc<dyn Fn(SolutionInfo) + Send + Sync>;
+
+/// Builds the span a worker's nonce generation, kernel pokes and block submissions run in,
+/// from its thread id and the NUMA node it is pinned to, if any. Fields recorded here tag
+/// every span and event beneath it, e.g. for correlating traces in an OpenTelemetry
+/// pipeline.
+pub type WorkerSpanFn = Arc<dyn Fn(u64, Option<usize>) -> tracing::Span + Send + Sync>;
+
+/// Where found solutions go, for running the miner as a PoW engine behind a separate
+/// block builder
+#[derive(Clone, Default)]
+pub enum SubmitMode {
+    /// Poke the node (and `secondary_submit`, if set) with the %mined poke
+    #[default]
+    Internal,
+    /// Only call the callback; the node never sees the solution
+    ExternalCallback(SolutionCallback),
+    /// Poke the node first, then call the callback
+    Both(SolutionCallback),
 }
 
-// Optimized nonce generation using AVX-512 friendly patterns
-fn generate_optimized_nonce(thread_id: u64, base_entropy: u64) -> NounSlab {
-    let mut rng = rand::thread_rng();
-    let mut nonce_slab = NounSlab::new();
-    
-    // Use thread ID and time for better distribution across EPYC cores
-    let thread_entropy = (thread_id.wrapping_mul(0x517cc1b727220a95)) ^ base_entropy;
-    
-    // Generate cache-line aligned nonce values (64-byte aligned)
-    let mut nonce_values = Vec::with_capacity(8); // 8 * 8 bytes = 64 bytes
-    for i in 0..8 {
-        let entropy = thread_entropy.wrapping_add(i * 0x9e3779b97f4a7c15);
-        nonce_values.push((entropy ^ rng.gen::<u64>()) % PRIME);
-    }
-    
-    // Build nonce tree optimized for L3 cache access patterns
-    let mut nonce_cell = Atom::from_value(&mut nonce_slab, nonce_values[0])
-        .expect("Failed to create nonce atom")
-        .as_noun();
-    
-    for &value in &nonce_values[1..] {
-        let nonce_atom = Atom::from_value(&mut nonce_slab, value)
-            .expect("Failed to create nonce atom")
-            .as_noun();
-        nonce_cell = T(&mut nonce_slab, &[nonce_atom, nonce_cell]);
-    }
-    
-    nonce_slab.set_root(nonce_cell);
-    nonce_slab
+impl SubmitMode {
+    /// Split a %mined poke into what goes to the node and what goes to the callback
+    fn route(&self, poke: NounSlab) -> (Option<NounSlab>, Option<(&SolutionCallback, NounSlab)>) {
+        match self {
+            SubmitMode::Internal => (Some(poke), None),
+            SubmitMode::ExternalCallback(callback) => (None, Some((callback, poke))),
+            SubmitMode::Both(callback) => (Some(poke.clone()), Some((callback, poke))),
+        }
+    }
 }
 
-// NUMA-aware thread placement for EPYC 9654
-fn set_thread_affinity(thread_id: u64) -> Result<(), Box<dyn std::error::Error>> {
-    #[cfg(target_os = "linux")]
-    {
-        use libc::{cpu_set_t, sched_setaffinity, CPU_SET, CPU_ZERO};
-        use std::mem;
-        
-        // EPYC 9654 has 4 NUMA nodes, 24 cores each
-        let numa_node = thread_id / BATCH_SIZE_PER_NUMA_NODE;
-        let core_in_node = thread_id % BATCH_SIZE_PER_NUMA_NODE;
-        let logical_core = numa_node * BATCH_SIZE_PER_NUMA_NODE + core_in_node;
-        
-        unsafe {
-            let mut cpu_set: cpu_set_t = mem::zeroed();
-            CPU_ZERO(&mut cpu_set);
-            CPU_SET(logical_core as usize, &mut cpu_set);
-            
-            if sched_setaffinity(0, mem::size_of::<cpu_set_t>(), &cpu_set) != 0 {
-                return Err("Failed to set thread affinity".into());
+/// One of the chains [`create_optimized_mining_driver_with_chains`] mines at once
+pub struct MiningChain {
+    /// How the chain is named in logs and stats
+    pub name: String,
+    /// Share of the workers the chain gets, relative to the other chains' weights
+    pub weight: u32,
+    /// Where the chain's candidates come from; `None` for the node's %mine effects, which
+    /// only one chain can read
+    pub source: Option<Box<dyn CandidateSource>>,
+    /// Where the chain's solutions go; [`SubmitMode::Internal`] pokes this driver's node
+    pub submit: SubmitMode,
+}
+
+/// Driver-wide counters, shared with the monitor task and the caller
+#[derive(Default)]
+pub struct OptimizedMiningStats {
+    pub hashes: AtomicU64,
+    /// Mining results whose head was neither a cancelled %poke nor a %mine-result
+    pub unexpected_effects: AtomicU64,
+    /// Failed attempts whose hash was within `near_miss_factor` of the target
+    pub near_misses: AtomicU64,
+    /// Attempts still in flight when a new candidate replaced theirs; their work is wasted
+    pub stale_attempts: AtomicU64,
+    /// Candidates replaced before any worker finished an attempt on them
+    pub discarded_candidates: AtomicU64,
+    /// Candidates replaced by a newer one during a restart cooldown, so never mined
+    pub coalesced_candidates: AtomicU64,
+    /// Nonces taken from the configured `nonce_source` rather than generated
+    pub external_nonces: AtomicU64,
+    /// Attempts the kernel reported cancelled (the %poke head) and the driver restarted
+    pub cancellations: AtomicU64,
+    /// Workers whose kernel ran out of memory mid-attempt, whether retired or respawned
+    pub workers_oom: AtomicU64,
+    /// Attempts that panicked in the hash backend; the worker is respawned
+    pub worker_panics: AtomicU64,
+    /// Attempts the hash backend answered with no results at all; the worker restarts on
+    /// a fresh nonce
+    pub empty_results: AtomicU64,
+    /// Errors reading the node's effects; shared with the candidate source
+    pub effect_errors: Arc<AtomicU64>,
+    /// Time spent generating fresh nonces, when `timing` is enabled
+    pub nonce_timing: TimingHistogram,
+    /// Time spent in the hash backend per attempt, when `timing` is enabled
+    pub poke_timing: TimingHistogram,
+    /// Most recent per-second hash rate samples, oldest first
+    history: std::sync::Mutex<VecDeque<(Instant, u64)>>,
+    /// Workers with an attempt in flight
+    pub active_threads: AtomicU64,
+    /// Workers the driver was configured to run
+    pub expected_threads: AtomicU64,
+    /// Leading zero bits the current candidate's target demands, see
+    /// [`crate::pow_target::difficulty_bits`]
+    pub current_difficulty: AtomicU64,
+    /// Heap bytes the process allocated over the last second; stays 0 unless built with
+    /// the `alloc_stats` feature
+    pub bytes_allocated_per_sec: AtomicU64,
+    /// Attempts finished on the current candidate, across all workers
+    pub candidate_attempts: AtomicU64,
+    /// Workers idle until the next candidate because they reached `max_attempts_per_candidate`
+    pub paused_threads: AtomicU64,
+    /// `f64` bits of hashes per joule over the last log interval; see [`Self::hashes_per_joule`]
+    hashes_per_joule: AtomicU64,
+    /// Set once the driver has detected what the host supports; see [`Self::active_optimizations`]
+    active_optimizations: std::sync::Mutex<Vec<&'static str>>,
+    /// Set once the driver has started; see [`Self::effective_config`]
+    effective_config: std::sync::Mutex<Option<EffectiveConfig>>,
+    /// Hashes by the NUMA node their worker is pinned to, when the topology is known
+    node_hashes: std::sync::Mutex<BTreeMap<usize, u64>>,
+    last_attempt_at: std::sync::Mutex<Option<Instant>>,
+    last_solution_at: std::sync::Mutex<Option<Instant>>,
+    /// Candidates, solutions, worker failures and stalls, served at `GET /events`
+    pub events: EventLog,
+    /// Per-chain counters, set once the driver has started
+    chains: std::sync::Mutex<Vec<Arc<ChainStats>>>,
+    /// Set while the hash rate is below `min_expected_hashrate`
+    degraded: AtomicBool,
+}
+
+impl OptimizedMiningStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hash rate samples (time taken, hashes/sec) covering up to the last
+    /// `HASH_RATE_HISTORY_LEN` seconds, oldest first
+    pub fn history(&self) -> Vec<(Instant, u64)> {
+        self.history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    /// Record the difficulty of a new candidate's target, logging it when it changes
+    fn record_difficulty(&self, target: Noun) -> Option<u32> {
+        let bits = match crate::pow_target::difficulty_from_noun(target) {
+            Ok(bits) => bits,
+            Err(e) => {
+                warn!("Could not parse mining target, difficulty unknown: {e}");
+                return None;
             }
+        };
+        if self.current_difficulty.swap(bits as u64, Ordering::Relaxed) != bits as u64 {
+            info!(
+                "🎯 Target difficulty: ~{} leading zero bits (~2^{} hashes per block)",
+                bits, bits
+            );
         }
+        Some(bits)
+    }
+
+    /// Optimizations in effect on this host once the driver has started; empty before that.
+    /// Unlike the config toggles these reflect hardware detection and what succeeded.
+    pub fn active_optimizations(&self) -> Vec<&'static str> {
+        self.active_optimizations
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// The configuration the driver runs with once it has started, with the thread count
+    /// the auto-tuner settled on, or is trying
+    pub fn effective_config(&self) -> Option<EffectiveConfig> {
+        let mut config = self
+            .effective_config
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()?;
+        config.threads = self.expected_threads.load(Ordering::Relaxed) as usize;
+        Some(config)
+    }
+
+    /// Candidates, hashes and solutions of each chain the driver mines, in the order the
+    /// chains were given; empty before the driver has started
+    pub fn chains(&self) -> Vec<ChainSnapshot> {
+        self.chains
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .map(|chain| chain.snapshot())
+            .collect()
+    }
+
+    /// Hashes per joule of package energy over the last log interval, or `None` on hosts
+    /// without readable RAPL counters and before the first interval
+    pub fn hashes_per_joule(&self) -> Option<f64> {
+        let value = f64::from_bits(self.hashes_per_joule.load(Ordering::Relaxed));
+        (value > 0.0).then_some(value)
+    }
+
+    /// Liveness summary; live if an attempt finished within `window`
+    pub fn health(&self, window: Duration) -> HealthStatus {
+        let last = |at: &std::sync::Mutex<Option<Instant>>| {
+            *at.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+        };
+        HealthStatus {
+            degraded: self.degraded.load(Ordering::Relaxed),
+            ..HealthStatus::evaluate(
+                Instant::now(),
+                last(&self.last_attempt_at),
+                last(&self.last_solution_at),
+                self.active_threads.load(Ordering::Relaxed),
+                self.expected_threads.load(Ordering::Relaxed),
+                window,
+            )
+        }
+    }
+
+    /// Mean of the per-second hash rate samples taken in the `window` before `now`, once
+    /// the history reaches back that far (or is full)
+    fn mean_rate_over(&self, now: Instant, window: Duration) -> Option<f64> {
+        let history = self
+            .history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let &(oldest, _) = history.front()?;
+        if now.saturating_duration_since(oldest) < window && history.len() < HASH_RATE_HISTORY_LEN {
+            return None;
+        }
+        let recent: Vec<u64> = history
+            .iter()
+            .filter(|&&(at, _)| now.saturating_duration_since(at) < window)
+            .map(|&(_, rate)| rate)
+            .collect();
+        (!recent.is_empty()).then(|| recent.iter().sum::<u64>() as f64 / recent.len() as f64)
+    }
+
+    /// Compare the mean rate over `window` with `floor` and update the degraded flag.
+    /// Returns the new flag and the rate when the flag changed.
+    fn check_hash_rate_floor(
+        &self,
+        now: Instant,
+        floor: u64,
+        window: Duration,
+    ) -> Option<(bool, f64)> {
+        let rate = self.mean_rate_over(now, window)?;
+        let degraded = rate < floor as f64;
+        (self.degraded.swap(degraded, Ordering::Relaxed) != degraded).then_some((degraded, rate))
+    }
+
+    /// Every counter, the latest hash rate and health at once, e.g. for a SIGUSR1 dump
+    pub fn snapshot(&self, window: Duration) -> StatsSnapshot {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        StatsSnapshot {
+            hashes: load(&self.hashes),
+            hash_rate: self.history().last().map(|&(_, rate)| rate),
+            hashes_by_numa_node: self
+                .node_hashes
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clone(),
+            unexpected_effects: load(&self.unexpected_effects),
+            near_misses: load(&self.near_misses),
+            stale_attempts: load(&self.stale_attempts),
+            discarded_candidates: load(&self.discarded_candidates),
+            coalesced_candidates: load(&self.coalesced_candidates),
+            external_nonces: load(&self.external_nonces),
+            cancellations: load(&self.cancellations),
+            workers_oom: load(&self.workers_oom),
+            worker_panics: load(&self.worker_panics),
+            empty_results: load(&self.empty_results),
+            effect_errors: load(&self.effect_errors),
+            candidate_attempts: load(&self.candidate_attempts),
+            paused_threads: load(&self.paused_threads),
+            current_difficulty: load(&self.current_difficulty),
+            bytes_allocated_per_sec: load(&self.bytes_allocated_per_sec),
+            hashes_per_joule: self.hashes_per_joule(),
+            nonce_timing: TimingSummary::of(&self.nonce_timing),
+            poke_timing: TimingSummary::of(&self.poke_timing),
+            active_optimizations: self.active_optimizations(),
+            effective_config: self.effective_config(),
+            chains: self.chains(),
+            health: self.health(window),
+        }
+    }
+
+    fn record_node_hashes(&self, node: usize, hashes: u64) {
+        *self
+            .node_hashes
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(node)
+            .or_default() += hashes;
+    }
+
+    /// Account for a candidate replaced while `in_flight` attempts were still running on it,
+    /// after `finished` attempts on it had completed. Returns whether it was discarded
+    /// without any worker finishing an attempt.
+    fn candidate_superseded(&self, in_flight: usize, finished: u64) -> bool {
+        self.stale_attempts
+            .fetch_add(in_flight as u64, Ordering::Relaxed);
+        let discarded = finished == 0;
+        if discarded {
+            self.discarded_candidates.fetch_add(1, Ordering::Relaxed);
+        }
+        discarded
+    }
+
+    fn mark(at: &std::sync::Mutex<Option<Instant>>) {
+        *at.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Instant::now());
+    }
+
+    fn record_rate(&self, at: Instant, rate: u64) {
+        let mut history = self
+            .history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if history.len() == HASH_RATE_HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back((at, rate));
     }
-    Ok(())
 }
 
-pub fn create_optimized_mining_driver(
-    mining_config: Option<Vec<crate::mining::MiningKeyConfig>>,
-    mine: bool,
-    config: OptimizedMiningConfig,
-    init_complete_tx: Option<tokio::sync::oneshot::Sender<()>>,
-) -> IODriverFn {
-    Box::new(move |handle| {
-        Box::pin(async move {
-            info!("🚀 Starting EPYC 9654 optimized mining with {} threads", OPTIMAL_MINING_THREADS);
-            
-            // Setup mining keys (same as original)
-            let Some(configs) = mining_config else {
-                crate::mining::enable_mining(&handle, false).await?;
-                if let Some(tx) = init_complete_tx {
-                    let _ = tx.send(());
-                }
-                return Ok(());
-            };
-            
-            if configs.len() == 1 && configs[0].share == 1 && configs[0].m == 1 && configs[0].keys.len() == 1 {
-                crate::mining::set_mining_key(&handle, configs[0].keys[0].clone()).await?;
-            } else {
-                crate::mining::set_mining_key_advanced(&handle, configs).await?;
-            }
-            crate::mining::enable_mining(&handle, mine).await?;
+/// Point-in-time copy of [`OptimizedMiningStats`], see [`OptimizedMiningStats::snapshot`]
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsSnapshot {
+    pub hashes: u64,
+    /// Hashes in the most recent one-second sample, if one was taken yet
+    pub hash_rate: Option<u64>,
+    /// Empty when the host topology is unknown
+    pub hashes_by_numa_node: BTreeMap<usize, u64>,
+    pub unexpected_effects: u64,
+    pub near_misses: u64,
+    pub stale_attempts: u64,
+    pub discarded_candidates: u64,
+    pub coalesced_candidates: u64,
+    pub external_nonces: u64,
+    pub cancellations: u64,
+    pub workers_oom: u64,
+    pub worker_panics: u64,
+    pub empty_results: u64,
+    pub effect_errors: u64,
+    pub candidate_attempts: u64,
+    pub paused_threads: u64,
+    pub current_difficulty: u64,
+    pub bytes_allocated_per_sec: u64,
+    pub hashes_per_joule: Option<f64>,
+    pub nonce_timing: TimingSummary,
+    pub poke_timing: TimingSummary,
+    pub active_optimizations: Vec<&'static str>,
+    pub effective_config: Option<EffectiveConfig>,
+    pub chains: Vec<ChainSnapshot>,
+    pub health: HealthStatus,
+}
 
-            if let Some(tx) = init_complete_tx {
-                let _ = tx.send(());
-            }
+/// A [`TimingHistogram`] boiled down to a few figures, in microseconds
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimingSummary {
+    pub samples: u64,
+    pub mean_micros: u64,
+    pub p50_micros: u64,
+    pub p99_micros: u64,
+}
 
-            if !mine {
-                return Ok(());
-            }
+impl TimingSummary {
+    fn of(histogram: &TimingHistogram) -> Self {
+        let micros = |d: Duration| u64::try_from(d.as_micros()).unwrap_or(u64::MAX);
+        Self {
+            samples: histogram.count(),
+            mean_micros: micros(histogram.mean()),
+            p50_micros: micros(histogram.quantile(0.5)),
+            p99_micros: micros(histogram.quantile(0.99)),
+        }
+    }
+}
 
-            // Enhanced mining loop with EPYC optimizations
-            let mut mining_attempts = tokio::task::JoinSet::<(
-                SerfThread<SaveableCheckpoint>,
-                u64,
-                Result<NounSlab, CrownError>,
-            )>::new();
-            
-            let hot_state = zkvm_jetpack::hot::produce_prover_hot_state();
-            let test_jets_str = std::env::var("NOCK_TEST_JETS").unwrap_or_default();
-            let test_jets = nockapp::kernel::boot::parse_test_jets(test_jets_str.as_str());
+/// Lock-free histogram of durations in power-of-two microsecond buckets
+#[derive(Default)]
+pub struct TimingHistogram {
+    buckets: [AtomicU64; TIMING_BUCKETS],
+    count: AtomicU64,
+    total_micros: AtomicU64,
+}
 
-            let mining_data: Mutex<Option<OptimizedMiningData>> = Mutex::new(None);
-            let mut cancel_tokens: Vec<NockCancelToken> = Vec::with_capacity(OPTIMAL_MINING_THREADS as usize);
-            
-            // Performance tracking
-            let hash_rate_counter = Arc::new(AtomicU64::new(0));
-            let hash_rate_counter_clone = hash_rate_counter.clone();
-            
-            // Spawn performance monitoring task
-            tokio::spawn(async move {
-                let mut last_count = 0;
-                loop {
-                    tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
-                    let current_count = hash_rate_counter_clone.load(Ordering::Relaxed);
-                    let rate = (current_count - last_count) / 10;
-                    info!("💎 Hash rate: {} hashes/sec", rate);
-                    last_count = current_count;
-                }
-            });
+impl TimingHistogram {
+    pub fn record(&self, elapsed: Duration) {
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        let bucket = ((u64::BITS - micros.leading_zeros()) as usize).saturating_sub(1);
+        self.buckets[bucket.min(TIMING_BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_micros.fetch_add(micros, Ordering::Relaxed);
+    }
 
-            loop {
-                tokio::select! {
-                    mining_result = mining_attempts.join_next(), if !mining_attempts.is_empty() => {
-                        let mining_result = mining_result.expect("Mining attempt failed");
-                        let (serf, id, slab_res) = mining_result.expect("Mining attempt result failed");
-                        let slab = slab_res.expect("Mining attempt result failed");
-                        let result = unsafe { slab.root() };
-                        
-                        // Update hash rate counter
-                        hash_rate_counter.fetch_add(1, Ordering::Relaxed);
-                        
-                        let hed = result.as_cell().expect("Expected result to be a cell").head();
-                        if hed.is_atom() && hed.eq_bytes("poke") {
-                            debug!("⚡ Mining thread {} cancelled, restarting on new block", id);
-                            start_optimized_mining_attempt(
-                                serf, 
-                                mining_data.lock().await, 
-                                &mut mining_attempts, 
-                                None, 
-                                id,
-                                &config
-                            ).await;
-                        } else {
-                            let effect = result.as_cell().expect("Expected result to be a cell").head();
-                            let [head, res, tail] = effect.uncell().expect("Expected three elements in mining result");
-                            if head.eq_bytes("mine-result") {
-                                if unsafe { res.raw_equals(&D(0)) } {
-                                    info!("🎉 BLOCK FOUND by thread {}! 🎉", id);
-                                    let [hash, poke] = tail.uncell().expect("Expected two elements in tail");
-                                    let mut poke_slab = NounSlab::new();
-                                    poke_slab.copy_into(poke);
-                                    handle.poke(crate::mining::MiningWire::Mined.to_wire(), poke_slab).await
-                                        .expect("Could not poke nockchain with mined PoW");
-
-                                    let mut nonce_slab = NounSlab::new();
-                                    nonce_slab.copy_into(hash);
-                                    start_optimized_mining_attempt(
-                                        serf, 
-                                        mining_data.lock().await, 
-                                        &mut mining_attempts, 
-                                        Some(nonce_slab), 
-                                        id,
-                                        &config
-                                    ).await;
-                                } else {
-                                    debug!("🔍 Thread {} continuing search", id);
-                                    let mut nonce_slab = NounSlab::new();
-                                    nonce_slab.copy_into(tail);
-                                    start_optimized_mining_attempt(
-                                        serf, 
-                                        mining_data.lock().await, 
-                                        &mut mining_attempts, 
-                                        Some(nonce_slab), 
-                                        id,
-                                        &config
-                                    ).await;
-                                }
-                            }
-                        }
-                    }
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
 
-                    effect_res = handle.next_effect() => {
-                        let Ok(effect) = effect_res else {
-                            warn!("Error receiving effect in optimized mining driver: {effect_res:?}");
-                            continue;
-                        };
-                        let Ok(effect_cell) = (unsafe { effect.root().as_cell() }) else {
-                            drop(effect);
-                            continue;
-                        };
+    pub fn mean(&self) -> Duration {
+        match self.count() {
+            0 => Duration::ZERO,
+            count => Duration::from_micros(self.total_micros.load(Ordering::Relaxed) / count),
+        }
+    }
 
-                        if effect_cell.head().eq_bytes("mine") {
-                            let (version_slab, header_slab, target_slab, pow_len) = {
-                                let [version, commit, target, pow_len_noun] = effect_cell.tail().uncell()
-                                    .expect("Expected three elements in %mine effect");
-                                let mut version_slab = NounSlab::new();
-                                version_slab.copy_into(version);
-                                let mut header_slab = NounSlab::new();
-                                header_slab.copy_into(commit);
-                                let mut target_slab = NounSlab::new();
-                                target_slab.copy_into(target);
-                                let pow_len = pow_len_noun.as_atom()
-                                    .expect("Expected pow-len to be an atom")
-                                    .as_u64()
-                                    .expect("Expected pow-len to be a u64");
-                                (version_slab, header_slab, target_slab, pow_len)
-                            };
-                            
-                            debug!("📦 New candidate block: {:?}",
-                                tip5_hash_to_base58(*unsafe { header_slab.root() })
-                                    .expect("Failed to convert header to Base58")
-                            );
-                            
-                            *(mining_data.lock().await) = Some(OptimizedMiningData {
-                                block_header: header_slab,
-                                version: version_slab,
-                                target: target_slab,
-                                pow_len: pow_len,
-                                optimization_stats: Arc::new(AtomicU64::new(0)),
-                            });
-
-                            if mining_attempts.is_empty() {
-                                info!("🚀 Starting {} EPYC-optimized mining threads", OPTIMAL_MINING_THREADS);
-                                for i in 0..OPTIMAL_MINING_THREADS {
-                                    let kernel = Vec::from(KERNEL);
-                                    let serf = SerfThread::<SaveableCheckpoint>::new(
-                                        kernel,
-                                        None,
-                                        hot_state.clone(),
-                                        OPTIMIZED_STACK_SIZE, // Use larger stack
-                                        test_jets.clone(),
-                                        false,
-                                    )
-                                    .await
-                                    .expect("Could not load mining kernel");
-
-                                    cancel_tokens.push(serf.cancel_token.clone());
-                                    start_optimized_mining_attempt(
-                                        serf, 
-                                        mining_data.lock().await, 
-                                        &mut mining_attempts, 
-                                        None, 
-                                        i,
-                                        &config
-                                    ).await;
-                                }
-                                info!("✅ All {} mining threads started", OPTIMAL_MINING_THREADS);
-                            } else {
-                                debug!("🔄 Restarting mining threads with new block");
-                                for token in &cancel_tokens {
-                                    token.cancel();
-                                }
-                            }
-                        }
-                    }
-                }
+    /// Upper bound of the bucket containing quantile `q` (0.0..=1.0)
+    pub fn quantile(&self, q: f64) -> Duration {
+        let buckets = self.buckets();
+        let total: u64 = buckets.iter().sum();
+        if total == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((total as f64 * q.clamp(0.0, 1.0)).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, &n) in buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return Duration::from_micros(1 << (i + 1));
             }
-        })
-    })
+        }
+        Duration::from_micros(1 << TIMING_BUCKETS)
+    }
+
+    /// Per-bucket counts; bucket `i` covers `[2^i, 2^(i+1))` microseconds
+    pub fn buckets(&self) -> Vec<u64> {
+        self.buckets
+            .iter()
+            .map(|b| b.load(Ordering::Relaxed))
+            .collect()
+    }
 }
 
-async fn start_optimized_mining_attempt(
-    serf: SerfThread<SaveableCheckpoint>,
-    mining_data: tokio::sync::MutexGuard<'_, Option<OptimizedMiningData>>,
-    mining_attempts: &mut tokio::task::JoinSet<(
-        SerfThread<SaveableCheckpoint>,
-        u64,
-        Result<NounSlab, CrownError>,
-    )>,
-    nonce: Option<NounSlab>,
-    id: u64,
-    config: &OptimizedMiningConfig,
-) {
-    // Set thread affinity for NUMA optimization
-    if config.thread_affinity {
-        if let Err(e) = set_thread_affinity(id) {
-            debug!("Could not set thread affinity for thread {}: {}", id, e);
-        }
-    }
-    
-    let mining_data_ref = mining_data.as_ref()
-        .expect("Mining data should already be initialized");
-    
-    let nonce = nonce.unwrap_or_else(|| {
-        generate_optimized_nonce(id, mining_data_ref.optimization_stats.load(Ordering::Relaxed))
-    });
-    
-    debug!("⚡ Thread {} starting optimized mining attempt", id);
-    let poke_slab = create_optimized_poke(mining_data_ref, &nonce);
-    
-    mining_attempts.spawn(async move {
-        let result = serf.poke(crate::mining::MiningWire::Candidate.to_wire(), poke_slab).await;
-        (serf, id, result)
-    });
+/// Control API view of the optimized driver
+struct OptimizedControl {
+    stats: Arc<OptimizedMiningStats>,
+    window: Duration,
 }
 
-fn create_optimized_poke(mining_data: &OptimizedMiningData, nonce: &NounSlab) -> NounSlab {
-    let mut slab = NounSlab::new();
-    let header = slab.copy_into(unsafe { *(mining_data.block_header.root()) });
-    let version = slab.copy_into(unsafe { *(mining_data.version.root()) });
-    let target = slab.copy_into(unsafe { *(mining_data.target.root()) });
-    let nonce = slab.copy_into(unsafe { *(nonce.root()) });
-    let poke_noun = T(
-        &mut slab,
-        &[version, header, nonce, target, D(mining_data.pow_len)],
-    );
-    slab.set_root(poke_noun);
-    slab
-}
\ No newline at end of file
+impl MinerControl for OptimizedControl {
+    fn health(&self) -> HealthStatus {
+        self.stats.health(self.window)
+    }
+
+    fn active_optimizations(&self) -> Vec<&'static str> {
+        self.stats.active_optimizations()
+    }
+
+    fn recent_events(&self) -> Vec<EventRecord> {
+        self.stats.events.recent()
+    }
+
+    fn effective_config(&self) -> Option<EffectiveConfig> {
+        self.stats.effective_config()
+    }
+}
+
+/// What an attempt hands back to the driver: its worker, its nonces, and its results,
+/// or the panic the backend raised while producing them
+type AttemptOutput = (u64, Vec<Nonce>, std::thread::Result<Vec<HashResult>>);
+
+struct OptimizedMiningData {
+    pub candidate: Candidate,
+    pub optimization_stats: Arc<AtomicU64>, // Track performance metrics
+    pub near_miss_bound: Option<UBig>,
+}
+
+/// Each chain's current candidate, by chain index; a chain is missing until its first
+type ChainCandidates = BTreeMap<usize, OptimizedMiningData>;
+
+// Slab recycling
+const SLAB_POOL_SLABS_PER_THREAD: usize = 2; // One nonce in flight and one returned hash per worker
+
+/// Free list of reset NounSlabs reused across mining attempts.
+///
+/// Every nonce is built on the driver task, so one pool serves all workers. A slab is
+/// only recycled once no noun inside it is referenced anymore: nonce slabs come back
+/// with their attempt's results, after the backend copied them into its poke. Hashes
+/// returned by the backend become the next nonces, and %mined pokes are moved into
+/// the node and never recycled.
+struct SlabPool {
+    free: Vec<NounSlab>,
+    capacity: usize,
+}
+
+impl SlabPool {
+    fn new(capacity: usize) -> Self {
+        Self {
+            free: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn take(&mut self) -> NounSlab {
+        self.free.pop().unwrap_or_default()
+    }
+
+    fn recycle(&mut self, mut slab: NounSlab) {
+        if self.free.len() < self.capacity {
+            // SAFETY: callers only recycle slabs whose nouns are no longer referenced
+            unsafe { slab.reset() };
+            self.free.push(slab);
+        }
+    }
+}
+
+/// Hash backends of the live workers, by worker id. A retired worker's backend is dropped
+/// and a respawned one's replaced, so cancelling every worker only reaches live serfs.
+#[derive(Default)]
+struct Workers {
+    backends: BTreeMap<u64, Arc<dyn HashBackend>>,
+    started: bool,
+    /// Chain each worker mines, by worker id; chain 0 for any not listed
+    chains: Vec<usize>,
+    /// Workers with an attempt in flight, retired or not
+    busy: BTreeSet<u64>,
+    /// Span each worker's attempts run in, by worker id
+    spans: Vec<Span>,
+}
+
+impl Workers {
+    /// Workers mining the chains in `chains`, by worker id
+    fn with_chains(chains: Vec<usize>) -> Self {
+        Self {
+            chains,
+            ..Self::default()
+        }
+    }
+
+    fn with_spans(self, spans: Vec<Span>) -> Self {
+        Self { spans, ..self }
+    }
+
+    /// Span worker `id`'s attempts run in; disabled for a worker without one
+    fn span(&self, id: u64) -> Span {
+        self.spans
+            .get(id as usize)
+            .cloned()
+            .unwrap_or_else(Span::none)
+    }
+
+    fn chain(&self, id: u64) -> usize {
+        self.chains.get(id as usize).copied().unwrap_or(0)
+    }
+
+    /// Every worker assigned to `chain`, live or not
+    fn on_chain(&self, chain: usize) -> Vec<u64> {
+        (0..self.chains.len() as u64)
+            .filter(|&id| self.chain(id) == chain)
+            .collect()
+    }
+
+    /// Add worker `id`, or replace its backend if it was respawned
+    fn insert(&mut self, id: u64, backend: Arc<dyn HashBackend>) {
+        self.backends.insert(id, backend);
+        self.started = true;
+    }
+
+    fn retire(&mut self, id: u64) {
+        self.backends.remove(&id);
+    }
+
+    fn is_live(&self, id: u64) -> bool {
+        self.backends.contains_key(&id)
+    }
+
+    /// Backend of worker `id`, which must be live
+    fn get(&self, id: u64) -> &Arc<dyn HashBackend> {
+        self.backends
+            .get(&id)
+            .unwrap_or_else(|| panic!("worker {} is not live", id))
+    }
+
+    fn cancel_all(&self) {
+        for backend in self.backends.values() {
+            backend.cancel();
+        }
+    }
+
+    /// Cancel the live workers mining `chain`, e.g. when it has a new candidate
+    fn cancel_chain(&self, chain: usize) {
+        for (&id, backend) in &self.backends {
+            if self.chain(id) == chain {
+                backend.cancel();
+            }
+        }
+    }
+
+    fn set_busy(&mut self, id: u64, busy: bool) {
+        if busy {
+            self.busy.insert(id);
+        } else {
+            self.busy.remove(&id);
+        }
+    }
+
+    fn is_busy(&self, id: u64) -> bool {
+        self.busy.contains(&id)
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.backends.len()
+    }
+
+    /// Whether workers were ever spawned, even if all have since been retired
+    fn started(&self) -> bool {
+        self.started
+    }
+}
+
+/// Attempts each worker has finished on the current candidate, and which workers
+/// `max_attempts_per_candidate` has paused until the next one arrives
+struct CandidateAttempts {
+    per_worker: Vec<u64>,
+    paused: Vec<u64>,
+}
+
+impl CandidateAttempts {
+    fn new(workers: usize) -> Self {
+        Self {
+            per_worker: vec![0; workers],
+            paused: Vec::new(),
+        }
+    }
+
+    /// Count a finished attempt by worker `id`, returning its total on this candidate
+    fn record(&mut self, id: u64) -> u64 {
+        let attempts = &mut self.per_worker[id as usize];
+        *attempts += 1;
+        *attempts
+    }
+
+    fn pause(&mut self, id: u64) {
+        self.paused.push(id);
+    }
+
+    fn total(&self) -> u64 {
+        self.per_worker.iter().sum()
+    }
+
+    /// Attempts finished by the workers `ids`
+    fn total_of(&self, ids: &[u64]) -> u64 {
+        ids.iter().map(|&id| self.per_worker[id as usize]).sum()
+    }
+
+    fn paused(&self) -> usize {
+        self.paused.len()
+    }
+
+    /// Start counting for a new candidate on the workers `ids`, returning those of them
+    /// that were paused
+    fn reset(&mut self, ids: &[u64]) -> Vec<u64> {
+        for &id in ids {
+            self.per_worker[id as usize] = 0;
+        }
+        let (restart, still_paused) = std::mem::take(&mut self.paused)
+            .into_iter()
+            .partition(|id| ids.contains(id));
+        self.paused = still_paused;
+        restart
+    }
+}
+
+// Optimized nonce generation using AVX-512 friendly patterns
+/// What [`RestartCooldown::offer`] did with a new candidate
+#[derive(Debug, PartialEq)]
+enum CooldownOffer<T> {
+    /// Restart the workers on it now
+    Install(T),
+    /// Held until the cooldown ends; `coalesced` if it replaced an earlier held candidate
+    Deferred { coalesced: bool },
+}
+
+/// Holds back candidates that arrive within `restart_cooldown` of the last restart,
+/// keeping only the newest for when the cooldown ends
+struct RestartCooldown<T> {
+    cooldown: Option<Duration>,
+    until: Option<Instant>,
+    pending: Option<T>,
+}
+
+impl<T> RestartCooldown<T> {
+    fn new(cooldown: Option<Duration>) -> Self {
+        Self {
+            cooldown,
+            until: None,
+            pending: None,
+        }
+    }
+
+    fn offer(&mut self, candidate: T, now: Instant) -> CooldownOffer<T> {
+        if self.until.is_none_or(|until| now >= until) {
+            // A held candidate would be older than this one
+            self.pending = None;
+            return CooldownOffer::Install(candidate);
+        }
+        CooldownOffer::Deferred {
+            coalesced: self.pending.replace(candidate).is_some(),
+        }
+    }
+
+    /// When the held candidate should be installed, if one is held
+    fn due(&self) -> Option<Instant> {
+        self.pending.as_ref().and(self.until)
+    }
+
+    fn take_pending(&mut self) -> Option<T> {
+        self.pending.take()
+    }
+
+    /// The workers were just restarted on a new candidate
+    fn restarted(&mut self, now: Instant) {
+        self.until = self.cooldown.map(|cooldown| now + cooldown);
+    }
+}
+
+/// A fresh nonce for worker `thread_id` of `workers`: a five-belt digest, the shape the
+/// kernel's `noun-digest:tip5` nonce must have.
+///
+/// The first belt is always congruent to `thread_id` modulo `workers`, so the workers
+/// draw from disjoint classes and no two of them can ever try the same fresh nonce on a
+/// candidate, however their RNGs are seeded. Nonces chained from a missed attempt's hash
+/// are digests too but fall in no particular class.
+pub(crate) fn generate_optimized_nonce(
+    nonce_slab: NounSlab,
+    thread_id: u64,
+    workers: u64,
+    base_entropy: u64,
+    rng: &mut impl Rng,
+) -> Nonce {
+    let workers = workers.max(1);
+    debug_assert!(thread_id < workers, "worker {} of {}", thread_id, workers);
+    // Use thread ID and time for better distribution across EPYC cores
+    let thread_entropy = (thread_id.wrapping_mul(0x517cc1b727220a95)) ^ base_entropy;
+
+    let mut belts = [0u64; 5];
+    for (i, belt) in (0u64..).zip(belts.iter_mut()) {
+        let entropy = thread_entropy.wrapping_add(i.wrapping_mul(0x9e3779b97f4a7c15));
+        *belt = (entropy ^ rng.gen::<u64>()) % PRIME;
+    }
+    // The largest member of the class is below PRIME / workers * workers <= PRIME
+    belts[0] = belts[0] % (PRIME / workers) * workers + thread_id % workers;
+
+    Nonce::from_belts_in(nonce_slab, belts)
+}
+
+// Logical core a worker is pinned to, one NUMA domain at a time so NPS2/NPS4 keep memory local
+fn optimized_cpu_for_thread(topology: Option<&Topology>, thread_id: u64) -> usize {
+    if let Some(topology) = topology {
+        return topology.cpu_for_worker(thread_id as usize);
+    }
+    // No sysfs: assume an EPYC 9654 with 4 NUMA nodes, 24 cores each
+    let numa_node = thread_id / BATCH_SIZE_PER_NUMA_NODE;
+    let core_in_node = thread_id % BATCH_SIZE_PER_NUMA_NODE;
+    (numa_node * BATCH_SIZE_PER_NUMA_NODE + core_in_node) as usize
+}
+
+/// Log a [`StatsSnapshot`] as JSON every time the process receives SIGUSR1
+#[cfg(unix)]
+fn spawn_stats_dump_on_signal(stats: Arc<OptimizedMiningStats>, window: Duration) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut signals = match signal(SignalKind::user_defined1()) {
+        Ok(signals) => signals,
+        Err(e) => {
+            warn!("Could not listen for SIGUSR1, stats dumps disabled: {}", e);
+            return;
+        }
+    };
+    info!("📋 Send SIGUSR1 to dump mining stats to the log");
+    tokio::spawn(async move {
+        while signals.recv().await.is_some() {
+            match serde_json::to_string(&stats.snapshot(window)) {
+                Ok(json) => info!("📋 Mining stats: {}", json),
+                Err(e) => warn!("Could not serialize mining stats: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn spawn_stats_dump_on_signal(_stats: Arc<OptimizedMiningStats>, _window: Duration) {
+    warn!("Stats dumps on SIGUSR1 need a Unix host");
+}
+
+/// Optimizations actually in effect, as opposed to requested in `config`: the field kernels
+/// the backend selection left in use, CPU placement only where sysfs showed a topology, and
+/// memory locking only if it succeeded. `memory_prefetch` and `cache_aligned` change nothing
+/// in this driver, so they never appear.
+fn effective_optimizations(
+    config: &OptimizedMiningConfig,
+    memory_locked: bool,
+) -> Vec<&'static str> {
+    use zkvm_jetpack::field::backend::{self, FieldBackend};
+
+    let mut active = Vec::new();
+    match backend::selected_backend() {
+        Some(FieldBackend::Scalar) => {}
+        Some(FieldBackend::Avx2) => active.push("avx2"),
+        _ if FieldBackend::Avx512.is_supported() => active.push("avx512"),
+        _ if FieldBackend::Avx2.is_supported() => active.push("avx2"),
+        _ => {}
+    }
+    if backend::selected_backend() == Some(FieldBackend::OpenCl) {
+        active.push("opencl");
+    }
+    if let Some(topology) = config.topology().filter(|_| config.thread_affinity) {
+        active.push("thread-affinity");
+        // Workers are placed one NUMA domain at a time, so placement is NUMA aware whenever
+        // there is more than one domain to spread over
+        if topology.numa_nodes.len() > 1 {
+            active.push("numa");
+        }
+    }
+    if memory_locked {
+        active.push("memlock");
+    }
+    active
+}
+
+/// What the driver runs with under `config` once `mining_threads` and the `optimizations`
+/// in effect are known. Reserved CPUs only count where workers are pinned around them.
+fn effective_config(
+    config: &OptimizedMiningConfig,
+    mining_threads: u64,
+    optimizations: Vec<&'static str>,
+) -> EffectiveConfig {
+    let pinned = config.thread_affinity
+        && config
+            .topology()
+            .is_some_and(|topology| !topology.cpu_domains().is_empty());
+    let hashing = match config.backend_factory {
+        Some(_) => "custom backend",
+        None => "miner kernel",
+    };
+    let backend = match zkvm_jetpack::field::backend::selected_backend() {
+        Some(field) => format!("{hashing}, {field} field kernels"),
+        None => format!("{hashing}, widest supported field kernels"),
+    };
+    EffectiveConfig {
+        miner: "optimized",
+        threads: mining_threads as usize,
+        optimizations,
+        reserved_cpus: if pinned {
+            config.reserved_cpus.clone()
+        } else {
+            Vec::new()
+        },
+        stack_size: config.stack_size,
+        backend,
+    }
+}
+
+/// Pin the field kernels to AVX2 when `use_avx512` is off, or when `avx512_license_aware`
+/// is on and this CPU downclocks for AVX-512; otherwise they keep picking the widest
+fn select_field_backend(config: &OptimizedMiningConfig) {
+    use zkvm_jetpack::field::backend::{self, FieldBackend};
+
+    if !config.use_avx512 {
+        let pinned = [FieldBackend::Avx2, FieldBackend::Scalar]
+            .into_iter()
+            .find(|&b| backend::set_backend(b).is_ok());
+        if let Some(pinned) = pinned {
+            info!("🧮 AVX-512 disabled, field kernels pinned to {}", pinned);
+        }
+    } else if config.avx512_license_aware && backend::avoid_avx512_downclock() {
+        info!("🧮 This CPU downclocks for AVX-512, field kernels pinned to avx2");
+    }
+    if config.verify_field_ops != VerifyMode::Off {
+        zkvm_jetpack::field::verify::set_verify_mode(config.verify_field_ops);
+        info!(
+            "🧮 Checking SIMD field results against the scalar code ({} on divergence)",
+            config.verify_field_ops
+        );
+    }
+}
+
+/// Why `chains` can't be mined together under `config`, if they can't
+fn check_chains(
+    chains: &[MiningChain],
+    config: &OptimizedMiningConfig,
+) -> Result<(), &'static str> {
+    if chains.iter().all(|chain| chain.weight == 0) {
+        return Err("no chain to mine has a weight above zero");
+    }
+    if chains.iter().filter(|chain| chain.source.is_none()).count() > 1 {
+        return Err("only one chain can mine the node's %mine effects");
+    }
+    if chains.len() > 1 && config.fixed_candidate.is_some() {
+        return Err("a fixed candidate can't be mined alongside other chains");
+    }
+    if chains.len() > 1 && config.nonce_source.is_some() {
+        return Err("an external nonce source can't feed several chains");
+    }
+    Ok(())
+}
+
+/// The span worker `id`, pinned to `numa_node` if known, runs its attempts in
+fn worker_span(config: &OptimizedMiningConfig, id: u64, numa_node: Option<usize>) -> Span {
+    match &config.worker_span {
+        Some(build) => build(id, numa_node),
+        None => info_span!("mining_worker", thread_id = id, numa_node),
+    }
+}
+
+/// Logical core each worker will be pinned to, indexed by worker id
+fn worker_cpus(config: &OptimizedMiningConfig) -> Vec<usize> {
+    match config.topology() {
+        Some(topology) => {
+            crate::topology::assign_workers(&topology, config.mining_threads as usize)
+        }
+        None => (0..config.mining_threads)
+            .map(|id| optimized_cpu_for_thread(None, id))
+            .collect(),
+    }
+}
+
+// NUMA-aware thread placement for EPYC 9654
+fn set_thread_affinity(logical_core: usize) -> Result<(), Box<dyn std::error::Error>> {
+    set_thread_affinity_set(&[logical_core])
+}
+
+// Let the calling thread run on any of `logical_cores`
+fn set_thread_affinity_set(logical_cores: &[usize]) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::mem;
+
+        use libc::{cpu_set_t, sched_setaffinity, CPU_SET, CPU_ZERO};
+
+        unsafe {
+            let mut cpu_set: cpu_set_t = mem::zeroed();
+            CPU_ZERO(&mut cpu_set);
+            for &logical_core in logical_cores {
+                CPU_SET(logical_core, &mut cpu_set);
+            }
+
+            if sched_setaffinity(0, mem::size_of::<cpu_set_t>(), &cpu_set) != 0 {
+                return Err("Failed to set thread affinity".into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Logical cores reserved for the async runtime: the CPUs no mining worker is pinned to
+/// in the NUMA domain with the most of them, or all of the first domain when the workers
+/// cover every CPU. Either way async work stays within one domain. Empty if the
+/// topology is unknown.
+fn runtime_cpus(config: &OptimizedMiningConfig) -> Vec<usize> {
+    let Some(topology) = config.topology() else {
+        return Vec::new();
+    };
+    let used: std::collections::HashSet<usize> = worker_cpus(config).into_iter().collect();
+    let domains = topology.cpu_domains();
+    let free = domains
+        .iter()
+        .map(|domain| {
+            domain
+                .cpus
+                .iter()
+                .copied()
+                .filter(|cpu| !used.contains(cpu))
+                .collect::<Vec<_>>()
+        })
+        .fold(Vec::new(), |best, cpus| {
+            if cpus.len() > best.len() {
+                cpus
+            } else {
+                best
+            }
+        });
+    if !free.is_empty() {
+        return free;
+    }
+    domains
+        .first()
+        .map(|domain| domain.cpus.clone())
+        .unwrap_or_default()
+}
+
+/// Multi-threaded tokio runtime whose threads are pinned to the cores the mining workers
+/// leave free, so async work (including the driver loop and effect handling) stops
+/// migrating across NUMA nodes. Blocking-pool threads get the same mask. Without a known
+/// topology the runtime is built unpinned.
+pub fn build_pinned_runtime(
+    config: &OptimizedMiningConfig,
+) -> std::io::Result<tokio::runtime::Runtime> {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    builder.enable_all();
+    let cpus = runtime_cpus(config);
+    if cpus.is_empty() {
+        debug!("Unknown CPU topology, tokio runtime threads stay unpinned");
+        return builder.build();
+    }
+    info!(
+        "📌 Pinning {} tokio worker threads to cores {:?}",
+        cpus.len(),
+        cpus
+    );
+    builder
+        .worker_threads(cpus.len())
+        .on_thread_start(move || {
+            if let Err(e) = set_thread_affinity_set(&cpus) {
+                debug!("Could not pin tokio runtime thread: {}", e);
+            }
+        })
+        .build()
+}
+
+/// IO driver that mines with `config.mining_threads` workers.
+///
+/// To keep the async side off the mining cores, run the node on a runtime from
+/// [`build_pinned_runtime`] built with the same config instead of `#[tokio::main]`:
+///
+/// ```ignore
+/// let runtime = build_pinned_runtime(&config)?;
+/// runtime.block_on(async move {
+///     nockapp.add_io_driver(create_optimized_mining_driver(keys, true, config, stats, None)).await;
+///     nockapp.run().await
+/// })
+/// ```
+pub fn create_optimized_mining_driver(
+    mining_config: Option<Vec<crate::mining::MiningKeyConfig>>,
+    mine: bool,
+    config: OptimizedMiningConfig,
+    stats: Arc<OptimizedMiningStats>,
+    init_complete_tx: Option<tokio::sync::oneshot::Sender<()>>,
+) -> IODriverFn {
+    create_optimized_mining_driver_with_source(
+        mining_config, mine, config, stats, init_complete_tx, None,
+    )
+}
+
+/// Like [`create_optimized_mining_driver`], but mining the candidates `source` yields
+/// instead of the node's %mine effects when it is set, e.g. a pool's jobs or a
+/// [`crate::candidate_source::ReplaySource`] for deterministic replays.
+/// A configured `fixed_candidate` still takes precedence over any source.
+pub fn create_optimized_mining_driver_with_source(
+    mining_config: Option<Vec<crate::mining::MiningKeyConfig>>,
+    mine: bool,
+    config: OptimizedMiningConfig,
+    stats: Arc<OptimizedMiningStats>,
+    init_complete_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    source: Option<Box<dyn CandidateSource>>,
+) -> IODriverFn {
+    let chain = MiningChain {
+        name: "primary".to_string(),
+        weight: 1,
+        source,
+        submit: config.submit.clone(),
+    };
+    create_optimized_mining_driver_with_chains(
+        mining_config,
+        mine,
+        config,
+        stats,
+        init_complete_tx,
+        vec![chain],
+    )
+}
+
+/// Like [`create_optimized_mining_driver`], but mining several chains at once, e.g. two
+/// related chains merge-mined from one rig. The workers are split between the chains by
+/// their weights (see [`crate::mining_chains::assign_workers`]), a new candidate on one
+/// chain only restarts that chain's workers, and each chain's solutions go where its
+/// [`MiningChain::submit`] says. Per-chain counters are in [`OptimizedMiningStats::chains`].
+///
+/// `fixed_candidate`, `nonce_source` and `submit` apply to a single chain, so the first
+/// two are refused with more than one; `record_candidates` records the first chain.
+pub fn create_optimized_mining_driver_with_chains(
+    mining_config: Option<Vec<crate::mining::MiningKeyConfig>>,
+    mine: bool,
+    config: OptimizedMiningConfig,
+    stats: Arc<OptimizedMiningStats>,
+    init_complete_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    chains: Vec<MiningChain>,
+) -> IODriverFn {
+    Box::new(move |handle| {
+        Box::pin(async move {
+            check_chains(&chains, &config).map_err(|e| {
+                error!("❌ Refusing to start the optimized mining driver: {e}");
+                NockAppError::OtherError
+            })?;
+            let mut autotuner = config.autotune.as_ref().and_then(Autotuner::new);
+            let mut config = config;
+            let mut nonce_source = config.nonce_source.take();
+            if let Some(tuner) = &autotuner {
+                // Every count the sweep tries needs a worker ready
+                config.mining_threads = tuner.max_threads();
+                info!(
+                    "🎛️ Auto-tuning the thread count, trying {:?}",
+                    tuner.counts()
+                );
+            }
+            let mining_threads = config.mining_threads;
+            let weights: Vec<u32> = chains.iter().map(|chain| chain.weight).collect();
+            let worker_chains = crate::mining_chains::assign_workers(&weights, mining_threads)
+                .expect("checked that some chain has a weight");
+            // Workers mining at any one time; the rest wait for the auto-tuner
+            let active_threads = |tuner: &Option<Autotuner>| {
+                tuner.as_ref().map_or(mining_threads, Autotuner::threads)
+            };
+            info!(
+                "🚀 Starting EPYC 9654 optimized mining with {} threads",
+                mining_threads
+            );
+
+            // Setup mining keys (same as original)
+            let configs = crate::mining::mining_keys(mining_config, mine).map_err(|e| {
+                error!("❌ Refusing to start the optimized mining driver: {e}");
+                NockAppError::OtherError
+            })?;
+            let Some(configs) = configs else {
+                crate::mining::enable_mining(&handle, false).await?;
+                if let Some(tx) = init_complete_tx {
+                    let _ = tx.send(());
+                }
+                return Ok(());
+            };
+
+            if configs.len() == 1
+                && configs[0].share == 1
+                && configs[0].m == 1
+                && configs[0].keys.len() == 1
+            {
+                crate::mining::set_mining_key(&handle, configs[0].keys[0].clone()).await?;
+            } else {
+                crate::mining::set_mining_key_advanced(&handle, configs).await?;
+            }
+            crate::mining::enable_mining(&handle, mine).await?;
+
+            if let Some(tx) = init_complete_tx {
+                let _ = tx.send(());
+            }
+
+            if !mine {
+                return Ok(());
+            }
+
+            select_field_backend(&config);
+            // Built once here so no worker pays for it on its first hash
+            let tables = zkvm_jetpack::field::tables::shared();
+            info!(
+                "🧮 Shared precomputed field tables: {} KiB",
+                tables.bytes() / 1024
+            );
+
+            if let Some(topology) = config.topology() {
+                info!(
+                    "🧭 NUMA layout: {} domains, {}",
+                    topology.numa_nodes.len(),
+                    topology.nps_mode()
+                );
+                if !config.reserved_cpus.is_empty() && topology.cpu_domains().is_empty() {
+                    warn!("Every CPU is reserved, workers will be pinned as if none were");
+                }
+            } else if !config.reserved_cpus.is_empty() {
+                warn!(
+                    "CPU topology unknown, reserved CPUs {:?} are not kept free of workers",
+                    config.reserved_cpus
+                );
+            }
+            if config.thread_affinity {
+                info!(
+                    "📌 Mining cores: {:?} (reserved: {:?})",
+                    config.mining_cpus(),
+                    config.reserved_cpus
+                );
+            }
+            // Some hypervisors accept affinity calls without honouring them
+            let pinning = config
+                .thread_affinity
+                .then(|| crate::topology::probe_pinning(&config.mining_cpus()));
+
+            if let Some(path) = &config.topology_report_path {
+                let assignment = worker_cpus(&config);
+                let result = match &config.topology {
+                    Some(topology) => topology
+                        .report(&assignment)
+                        .with_pinning(pinning.as_ref())
+                        .write_to(path),
+                    None => {
+                        crate::topology::export_topology_report(path, &assignment, pinning.as_ref())
+                    }
+                };
+                if let Err(e) = result {
+                    warn!(
+                        "Could not write topology report to {}: {}",
+                        path.display(),
+                        e
+                    );
+                }
+            }
+
+            // Enhanced mining loop with EPYC optimizations
+            let mut mining_attempts = tokio::task::JoinSet::<AttemptOutput>::new();
+
+            let hot_state = zkvm_jetpack::hot::produce_prover_hot_state();
+            let test_jets_str = std::env::var("NOCK_TEST_JETS").unwrap_or_default();
+            let test_jets = nockapp::kernel::boot::parse_test_jets(test_jets_str.as_str());
+
+            let mining_data: Mutex<ChainCandidates> = Mutex::new(BTreeMap::new());
+            let mut slab_pool = SlabPool::new(mining_threads as usize * SLAB_POOL_SLABS_PER_THREAD);
+            // A fixed candidate's workers replay seeded streams, each kept across attempts
+            let mut nonce_rngs: Vec<NonceRng> = (0..mining_threads)
+                .map(|id| match &config.fixed_candidate {
+                    Some(fixed) => {
+                        NonceRng::Seeded(Box::new(StdRng::seed_from_u64(fixed.nonce_seed ^ id)))
+                    }
+                    None => config.entropy_source.for_worker(id),
+                })
+                .collect();
+            match &config.fixed_candidate {
+                Some(fixed) => info!("🎲 Nonce entropy source: fixed seed {}", fixed.nonce_seed),
+                None => info!("🎲 Nonce entropy source: {}", config.entropy_source),
+            }
+            // NUMA node of each pinned worker, to split the hash count by node
+            let worker_nodes: Vec<Option<usize>> =
+                match config.topology().filter(|_| config.thread_affinity) {
+                    Some(topology) => (0..mining_threads)
+                        .map(|id| {
+                            let cpu = topology.cpu_for_worker(id as usize);
+                            topology
+                                .cpus
+                                .iter()
+                                .find(|info| info.cpu == cpu)
+                                .map(|info| info.numa_node)
+                        })
+                        .collect(),
+                    None => Vec::new(),
+                };
+            let worker_spans = (0..mining_threads)
+                .map(|id| {
+                    worker_span(
+                        &config,
+                        id,
+                        worker_nodes.get(id as usize).copied().flatten(),
+                    )
+                })
+                .collect();
+            let mut workers = Workers::with_chains(worker_chains).with_spans(worker_spans);
+            // Stacks of workers respawned after running out of memory; the rest use `stack_size`
+            let mut worker_stack_sizes: BTreeMap<u64, usize> = BTreeMap::new();
+            // Attempts completed on the current candidate, to spot candidates replaced before
+            // any finished and to pause workers that have ground on one for too long
+            let mut candidate_attempts = CandidateAttempts::new(mining_threads as usize);
+            // Solutions found so far, for `stop_after_solutions`
+            let mut solutions_found = 0u64;
+            let mut sources: Vec<Box<dyn CandidateSource + '_>> = Vec::with_capacity(chains.len());
+            // Where each chain's solutions go, and its counters
+            let mut chain_submits = Vec::with_capacity(chains.len());
+            let mut chain_stats = Vec::with_capacity(chains.len());
+            for (index, chain) in chains.into_iter().enumerate() {
+                let chain_workers = workers.on_chain(index).len() as u64;
+                if weights.len() > 1 {
+                    info!(
+                        "⛓️ Mining chain {} with {} of {} threads",
+                        chain.name, chain_workers, mining_threads
+                    );
+                }
+                chain_stats.push(Arc::new(ChainStats::new(
+                    chain.name, chain.weight, chain_workers,
+                )));
+                chain_submits.push(chain.submit);
+                sources.push(match chain.source {
+                    Some(source) => source,
+                    None => Box::new(
+                        EffectCandidateSource::new(&handle)
+                            .with_retry(config.effect_retry)
+                            .with_error_counter(stats.effect_errors.clone()),
+                    ),
+                });
+            }
+            *stats
+                .chains
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = chain_stats.clone();
+            if let Some(path) = &config.record_candidates {
+                let first = sources.remove(0);
+                let recorded: Box<dyn CandidateSource + '_> =
+                    match crate::candidate_source::record_candidates(first, path) {
+                        Ok(recording) => {
+                            info!("📼 Recording candidates to {}", path.display());
+                            Box::new(recording)
+                        }
+                        Err(e) => {
+                            warn!(
+                                "Could not open candidate recording {}: {}",
+                                path.display(),
+                                e
+                            );
+                            return Err(NockAppError::IoError(e));
+                        }
+                    };
+                sources.insert(0, recorded);
+            }
+            let mut candidates = ChainSources::new(sources);
+            // A fixed candidate is mined on its own, so the source is never polled
+            let mut candidates_open = config.fixed_candidate.is_none();
+            let mut cooldowns: Vec<RestartCooldown<Candidate>> = chain_stats
+                .iter()
+                .map(|_| RestartCooldown::new(config.restart_cooldown))
+                .collect();
+            stats
+                .expected_threads
+                .store(active_threads(&autotuner), Ordering::Relaxed);
+
+            if let Some(addr) = config.control_addr {
+                let control = Arc::new(OptimizedControl {
+                    stats: stats.clone(),
+                    window: config.health_window,
+                });
+                tokio::spawn(async move {
+                    if let Err(e) = crate::control::serve(addr, control).await {
+                        warn!("Control API on {} stopped: {}", addr, e);
+                    }
+                });
+            }
+
+            if config.stats_signal {
+                spawn_stats_dump_on_signal(stats.clone(), config.health_window);
+            }
+
+            let solution_log = match &config.solution_log {
+                Some(path) => match SolutionLog::open(path) {
+                    Ok(log) => {
+                        info!("📝 Recording found solutions to {}", path.display());
+                        Some(log)
+                    }
+                    Err(e) => {
+                        warn!("Could not open solution log {}: {}", path.display(), e);
+                        return Err(NockAppError::IoError(e));
+                    }
+                },
+                None => None,
+            };
+            let secondary = config.secondary_submit.clone().map(|path| {
+                info!("📮 Also submitting found blocks to {}", path.display());
+                NpcSubmitTarget::new(path)
+            });
+            let attempt_log = AttemptLog::new(config.attempt_log);
+
+            // Before any backend maps its Nock stack, so the stacks are locked as they are mapped
+            let memory_locked = config.lock_memory
+                && match crate::memlock::lock_all() {
+                    Ok(()) => {
+                        info!("🔒 Locked mining memory, including the Nock stacks");
+                        true
+                    }
+                    Err(e) => {
+                        warn!("Could not lock mining memory, continuing unlocked: {}", e);
+                        false
+                    }
+                };
+            let optimizations = effective_optimizations(&config, memory_locked);
+            info!("⚙️ Active optimizations: {}", optimizations.join(", "));
+            let effective = effective_config(&config, mining_threads, optimizations.clone());
+            info!(
+                "⚙️ Effective config: {}",
+                serde_json::to_string(&effective).unwrap_or_default()
+            );
+            *stats
+                .effective_config
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(effective);
+            *stats
+                .active_optimizations
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = optimizations;
+            if config.max_alloc_bytes_per_sec.is_some() && !crate::alloc_stats::ENABLED {
+                warn!("Allocation cap ignored: this build does not count allocations (enable the alloc_stats feature)");
+            }
+
+            // Spawn performance monitoring task
+            let monitor_stats = stats.clone();
+            let near_miss_enabled = config.near_miss_factor.is_some();
+            let timing_enabled = config.timing;
+            let log_stale_candidates = config.log_stale_candidates;
+            let health_window = config.health_window;
+            let min_expected_hashrate = config.min_expected_hashrate;
+            let hashrate_alert_window = config.hashrate_alert_window;
+            let hashrate_format = config.hashrate_format;
+            let mut energy_meter = match crate::power::EnergyMeter::detect() {
+                Ok(meter) => {
+                    info!(
+                        "⚡ Reading RAPL energy counters for {} CPU packages",
+                        meter.packages()
+                    );
+                    Some(meter)
+                }
+                Err(e) => {
+                    info!("⚡ No readable RAPL energy counters ({}), hashes per joule will not be reported", e);
+                    None
+                }
+            };
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
+                interval.tick().await;
+                let mut last_count = 0;
+                let mut last_logged_count = 0;
+                let mut last_allocated = crate::alloc_stats::allocated_bytes();
+                let mut last_joules = 0.0;
+                let mut ticks = 0u64;
+                let (mut was_live, mut stalled) = (false, false);
+                loop {
+                    interval.tick().await;
+                    let health = monitor_stats.health(health_window);
+                    if was_live && !health.live {
+                        monitor_stats.events.push(MinerEvent::Stalled {
+                            seconds_since_last_attempt: health.seconds_since_last_attempt,
+                        });
+                        stalled = true;
+                    } else if stalled && health.live {
+                        monitor_stats.events.push(MinerEvent::Resumed);
+                        stalled = false;
+                    }
+                    was_live = health.live;
+                    let current_count = monitor_stats.hashes.load(Ordering::Relaxed);
+                    match hash_count_delta(current_count, last_count) {
+                        Some(delta) => monitor_stats.record_rate(Instant::now(), delta),
+                        None => {
+                            warn!(
+                                "Hash counter went backwards ({} -> {}), skipping this rate sample",
+                                last_count, current_count
+                            );
+                            last_logged_count = current_count;
+                        }
+                    }
+                    last_count = current_count;
+                    if let Some(floor) = min_expected_hashrate {
+                        match monitor_stats.check_hash_rate_floor(
+                            Instant::now(),
+                            floor,
+                            hashrate_alert_window,
+                        ) {
+                            Some((true, rate)) => {
+                                warn!(
+                                    "🚨 HASH RATE DEGRADED: {} over the last {:?}, below the expected {}. Check for throttling, wedged workers or misconfiguration",
+                                    hashrate_format.format(rate), hashrate_alert_window, hashrate_format.format(floor as f64)
+                                );
+                                monitor_stats.events.push(MinerEvent::HashRateDegraded {
+                                    hashes_per_sec: rate,
+                                    min_expected: floor,
+                                });
+                            }
+                            Some((false, rate)) => {
+                                info!(
+                                    "✅ Hash rate recovered to {}, above the expected {}",
+                                    hashrate_format.format(rate),
+                                    hashrate_format.format(floor as f64)
+                                );
+                                monitor_stats.events.push(MinerEvent::HashRateRecovered {
+                                    hashes_per_sec: rate,
+                                });
+                            }
+                            None => {}
+                        }
+                    }
+                    let allocated = crate::alloc_stats::allocated_bytes();
+                    monitor_stats
+                        .bytes_allocated_per_sec
+                        .store(allocated - last_allocated, Ordering::Relaxed);
+                    last_allocated = allocated;
+
+                    ticks += 1;
+                    if ticks % HASH_RATE_LOG_INTERVAL_SECS == 0 {
+                        let rate = current_count.saturating_sub(last_logged_count) as f64
+                            / HASH_RATE_LOG_INTERVAL_SECS as f64;
+                        info!(
+                            "💎 Hash rate: {} at difficulty {} bits",
+                            hashrate_format.format(rate),
+                            monitor_stats.current_difficulty.load(Ordering::Relaxed)
+                        );
+                        if let Some(meter) = &mut energy_meter {
+                            match meter.read_joules() {
+                                Ok(joules) => {
+                                    let hashes = current_count.saturating_sub(last_logged_count);
+                                    let interval_joules = joules - last_joules;
+                                    if let Some(efficiency) =
+                                        crate::power::hashes_per_joule(hashes, interval_joules)
+                                    {
+                                        monitor_stats
+                                            .hashes_per_joule
+                                            .store(efficiency.to_bits(), Ordering::Relaxed);
+                                        info!(
+                                            "⚡ Efficiency: {:.6} hashes/joule at {:.0} W",
+                                            efficiency,
+                                            interval_joules / HASH_RATE_LOG_INTERVAL_SECS as f64
+                                        );
+                                    }
+                                    last_joules = joules;
+                                }
+                                Err(e) => {
+                                    warn!("Could not read RAPL energy counters, no longer reporting hashes per joule: {}", e);
+                                    energy_meter = None;
+                                }
+                            }
+                        }
+                        if near_miss_enabled {
+                            info!(
+                                "🎯 Near misses: {}",
+                                monitor_stats.near_misses.load(Ordering::Relaxed)
+                            );
+                        }
+                        let chains = monitor_stats.chains();
+                        if chains.len() > 1 {
+                            for chain in chains {
+                                info!(
+                                    "⛓️ Chain {}: {} hashes, {} solutions, {} candidates, difficulty {} bits",
+                                    chain.name, chain.hashes, chain.solutions, chain.candidates, chain.current_difficulty
+                                );
+                            }
+                        }
+                        if crate::alloc_stats::ENABLED {
+                            info!(
+                                "🧮 Allocation rate: {} bytes/sec",
+                                monitor_stats
+                                    .bytes_allocated_per_sec
+                                    .load(Ordering::Relaxed)
+                            );
+                        }
+                        if log_stale_candidates {
+                            info!(
+                                "♻️ Stale attempts: {}, discarded candidates: {}, coalesced candidates: {}, cancellations: {}",
+                                monitor_stats.stale_attempts.load(Ordering::Relaxed),
+                                monitor_stats.discarded_candidates.load(Ordering::Relaxed),
+                                monitor_stats.coalesced_candidates.load(Ordering::Relaxed),
+                                monitor_stats.cancellations.load(Ordering::Relaxed)
+                            );
+                        }
+                        if timing_enabled {
+                            for (name, histogram) in [
+                                ("nonce", &monitor_stats.nonce_timing),
+                                ("poke", &monitor_stats.poke_timing),
+                            ] {
+                                info!(
+                                    "⏱️ {} timing: mean {:?}, p50 <{:?}, p99 <{:?} over {} samples",
+                                    name,
+                                    histogram.mean(),
+                                    histogram.quantile(0.5),
+                                    histogram.quantile(0.99),
+                                    histogram.count()
+                                );
+                            }
+                        }
+                        last_logged_count = current_count;
+                    }
+                }
+            });
+
+            if let Some(fixed) = &config.fixed_candidate {
+                info!("🧪 Mining fixed candidate, %mine effects will be ignored");
+                let candidate = fixed.to_candidate();
+                let difficulty = stats.record_difficulty(candidate.target());
+                stats.events.push(MinerEvent::CandidateReceived {
+                    difficulty_bits: difficulty,
+                });
+                chain_stats[0].candidates.fetch_add(1, Ordering::Relaxed);
+                chain_stats[0]
+                    .current_difficulty
+                    .store(difficulty.unwrap_or_default() as u64, Ordering::Relaxed);
+                let near_miss_bound =
+                    near_miss_bound_for(candidate.target(), config.near_miss_factor);
+                mining_data.lock().await.insert(
+                    0,
+                    OptimizedMiningData {
+                        candidate,
+                        optimization_stats: Arc::new(AtomicU64::new(0)),
+                        near_miss_bound,
+                    },
+                );
+                start_optimized_mining_threads(
+                    &hot_state,
+                    test_jets.clone(),
+                    &mining_data,
+                    &mut mining_attempts,
+                    &mut slab_pool,
+                    &mut nonce_rngs,
+                    &mut nonce_source,
+                    &mut workers,
+                    active_threads(&autotuner),
+                    &config,
+                    &stats,
+                )
+                .await;
+                if let Some(tuner) = &mut autotuner {
+                    tuner.start(Instant::now());
+                }
+            }
+
+            loop {
+                stats
+                    .active_threads
+                    .store(mining_attempts.len() as u64, Ordering::Relaxed);
+                let tune_at = autotuner.as_ref().and_then(Autotuner::wake_at);
+                // The chain whose held candidate is due first
+                let cooldown_due = cooldowns
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(chain, cooldown)| Some((cooldown.due()?, chain)))
+                    .min();
+                // Set by the arms that hand a chain's workers a new candidate
+                let mut install = None;
+                tokio::select! {
+                    mining_result = mining_attempts.join_next(), if !mining_attempts.is_empty() => {
+                        let mining_result = mining_result.expect("Mining attempt failed");
+                        let (id, nonces, results) = mining_result.expect("Mining attempt result failed");
+                        workers.set_busy(id, false);
+                        let chain = workers.chain(id);
+                        let results = match results {
+                            Ok(results) => results,
+                            Err(payload) => {
+                                // Caught at the task boundary; the nonces are left to drop
+                                stats.worker_panics.fetch_add(1, Ordering::Relaxed);
+                                stats.events.push(MinerEvent::WorkerPanicked { thread: id });
+                                if !workers.is_live(id) {
+                                    continue;
+                                }
+                                let stack_size = *worker_stack_sizes.get(&id).unwrap_or(&config.stack_size);
+                                error!("💥 Thread {} panicked, respawning it: {}", id, panic_message(payload.as_ref()));
+                                match new_backend(&config, &hot_state, test_jets.clone(), stack_size).await {
+                                    Ok(backend) => {
+                                        workers.insert(id, backend);
+                                        stats.events.push(MinerEvent::WorkerRestarted { thread: id, stack_size });
+                                    }
+                                    Err(e) => {
+                                        warn!("💥 Could not respawn thread {}, taking it out of rotation: {}", id, e);
+                                        workers.retire(id);
+                                        stats.events.push(MinerEvent::WorkerRetired { thread: id });
+                                        continue;
+                                    }
+                                }
+                                start_optimized_mining_attempt(
+                                    &mut workers,
+                                    mining_data.lock().await,
+                                    &mut mining_attempts,
+                                    &mut slab_pool,
+                                    &mut nonce_rngs,
+                                    &mut nonce_source,
+                                    None,
+                                    id,
+                                    true,
+                                    &config,
+                                    &stats
+                                ).await;
+                                continue;
+                            }
+                        };
+                        let attempted = results.len() as u64;
+                        let (solutions, rest) = split_solutions(results);
+                        if rest.is_none() && solutions.is_empty() {
+                            // Nothing to chain from, so the restart below draws a fresh nonce
+                            stats.empty_results.fetch_add(1, Ordering::Relaxed);
+                            warn!("Hash backend returned no results for thread {}, restarting it with a fresh nonce", id);
+                        }
+
+                        // Submit every block the attempt found, in nonce order, not just the first.
+                        // With `prioritize_submission` nothing else (the mining_data lock, the
+                        // solution log, bookkeeping) runs between the find and the submit.
+                        let found = solutions.len();
+                        let mut solution_nonce = None;
+                        let mut to_log = Vec::new();
+                        if found > 1 {
+                            info!("🎉 Thread {} found {} blocks in one attempt, submitting all of them", id, found);
+                        }
+                        for (n, (hash, poke)) in solutions.into_iter().enumerate() {
+                            if !config.prioritize_submission {
+                                if let Some(log) = &solution_log {
+                                    log_solution(log, &*mining_data.lock().await, chain, id, &poke, &hash);
+                                }
+                            } else if solution_log.is_some() {
+                                to_log.push((poke.clone(), hash.clone()));
+                            }
+                            let (internal, external) = chain_submits[chain].route(poke);
+                            if let Some(poke) = internal {
+                                let submitted = crate::mining::submit_mined_block(&handle, secondary.as_ref(), poke)
+                                    .instrument(workers.span(id))
+                                    .await;
+                                if submitted.is_err() {
+                                    warn!("💔 Block found by thread {} (solution {} of {}) was not submitted", id, n + 1, found);
+                                }
+                            }
+                            if let Some((callback, poke)) = external {
+                                deliver_solution(callback, &*mining_data.lock().await, chain, id, poke, &hash);
+                            }
+                            info!("🎉 BLOCK FOUND by thread {}! 🎉 (solution {} of {})", id, n + 1, found);
+                            stats.events.push(MinerEvent::BlockFound { thread: id });
+                            OptimizedMiningStats::mark(&stats.last_solution_at);
+                            solution_nonce = Some(Nonce::from_slab(hash));
+                        }
+                        if let (false, Some(log)) = (to_log.is_empty(), &solution_log) {
+                            let candidates = mining_data.lock().await;
+                            for (poke, hash) in &to_log {
+                                log_solution(log, &candidates, chain, id, poke, hash);
+                            }
+                        }
+
+                        // The backend copied each nonce into its poke, so they can be reused
+                        for nonce in nonces {
+                            slab_pool.recycle(nonce.into_slab());
+                        }
+
+                        // Update hash rate counter
+                        stats.hashes.fetch_add(attempted, Ordering::Relaxed);
+                        chain_stats[chain].hashes.fetch_add(attempted, Ordering::Relaxed);
+                        chain_stats[chain].solutions.fetch_add(found as u64, Ordering::Relaxed);
+                        if let Some(node) = worker_nodes.get(id as usize).copied().flatten() {
+                            stats.record_node_hashes(node, attempted);
+                        }
+                        OptimizedMiningStats::mark(&stats.last_attempt_at);
+
+                        let mut exhausted = false;
+                        if !matches!(rest, Some(HashResult::Cancelled)) {
+                            let attempts = candidate_attempts.record(id);
+                            stats.candidate_attempts.store(candidate_attempts.total(), Ordering::Relaxed);
+                            exhausted = config.max_attempts_per_candidate.is_some_and(|max| attempts >= max);
+                        }
+                        // Misses are routine and only sampled; everything else is a state change
+                        let log_attempt = found > 0
+                            || !matches!(rest, Some(HashResult::Miss { .. }))
+                            || attempt_log.sample();
+
+                        solutions_found += found as u64;
+                        if config.stop_after_solutions.is_some_and(|limit| solutions_found >= limit) {
+                            info!("🏁 Found {} solutions, stopping as configured", solutions_found);
+                            workers.cancel_all();
+                            handle.exit.exit(0).await?;
+                            return Ok(());
+                        }
+
+                        if !workers.is_live(id) {
+                            // Retired by the auto-tuner while this attempt was in flight
+                            continue;
+                        }
+
+                        if let Some(HashResult::Failed(e)) = &rest {
+                            if !crate::hash_backend::worker_died(e) {
+                                panic!("Mining attempt result failed: {e:?}");
+                            }
+                            // The serf is gone, most likely out of memory; keep the rest of the node mining without it
+                            stats.workers_oom.fetch_add(1, Ordering::Relaxed);
+                            let stack_size = *worker_stack_sizes.get(&id).unwrap_or(&config.stack_size);
+                            stats.events.push(MinerEvent::WorkerOutOfMemory { thread: id, stack_size });
+                            let Some(smaller) = respawn_stack_size(stack_size).filter(|_| config.respawn_after_oom) else {
+                                warn!("💥 Thread {} worker died with a {} byte stack (likely out of memory), taking it out of rotation: {}", id, stack_size, e);
+                                workers.retire(id);
+                                stats.events.push(MinerEvent::WorkerRetired { thread: id });
+                                continue;
+                            };
+                            warn!("💥 Thread {} worker died with a {} byte stack (likely out of memory), respawning it with {} bytes: {}", id, stack_size, smaller, e);
+                            match new_backend(&config, &hot_state, test_jets.clone(), smaller).await {
+                                Ok(backend) => {
+                                    workers.insert(id, backend);
+                                    worker_stack_sizes.insert(id, smaller);
+                                    stats.events.push(MinerEvent::WorkerRestarted { thread: id, stack_size: smaller });
+                                }
+                                Err(e) => {
+                                    warn!("💥 Could not respawn thread {}, taking it out of rotation: {}", id, e);
+                                    workers.retire(id);
+                                    stats.events.push(MinerEvent::WorkerRetired { thread: id });
+                                    continue;
+                                }
+                            }
+                        }
+
+                        let next_nonce = match rest {
+                            None => solution_nonce,
+                            Some(HashResult::Cancelled) => {
+                                stats.cancellations.fetch_add(1, Ordering::Relaxed);
+                                debug!("⚡ Mining thread {} cancelled, restarting on new block", id);
+                                None
+                            }
+                            Some(HashResult::Found { .. }) => unreachable!("solutions are split out of the results"),
+                            Some(HashResult::Miss { hash }) => {
+                                if log_attempt {
+                                    debug!("🔍 Thread {} continuing search", id);
+                                }
+                                let digest = unsafe { *hash.root() };
+                                let near_miss = mining_data.lock().await.get(&chain)
+                                    .and_then(|data| data.near_miss_bound.as_ref())
+                                    .is_some_and(|bound| crate::pow_target::digest_within(digest, bound).unwrap_or(false));
+                                if near_miss {
+                                    stats.near_misses.fetch_add(1, Ordering::Relaxed);
+                                    debug!(
+                                        "🎯 Near miss from thread {}: {}",
+                                        id,
+                                        tip5_hash_to_base58(digest).unwrap_or_default()
+                                    );
+                                }
+                                Some(Nonce::from_slab(hash))
+                            }
+                            Some(HashResult::Unexpected { head }) => {
+                                stats.unexpected_effects.fetch_add(1, Ordering::Relaxed);
+                                debug!("Unexpected mining result head {} from thread {}", head, id);
+                                // Keep the worker in rotation rather than letting it stall
+                                None
+                            }
+                            // Respawned above after running out of memory
+                            Some(HashResult::Failed(_)) => None,
+                        };
+                        if exhausted {
+                            // Idle rather than keep grinding a candidate the network has likely moved past
+                            info!("⏸️ Thread {} reached its attempt limit on this candidate, pausing until the next one", id);
+                            candidate_attempts.pause(id);
+                            stats.paused_threads.fetch_add(1, Ordering::Relaxed);
+                        } else {
+                            start_optimized_mining_attempt(
+                                &mut workers,
+                                mining_data.lock().await,
+                                &mut mining_attempts,
+                                &mut slab_pool,
+                                &mut nonce_rngs,
+                                &mut nonce_source,
+                                next_nonce,
+                                id,
+                                log_attempt,
+                                &config,
+                                &stats
+                            ).await;
+                        }
+                    }
+
+                    _ = tokio::time::sleep_until(tune_at.unwrap_or_else(Instant::now).into()), if tune_at.is_some() => {
+                        let Some(tuner) = &mut autotuner else { continue };
+                        match tuner.poll(Instant::now(), stats.hashes.load(Ordering::Relaxed)) {
+                            TuneStep::Wait(_) => {}
+                            TuneStep::Next { previous, rate, threads } => {
+                                info!("🎛️ Auto-tune: {} threads hashed {}, trying {}", previous, config.hashrate_format.format(rate), threads);
+                                let starting: Vec<u64> = (previous..threads).filter(|&id| workers.is_live(id)).collect();
+                                for id in starting {
+                                    start_optimized_mining_attempt(
+                                        &mut workers,
+                                        mining_data.lock().await,
+                                        &mut mining_attempts,
+                                        &mut slab_pool,
+                                        &mut nonce_rngs,
+                                        &mut nonce_source,
+                                        None,
+                                        id,
+                                        true,
+                                        &config,
+                                        &stats
+                                    ).await;
+                                }
+                                stats.expected_threads.store(threads, Ordering::Relaxed);
+                            }
+                            TuneStep::Done { threads, rate } => {
+                                let measured: Vec<String> = tuner.rates().iter()
+                                    .map(|(threads, rate)| format!("{} threads {}", threads, config.hashrate_format.format(*rate)))
+                                    .collect();
+                                info!("🎛️ Auto-tune chose {} threads at {} ({})", threads, config.hashrate_format.format(rate), measured.join(", "));
+                                // Stop the workers above the winner and free their kernels
+                                for id in threads..mining_threads {
+                                    if workers.is_live(id) {
+                                        workers.get(id).cancel();
+                                        workers.retire(id);
+                                    }
+                                }
+                                stats.expected_threads.store(threads, Ordering::Relaxed);
+                            }
+                        }
+                    }
+
+                    candidate = candidates.next_candidate(), if candidates_open => {
+                        let candidate = match candidate {
+                            Ok(candidate) => candidate,
+                            Err(e) => {
+                                error!("❌ Lost the candidate source, exiting so the node can be restarted: {:?}", e);
+                                workers.cancel_all();
+                                handle.exit.exit(1).await?;
+                                return Err(e);
+                            }
+                        };
+                        let Some((chain, candidate)) = candidate else {
+                            info!("📭 Candidate source exhausted, finishing the current candidate");
+                            candidates_open = false;
+                            continue;
+                        };
+                        match cooldowns[chain].offer(candidate, Instant::now()) {
+                            CooldownOffer::Install(candidate) => install = Some((chain, candidate)),
+                            CooldownOffer::Deferred { coalesced } => {
+                                if coalesced {
+                                    stats.coalesced_candidates.fetch_add(1, Ordering::Relaxed);
+                                }
+                                debug!("🧊 New candidate within the restart cooldown, deferring it");
+                            }
+                        }
+                    }
+
+                    _ = tokio::time::sleep_until(cooldown_due.map_or_else(Instant::now, |(due, _)| due).into()), if cooldown_due.is_some() => {
+                        if let Some((_, chain)) = cooldown_due {
+                            install = cooldowns[chain].take_pending().map(|candidate| (chain, candidate));
+                        }
+                    }
+
+                    else => {
+                        info!("🏁 No candidates left and no attempts in flight, stopping the mining driver");
+                        return Ok(());
+                    }
+                }
+
+                let Some((chain, candidate)) = install else {
+                    continue;
+                };
+                // The headline difficulty follows the first chain
+                let difficulty = match chain {
+                    0 => stats.record_difficulty(candidate.target()),
+                    _ => crate::pow_target::difficulty_from_noun(candidate.target()).ok(),
+                };
+                stats.events.push(MinerEvent::CandidateReceived {
+                    difficulty_bits: difficulty,
+                });
+                chain_stats[chain]
+                    .candidates
+                    .fetch_add(1, Ordering::Relaxed);
+                chain_stats[chain]
+                    .current_difficulty
+                    .store(difficulty.unwrap_or_default() as u64, Ordering::Relaxed);
+                debug!(
+                    "📦 New candidate block for chain {}: {:?}, difficulty {:?} bits",
+                    chain_stats[chain].name,
+                    tip5_hash_to_base58(candidate.header())
+                        .expect("Failed to convert header to Base58"),
+                    difficulty
+                );
+
+                let near_miss_bound =
+                    near_miss_bound_for(candidate.target(), config.near_miss_factor);
+                if let Some(source) = &mut nonce_source {
+                    source.candidate_changed(&candidate);
+                }
+                let replaced = mining_data
+                    .lock()
+                    .await
+                    .insert(
+                        chain,
+                        OptimizedMiningData {
+                            candidate,
+                            optimization_stats: Arc::new(AtomicU64::new(0)),
+                            near_miss_bound,
+                        },
+                    )
+                    .is_some();
+
+                // Only this chain's workers move to the new candidate
+                let chain_workers = workers.on_chain(chain);
+                let finished_on_candidate = candidate_attempts.total_of(&chain_workers);
+                candidate_attempts.reset(&chain_workers);
+                stats
+                    .candidate_attempts
+                    .store(candidate_attempts.total(), Ordering::Relaxed);
+                stats
+                    .paused_threads
+                    .store(candidate_attempts.paused() as u64, Ordering::Relaxed);
+                // Every worker may be paused or retired, so check rather than count attempts
+                if !workers.started() {
+                    start_optimized_mining_threads(
+                        &hot_state,
+                        test_jets.clone(),
+                        &mining_data,
+                        &mut mining_attempts,
+                        &mut slab_pool,
+                        &mut nonce_rngs,
+                        &mut nonce_source,
+                        &mut workers,
+                        active_threads(&autotuner),
+                        &config,
+                        &stats,
+                    )
+                    .await;
+                    if let Some(tuner) = &mut autotuner {
+                        tuner.start(Instant::now());
+                    }
+                } else {
+                    let in_flight = chain_workers
+                        .iter()
+                        .filter(|&&id| workers.is_busy(id))
+                        .count();
+                    if replaced && stats.candidate_superseded(in_flight, finished_on_candidate) {
+                        if config.log_stale_candidates {
+                            info!("♻️ Candidate superseded before any worker finished, discarding {} attempts", in_flight);
+                        } else {
+                            debug!("♻️ Candidate superseded before any worker finished, discarding {} attempts", in_flight);
+                        }
+                    }
+                    debug!("🔄 Restarting mining threads with new block after {} attempts on the last one", finished_on_candidate);
+                    workers.cancel_chain(chain);
+                    // Attempts in flight come back cancelled and restart on their own; start
+                    // the workers that were paused, or idle until their chain's first candidate
+                    let active = active_threads(&autotuner);
+                    let idle: Vec<u64> = chain_workers
+                        .into_iter()
+                        .filter(|&id| id < active && workers.is_live(id) && !workers.is_busy(id))
+                        .collect();
+                    for id in idle {
+                        start_optimized_mining_attempt(
+                            &mut workers,
+                            mining_data.lock().await,
+                            &mut mining_attempts,
+                            &mut slab_pool,
+                            &mut nonce_rngs,
+                            &mut nonce_source,
+                            None,
+                            id,
+                            true,
+                            &config,
+                            &stats,
+                        )
+                        .await;
+                    }
+                }
+                cooldowns[chain].restarted(Instant::now());
+            }
+        })
+    })
+}
+
+/// A worker's backend with `stack_size` bytes of Nock stack: from the configured factory,
+/// or the miner kernel on its own serf. Slabs aren't `Sync`, so the test jets are passed
+/// by value to keep the driver's future `Send`.
+async fn new_backend(
+    config: &OptimizedMiningConfig,
+    hot_state: &[HotEntry],
+    test_jets: Vec<NounSlab>,
+    stack_size: usize,
+) -> Result<Arc<dyn HashBackend>, nockapp::CrownError> {
+    match &config.backend_factory {
+        Some(factory) => factory(stack_size).await,
+        None => Ok(Arc::new(
+            CpuSerfBackend::new(hot_state.to_vec(), stack_size, test_jets).await?,
+        )),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn start_optimized_mining_threads(
+    hot_state: &[HotEntry],
+    test_jets: Vec<NounSlab>,
+    mining_data: &Mutex<ChainCandidates>,
+    mining_attempts: &mut tokio::task::JoinSet<AttemptOutput>,
+    slab_pool: &mut SlabPool,
+    nonce_rngs: &mut [NonceRng],
+    nonce_source: &mut Option<Box<dyn NonceSource>>,
+    workers: &mut Workers,
+    active: u64,
+    config: &OptimizedMiningConfig,
+    stats: &Arc<OptimizedMiningStats>,
+) {
+    info!(
+        "🚀 Starting {} EPYC-optimized mining threads",
+        config.mining_threads
+    );
+    for i in 0..config.mining_threads {
+        let backend = new_backend(config, hot_state, test_jets.clone(), config.stack_size)
+            .await
+            .expect("Could not load mining kernel");
+        workers.insert(i, backend);
+        // The rest stay loaded but idle until the auto-tuner asks for them
+        if i >= active {
+            continue;
+        }
+        start_optimized_mining_attempt(
+            workers,
+            mining_data.lock().await,
+            mining_attempts,
+            slab_pool,
+            nonce_rngs,
+            nonce_source,
+            None,
+            i,
+            true,
+            config,
+            stats,
+        )
+        .await;
+    }
+    info!("✅ All {} mining threads started", config.mining_threads);
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn start_optimized_mining_attempt(
+    workers: &mut Workers,
+    mining_data: tokio::sync::MutexGuard<'_, ChainCandidates>,
+    mining_attempts: &mut tokio::task::JoinSet<AttemptOutput>,
+    slab_pool: &mut SlabPool,
+    nonce_rngs: &mut [NonceRng],
+    nonce_source: &mut Option<Box<dyn NonceSource>>,
+    nonce: Option<Nonce>,
+    id: u64,
+    log_attempt: bool,
+    config: &OptimizedMiningConfig,
+    stats: &Arc<OptimizedMiningStats>,
+) {
+    // Nothing below awaits, so the worker's span can stay entered until the attempt is spawned
+    let span = workers.span(id);
+    let _worker = span.clone().entered();
+
+    // A worker whose chain has no candidate yet idles until the first one arrives
+    let Some(mining_data_ref) = mining_data.get(&workers.chain(id)) else {
+        return;
+    };
+
+    // Only nonce generation is timed; a batch of one that reuses the last hash needs none
+    let timing = config.timing.then(|| stats.clone());
+    let batch = config.nonces_per_attempt.max(1) as usize;
+    let mut nonces = Vec::with_capacity(batch);
+    // External nonces come first so a batch of one doesn't always go to the chained hash
+    if let (Some(source), None) = (nonce_source.as_mut(), &config.fixed_candidate) {
+        nonces.extend(source.take(id, batch));
+        nonces.truncate(batch);
+        stats
+            .external_nonces
+            .fetch_add(nonces.len() as u64, Ordering::Relaxed);
+    }
+    if nonces.len() < batch {
+        nonces.extend(nonce);
+    }
+    if nonces.len() < batch {
+        let _span = timing
+            .is_some()
+            .then(|| debug_span!("nonce_generation", thread = id).entered());
+        let started = Instant::now();
+        let base_entropy = match &config.fixed_candidate {
+            Some(fixed) => fixed.nonce_seed,
+            None => mining_data_ref.optimization_stats.load(Ordering::Relaxed),
+        };
+        while nonces.len() < batch {
+            nonces.push(generate_optimized_nonce(
+                slab_pool.take(),
+                id,
+                config.mining_threads,
+                base_entropy,
+                &mut nonce_rngs[id as usize],
+            ));
+        }
+        if let Some(stats) = &timing {
+            stats.nonce_timing.record(started.elapsed());
+        }
+    }
+
+    if log_attempt {
+        debug!("⚡ Thread {} starting optimized mining attempt", id);
+    }
+    let candidate = mining_data_ref.candidate.clone();
+    let backend = workers.get(id).clone();
+    workers.set_busy(id, true);
+    let duty_cycle_percent = config.duty_cycle_percent.clamp(1, 100) as u32;
+    let alloc_cap = config
+        .max_alloc_bytes_per_sec
+        .filter(|_| crate::alloc_stats::ENABLED)
+        .map(|cap| (cap, stats.clone()));
+    let catch_panics = config.respawn_after_panic;
+    // Only the thread that hashes is pinned; this one runs the driver on the runtime's cores
+    let cpu = config
+        .thread_affinity
+        .then(|| optimized_cpu_for_thread(config.topology().as_deref(), id));
+
+    mining_attempts.spawn_blocking(move || {
+        let _worker = span.entered();
+        if let Some(cpu) = cpu {
+            if let Err(e) = set_thread_affinity(cpu) {
+                debug!("Could not set thread affinity for thread {}: {}", id, e);
+            }
+        }
+        let started = std::time::Instant::now();
+        let results = {
+            let _span = timing
+                .is_some()
+                .then(|| debug_span!("kernel_poke", thread = id).entered());
+            if catch_panics {
+                // The backend is replaced afterwards, so whatever state it was left in is never reused
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    backend.hash_candidates(&candidate, &nonces)
+                }))
+            } else {
+                Ok(backend.hash_candidates(&candidate, &nonces))
+            }
+        };
+        let results = match results {
+            Ok(results) => results,
+            Err(payload) => return (id, nonces, Err(payload)),
+        };
+        if let Some(stats) = &timing {
+            stats.poke_timing.record(started.elapsed());
+        }
+        if duty_cycle_percent < 100 {
+            // Idle in proportion to the time spent mining to hit the requested duty cycle
+            let idle = started.elapsed() * (100 - duty_cycle_percent) / duty_cycle_percent;
+            std::thread::sleep(idle);
+        }
+        if let Some((cap, stats)) = &alloc_cap {
+            let rate = stats.bytes_allocated_per_sec.load(Ordering::Relaxed);
+            if let Some(idle) = alloc_throttle(started.elapsed(), rate, *cap) {
+                std::thread::sleep(idle);
+            }
+        }
+        (id, nonces, Ok(results))
+    });
+}
+
+/// How long a worker should idle after an attempt that took `elapsed` while the process
+/// allocates `rate` bytes/sec against a `cap`: long enough to scale the rate down to the
+/// cap if every worker does the same, but never more than a second per attempt
+fn alloc_throttle(elapsed: Duration, rate: u64, cap: u64) -> Option<Duration> {
+    if rate <= cap {
+        return None;
+    }
+    let excess = (rate - cap) as f64 / cap.max(1) as f64;
+    Some(elapsed.mul_f64(excess).min(Duration::from_secs(1)))
+}
+
+/// Split one attempt's results into every block it found, as `(hash, poke)` in nonce
+/// order, and the last other result. A batch can hold several solutions, and all of
+/// them must be submitted rather than only the first.
+fn split_solutions(results: Vec<HashResult>) -> (Vec<(NounSlab, NounSlab)>, Option<HashResult>) {
+    let mut solutions = Vec::new();
+    let mut rest = None;
+    for result in results {
+        match result {
+            HashResult::Found { hash, poke } => solutions.push((hash, poke)),
+            other => rest = Some(other),
+        }
+    }
+    (solutions, rest)
+}
+
+/// Append a found solution to the solution log, if there is one
+fn log_solution(
+    log: &SolutionLog,
+    candidates: &ChainCandidates,
+    chain: usize,
+    id: u64,
+    poke: &NounSlab,
+    hash: &NounSlab,
+) {
+    if let Some(data) = candidates.get(&chain) {
+        log.record(
+            id,
+            unsafe { *poke.root() },
+            data.candidate.target(),
+            unsafe { *hash.root() },
+        );
+    }
+}
+
+/// Hand a found solution to an external callback. Like the solution log, a solution that
+/// cannot be described is logged rather than holding up the driver.
+fn deliver_solution(
+    callback: &SolutionCallback,
+    candidates: &ChainCandidates,
+    chain: usize,
+    id: u64,
+    poke: NounSlab,
+    hash: &NounSlab,
+) {
+    let info = match candidates.get(&chain) {
+        Some(data) => SolutionInfo::from_mined_poke(id, poke, data.candidate.target(), unsafe {
+            *hash.root()
+        }),
+        None => return,
+    };
+    match info {
+        Ok(info) => callback(info),
+        Err(e) => warn!(
+            "💔 Could not hand the solution from thread {} to the external callback: {}",
+            id, e
+        ),
+    }
+}
+
+/// A solution found by [`mine_one_block_for_test`]
+pub struct SmokeTestSolution {
+    /// Attempts it took, counting the successful one
+    pub attempts: u64,
+    pub hash: NounSlab,
+    /// The %mined poke that would be submitted to the node
+    pub poke: NounSlab,
+    /// The solution as it would be written to the solution log
+    pub record: SolutionRecord,
+}
+
+/// Mine [`OptimizedMiningConfig::smoke_test`]'s candidate without a node, exactly as
+/// its one worker would, and return the first solution.
+///
+/// This boots one miner kernel and needs a multi-threaded runtime.
+pub async fn mine_one_block_for_test() -> Result<SmokeTestSolution, NockAppError> {
+    let config = OptimizedMiningConfig::smoke_test(SMOKE_TEST_NONCE_SEED);
+    let fixed = config
+        .fixed_candidate
+        .as_ref()
+        .expect("smoke test mines a fixed candidate");
+    let candidate = fixed.to_candidate();
+    let backend = CpuSerfBackend::new(
+        zkvm_jetpack::hot::produce_prover_hot_state(),
+        config.stack_size,
+        Vec::new(),
+    )
+    .await?;
+
+    // Worker 0's first nonce, as start_optimized_mining_attempt derives it
+    let mut rng = StdRng::seed_from_u64(fixed.nonce_seed);
+    let mut nonce = generate_optimized_nonce(
+        NounSlab::new(),
+        0,
+        config.mining_threads,
+        fixed.nonce_seed,
+        &mut rng,
+    );
+    for attempts in 1..=SMOKE_TEST_MAX_ATTEMPTS {
+        let nonces = [nonce];
+        let result = tokio::task::block_in_place(|| backend.hash_candidates(&candidate, &nonces))
+            .into_iter()
+            .next();
+        match result {
+            Some(HashResult::Found { hash, poke }) => {
+                let record = SolutionRecord::from_mined_poke(
+                    0,
+                    unsafe { *poke.root() },
+                    candidate.target(),
+                    unsafe { *hash.root() },
+                )?;
+                return Ok(SmokeTestSolution {
+                    attempts,
+                    hash,
+                    poke,
+                    record,
+                });
+            }
+            Some(HashResult::Miss { hash }) => nonce = Nonce::from_slab(hash),
+            Some(HashResult::Failed(e)) => return Err(e.into()),
+            Some(HashResult::Unexpected { head }) => {
+                warn!("Smoke test got unexpected mining result {}", head);
+                return Err(NockAppError::UnexpectedResult);
+            }
+            Some(HashResult::Cancelled) | None => return Err(NockAppError::UnexpectedResult),
+        }
+    }
+    warn!(
+        "Smoke test found no solution in {} attempts",
+        SMOKE_TEST_MAX_ATTEMPTS
+    );
+    Err(NockAppError::UnexpectedResult)
+}
+
+/// Result of [`run_hashrate_only`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchmarkReport {
+    pub threads: u64,
+    pub pow_len: u64,
+    /// Wall time from the first attempt until every worker finished its last one
+    pub elapsed_secs: f64,
+    pub hashes: u64,
+    pub hashes_per_sec: f64,
+    /// Hashes by worker id
+    pub hashes_per_thread: Vec<u64>,
+    /// Solutions found; always 0 for the default unsolvable candidate
+    pub solutions: u64,
+}
+
+/// Mine for `duration` without a node and report the hash rate.
+///
+/// Runs `config.mining_threads` workers through the same kernel, nonce generation, batch
+/// size, stack size and pinning as the driver, on `config.fixed_candidate` or else
+/// [`FixedCandidate::unsolvable`], with no NockApp, network or checkpoint. Workers
+/// start no attempt once `duration` has passed, so the run overshoots it by up to one
+/// attempt. Boots one miner kernel per worker and needs a multi-threaded runtime.
+pub async fn run_hashrate_only(
+    config: &OptimizedMiningConfig,
+    duration: Duration,
+) -> Result<BenchmarkReport, NockAppError> {
+    let threads = config.mining_threads.max(1);
+    let fixed = config
+        .fixed_candidate
+        .clone()
+        .unwrap_or_else(|| FixedCandidate::unsolvable(SMOKE_TEST_NONCE_SEED));
+    let candidate = fixed.to_candidate();
+    let hot_state = zkvm_jetpack::hot::produce_prover_hot_state();
+    select_field_backend(config);
+
+    let mut backends = Vec::with_capacity(threads as usize);
+    for _ in 0..threads {
+        backends.push(CpuSerfBackend::new(hot_state.clone(), config.stack_size, Vec::new()).await?);
+    }
+    info!(
+        "⏱️ Benchmarking {} threads at pow_len {} for {:?}",
+        threads, fixed.pow_len, duration
+    );
+
+    let batch = config.nonces_per_attempt.max(1) as usize;
+    let started = Instant::now();
+    let deadline = started + duration;
+    let mut workers = tokio::task::JoinSet::new();
+    for (id, backend) in (0..threads).zip(backends) {
+        let cpu = config
+            .thread_affinity
+            .then(|| optimized_cpu_for_thread(config.topology().as_deref(), id));
+        let candidate = candidate.clone();
+        let nonce_seed = fixed.nonce_seed;
+        workers.spawn_blocking(move || -> Result<(u64, u64, u64), NockAppError> {
+            if let Some(cpu) = cpu {
+                if let Err(e) = set_thread_affinity(cpu) {
+                    debug!("Could not set thread affinity for thread {}: {}", id, e);
+                }
+            }
+            let mut rng = StdRng::seed_from_u64(nonce_seed ^ id);
+            let (mut hashes, mut solutions) = (0, 0);
+            let mut chained = None;
+            while Instant::now() < deadline {
+                // Fresh nonces top up the batch behind the last miss, as in the driver
+                let mut nonces: Vec<Nonce> = chained.take().into_iter().collect();
+                while nonces.len() < batch {
+                    nonces.push(generate_optimized_nonce(
+                        NounSlab::new(),
+                        id,
+                        threads,
+                        nonce_seed,
+                        &mut rng,
+                    ));
+                }
+                for result in backend.hash_candidates(&candidate, &nonces) {
+                    hashes += 1;
+                    match result {
+                        HashResult::Miss { hash } => chained = Some(Nonce::from_slab(hash)),
+                        HashResult::Found { .. } => solutions += 1,
+                        HashResult::Failed(e) => return Err(e.into()),
+                        HashResult::Unexpected { head } => {
+                            warn!(
+                                "Benchmark thread {} got unexpected mining result {}",
+                                id, head
+                            );
+                            return Err(NockAppError::UnexpectedResult);
+                        }
+                        HashResult::Cancelled => return Err(NockAppError::UnexpectedResult),
+                    }
+                }
+            }
+            Ok((id, hashes, solutions))
+        });
+    }
+
+    let mut hashes_per_thread = vec![0; threads as usize];
+    let mut solutions = 0;
+    while let Some(joined) = workers.join_next().await {
+        let (id, hashes, found) = joined.expect("benchmark worker panicked")?;
+        hashes_per_thread[id as usize] = hashes;
+        solutions += found;
+    }
+    let elapsed_secs = started.elapsed().as_secs_f64();
+    let hashes: u64 = hashes_per_thread.iter().sum();
+    let report = BenchmarkReport {
+        threads,
+        pow_len: fixed.pow_len,
+        elapsed_secs,
+        hashes,
+        hashes_per_sec: hashes as f64 / elapsed_secs,
+        hashes_per_thread,
+        solutions,
+    };
+    info!(
+        "⏱️ {} hashes in {:.1}s: {}",
+        report.hashes,
+        report.elapsed_secs,
+        config.hashrate_format.format(report.hashes_per_sec)
+    );
+    Ok(report)
+}
+
+/// One batch size's run in [`run_batching_benchmark`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatchingResult {
+    pub nonces_per_attempt: u64,
+    /// Round trips through the driver's blocking pool
+    pub attempts: u64,
+    pub hashes: u64,
+    pub hashes_per_sec: f64,
+    /// Mean time a worker spent in the kernel per hash
+    pub kernel_secs_per_hash: f64,
+    /// Mean worker time per hash spent outside the kernel: dispatching the attempt,
+    /// generating its nonces and handing the results back
+    pub overhead_secs_per_hash: f64,
+}
+
+impl BatchingResult {
+    fn new(
+        nonces_per_attempt: u64,
+        threads: u64,
+        elapsed: Duration,
+        attempts: u64,
+        hashes: u64,
+        kernel: Duration,
+    ) -> Self {
+        let hashes_f = hashes.max(1) as f64;
+        let worker_secs_per_hash = elapsed.as_secs_f64() * threads as f64 / hashes_f;
+        let kernel_secs_per_hash = kernel.as_secs_f64() / hashes_f;
+        Self {
+            nonces_per_attempt,
+            attempts,
+            hashes,
+            hashes_per_sec: hashes as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+            kernel_secs_per_hash,
+            overhead_secs_per_hash: (worker_secs_per_hash - kernel_secs_per_hash).max(0.0),
+        }
+    }
+
+    /// Share of worker time per hash spent outside the kernel
+    pub fn overhead_fraction(&self) -> f64 {
+        let total = self.kernel_secs_per_hash + self.overhead_secs_per_hash;
+        if total > 0.0 {
+            self.overhead_secs_per_hash / total
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Result of [`run_batching_benchmark`], one entry per batch size in the order given
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatchingReport {
+    pub threads: u64,
+    pub pow_len: u64,
+    pub results: Vec<BatchingResult>,
+}
+
+impl BatchingReport {
+    /// Hash rate of the fastest batch size relative to one nonce per attempt, or `None`
+    /// if a batch of one was not measured
+    pub fn best_speedup(&self) -> Option<f64> {
+        let single = self
+            .results
+            .iter()
+            .find(|result| result.nonces_per_attempt == 1)?;
+        let best = self
+            .results
+            .iter()
+            .map(|result| result.hashes_per_sec)
+            .fold(0.0, f64::max);
+        Some(best / single.hashes_per_sec.max(f64::EPSILON))
+    }
+}
+
+/// Measure what the driver's per-attempt round trip costs at each of `batch_sizes`.
+///
+/// Unlike [`run_hashrate_only`], whose workers loop on their own threads, this dispatches
+/// every attempt the way the driver does: nonces are generated on the async side, the
+/// attempt hashes `nonces_per_attempt` of them in one `spawn_blocking` task, and its
+/// results come back through a `JoinSet` before the worker's next attempt starts. Each
+/// nonce is still its own kernel poke, so the difference between batch sizes is the
+/// driver overhead that batching amortizes, not the cost of the poke itself.
+///
+/// Every batch size runs for `duration` on the same kernels, candidate and
+/// `config.mining_threads` workers as [`run_hashrate_only`]. Pinning and duty cycling are
+/// left out so that only the dispatch differs.
+pub async fn run_batching_benchmark(
+    config: &OptimizedMiningConfig,
+    batch_sizes: &[u64],
+    duration: Duration,
+) -> Result<BatchingReport, NockAppError> {
+    let threads = config.mining_threads.max(1);
+    let fixed = config
+        .fixed_candidate
+        .clone()
+        .unwrap_or_else(|| FixedCandidate::unsolvable(SMOKE_TEST_NONCE_SEED));
+    let candidate = fixed.to_candidate();
+    let hot_state = zkvm_jetpack::hot::produce_prover_hot_state();
+    select_field_backend(config);
+
+    let mut backends = Vec::with_capacity(threads as usize);
+    for _ in 0..threads {
+        backends.push(Arc::new(
+            CpuSerfBackend::new(hot_state.clone(), config.stack_size, Vec::new()).await?,
+        ));
+    }
+
+    let mut results = Vec::with_capacity(batch_sizes.len());
+    for &nonces_per_attempt in batch_sizes {
+        let batch = nonces_per_attempt.max(1) as usize;
+        info!(
+            "⏱️ Benchmarking {} threads at {} nonces per attempt for {:?}",
+            threads, batch, duration
+        );
+        let mut rngs: Vec<StdRng> = (0..threads)
+            .map(|id| StdRng::seed_from_u64(fixed.nonce_seed ^ id))
+            .collect();
+        let mut chained: Vec<Option<Nonce>> = vec![None; threads as usize];
+        let (mut attempts, mut hashes, mut kernel) = (0, 0, Duration::ZERO);
+        let started = Instant::now();
+        let deadline = started + duration;
+        let mut in_flight = tokio::task::JoinSet::new();
+        let mut dispatch =
+            |in_flight: &mut tokio::task::JoinSet<(u64, Duration, Vec<HashResult>)>,
+             id: u64,
+             chained: Option<Nonce>| {
+                let mut nonces: Vec<Nonce> = chained.into_iter().collect();
+                while nonces.len() < batch {
+                    nonces.push(generate_optimized_nonce(
+                        NounSlab::new(),
+                        id,
+                        threads,
+                        fixed.nonce_seed,
+                        &mut rngs[id as usize],
+                    ));
+                }
+                let backend = backends[id as usize].clone();
+                let candidate = candidate.clone();
+                in_flight.spawn_blocking(move || {
+                    let poked = Instant::now();
+                    let results = backend.hash_candidates(&candidate, &nonces);
+                    (id, poked.elapsed(), results)
+                });
+            };
+        for id in 0..threads {
+            dispatch(&mut in_flight, id, None);
+        }
+        while let Some(joined) = in_flight.join_next().await {
+            let (id, spent, attempt_results) = joined.expect("benchmark attempt panicked");
+            attempts += 1;
+            kernel += spent;
+            for result in attempt_results {
+                hashes += 1;
+                match result {
+                    HashResult::Miss { hash } => {
+                        chained[id as usize] = Some(Nonce::from_slab(hash))
+                    }
+                    HashResult::Found { .. } => chained[id as usize] = None,
+                    HashResult::Failed(e) => return Err(e.into()),
+                    HashResult::Unexpected { head } => {
+                        warn!(
+                            "Benchmark thread {} got unexpected mining result {}",
+                            id, head
+                        );
+                        return Err(NockAppError::UnexpectedResult);
+                    }
+                    HashResult::Cancelled => return Err(NockAppError::UnexpectedResult),
+                }
+            }
+            if Instant::now() < deadline {
+                dispatch(&mut in_flight, id, chained[id as usize].take());
+            }
+        }
+        let result = BatchingResult::new(
+            nonces_per_attempt,
+            threads,
+            started.elapsed(),
+            attempts,
+            hashes,
+            kernel,
+        );
+        info!(
+            "⏱️ {} nonces per attempt: {}, {:.1}% of worker time outside the kernel",
+            nonces_per_attempt,
+            config.hashrate_format.format(result.hashes_per_sec),
+            result.overhead_fraction() * 100.0
+        );
+        results.push(result);
+    }
+    Ok(BatchingReport {
+        threads,
+        pow_len: fixed.pow_len,
+        results,
+    })
+}
+
+/// Hashes counted since the `last` sample, or `None` if the counter went backwards
+/// (e.g. it was reset), in which case no meaningful rate exists for the interval
+fn hash_count_delta(current: u64, last: u64) -> Option<u64> {
+    current.checked_sub(last)
+}
+
+/// Near-miss bound for a candidate's target, if near-miss counting is enabled
+fn near_miss_bound_for(target: Noun, near_miss_factor: Option<u64>) -> Option<UBig> {
+    let factor = near_miss_factor?;
+    match crate::pow_target::target_from_noun(target) {
+        Ok(target) => Some(crate::pow_target::near_miss_bound(&target, factor)),
+        Err(e) => {
+            warn!("Could not parse mining target, near misses will not be counted: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nockapp::nockapp::driver::{IOAction, PokeResult};
+    use nockapp::nockapp::wire::{Wire, WireRepr};
+    use nockvm::noun::D;
+    use zkvm_jetpack::noun::noun_ext::NounExt;
+
+    use super::*;
+    use crate::hash_backend::{Scripted, ScriptedBackend};
+
+    #[test]
+    fn test_worker_cpus_follow_injected_topology() {
+        // Two NUMA nodes of four cores (eight logical CPUs) each
+        let topology = Topology::synthetic(1, 2, 4);
+        let config = OptimizedMiningConfig {
+            mining_threads: 16,
+            topology: Some(topology.clone()),
+            ..OptimizedMiningConfig::default()
+        };
+        let cpus = worker_cpus(&config);
+        assert_eq!(cpus.len(), 16);
+        for (id, &cpu) in cpus.iter().enumerate() {
+            assert_eq!(topology.cpus[cpu].numa_node, id % 2);
+        }
+        let mut sorted = cpus.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..16).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_reserved_cpus_left_to_the_os() {
+        // Two NUMA nodes of eight logical CPUs each; 0 and 4 take the NIC's interrupts
+        let topology = Topology::synthetic(1, 2, 4);
+        let config = OptimizedMiningConfig {
+            mining_threads: 14,
+            reserved_cpus: vec![0, 4],
+            topology: Some(topology.clone()),
+            ..OptimizedMiningConfig::default()
+        };
+        let mut cpus = config.mining_cpus();
+        assert_eq!(cpus.len(), 14);
+        for (id, &cpu) in cpus.iter().enumerate() {
+            assert_eq!(topology.cpus[cpu].numa_node, id % 2);
+            assert_eq!(
+                optimized_cpu_for_thread(config.topology().as_deref(), id as u64),
+                cpu
+            );
+        }
+        cpus.sort_unstable();
+        assert_eq!(cpus, (1..16).filter(|&cpu| cpu != 4).collect::<Vec<_>>());
+        // Every CPU but the reserved ones mines, so the runtime shares a domain, still
+        // without them
+        let runtime = runtime_cpus(&config);
+        assert!(!runtime.is_empty());
+        assert!(runtime
+            .iter()
+            .all(|cpu| !config.reserved_cpus.contains(cpu)));
+    }
+
+    #[test]
+    fn test_runtime_cpus_avoid_mining_workers() {
+        // Two NUMA nodes of eight logical CPUs each
+        let topology = Topology::synthetic(1, 2, 4);
+        let config = OptimizedMiningConfig {
+            mining_threads: 12,
+            topology: Some(topology.clone()),
+            ..OptimizedMiningConfig::default()
+        };
+        let workers = worker_cpus(&config);
+        let cpus = runtime_cpus(&config);
+        assert!(!cpus.is_empty());
+        assert!(cpus.iter().all(|cpu| !workers.contains(cpu)));
+        let node = topology.cpus[cpus[0]].numa_node;
+        assert!(cpus.iter().all(|&cpu| topology.cpus[cpu].numa_node == node));
+
+        // Every CPU mines: share the first domain rather than spreading out
+        let config = OptimizedMiningConfig {
+            mining_threads: 16,
+            ..config
+        };
+        assert_eq!(runtime_cpus(&config), topology.cpu_domains()[0].cpus);
+    }
+
+    #[test]
+    fn test_hash_count_delta_rejects_backwards_counter() {
+        assert_eq!(hash_count_delta(150, 100), Some(50));
+        assert_eq!(hash_count_delta(100, 100), Some(0));
+        // A reset counter must not wrap into an ~18 quintillion hashes/sec sample
+        assert_eq!(hash_count_delta(10, 100), None);
+        assert_eq!(hash_count_delta(0, u64::MAX), None);
+    }
+
+    #[test]
+    fn test_candidate_superseded_counts_stale_work() {
+        let stats = OptimizedMiningStats::new();
+        assert!(!stats.candidate_superseded(4, 3));
+        assert!(stats.candidate_superseded(4, 0));
+        assert_eq!(stats.stale_attempts.load(Ordering::Relaxed), 8);
+        assert_eq!(stats.discarded_candidates.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_smoke_test_config() {
+        let config = OptimizedMiningConfig::smoke_test(7);
+        assert_eq!(config.mining_threads, 1);
+        assert_eq!(config.stop_after_solutions, Some(1));
+        let fixed = config.fixed_candidate.expect("fixed candidate");
+        assert_eq!(fixed.nonce_seed, 7);
+        let target = crate::pow_target::bignum_limbs_to_ubig(&fixed.target);
+        assert_eq!(crate::pow_target::difficulty_bits(&target), 0);
+    }
+
+    #[test]
+    fn test_effective_optimizations_follow_the_host() {
+        use zkvm_jetpack::field::backend::FieldBackend;
+
+        let config = OptimizedMiningConfig {
+            topology: Some(Topology::synthetic(1, 2, 4)),
+            lock_memory: true,
+            ..OptimizedMiningConfig::from_profile(MiningProfile::Server)
+        };
+        let active = effective_optimizations(&config, false);
+        assert!(active.contains(&"thread-affinity") && active.contains(&"numa"));
+        // Requested but failed, so not in effect
+        assert!(!active.contains(&"memlock"));
+        assert!(effective_optimizations(&config, true).contains(&"memlock"));
+        assert_eq!(
+            active.contains(&"avx512"),
+            FieldBackend::Avx512.is_supported()
+        );
+
+        let config = OptimizedMiningConfig {
+            topology: Some(Topology::synthetic(1, 1, 8)),
+            ..OptimizedMiningConfig::from_profile(MiningProfile::Laptop)
+        };
+        let active = effective_optimizations(&config, false);
+        assert!(!active.contains(&"thread-affinity") && !active.contains(&"numa"));
+    }
+
+    #[test]
+    fn test_submit_mode_routes_solutions() {
+        let poke = || {
+            let mut slab = NounSlab::new();
+            slab.set_root(D(11));
+            slab
+        };
+        let callback: SolutionCallback = Arc::new(|_| {});
+        let (internal, external) = SubmitMode::Internal.route(poke());
+        assert!(internal.is_some() && external.is_none());
+        let external_mode = SubmitMode::ExternalCallback(callback.clone());
+        let (internal, external) = external_mode.route(poke());
+        assert!(internal.is_none() && external.is_some());
+        let both_mode = SubmitMode::Both(callback);
+        let (internal, external) = both_mode.route(poke());
+        let (_, external) = external.expect("both modes call the callback");
+        assert_eq!(
+            internal.expect("both modes poke the node").jam(),
+            external.jam()
+        );
+    }
+
+    /// Counts how often it was cancelled
+    #[derive(Default)]
+    struct CancelCounter(AtomicU64);
+
+    impl HashBackend for CancelCounter {
+        fn hash_candidates(&self, _candidate: &Candidate, _nonces: &[Nonce]) -> Vec<HashResult> {
+            Vec::new()
+        }
+
+        fn cancel(&self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_workers_track_live_backends_over_candidate_cycles() {
+        let mut workers = Workers::default();
+        assert!(!workers.started());
+        let mut live: BTreeMap<u64, Arc<CancelCounter>> = BTreeMap::new();
+        // Replaced or retired backends, with their cancel count at that moment
+        let mut dead: Vec<(Arc<CancelCounter>, u64)> = Vec::new();
+        for id in 0..8 {
+            let backend = Arc::new(CancelCounter::default());
+            workers.insert(id, backend.clone());
+            live.insert(id, backend);
+        }
+
+        for cycle in 0..100u64 {
+            let id = cycle % 8;
+            // Only odd workers retire, so some are live at the end
+            if cycle % 7 == 3 && id % 2 == 1 && live.contains_key(&id) {
+                workers.retire(id);
+                let old = live.remove(&id).unwrap();
+                let cancels = old.0.load(Ordering::Relaxed);
+                dead.push((old, cancels));
+            } else if cycle % 5 == 1 && live.contains_key(&id) {
+                let fresh = Arc::new(CancelCounter::default());
+                workers.insert(id, fresh.clone());
+                let old = live.insert(id, fresh).unwrap();
+                let cancels = old.0.load(Ordering::Relaxed);
+                dead.push((old, cancels));
+            }
+            // A new candidate arrives
+            workers.cancel_all();
+            assert_eq!(workers.len(), live.len(), "cycle {}", cycle);
+        }
+
+        assert!(workers.started());
+        assert!(!dead.is_empty() && !live.is_empty());
+        for (backend, cancels) in &dead {
+            assert_eq!(
+                backend.0.load(Ordering::Relaxed),
+                *cancels,
+                "a dead worker was cancelled"
+            );
+        }
+        for (id, backend) in &live {
+            assert!(backend.0.load(Ordering::Relaxed) > 0);
+            let expected: Arc<dyn HashBackend> = backend.clone();
+            assert!(
+                Arc::ptr_eq(workers.get(*id), &expected),
+                "worker {} has a stale backend",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn test_workers_cancel_only_their_chain() {
+        let mut workers = Workers::with_chains(vec![0, 1, 0, 1]);
+        let backends: Vec<Arc<CancelCounter>> =
+            (0..4).map(|_| Arc::new(CancelCounter::default())).collect();
+        for (id, backend) in backends.iter().enumerate() {
+            workers.insert(id as u64, backend.clone());
+        }
+        assert_eq!(workers.on_chain(1), vec![1, 3]);
+        // Workers past the assignment mine the first chain
+        assert_eq!(workers.chain(7), 0);
+
+        workers.retire(3);
+        workers.cancel_chain(1);
+        let cancels: Vec<u64> = backends
+            .iter()
+            .map(|backend| backend.0.load(Ordering::Relaxed))
+            .collect();
+        assert_eq!(cancels, vec![0, 1, 0, 0]);
+
+        workers.set_busy(2, true);
+        assert!(workers.is_busy(2) && !workers.is_busy(0));
+        workers.set_busy(2, false);
+        assert!(!workers.is_busy(2));
+    }
+
+    #[test]
+    fn test_check_chains() {
+        let chain = |weight, source: bool| MiningChain {
+            name: "chain".to_string(),
+            weight,
+            source: source.then(|| {
+                let (_, rx) = tokio::sync::mpsc::channel::<Candidate>(1);
+                Box::new(rx) as Box<dyn CandidateSource>
+            }),
+            submit: SubmitMode::Internal,
+        };
+        let config = OptimizedMiningConfig {
+            fixed_candidate: None,
+            ..OptimizedMiningConfig::smoke_test(1)
+        };
+        assert!(check_chains(&[chain(1, false), chain(2, true)], &config).is_ok());
+        assert!(check_chains(&[chain(0, false), chain(0, true)], &config).is_err());
+        assert!(check_chains(&[chain(1, false), chain(1, false)], &config).is_err());
+
+        let fixed = OptimizedMiningConfig::smoke_test(1);
+        assert!(check_chains(&[chain(1, false)], &fixed).is_ok());
+        assert!(check_chains(&[chain(1, false), chain(1, true)], &fixed).is_err());
+    }
+
+    #[test]
+    fn test_restart_cooldown_coalesces_bursts() {
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut cooldown = RestartCooldown::new(Some(Duration::from_millis(100)));
+        assert_eq!(cooldown.offer(1, at(0)), CooldownOffer::Install(1));
+        cooldown.restarted(at(0));
+
+        // A burst inside the cooldown: only the newest survives
+        assert_eq!(
+            cooldown.offer(2, at(10)),
+            CooldownOffer::Deferred { coalesced: false }
+        );
+        assert_eq!(
+            cooldown.offer(3, at(20)),
+            CooldownOffer::Deferred { coalesced: true }
+        );
+        assert_eq!(
+            cooldown.offer(4, at(30)),
+            CooldownOffer::Deferred { coalesced: true }
+        );
+        assert_eq!(cooldown.due(), Some(at(100)));
+        assert_eq!(cooldown.take_pending(), Some(4));
+        assert_eq!(cooldown.due(), None);
+        cooldown.restarted(at(100));
+
+        // Once the cooldown is over a candidate goes straight through
+        assert_eq!(cooldown.offer(5, at(250)), CooldownOffer::Install(5));
+
+        let mut disabled = RestartCooldown::new(None);
+        disabled.restarted(at(0));
+        assert_eq!(disabled.offer(1, at(0)), CooldownOffer::Install(1));
+        assert_eq!(disabled.due(), None);
+    }
+
+    #[test]
+    fn test_worker_nonces_are_disjoint() {
+        for workers in [1u64, 3, 8, 96] {
+            let mut seen = std::collections::HashSet::new();
+            for id in 0..workers {
+                // Same seed for every worker, the worst case for independent RNGs
+                let mut rng = StdRng::seed_from_u64(7);
+                for _ in 0..64 {
+                    let nonce = generate_optimized_nonce(NounSlab::new(), id, workers, 0, &mut rng);
+                    // A noun-digest:tip5, five belts in the field
+                    let belts: [Noun; 5] = nonce.as_noun().uncell().unwrap();
+                    let belts = belts.map(|belt| belt.as_atom().unwrap().as_u64().unwrap());
+                    assert!(belts.iter().all(|&belt| belt < PRIME));
+                    assert_eq!(
+                        belts[0] % workers,
+                        id,
+                        "worker {} of {} left its class",
+                        id,
+                        workers
+                    );
+                    assert!(
+                        seen.insert(nonce.into_slab().jam()),
+                        "worker {} of {} repeated a nonce",
+                        id,
+                        workers
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_snapshot_covers_counters() {
+        let stats = OptimizedMiningStats::new();
+        stats.hashes.store(40, Ordering::Relaxed);
+        stats.cancellations.store(3, Ordering::Relaxed);
+        stats.coalesced_candidates.store(2, Ordering::Relaxed);
+        stats.external_nonces.store(12, Ordering::Relaxed);
+        stats.effect_errors.store(4, Ordering::Relaxed);
+        stats.worker_panics.store(1, Ordering::Relaxed);
+        stats.empty_results.store(5, Ordering::Relaxed);
+        stats.record_node_hashes(1, 25);
+        stats.record_node_hashes(1, 5);
+        stats.record_rate(Instant::now(), 12);
+        stats.poke_timing.record(Duration::from_micros(100));
+        let chain = Arc::new(ChainStats::new("side", 2, 3));
+        chain.solutions.store(1, Ordering::Relaxed);
+        *stats.chains.lock().unwrap() = vec![chain];
+
+        let snapshot = stats.snapshot(HEALTH_WINDOW);
+        assert_eq!(snapshot.hashes, 40);
+        assert_eq!(snapshot.cancellations, 3);
+        assert_eq!(snapshot.coalesced_candidates, 2);
+        assert_eq!(snapshot.external_nonces, 12);
+        assert_eq!(snapshot.effect_errors, 4);
+        assert_eq!(snapshot.worker_panics, 1);
+        assert_eq!(snapshot.empty_results, 5);
+        assert_eq!(snapshot.hash_rate, Some(12));
+        assert_eq!(snapshot.hashes_by_numa_node, BTreeMap::from([(1, 30)]));
+        assert_eq!(snapshot.poke_timing.samples, 1);
+        assert_eq!(snapshot.chains.len(), 1);
+        assert_eq!(
+            (snapshot.chains[0].workers, snapshot.chains[0].solutions),
+            (3, 1)
+        );
+        assert!(!snapshot.health.live);
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(
+            json.contains("\"hashes_by_numa_node\":{\"1\":30}"),
+            "{json}"
+        );
+    }
+
+    #[test]
+    fn test_effective_config_reflects_what_runs() {
+        let config = OptimizedMiningConfig {
+            topology: Some(Topology::synthetic(1, 2, 4)),
+            reserved_cpus: vec![0],
+            thread_affinity: true,
+            ..OptimizedMiningConfig::smoke_test(1)
+        };
+        let effective = effective_config(&config, 3, vec!["numa"]);
+        assert_eq!((effective.miner, effective.threads), ("optimized", 3));
+        assert_eq!(effective.reserved_cpus, vec![0]);
+        assert_eq!(effective.stack_size, config.stack_size);
+        assert!(
+            effective.backend.starts_with("miner kernel"),
+            "{}",
+            effective.backend
+        );
+
+        // Unpinned workers may land on reserved CPUs
+        let unpinned = OptimizedMiningConfig {
+            thread_affinity: false,
+            ..config
+        };
+        assert!(effective_config(&unpinned, 3, Vec::new())
+            .reserved_cpus
+            .is_empty());
+
+        // The thread count follows the auto-tuner
+        let stats = OptimizedMiningStats::new();
+        assert!(stats.effective_config().is_none());
+        *stats.effective_config.lock().unwrap() = Some(effective);
+        stats.expected_threads.store(2, Ordering::Relaxed);
+        assert_eq!(stats.effective_config().unwrap().threads, 2);
+        assert_eq!(
+            stats
+                .snapshot(HEALTH_WINDOW)
+                .effective_config
+                .unwrap()
+                .optimizations,
+            vec!["numa"]
+        );
+    }
+
+    #[test]
+    fn test_hash_rate_floor_marks_degraded() {
+        let stats = OptimizedMiningStats::new();
+        let start = Instant::now();
+        let window = Duration::from_secs(10);
+        for second in 0..10 {
+            stats.record_rate(start + Duration::from_secs(second), 50);
+        }
+        // Not judged until the history covers the whole window
+        assert_eq!(
+            stats.check_hash_rate_floor(start + Duration::from_secs(9), 100, window),
+            None
+        );
+        let now = start + Duration::from_secs(10);
+        assert_eq!(
+            stats.check_hash_rate_floor(now, 100, window),
+            Some((true, 50.0))
+        );
+        assert!(stats.health(HEALTH_WINDOW).degraded);
+        // Only changes are reported
+        assert_eq!(stats.check_hash_rate_floor(now, 100, window), None);
+
+        for second in 10..20 {
+            stats.record_rate(start + Duration::from_secs(second), 150);
+        }
+        let now = start + Duration::from_secs(20);
+        assert_eq!(
+            stats.check_hash_rate_floor(now, 100, window),
+            Some((false, 150.0))
+        );
+        assert!(!stats.health(HEALTH_WINDOW).degraded);
+    }
+
+    #[test]
+    fn test_hashes_per_joule_unknown_until_measured() {
+        let stats = OptimizedMiningStats::new();
+        assert_eq!(stats.hashes_per_joule(), None);
+        stats
+            .hashes_per_joule
+            .store(0.25f64.to_bits(), Ordering::Relaxed);
+        assert_eq!(stats.hashes_per_joule(), Some(0.25));
+    }
+
+    #[test]
+    fn test_candidate_attempts() {
+        let mut attempts = CandidateAttempts::new(3);
+        assert_eq!(attempts.record(0), 1);
+        assert_eq!(attempts.record(0), 2);
+        assert_eq!(attempts.record(2), 1);
+        assert_eq!(attempts.total(), 3);
+
+        attempts.pause(0);
+        attempts.pause(2);
+        assert_eq!(attempts.reset(&[0, 1, 2]), vec![0, 2]);
+        assert_eq!(attempts.total(), 0);
+        assert_eq!(attempts.record(0), 1);
+        assert!(attempts.reset(&[0, 1, 2]).is_empty());
+
+        // A new candidate on one chain leaves the other chain's workers alone
+        attempts.record(0);
+        attempts.record(1);
+        attempts.pause(0);
+        attempts.pause(1);
+        assert_eq!(attempts.total_of(&[1]), 1);
+        assert_eq!(attempts.reset(&[1]), vec![1]);
+        assert_eq!(attempts.total(), 1);
+        assert_eq!(attempts.paused(), 1);
+    }
+
+    // Run in release by the mining-release workflow
+    #[ignore = "Boots the miner kernel and proves, which is slow outside release builds"]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_mine_one_block_for_test() {
+        let solution = mine_one_block_for_test().await.unwrap();
+        assert_eq!(solution.attempts, 1);
+        assert_eq!(solution.record.thread, 0);
+        // Deterministic: the same seed finds the same block
+        let again = mine_one_block_for_test().await.unwrap();
+        assert_eq!(again.record.hash, solution.record.hash);
+    }
+
+    // Run in release by the mining-release workflow
+    #[ignore = "Boots the miner kernel and proves, which is slow outside release builds"]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_run_hashrate_only() {
+        // The trivial candidate keeps each attempt short
+        let config = OptimizedMiningConfig {
+            mining_threads: 2,
+            thread_affinity: false,
+            fixed_candidate: Some(FixedCandidate::trivial(SMOKE_TEST_NONCE_SEED)),
+            ..OptimizedMiningConfig::from_profile(MiningProfile::Laptop)
+        };
+        let report = run_hashrate_only(&config, Duration::from_millis(200))
+            .await
+            .unwrap();
+        assert_eq!(report.threads, 2);
+        assert_eq!(report.pow_len, SMOKE_TEST_POW_LEN);
+        assert!(
+            report.hashes_per_thread.iter().all(|&hashes| hashes > 0),
+            "{report:?}"
+        );
+        assert_eq!(report.hashes, report.hashes_per_thread.iter().sum::<u64>());
+        // Every hash solves the trivial target
+        assert_eq!(report.solutions, report.hashes);
+        assert!(report.hashes_per_sec > 0.0);
+    }
+
+    // Run in release by the mining-release workflow
+    #[ignore = "Boots the miner kernel and proves, which is slow outside release builds"]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_run_batching_benchmark() {
+        let config = OptimizedMiningConfig {
+            mining_threads: 2,
+            fixed_candidate: Some(FixedCandidate::trivial(SMOKE_TEST_NONCE_SEED)),
+            ..OptimizedMiningConfig::from_profile(MiningProfile::Laptop)
+        };
+        let report = run_batching_benchmark(&config, &[1, 8], Duration::from_millis(200))
+            .await
+            .unwrap();
+        assert_eq!(report.pow_len, SMOKE_TEST_POW_LEN);
+        assert_eq!(report.results.len(), 2);
+        for result in &report.results {
+            assert!(result.hashes > 0, "{result:?}");
+            assert!(
+                result.attempts * result.nonces_per_attempt >= result.hashes,
+                "{result:?}"
+            );
+        }
+        assert!(report.best_speedup().unwrap() >= 1.0);
+    }
+
+    #[test]
+    fn test_batching_result_splits_worker_time() {
+        // 2 workers for 10s hashed 100 times with 15s in the kernel: 0.2s of worker time
+        // per hash, 0.15s of it proving
+        let result = BatchingResult::new(
+            4,
+            2,
+            Duration::from_secs(10),
+            25,
+            100,
+            Duration::from_secs(15),
+        );
+        assert_eq!(result.hashes_per_sec, 10.0);
+        assert!((result.kernel_secs_per_hash - 0.15).abs() < 1e-9);
+        assert!((result.overhead_secs_per_hash - 0.05).abs() < 1e-9);
+        assert!((result.overhead_fraction() - 0.25).abs() < 1e-9);
+
+        let report = BatchingReport {
+            threads: 2,
+            pow_len: 1,
+            results: vec![
+                BatchingResult::new(
+                    1,
+                    2,
+                    Duration::from_secs(10),
+                    80,
+                    80,
+                    Duration::from_secs(15),
+                ),
+                result,
+            ],
+        };
+        assert_eq!(report.best_speedup(), Some(1.25));
+        let empty = BatchingResult::new(1, 1, Duration::from_secs(1), 0, 0, Duration::ZERO);
+        assert_eq!(
+            (empty.hashes_per_sec, empty.overhead_fraction()),
+            (0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_unsolvable_candidate_has_mainnet_cost() {
+        let candidate = FixedCandidate::unsolvable(1).to_candidate();
+        assert_eq!(candidate.pow_len(), BENCHMARK_POW_LEN);
+        let target = crate::pow_target::target_from_noun(candidate.target()).unwrap();
+        assert_eq!(target, UBig::from(0u8));
+    }
+
+    #[test]
+    fn test_alloc_throttle() {
+        let attempt = Duration::from_millis(100);
+        assert_eq!(alloc_throttle(attempt, 500, 1000), None);
+        assert_eq!(alloc_throttle(attempt, 1000, 1000), None);
+        // Twice the cap: idle as long again, halving the rate
+        assert_eq!(alloc_throttle(attempt, 2000, 1000), Some(attempt));
+        assert_eq!(
+            alloc_throttle(attempt, 4000, 1000),
+            Some(Duration::from_millis(300))
+        );
+        assert_eq!(
+            alloc_throttle(attempt, u64::MAX, 0),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_record_difficulty() {
+        let stats = OptimizedMiningStats::new();
+        // 2^300 as u32 limbs: nine zero limbs, then bit 12 of the tenth
+        let mut limbs = [0u32; 10];
+        limbs[9] = 1 << 12;
+        let candidate = Candidate::from_parts(2, [1, 2, 3, 4, 5], &limbs, 64);
+        assert_eq!(stats.record_difficulty(candidate.target()), Some(19));
+        assert_eq!(stats.current_difficulty.load(Ordering::Relaxed), 19);
+
+        assert_eq!(stats.record_difficulty(D(7)), None);
+        assert_eq!(stats.current_difficulty.load(Ordering::Relaxed), 19);
+    }
+
+    #[test]
+    fn test_split_solutions_keeps_every_block() {
+        let slab = |n: u64| {
+            let mut slab = NounSlab::new();
+            slab.set_root(D(n));
+            slab
+        };
+        // Two workers' nonces in one batch both met the target, around a miss
+        let results = vec![
+            HashResult::Found {
+                hash: slab(1),
+                poke: slab(11),
+            },
+            HashResult::Miss { hash: slab(2) },
+            HashResult::Found {
+                hash: slab(3),
+                poke: slab(13),
+            },
+        ];
+        let (solutions, rest) = split_solutions(results);
+        let found: Vec<(u64, u64)> = solutions
+            .iter()
+            .map(|(hash, poke)| unsafe {
+                (
+                    hash.root().as_direct().unwrap().data(),
+                    poke.root().as_direct().unwrap().data(),
+                )
+            })
+            .collect();
+        assert_eq!(found, vec![(1, 11), (3, 13)]);
+        assert!(matches!(rest, Some(HashResult::Miss { .. })));
+
+        let (solutions, rest) = split_solutions(vec![HashResult::Cancelled]);
+        assert!(solutions.is_empty());
+        assert!(matches!(rest, Some(HashResult::Cancelled)));
+    }
+
+    /// Run the driver with one worker on `backend` until its first solution, answering its
+    /// pokes as a node would. Returns the stats, the wires the driver poked and the stack
+    /// sizes it built backends with.
+    async fn drive_scripted(
+        backend: Arc<ScriptedBackend>,
+        config: OptimizedMiningConfig,
+    ) -> (Arc<OptimizedMiningStats>, Vec<WireRepr>, Vec<usize>) {
+        let stacks = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let requested = stacks.clone();
+        let config = OptimizedMiningConfig {
+            thread_affinity: false,
+            backend_factory: Some(Box::new(move |stack_size| {
+                requested.lock().unwrap().push(stack_size);
+                let backend: Arc<dyn HashBackend> = backend.clone();
+                Box::pin(async move { Ok(backend) })
+            })),
+            ..config
+        };
+        let stats = Arc::new(OptimizedMiningStats::default());
+        let keys = vec![crate::mining::MiningKeyConfig {
+            share: 1,
+            m: 1,
+            keys: vec!["key".to_string()],
+        }];
+        let driver = create_optimized_mining_driver(Some(keys), true, config, stats.clone(), None);
+        let wires = answer_as_node(driver).await;
+        let stacks = stacks.lock().unwrap().clone();
+        (stats, wires, stacks)
+    }
+
+    /// Run `driver` until it stops, acking its pokes as a node would, and return the wires
+    /// it poked
+    async fn answer_as_node(driver: IODriverFn) -> Vec<WireRepr> {
+        let (handle, mut actions, _exits) = nockapp::nockapp::test::detached_handle();
+        let node = tokio::spawn(async move {
+            let mut wires = Vec::new();
+            while let Some(action) = actions.recv().await {
+                if let IOAction::Poke {
+                    wire, ack_channel, ..
+                } = action
+                {
+                    wires.push(wire);
+                    let _ = ack_channel.send(PokeResult::Ack);
+                }
+            }
+            wires
+        });
+        tokio::time::timeout(Duration::from_secs(10), driver(handle))
+            .await
+            .expect("Driver did not stop after its last solution")
+            .expect("Driver failed");
+        node.await.unwrap()
+    }
+
+    fn mined_wires(wires: &[WireRepr]) -> usize {
+        let mined = crate::mining::MiningWire::Mined.to_wire();
+        wires.iter().filter(|&wire| *wire == mined).count()
+    }
+
+    #[tokio::test]
+    async fn test_driver_submits_scripted_solution() {
+        let backend = Arc::new(ScriptedBackend::new([
+            Scripted::Miss,
+            Scripted::Miss,
+            Scripted::Found,
+        ]));
+        let (stats, wires, stacks) =
+            drive_scripted(backend, OptimizedMiningConfig::smoke_test(1)).await;
+        assert_eq!(mined_wires(&wires), 1);
+        assert!(stats.hashes.load(Ordering::Relaxed) >= 3);
+        assert_eq!(stacks.len(), 1);
+        let events: Vec<MinerEvent> = stats
+            .events
+            .recent()
+            .into_iter()
+            .map(|record| record.event)
+            .collect();
+        assert!(
+            events.contains(&MinerEvent::BlockFound { thread: 0 }),
+            "{events:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_driver_respawns_scripted_worker_after_oom() {
+        let backend = Arc::new(ScriptedBackend::new([
+            Scripted::OutOfMemory,
+            Scripted::Found,
+        ]));
+        let config = OptimizedMiningConfig {
+            stack_size: NOCK_STACK_SIZE_TINY * 4,
+            respawn_after_oom: true,
+            ..OptimizedMiningConfig::smoke_test(1)
+        };
+        let (stats, wires, stacks) = drive_scripted(backend, config).await;
+        assert_eq!(
+            stacks,
+            vec![NOCK_STACK_SIZE_TINY * 4, NOCK_STACK_SIZE_TINY * 2]
+        );
+        assert_eq!(stats.workers_oom.load(Ordering::Relaxed), 1);
+        assert_eq!(mined_wires(&wires), 1);
+        let events: Vec<MinerEvent> = stats
+            .events
+            .recent()
+            .into_iter()
+            .map(|record| record.event)
+            .collect();
+        assert!(
+            events.contains(&MinerEvent::WorkerRestarted {
+                thread: 0,
+                stack_size: NOCK_STACK_SIZE_TINY * 2
+            }),
+            "{events:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_driver_survives_scripted_worker_panic() {
+        // One worker, so the solution can only come after the panic has been handled
+        let backend = Arc::new(ScriptedBackend::new([
+            Scripted::Panic,
+            Scripted::Miss,
+            Scripted::Found,
+        ]));
+        let (stats, wires, stacks) =
+            drive_scripted(backend, OptimizedMiningConfig::smoke_test(1)).await;
+        assert_eq!(stats.worker_panics.load(Ordering::Relaxed), 1);
+        assert_eq!(mined_wires(&wires), 1);
+        assert!(stats.hashes.load(Ordering::Relaxed) >= 2);
+        // The worker at startup and a fresh backend after it panicked
+        assert_eq!(stacks.len(), 2);
+        let events: Vec<MinerEvent> = stats
+            .events
+            .recent()
+            .into_iter()
+            .map(|record| record.event)
+            .collect();
+        assert!(
+            events.contains(&MinerEvent::WorkerPanicked { thread: 0 }),
+            "{events:?}"
+        );
+        assert!(
+            events.contains(&MinerEvent::WorkerRestarted {
+                thread: 0,
+                stack_size: stacks[0]
+            }),
+            "{events:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_driver_builds_worker_spans_from_config() {
+        let built = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let record = built.clone();
+        let config = OptimizedMiningConfig {
+            mining_threads: 2,
+            worker_span: Some(Arc::new(move |thread, numa_node| {
+                record.lock().unwrap().push((thread, numa_node));
+                info_span!("custom_worker", thread)
+            })),
+            ..OptimizedMiningConfig::smoke_test(1)
+        };
+        let backend = Arc::new(ScriptedBackend::new([Scripted::Found]));
+        let (_, wires, _) = drive_scripted(backend, config).await;
+        assert_eq!(mined_wires(&wires), 1);
+        // Once per worker, not per attempt; unpinned workers have no node
+        assert_eq!(*built.lock().unwrap(), vec![(0, None), (1, None)]);
+    }
+
+    #[tokio::test]
+    async fn test_driver_keeps_scripted_worker_after_cancel_and_unexpected() {
+        let backend = Arc::new(ScriptedBackend::new([
+            Scripted::Unexpected,
+            Scripted::Cancelled,
+            Scripted::Found,
+        ]));
+        let (stats, wires, _) =
+            drive_scripted(backend.clone(), OptimizedMiningConfig::smoke_test(1)).await;
+        assert_eq!(stats.unexpected_effects.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.cancellations.load(Ordering::Relaxed), 1);
+        assert_eq!(mined_wires(&wires), 1);
+        // Stopping after the solution cancels the worker
+        assert_eq!(backend.cancels.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_driver_restarts_worker_after_empty_results() {
+        let backend = Arc::new(ScriptedBackend::new([Scripted::Empty, Scripted::Found]));
+        let (stats, wires, stacks) =
+            drive_scripted(backend, OptimizedMiningConfig::smoke_test(1)).await;
+        assert_eq!(stats.empty_results.load(Ordering::Relaxed), 1);
+        assert_eq!(mined_wires(&wires), 1);
+        // The same worker carried on
+        assert_eq!(stacks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fixed_candidate_workers_keep_their_rng() {
+        /// Records every nonce it is asked to hash, then follows its script
+        struct Recording(ScriptedBackend, std::sync::Mutex<Vec<Vec<u8>>>);
+        impl HashBackend for Recording {
+            fn hash_candidates(&self, candidate: &Candidate, nonces: &[Nonce]) -> Vec<HashResult> {
+                let jams = nonces.iter().map(|nonce| {
+                    let slab: NounSlab = NounSlab::from(nonce.as_noun());
+                    slab.jam().to_vec()
+                });
+                self.1.lock().unwrap().extend(jams);
+                self.0.hash_candidates(candidate, nonces)
+            }
+        }
+
+        let backend = Arc::new(Recording(
+            ScriptedBackend::new([Scripted::Miss, Scripted::Miss, Scripted::Found]),
+            std::sync::Mutex::new(Vec::new()),
+        ));
+        let shared = backend.clone();
+        let config = OptimizedMiningConfig {
+            nonces_per_attempt: 4,
+            thread_affinity: false,
+            backend_factory: Some(Box::new(move |_| {
+                let backend: Arc<dyn HashBackend> = shared.clone();
+                Box::pin(async move { Ok(backend) })
+            })),
+            ..OptimizedMiningConfig::smoke_test(1)
+        };
+        let keys = vec![crate::mining::MiningKeyConfig {
+            share: 1,
+            m: 1,
+            keys: vec!["key".to_string()],
+        }];
+        let driver = create_optimized_mining_driver(
+            Some(keys),
+            true,
+            config,
+            Arc::new(OptimizedMiningStats::default()),
+            None,
+        );
+        answer_as_node(driver).await;
+
+        // Each attempt tops up the batch with fresh nonces; a reseeded RNG would repeat them
+        let nonces = backend.1.lock().unwrap();
+        assert_eq!(nonces.len(), 12);
+        let distinct: std::collections::HashSet<&Vec<u8>> = nonces.iter().collect();
+        assert_eq!(distinct.len(), nonces.len());
+    }
+
+    #[tokio::test]
+    async fn test_driver_mines_two_chains() {
+        // One worker per chain; workers are built in id order
+        let backends = std::sync::Mutex::new(std::collections::VecDeque::from([
+            Arc::new(ScriptedBackend::new([Scripted::Found])),
+            Arc::new(ScriptedBackend::new([Scripted::Miss, Scripted::Found])),
+        ]));
+        let config = OptimizedMiningConfig {
+            mining_threads: 2,
+            fixed_candidate: None,
+            stop_after_solutions: Some(2),
+            thread_affinity: false,
+            backend_factory: Some(Box::new(move |_| {
+                let backend: Arc<dyn HashBackend> = backends
+                    .lock()
+                    .unwrap()
+                    .pop_front()
+                    .expect("one backend per worker");
+                Box::pin(async move { Ok(backend) })
+            })),
+            ..OptimizedMiningConfig::smoke_test(1)
+        };
+        let delivered = Arc::new(AtomicU64::new(0));
+        let counted = delivered.clone();
+        let callback: SolutionCallback = Arc::new(move |_| {
+            counted.fetch_add(1, Ordering::Relaxed);
+        });
+        let chain = |name: &str, submit| {
+            let (tx, rx) = tokio::sync::mpsc::channel(1);
+            tx.try_send(FixedCandidate::trivial(1).to_candidate())
+                .unwrap();
+            MiningChain {
+                name: name.to_string(),
+                weight: 1,
+                source: Some(Box::new(rx)),
+                submit,
+            }
+        };
+        let chains = vec![
+            chain("main", SubmitMode::Internal),
+            chain("side", SubmitMode::ExternalCallback(callback)),
+        ];
+
+        let stats = Arc::new(OptimizedMiningStats::default());
+        let keys = vec![crate::mining::MiningKeyConfig {
+            share: 1,
+            m: 1,
+            keys: vec!["key".to_string()],
+        }];
+        let driver = create_optimized_mining_driver_with_chains(
+            Some(keys),
+            true,
+            config,
+            stats.clone(),
+            None,
+            chains,
+        );
+        let wires = answer_as_node(driver).await;
+
+        // Each chain's solution went to that chain
+        assert_eq!(mined_wires(&wires), 1);
+        assert_eq!(delivered.load(Ordering::Relaxed), 1);
+        let chains = stats.chains();
+        let split: Vec<(&str, u64, u64, u64)> = chains
+            .iter()
+            .map(|chain| {
+                (
+                    chain.name.as_str(),
+                    chain.workers,
+                    chain.candidates,
+                    chain.solutions,
+                )
+            })
+            .collect();
+        assert_eq!(split, vec![("main", 1, 1, 1), ("side", 1, 1, 1)]);
+    }
+}
@@ -0,0 +1,204 @@
+//! Randomness for fresh nonces.
+//!
+//! A nonce only has to differ from every other nonce tried on the same candidate, so the
+//! generator's job is to avoid repeats, not to be unpredictable. The sources trade
+//! throughput for quality:
+//!
+//! - [`EntropySource::Os`] asks the kernel (`getrandom`) for every word. Strongest, and a
+//!   system call per word, so the slowest by far.
+//! - [`EntropySource::ThreadLocal`] is `rand`'s thread RNG, ChaCha reseeded from the OS.
+//!   Cryptographic quality at memory speed; the default.
+//! - [`EntropySource::Fast`] is SplitMix64 seeded from a fixed value and the worker id.
+//!   Not cryptographic, but a single add and mix per word and reproducible from the seed,
+//!   for benchmarks and replays. Each worker's stream repeats only after 2^64 words; two
+//!   miners given the same seed repeat each other's nonces, wasting work.
+//!
+//! A PoW attempt takes far longer than generating its nonce with any of them, so [`Os`]
+//! only shows up in the hash rate when attempts are tiny, e.g. a small fakenet `pow_len`.
+//!
+//! [`Os`]: EntropySource::Os
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand::rngs::{OsRng, StdRng};
+use rand::RngCore;
+
+/// Where fresh nonces get their randomness
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EntropySource {
+    /// The operating system's RNG, for every word
+    Os,
+    /// SplitMix64 from this seed, mixed with the worker id
+    Fast(u64),
+    /// `rand`'s thread-local RNG
+    #[default]
+    ThreadLocal,
+}
+
+impl EntropySource {
+    /// A generator for one worker's nonces
+    pub fn for_worker(self, worker: u64) -> NonceRng {
+        match self {
+            EntropySource::Os => NonceRng::Os,
+            EntropySource::Fast(seed) => {
+                NonceRng::Fast(SplitMix64::new(seed ^ SplitMix64::new(worker).next_u64()))
+            }
+            EntropySource::ThreadLocal => NonceRng::ThreadLocal,
+        }
+    }
+}
+
+impl fmt::Display for EntropySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntropySource::Os => write!(f, "os"),
+            EntropySource::Fast(seed) => write!(f, "fast:{seed}"),
+            EntropySource::ThreadLocal => write!(f, "thread-local"),
+        }
+    }
+}
+
+impl FromStr for EntropySource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "os" => Ok(EntropySource::Os),
+            "thread-local" => Ok(EntropySource::ThreadLocal),
+            "fast" => Ok(EntropySource::Fast(0)),
+            _ => match s.strip_prefix("fast:").map(str::parse::<u64>) {
+                Some(Ok(seed)) => Ok(EntropySource::Fast(seed)),
+                _ => Err(format!(
+                    "Invalid entropy source '{s}'. Expected 'os', 'thread-local', 'fast' or 'fast:SEED'"
+                )),
+            },
+        }
+    }
+}
+
+/// One worker's nonce generator, built by [`EntropySource::for_worker`]
+///
+/// The thread-local variant fetches the RNG on every call rather than holding it, so a
+/// `NonceRng` can live in the driver across awaits.
+#[derive(Debug, Clone)]
+pub enum NonceRng {
+    Os,
+    Fast(SplitMix64),
+    ThreadLocal,
+    /// `StdRng` from a fixed seed, for replaying a fixed candidate's nonces
+    Seeded(Box<StdRng>),
+}
+
+impl RngCore for NonceRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            NonceRng::Os => OsRng.next_u64(),
+            NonceRng::Fast(rng) => rng.next_u64(),
+            NonceRng::ThreadLocal => rand::thread_rng().next_u64(),
+            NonceRng::Seeded(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            NonceRng::Os => OsRng.fill_bytes(dest),
+            NonceRng::Fast(rng) => rng.fill_bytes(dest),
+            NonceRng::ThreadLocal => rand::thread_rng().fill_bytes(dest),
+            NonceRng::Seeded(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        match self {
+            NonceRng::Os => OsRng.try_fill_bytes(dest),
+            NonceRng::Fast(rng) => rng.try_fill_bytes(dest),
+            NonceRng::ThreadLocal => rand::thread_rng().try_fill_bytes(dest),
+            NonceRng::Seeded(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+/// Steele, Lea and Flood's SplitMix64: a 64-bit counter run through a mixing function
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
+
+impl RngCore for SplitMix64 {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let word = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&word[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_entropy_source() {
+        for source in [EntropySource::Os, EntropySource::Fast(42), EntropySource::ThreadLocal] {
+            assert_eq!(source.to_string().parse::<EntropySource>(), Ok(source));
+        }
+        assert_eq!("fast".parse::<EntropySource>(), Ok(EntropySource::Fast(0)));
+        assert!("fast:x".parse::<EntropySource>().is_err());
+        assert!("rdrand".parse::<EntropySource>().is_err());
+    }
+
+    #[test]
+    fn test_fast_source_is_reproducible_per_worker() {
+        let words = |worker| {
+            let mut rng = EntropySource::Fast(7).for_worker(worker);
+            (0..1000).map(|_| rng.next_u64()).collect::<Vec<_>>()
+        };
+        assert_eq!(words(0), words(0));
+        // Workers sharing a seed must not retrace each other's nonces
+        let mut seen = HashSet::new();
+        for worker in 0..8 {
+            for word in words(worker) {
+                assert!(seen.insert(word), "worker {worker} repeated a word");
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_source_yields_words() {
+        for source in [EntropySource::Os, EntropySource::Fast(1), EntropySource::ThreadLocal] {
+            let mut rng = source.for_worker(3);
+            let words: HashSet<u64> = (0..16).map(|_| rng.next_u64()).collect();
+            assert_eq!(words.len(), 16, "{source}");
+        }
+    }
+}
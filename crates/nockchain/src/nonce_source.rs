@@ -0,0 +1,165 @@
+//! Nonces computed outside the driver, e.g. by an FPGA nonce generator.
+//!
+//! By default the optimized driver makes its own nonces: fresh ones from
+//! [`crate::nonce_entropy`], then each miss's hash as the next nonce. A [`NonceSource`] in
+//! `OptimizedMiningConfig::nonce_source` is asked first instead. The driver still hashes
+//! every nonce with the miner kernel, checks the result against the target and submits
+//! solutions itself, so a source only has to produce nonces.
+//!
+//! Sources are polled without waiting. When one has fewer nonces ready than an attempt
+//! needs, the driver fills the rest in the usual way, so slow hardware never stalls a
+//! worker; the `external_nonces` counter in the driver's stats shows how many it used.
+//!
+//! # Format
+//!
+//! A nonce is a tip5 digest: five base field elements, each below [`PRIME`], which the
+//! kernel receives as the noun `[a b c d e]`. On the wire it is [`NONCE_BYTES`] bytes, the
+//! five elements in order as little-endian u64s. [`nonce_from_bytes`] parses that form and
+//! [`nonce_from_belts`] the elements themselves; both reject elements outside the field.
+
+use std::fmt;
+
+use tokio::sync::mpsc;
+use tracing::warn;
+use zkvm_jetpack::form::PRIME;
+
+use crate::hash_backend::{Candidate, Nonce};
+
+/// Length of a nonce on the wire
+pub const NONCE_BYTES: usize = 40;
+
+/// Nonces for the driver's workers, in place of generating them
+pub trait NonceSource: Send + Sync {
+    /// Up to `max` nonces for worker `worker`, from whatever is ready now. Must not block:
+    /// the driver calls it from its event loop.
+    fn take(&mut self, worker: u64, max: usize) -> Vec<Nonce>;
+
+    /// The workers moved on to `candidate`, so any nonces prepared for the last one can
+    /// be dropped
+    fn candidate_changed(&mut self, _candidate: &Candidate) {}
+}
+
+/// Why bytes or field elements do not form a nonce
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NonceFormatError {
+    /// The nonce was this many bytes rather than [`NONCE_BYTES`]
+    Length(usize),
+    /// Element `index` is not below [`PRIME`]
+    OutOfField { index: usize, value: u64 },
+}
+
+impl fmt::Display for NonceFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NonceFormatError::Length(len) => {
+                write!(f, "nonce is {len} bytes, expected {NONCE_BYTES}")
+            }
+            NonceFormatError::OutOfField { index, value } => {
+                write!(
+                    f,
+                    "nonce element {index} ({value:#x}) is not below the field prime"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for NonceFormatError {}
+
+/// A nonce from its five field elements
+pub fn nonce_from_belts(belts: [u64; 5]) -> Result<Nonce, NonceFormatError> {
+    if let Some(index) = belts.iter().position(|&belt| belt >= PRIME) {
+        return Err(NonceFormatError::OutOfField {
+            index,
+            value: belts[index],
+        });
+    }
+    Ok(Nonce::from_belts(belts))
+}
+
+/// A nonce from its [`NONCE_BYTES`]-byte wire form
+pub fn nonce_from_bytes(bytes: &[u8]) -> Result<Nonce, NonceFormatError> {
+    if bytes.len() != NONCE_BYTES {
+        return Err(NonceFormatError::Length(bytes.len()));
+    }
+    let belts = std::array::from_fn(|i| {
+        u64::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().expect("8 bytes"))
+    });
+    nonce_from_belts(belts)
+}
+
+/// Nonces pushed as field elements through a channel, e.g. by a thread reading the
+/// hardware, shared by every worker. Elements outside the field are dropped with a
+/// warning.
+impl NonceSource for mpsc::Receiver<[u64; 5]> {
+    fn take(&mut self, _worker: u64, max: usize) -> Vec<Nonce> {
+        let mut nonces = Vec::with_capacity(max);
+        while nonces.len() < max {
+            let Ok(belts) = self.try_recv() else {
+                break;
+            };
+            match nonce_from_belts(belts) {
+                Ok(nonce) => nonces.push(nonce),
+                Err(e) => warn!("Dropping external nonce: {e}"),
+            }
+        }
+        nonces
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nockvm::noun::Noun;
+    use zkvm_jetpack::noun::noun_ext::NounExt;
+
+    use super::*;
+
+    fn belts(nonce: &Nonce) -> Vec<u64> {
+        let belts: [Noun; 5] = nonce.as_noun().uncell().unwrap();
+        belts
+            .iter()
+            .map(|belt| belt.as_atom().unwrap().as_u64().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_nonce_wire_format() {
+        let mut bytes = Vec::new();
+        for belt in [1u64, 2, 3, 4, PRIME - 1] {
+            bytes.extend_from_slice(&belt.to_le_bytes());
+        }
+        let nonce = nonce_from_bytes(&bytes).unwrap();
+        assert_eq!(belts(&nonce), vec![1, 2, 3, 4, PRIME - 1]);
+
+        assert_eq!(
+            nonce_from_bytes(&bytes[..39]).err(),
+            Some(NonceFormatError::Length(39))
+        );
+        bytes[16..24].copy_from_slice(&PRIME.to_le_bytes());
+        assert_eq!(
+            nonce_from_bytes(&bytes).err(),
+            Some(NonceFormatError::OutOfField {
+                index: 2,
+                value: PRIME
+            })
+        );
+    }
+
+    #[test]
+    fn test_channel_source_hands_out_what_is_ready() {
+        let (tx, mut rx) = mpsc::channel(8);
+        for i in 0..3 {
+            tx.try_send([i, 0, 0, 0, 0]).unwrap();
+        }
+        tx.try_send([u64::MAX, 0, 0, 0, 0]).unwrap();
+        tx.try_send([7, 0, 0, 0, 0]).unwrap();
+
+        let first = rx.take(0, 2);
+        assert_eq!(first.len(), 2);
+        assert_eq!(belts(&first[1])[0], 1);
+        // The out-of-field nonce is skipped, and an empty channel doesn't block
+        let rest: Vec<u64> = rx.take(1, 8).iter().map(|nonce| belts(nonce)[0]).collect();
+        assert_eq!(rest, vec![2, 7]);
+        assert!(rx.take(0, 8).is_empty());
+    }
+}
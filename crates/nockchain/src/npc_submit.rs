@@ -0,0 +1,166 @@
+//! Submitting found blocks to another node through its npc socket.
+//!
+//! Miners that run against one node can also hand every found block to a redundant
+//! node, so a single node being down does not lose the block. The block is sent as an
+//! npc `%poke` on a fresh connection, and the node's `%pack` or `%nack` is the answer.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use nockapp::nockapp::driver::PokeResult;
+use nockapp::nockapp::NockAppError;
+use nockapp::noun::slab::NounSlab;
+use nockapp::Bytes;
+use nockvm::noun::D;
+use nockvm_macros::tas;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tracing::debug;
+
+/// How long one submission may take, from connecting to reading the answer
+const SUBMIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A node reachable on its npc socket
+pub struct NpcSubmitTarget {
+    path: PathBuf,
+    next_pid: AtomicU64,
+}
+
+impl NpcSubmitTarget {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            next_pid: AtomicU64::new(1),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Poke the node with `poke_slab` and wait for it to ack or nack
+    pub async fn poke(&self, mut poke_slab: NounSlab) -> Result<PokeResult, NockAppError> {
+        let pid = self.next_pid.fetch_add(1, Ordering::Relaxed);
+        poke_slab.modify(|poke| vec![D(pid), D(tas!(b"poke")), poke]);
+        tokio::time::timeout(SUBMIT_TIMEOUT, self.exchange(pid, poke_slab))
+            .await
+            .map_err(|_| {
+                NockAppError::IoError(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "no answer from {} within {SUBMIT_TIMEOUT:?}",
+                        self.path.display()
+                    ),
+                ))
+            })?
+    }
+
+    async fn exchange(&self, pid: u64, message: NounSlab) -> Result<PokeResult, NockAppError> {
+        let mut stream = UnixStream::connect(&self.path)
+            .await
+            .map_err(NockAppError::IoError)?;
+        write_frame(&mut stream, &message.jam())
+            .await
+            .map_err(NockAppError::IoError)?;
+
+        // The node also forwards its %npc effects to every connection, so skip anything
+        // that is not the answer to this poke
+        loop {
+            let frame = read_frame(&mut stream)
+                .await
+                .map_err(NockAppError::IoError)?;
+            let mut slab: NounSlab = NounSlab::new();
+            let reply = slab.cue_into(Bytes::from(frame))?;
+            let Ok(reply) = reply.as_cell() else {
+                continue;
+            };
+            let (Ok(reply_pid), Ok(rest)) = (reply.head().as_direct(), reply.tail().as_cell())
+            else {
+                continue;
+            };
+            if reply_pid.data() != pid {
+                continue;
+            }
+            match rest.head().as_direct().map(|tag| tag.data()) {
+                Ok(tas!(b"pack")) => return Ok(PokeResult::Ack),
+                Ok(tas!(b"nack")) => return Ok(PokeResult::Nack),
+                _ => debug!("npc submit: ignoring unexpected reply to pid {pid}"),
+            }
+        }
+    }
+}
+
+/// Write one length-prefixed jam, framed as the npc driver expects
+async fn write_frame(stream: &mut UnixStream, jammed: &[u8]) -> io::Result<()> {
+    stream.write_all(&jammed.len().to_le_bytes()).await?;
+    stream.write_all(jammed).await?;
+    stream.flush().await
+}
+
+async fn read_frame(stream: &mut UnixStream) -> io::Result<Vec<u8>> {
+    let mut size_bytes = [0u8; 8];
+    stream.read_exact(&mut size_bytes).await?;
+    let mut frame = vec![0u8; usize::from_le_bytes(size_bytes)];
+    stream.read_exact(&mut frame).await?;
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::UnixListener;
+
+    use super::*;
+
+    /// Answer the first poke on `listener` with `tag`, after an unrelated effect
+    async fn fake_node(listener: UnixListener, tag: u64) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let frame = read_frame(&mut stream).await.unwrap();
+        let pid = {
+            let mut slab: NounSlab = NounSlab::new();
+            let message = slab
+                .cue_into(Bytes::from(frame))
+                .unwrap()
+                .as_cell()
+                .unwrap();
+            let directive = message.tail().as_cell().unwrap();
+            assert_eq!(directive.head().as_direct().unwrap().data(), tas!(b"poke"));
+            assert!(unsafe { directive.tail().raw_equals(&D(42)) });
+            message.head().as_direct().unwrap().data()
+        };
+
+        for (reply_pid, reply_tag) in [(pid + 100, tas!(b"bind")), (pid, tag)] {
+            let mut reply: NounSlab = NounSlab::new();
+            reply.modify(|_| vec![D(reply_pid), D(reply_tag), D(0)]);
+            write_frame(&mut stream, &reply.jam()).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poke_reads_the_nodes_answer() {
+        let dir = tempfile::tempdir().unwrap();
+        for (tag, acked) in [(tas!(b"pack"), true), (tas!(b"nack"), false)] {
+            let path = dir.path().join(format!("npc-{tag}.sock"));
+            let node = tokio::spawn(fake_node(UnixListener::bind(&path).unwrap(), tag));
+
+            let mut slab = NounSlab::new();
+            slab.set_root(D(42));
+            let result = NpcSubmitTarget::new(path).poke(slab).await.unwrap();
+            assert_eq!(matches!(result, PokeResult::Ack), acked);
+            node.await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poke_fails_when_node_is_down() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = NpcSubmitTarget::new(dir.path().join("missing.sock"));
+        let mut slab = NounSlab::new();
+        slab.set_root(D(42));
+        assert!(matches!(
+            target.poke(slab).await,
+            Err(NockAppError::IoError(_))
+        ));
+    }
+}
@@ -0,0 +1,229 @@
+//! PoW evaluation, kept apart from the node's I/O.
+//!
+//! [`evaluate`] runs one nonce of a [`Candidate`] through the miner kernel and reads the
+//! verdict. It is synchronous and needs neither a `NockAppHandle` nor a tokio runtime,
+//! so a standalone verifier can call it exactly as the drivers do; [`evaluate_batch`]
+//! runs several nonces back to back. [`meets_target`] checks a claimed hash against a
+//! candidate's target without running the kernel.
+
+use nockapp::kernel::form::SerfThread;
+use nockapp::nockapp::wire::Wire;
+use nockapp::noun::slab::NounSlab;
+use nockapp::{CrownError, NockAppError};
+
+use crate::hash_backend::{Candidate, HashResult, Nonce};
+use crate::mining::MiningWire;
+use crate::pow_target::{digest_within, target_from_noun};
+
+/// A loaded miner kernel that can be poked synchronously
+pub trait PowKernel {
+    /// Poke the kernel with a `[version header nonce target pow-len]` cause and block
+    /// until it returns its effects
+    fn poke_candidate(&self, cause: NounSlab) -> Result<NounSlab, CrownError>;
+}
+
+impl<C> PowKernel for SerfThread<C> {
+    fn poke_candidate(&self, cause: NounSlab) -> Result<NounSlab, CrownError> {
+        self.poke_sync(MiningWire::Candidate.to_wire(), cause)
+    }
+}
+
+/// Hash `nonce` for `candidate` with `kernel` and report whether it found a block.
+///
+/// This blocks for as long as the proof takes; call it from a blocking thread.
+pub fn evaluate<K: PowKernel + ?Sized>(
+    kernel: &K,
+    candidate: &Candidate,
+    nonce: &Nonce,
+) -> HashResult {
+    match kernel.poke_candidate(candidate.poke(nonce)) {
+        Ok(effects) => HashResult::from_effects(&effects),
+        Err(e) => HashResult::Failed(e),
+    }
+}
+
+/// Hash `nonces` for `candidate` in order, stopping after the first result that is not a
+/// miss: a solution should be submitted at once, and after a cancellation the rest of the
+/// batch is for a stale candidate. The results cover a prefix of `nonces`.
+pub fn evaluate_batch<K: PowKernel + ?Sized>(
+    kernel: &K,
+    candidate: &Candidate,
+    nonces: &[Nonce],
+) -> Vec<HashResult> {
+    let mut results = Vec::with_capacity(nonces.len());
+    for nonce in nonces {
+        let result = evaluate(kernel, candidate, nonce);
+        let done = !matches!(result, HashResult::Miss { .. });
+        results.push(result);
+        if done {
+            break;
+        }
+    }
+    results
+}
+
+/// Whether `hash`, a tip5 digest, meets `candidate`'s target, by the comparison the
+/// kernel uses to accept a block
+pub fn meets_target(candidate: &Candidate, hash: &NounSlab) -> Result<bool, NockAppError> {
+    let target = target_from_noun(candidate.target())?;
+    digest_within(unsafe { *hash.root() }, &target)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use nockapp::noun::AtomExt;
+    use nockvm::noun::{Atom, Noun, D, T};
+    use nockvm_macros::tas;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use zkvm_jetpack::noun::noun_ext::NounExt;
+
+    use super::*;
+    use crate::mining_epyc7k62_dual::{DualSocketMiner, DualSocketMiningConfig};
+    use crate::mining_epyc9b14::{EpycMiner, EpycMiningConfig};
+    use crate::mining_optimized::{generate_optimized_nonce, MiningProfile, OptimizedMiningConfig};
+    use crate::topology::Topology;
+
+    /// Stands in for the miner kernel: the nonce is its own hash, and it is a block when
+    /// it meets the target
+    struct EchoKernel;
+
+    impl PowKernel for EchoKernel {
+        fn poke_candidate(&self, cause: NounSlab) -> Result<NounSlab, CrownError> {
+            let [_version, _header, nonce, target, _pow_len] = unsafe { cause.root() }
+                .uncell()
+                .expect("five-element cause");
+            let found = digest_within(nonce, &target_from_noun(target).unwrap()).unwrap();
+
+            let mut effects = NounSlab::new();
+            let tag = Atom::from_value(&mut effects, "mine-result")
+                .unwrap()
+                .as_noun();
+            let nonce = effects.copy_into(nonce);
+            let effect = if found {
+                T(&mut effects, &[tag, D(0), nonce, D(0)])
+            } else {
+                T(&mut effects, &[tag, D(1), nonce])
+            };
+            let list = T(&mut effects, &[effect, D(0)]);
+            effects.set_root(list);
+            Ok(effects)
+        }
+    }
+
+    /// Keeps the cause it was poked with and returns no effects
+    struct CapturingKernel(Mutex<Option<NounSlab>>);
+
+    impl PowKernel for CapturingKernel {
+        fn poke_candidate(&self, cause: NounSlab) -> Result<NounSlab, CrownError> {
+            *self.0.lock().unwrap() = Some(cause);
+            Ok(NounSlab::new())
+        }
+    }
+
+    /// Send `nonce` for `candidate` through `evaluate` and check the cause the kernel gets
+    /// against one built by hand from the nonce's five belts
+    fn assert_cause_layout(candidate: &Candidate, nonce: &Nonce) {
+        // A noun-digest:tip5: exactly five atoms, the last not a cell
+        let belts: [Noun; 5] = nonce.as_noun().uncell().expect("nonce is a five-tuple");
+        assert!(
+            belts.iter().all(|belt| belt.is_atom()),
+            "nonce is a five-atom tuple"
+        );
+
+        let kernel = CapturingKernel(Mutex::new(None));
+        evaluate(&kernel, candidate, nonce);
+        let sent = kernel.0.lock().unwrap().take().expect("kernel was poked");
+
+        let mut expected: NounSlab = NounSlab::new();
+        let header = T(&mut expected, &[D(1), D(2), D(3), D(4), D(5)]);
+        let belts: Vec<Noun> = belts
+            .iter()
+            .map(|belt| {
+                // Belts run up to 64 bits, past what fits in a direct atom
+                let belt = belt.as_atom().unwrap().as_u64().unwrap();
+                Atom::from_value(&mut expected, belt).unwrap().as_noun()
+            })
+            .collect();
+        let nonce = T(&mut expected, &belts);
+        let limbs = T(&mut expected, &[D(1000), D(7), D(0)]);
+        let target = T(&mut expected, &[D(tas!(b"bn")), limbs]);
+        let cause = T(&mut expected, &[D(2), header, nonce, target, D(64)]);
+        expected.set_root(cause);
+        assert_eq!(sent.jam(), expected.jam());
+    }
+
+    /// Every driver, the optimized one through `CpuSerfBackend` included, reaches the
+    /// kernel through `evaluate`, so this pins the cause layout for each way a nonce is
+    /// made: given as belts, and fresh from the optimized driver's generator at its own
+    /// thread counts and at those of the two EPYC miners. The EPYC miners don't poke the
+    /// kernel themselves, so their own loops aren't covered until they are wired to
+    /// `evaluate`.
+    #[test]
+    fn test_poke_layout_matches_hand_built_cause() {
+        let candidate = Candidate::from_parts(2, [1, 2, 3, 4, 5], &[1000, 7], 64);
+        assert_cause_layout(&candidate, &Nonce::from_belts([9, 8, 7, 6, 5]));
+
+        let epyc9b14 = EpycMiner::with_topology(
+            EpycMiningConfig::default(),
+            Some(Topology::synthetic(1, 1, 32)),
+        );
+        // 94 threads on each of its two sockets
+        let epyc7k62 = DualSocketMiner::with_topology(
+            DualSocketMiningConfig::default(),
+            Some(Topology::synthetic(2, 2, 48)),
+        );
+        let thread_counts = [
+            1,
+            OptimizedMiningConfig::from_profile(MiningProfile::Server).mining_threads,
+            epyc9b14.effective_config().threads as u64,
+            epyc7k62.effective_config().threads as u64,
+        ];
+        let mut rng = StdRng::seed_from_u64(7);
+        for workers in thread_counts {
+            for id in 0..workers {
+                let nonce = generate_optimized_nonce(NounSlab::new(), id, workers, 0, &mut rng);
+                assert_cause_layout(&candidate, &nonce);
+            }
+        }
+    }
+
+    #[test]
+    fn test_evaluate_batch_stops_at_first_solution() {
+        let candidate = Candidate::from_parts(2, [1, 2, 3, 4, 5], &[1000], 2);
+        let nonces = [
+            Nonce::from_belts([0, 1, 0, 0, 0]),
+            Nonce::from_belts([0, 2, 0, 0, 0]),
+            Nonce::from_belts([999, 0, 0, 0, 0]),
+            Nonce::from_belts([0, 3, 0, 0, 0]),
+        ];
+        let results = evaluate_batch(&EchoKernel, &candidate, &nonces);
+        assert_eq!(results.len(), 3);
+        assert!(matches!(results[0], HashResult::Miss { .. }));
+        assert!(matches!(results[1], HashResult::Miss { .. }));
+        assert!(matches!(results[2], HashResult::Found { .. }));
+
+        let misses = evaluate_batch(&EchoKernel, &candidate, &nonces[..2]);
+        assert_eq!(misses.len(), 2);
+        assert!(evaluate_batch(&EchoKernel, &candidate, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_without_a_node() {
+        let candidate = Candidate::from_parts(2, [1, 2, 3, 4, 5], &[1000], 2);
+        let hit = Nonce::from_belts([999, 0, 0, 0, 0]);
+        let miss = Nonce::from_belts([0, 1, 0, 0, 0]);
+
+        let HashResult::Found { hash, .. } = evaluate(&EchoKernel, &candidate, &hit) else {
+            panic!("nonce under the target should find a block");
+        };
+        assert!(meets_target(&candidate, &hash).unwrap());
+
+        let HashResult::Miss { hash } = evaluate(&EchoKernel, &candidate, &miss) else {
+            panic!("nonce over the target should miss");
+        };
+        assert!(!meets_target(&candidate, &hash).unwrap());
+    }
+}
@@ -0,0 +1,180 @@
+//! Host-side view of PoW targets.
+//!
+//! The miner kernel decides whether a proof hash meets the target. These helpers
+//! rebuild the same comparison outside the kernel so the drivers can reason about
+//! hashes that did not quite make it, e.g. to count near misses, and report how hard
+//! a target is. They also let the drivers check that the node asks for a PoW version
+//! the bundled kernel can prove.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use ibig::UBig;
+use nockapp::NockAppError;
+use nockchain_libp2p_io::tip5_util::{base_p_to_decimal, extract_5_tuple};
+use nockvm::noun::Noun;
+use tracing::{info, warn};
+
+/// Newest proof version the bundled miner kernel accepts (the `cause` type in
+/// `hoon/apps/dumbnet/miner.hoon`)
+pub const POW_ALGORITHM_VERSION: u32 = 2;
+
+/// Bit length of the largest tip5 digest read as a base-p number, `p^5 - 1`
+pub const DIGEST_BITS: u32 = 320;
+
+/// PoW algorithm version this miner implements
+pub fn pow_algorithm_version() -> u32 {
+    POW_ALGORITHM_VERSION
+}
+
+/// Compare the `version` of a %mine effect against [`pow_algorithm_version`] and warn
+/// loudly on skew. Each distinct version is reported once. Returns whether they match.
+pub fn check_pow_version(version: Noun) -> bool {
+    static LAST_SEEN: AtomicU64 = AtomicU64::new(u64::MAX);
+
+    let Some(version) = version.as_atom().ok().and_then(|atom| atom.as_u64().ok()) else {
+        warn!(
+            "!!! %mine effect carries a non-numeric PoW version; the miner kernel will reject it"
+        );
+        return false;
+    };
+    let ours = u64::from(pow_algorithm_version());
+    let first_time = LAST_SEEN.swap(version, Ordering::Relaxed) != version;
+    if version == ours {
+        if first_time {
+            info!("Mining PoW version {}", version);
+        }
+        return true;
+    }
+    if first_time {
+        if version > ours {
+            warn!(
+                "!!! Node requested PoW version {} but this miner only supports up to {}. \
+                 Every attempt will be rejected; upgrade the miner.",
+                version, ours
+            );
+        } else {
+            warn!(
+                "!!! Node requested PoW version {} but this miner implements {}. \
+                 The node may be syncing or running an outdated release.",
+                version, ours
+            );
+        }
+    }
+    false
+}
+
+/// Value of a kernel `bignum`, given its u32 limbs in least-significant-first order
+pub fn bignum_limbs_to_ubig(limbs: &[u32]) -> UBig {
+    limbs
+        .iter()
+        .rev()
+        .fold(UBig::from(0u8), |acc, &limb| (acc << 32) + UBig::from(limb))
+}
+
+/// Parse a kernel `[%bn p=(list u32)]` target into its value
+pub fn target_from_noun(target: Noun) -> Result<UBig, NockAppError> {
+    let mut limbs = Vec::new();
+    let mut list = target.as_cell()?.tail();
+    while let Ok(item) = list.as_cell() {
+        let limb = item.head().as_atom()?.as_u64()?;
+        limbs.push(u32::try_from(limb).map_err(|_| NockAppError::UnexpectedResult)?);
+        list = item.tail();
+    }
+    Ok(bignum_limbs_to_ubig(&limbs))
+}
+
+/// Loosened bound under which a hash counts as a near miss: `target * factor`
+pub fn near_miss_bound(target: &UBig, factor: u64) -> UBig {
+    target * UBig::from(factor)
+}
+
+/// Whether a `noun-digest` is at or under `bound`, the same comparison the kernel
+/// applies to the real target
+pub fn digest_within(digest: Noun, bound: &UBig) -> Result<bool, NockAppError> {
+    Ok(base_p_to_decimal(extract_5_tuple(digest)?)? <= *bound)
+}
+
+/// Approximate difficulty of `target` in leading zero bits: how many of a digest's
+/// [`DIGEST_BITS`] must be zero for it to pass [`digest_within`]. Each extra bit doubles
+/// the expected number of attempts per block.
+pub fn difficulty_bits(target: &UBig) -> u32 {
+    DIGEST_BITS.saturating_sub(target.bit_len() as u32)
+}
+
+/// [`difficulty_bits`] of a kernel `[%bn p=(list u32)]` target
+pub fn difficulty_from_noun(target: Noun) -> Result<u32, NockAppError> {
+    Ok(difficulty_bits(&target_from_noun(target)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use nockapp::noun::slab::NounSlab;
+    use nockvm::noun::{D, T};
+
+    use super::*;
+
+    #[test]
+    fn test_bignum_limbs_to_ubig() {
+        assert_eq!(bignum_limbs_to_ubig(&[]), UBig::from(0u8));
+        assert_eq!(
+            bignum_limbs_to_ubig(&[0xdead_beef, 0x1]),
+            UBig::from(0x1_dead_beefu64)
+        );
+    }
+
+    #[test]
+    fn test_check_pow_version() {
+        let mut slab: NounSlab = NounSlab::new();
+        assert!(check_pow_version(D(pow_algorithm_version() as u64)));
+        assert!(!check_pow_version(D(pow_algorithm_version() as u64 + 1)));
+        assert!(!check_pow_version(D(0)));
+        assert!(!check_pow_version(T(&mut slab, &[D(1), D(2)])));
+    }
+
+    #[test]
+    fn test_target_from_noun() {
+        let mut slab: NounSlab = NounSlab::new();
+        let target = T(&mut slab, &[D(0x6e62), D(0xdead_beef), D(0x1), D(0)]);
+        assert_eq!(
+            target_from_noun(target).expect("valid bignum"),
+            UBig::from(0x1_dead_beefu64)
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // ibig has a memory leak so miri fails this test
+    fn test_difficulty_bits() {
+        let max_digest = UBig::from(zkvm_jetpack::form::PRIME).pow(5) - UBig::from(1u8);
+        assert_eq!(max_digest.bit_len() as u32, DIGEST_BITS);
+        assert_eq!(difficulty_bits(&max_digest), 0);
+        assert_eq!(difficulty_bits(&(UBig::from(1u8) << 300)), 19);
+        assert_eq!(difficulty_bits(&UBig::from(0u8)), DIGEST_BITS);
+
+        // A target spanning every digest needs no leading zeros
+        let mut slab: NounSlab = NounSlab::new();
+        let limbs = [u32::MAX; 10];
+        let mut list = D(0);
+        for &limb in limbs.iter().rev() {
+            list = T(&mut slab, &[D(limb as u64), list]);
+        }
+        let target = T(&mut slab, &[D(0x6e62), list]);
+        assert_eq!(difficulty_from_noun(target).unwrap(), 0);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // ibig has a memory leak so miri fails this test
+    fn test_digest_within_near_miss_bound() {
+        let mut slab: NounSlab = NounSlab::new();
+        let target = UBig::from(1000u32);
+        let bound = near_miss_bound(&target, 4);
+
+        let hit = T(&mut slab, &[D(1000), D(0), D(0), D(0), D(0)]);
+        let near = T(&mut slab, &[D(4000), D(0), D(0), D(0), D(0)]);
+        let far = T(&mut slab, &[D(0), D(1), D(0), D(0), D(0)]);
+
+        assert!(digest_within(hit, &target).unwrap());
+        assert!(!digest_within(near, &target).unwrap());
+        assert!(digest_within(near, &bound).unwrap());
+        assert!(!digest_within(far, &bound).unwrap());
+    }
+}
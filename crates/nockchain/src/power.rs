@@ -0,0 +1,147 @@
+//! Package energy readings from the Linux powercap interface (RAPL).
+//!
+//! Intel exposes RAPL counters as `/sys/class/powercap/intel-rapl:N`, and kernels with
+//! AMD RAPL support expose them under the same names. [`EnergyMeter`] sums the package
+//! zones, skips their subzones (core, uncore, dram), which the package already covers,
+//! and accounts for each counter wrapping at its `max_energy_range_uj`. The counters
+//! cover the whole package, so the node and anything else on the box is included.
+//!
+//! Hosts without RAPL, and hosts where `energy_uj` is readable only by root, have no
+//! meter; callers should treat the error as "efficiency unknown".
+
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+const POWERCAP_ROOT: &str = "/sys/class/powercap";
+const PACKAGE_ZONE_PREFIX: &str = "intel-rapl:";
+
+#[derive(Debug)]
+struct Zone {
+    energy_path: PathBuf,
+    max_range_uj: u64,
+    last_uj: u64,
+}
+
+/// Cumulative energy drawn by every CPU package on the host
+#[derive(Debug)]
+pub struct EnergyMeter {
+    zones: Vec<Zone>,
+    total_uj: u64,
+}
+
+impl EnergyMeter {
+    /// Open the host's RAPL package counters
+    pub fn detect() -> io::Result<Self> {
+        Self::from_powercap(Path::new(POWERCAP_ROOT))
+    }
+
+    /// Open the RAPL package counters in a powercap tree rooted at `root`
+    pub fn from_powercap(root: &Path) -> io::Result<Self> {
+        let mut zones = Vec::new();
+        for entry in fs::read_dir(root)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            // Packages are `intel-rapl:N`; subzones are `intel-rapl:N:M`
+            let is_package = name
+                .to_str()
+                .and_then(|n| n.strip_prefix(PACKAGE_ZONE_PREFIX))
+                .is_some_and(|n| n.parse::<u32>().is_ok());
+            if !is_package {
+                continue;
+            }
+            let energy_path = entry.path().join("energy_uj");
+            zones.push(Zone {
+                last_uj: read_u64(&energy_path)?,
+                max_range_uj: read_u64(&entry.path().join("max_energy_range_uj"))?,
+                energy_path,
+            });
+        }
+        if zones.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no RAPL package zones under {}", root.display()),
+            ));
+        }
+        Ok(Self { zones, total_uj: 0 })
+    }
+
+    /// Number of packages being metered
+    pub fn packages(&self) -> usize {
+        self.zones.len()
+    }
+
+    /// Joules drawn since the meter was opened. Call it more often than the counters
+    /// wrap, which takes minutes even on large parts.
+    pub fn read_joules(&mut self) -> io::Result<f64> {
+        for zone in &mut self.zones {
+            let now = read_u64(&zone.energy_path)?;
+            let delta = if now >= zone.last_uj {
+                now - zone.last_uj
+            } else {
+                zone.max_range_uj.saturating_sub(zone.last_uj) + now
+            };
+            self.total_uj += delta;
+            zone.last_uj = now;
+        }
+        Ok(self.total_uj as f64 / 1e6)
+    }
+}
+
+/// Hashes per joule over an interval, or `None` if no energy was measured
+pub fn hashes_per_joule(hashes: u64, joules: f64) -> Option<f64> {
+    (joules > 0.0).then(|| hashes as f64 / joules)
+}
+
+fn read_u64(path: &Path) -> io::Result<u64> {
+    fs::read_to_string(path)?
+        .trim()
+        .parse::<u64>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_zone(root: &Path, name: &str, energy_uj: u64, max_range_uj: u64) {
+        let dir = root.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("energy_uj"), format!("{energy_uj}\n")).unwrap();
+        fs::write(dir.join("max_energy_range_uj"), format!("{max_range_uj}\n")).unwrap();
+    }
+
+    #[test]
+    fn test_energy_meter_sums_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        write_zone(dir.path(), "intel-rapl:0", 1_000_000, 10_000_000);
+        write_zone(dir.path(), "intel-rapl:1", 9_000_000, 10_000_000);
+        // Subzones and the MMIO mirror double count the packages and must be skipped
+        write_zone(dir.path(), "intel-rapl:0:0", 0, 10_000_000);
+        write_zone(dir.path(), "intel-rapl-mmio:0", 0, 10_000_000);
+
+        let mut meter = EnergyMeter::from_powercap(dir.path()).unwrap();
+        assert_eq!(meter.packages(), 2);
+        assert_eq!(meter.read_joules().unwrap(), 0.0);
+
+        write_zone(dir.path(), "intel-rapl:0", 3_000_000, 10_000_000);
+        // Package 1 wraps: 1 J to the top of its range, then 0.5 J past zero
+        write_zone(dir.path(), "intel-rapl:1", 500_000, 10_000_000);
+        write_zone(dir.path(), "intel-rapl:0:0", 5_000_000, 10_000_000);
+        assert_eq!(meter.read_joules().unwrap(), 3.5);
+    }
+
+    #[test]
+    fn test_energy_meter_without_rapl() {
+        let dir = tempfile::tempdir().unwrap();
+        write_zone(dir.path(), "intel-rapl-mmio:0", 0, 10_000_000);
+        let err = EnergyMeter::from_powercap(dir.path()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(EnergyMeter::from_powercap(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_hashes_per_joule() {
+        assert_eq!(hashes_per_joule(30, 120.0), Some(0.25));
+        assert_eq!(hashes_per_joule(30, 0.0), None);
+    }
+}
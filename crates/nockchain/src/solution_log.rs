@@ -0,0 +1,255 @@
+//! Out-of-band audit trail of found solutions.
+//!
+//! Each solution is written as one JSON line to a file or named pipe before it is
+//! submitted to the node, so submitted blocks can be reconciled against an independent
+//! record. Opening a named pipe blocks until a reader is attached. [`SolutionInfo`] carries
+//! the same solution with typed fields, for code that assembles blocks itself.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ibig::UBig;
+use nockapp::noun::slab::NounSlab;
+use nockapp::NockAppError;
+use nockchain_libp2p_io::tip5_util::{extract_5_tuple, tip5_hash_to_base58};
+use nockvm::noun::Noun;
+use serde::Serialize;
+use tracing::error;
+
+/// One found solution, as written to the log
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SolutionRecord {
+    /// Seconds since the Unix epoch when the solution was found
+    pub timestamp: u64,
+    pub thread: u64,
+    /// Block commitment in base58
+    pub header: String,
+    /// Nonce belts as 16-digit hex, in tuple order
+    pub nonce: String,
+    /// Target in decimal
+    pub target: String,
+    /// Proof hash in base58
+    pub hash: String,
+}
+
+impl SolutionRecord {
+    /// Describe a solution from the %mined poke the miner kernel returned (`[%command
+    /// %pow proof dig header nonce]`), the target it was mined against and its hash
+    pub fn from_mined_poke(
+        thread: u64,
+        poke: Noun,
+        target: Noun,
+        hash: Noun,
+    ) -> Result<Self, NockAppError> {
+        let (header, nonce) = mined_header_and_nonce(poke)?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+        Ok(Self {
+            timestamp,
+            thread,
+            header: tip5_hash_to_base58(header)?,
+            nonce: digest_hex(nonce)?,
+            target: crate::pow_target::target_from_noun(target)?.to_string(),
+            hash: tip5_hash_to_base58(hash)?,
+        })
+    }
+}
+
+/// One found solution with typed fields, e.g. for an external block builder
+#[derive(Clone)]
+pub struct SolutionInfo {
+    pub thread: u64,
+    /// Block commitment the nonce was mined against, as tip5 belts
+    pub header: [u64; 5],
+    pub nonce: [u64; 5],
+    /// Proof hash that met the target
+    pub hash: [u64; 5],
+    pub target: UBig,
+    /// The %mined poke (`[%command %pow proof dig header nonce]`) a node would accept
+    pub poke: NounSlab,
+}
+
+impl SolutionInfo {
+    /// Describe a solution from the %mined poke the miner kernel returned, the target it
+    /// was mined against and its hash
+    pub fn from_mined_poke(
+        thread: u64,
+        poke: NounSlab,
+        target: Noun,
+        hash: Noun,
+    ) -> Result<Self, NockAppError> {
+        let (header, nonce) = mined_header_and_nonce(unsafe { *poke.root() })?;
+        Ok(Self {
+            thread,
+            header: digest_belts(header)?,
+            nonce: digest_belts(nonce)?,
+            hash: digest_belts(hash)?,
+            target: crate::pow_target::target_from_noun(target)?,
+            poke,
+        })
+    }
+}
+
+/// The header and nonce at the end of a %mined poke
+fn mined_header_and_nonce(poke: Noun) -> Result<(Noun, Noun), NockAppError> {
+    let mut rest = poke;
+    for _ in 0..4 {
+        rest = rest.as_cell()?.tail();
+    }
+    let rest = rest.as_cell()?;
+    Ok((rest.head(), rest.tail()))
+}
+
+/// Append-only JSONL sink for [`SolutionRecord`]s
+pub struct SolutionLog {
+    file: Mutex<File>,
+}
+
+impl SolutionLog {
+    /// Open `path` for appending, creating it if it does not exist
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Write `record` as a single line and flush it
+    pub fn append(&self, record: &SolutionRecord) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let mut file = self
+            .file
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        file.write_all(&line)?;
+        file.flush()
+    }
+
+    /// Build and append the record for a found block. Failures are logged rather than
+    /// returned so they never hold up submitting the block.
+    pub fn record(&self, thread: u64, poke: Noun, target: Noun, hash: Noun) {
+        let result = SolutionRecord::from_mined_poke(thread, poke, target, hash)
+            .map_err(|e| e.to_string())
+            .and_then(|record| self.append(&record).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            error!("Could not record found solution from thread={thread}: {e}");
+        }
+    }
+}
+
+/// A tip5 digest's five belts as 16-digit hex each, concatenated in tuple order
+pub fn digest_hex(digest: Noun) -> Result<String, NockAppError> {
+    Ok(digest_belts(digest)?
+        .iter()
+        .map(|belt| format!("{:016x}", belt))
+        .collect())
+}
+
+/// A tip5 digest's five belts in tuple order
+pub fn digest_belts(digest: Noun) -> Result<[u64; 5], NockAppError> {
+    let mut belts = [0; 5];
+    for (belt, noun) in belts.iter_mut().zip(extract_5_tuple(digest)?) {
+        *belt = noun.as_atom()?.as_u64()?;
+    }
+    Ok(belts)
+}
+
+#[cfg(test)]
+mod tests {
+    use nockapp::noun::slab::NounSlab;
+    use nockvm::noun::{D, T};
+    use nockvm_macros::tas;
+
+    use super::*;
+
+    #[test]
+    fn test_digest_hex() {
+        let mut slab: NounSlab = NounSlab::new();
+        let digest = T(&mut slab, &[D(1), D(0xff), D(0), D(0x10), D(2)]);
+        assert_eq!(
+            digest_hex(digest).unwrap(),
+            "0000000000000001\
+             00000000000000ff\
+             0000000000000000\
+             0000000000000010\
+             0000000000000002"
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // ibig has a memory leak so miri fails this test
+    fn test_record_from_mined_poke() {
+        let mut slab: NounSlab = NounSlab::new();
+        let header = T(&mut slab, &[D(1), D(2), D(3), D(4), D(5)]);
+        let nonce = T(&mut slab, &[D(6), D(7), D(8), D(9), D(10)]);
+        let proof = T(&mut slab, &[D(0), D(0)]);
+        let poke = T(
+            &mut slab,
+            &[D(tas!(b"command")), D(tas!(b"pow")), proof, D(11), header, nonce],
+        );
+        let target = T(&mut slab, &[D(0x6e62), D(1000), D(0)]);
+        let hash = T(&mut slab, &[D(11), D(0), D(0), D(0), D(0)]);
+
+        let record = SolutionRecord::from_mined_poke(2, poke, target, hash).unwrap();
+        assert_eq!(record.thread, 2);
+        assert_eq!(record.header, tip5_hash_to_base58(header).unwrap());
+        assert!(record.nonce.starts_with("0000000000000006"));
+        assert_eq!(record.target, "1000");
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // ibig has a memory leak so miri fails this test
+    fn test_info_from_mined_poke() {
+        let mut poke_slab: NounSlab = NounSlab::new();
+        let header = T(&mut poke_slab, &[D(1), D(2), D(3), D(4), D(5)]);
+        let nonce = T(&mut poke_slab, &[D(6), D(7), D(8), D(9), D(10)]);
+        let proof = T(&mut poke_slab, &[D(0), D(0)]);
+        let poke = T(
+            &mut poke_slab,
+            &[D(tas!(b"command")), D(tas!(b"pow")), proof, D(11), header, nonce],
+        );
+        poke_slab.set_root(poke);
+        let mut slab: NounSlab = NounSlab::new();
+        let target = T(&mut slab, &[D(0x6e62), D(1000), D(0)]);
+        let hash = T(&mut slab, &[D(11), D(0), D(0), D(0), D(0)]);
+
+        let info = SolutionInfo::from_mined_poke(2, poke_slab.clone(), target, hash).unwrap();
+        assert_eq!(info.thread, 2);
+        assert_eq!(info.header, [1, 2, 3, 4, 5]);
+        assert_eq!(info.nonce, [6, 7, 8, 9, 10]);
+        assert_eq!(info.hash, [11, 0, 0, 0, 0]);
+        assert_eq!(info.target, UBig::from(1000u32));
+        assert_eq!(info.poke.jam(), poke_slab.jam());
+    }
+
+    #[test]
+    fn test_solution_log_appends_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("solutions.jsonl");
+        let record = SolutionRecord {
+            timestamp: 1_700_000_000,
+            thread: 3,
+            header: "header".to_string(),
+            nonce: "00".repeat(40),
+            target: "12345".to_string(),
+            hash: "hash".to_string(),
+        };
+
+        SolutionLog::open(&path).unwrap().append(&record).unwrap();
+        // Reopening appends rather than truncating
+        SolutionLog::open(&path).unwrap().append(&record).unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let json: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(json["thread"], 3);
+        assert_eq!(json["target"], "12345");
+    }
+}
@@ -0,0 +1,122 @@
+//! Found solutions per hour over a sliding window.
+//!
+//! A miner's solution counter only ever grows, so it cannot show that the miner has gone
+//! cold recently. [`SolutionRate`] keeps the times of the last
+//! [`SOLUTION_RATE_CAPACITY`] solutions and reports how many fell within its window,
+//! scaled to an hour, as a "luck" indicator for dashboards.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Solution times a [`SolutionRate`] keeps
+pub const SOLUTION_RATE_CAPACITY: usize = 64;
+
+/// Default window: long enough that a solo miner finding a few blocks a day doesn't read
+/// as cold between each of them
+pub const SOLUTION_RATE_WINDOW: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Times of recent solutions, for a windowed rate
+#[derive(Debug)]
+pub struct SolutionRate {
+    window: Duration,
+    times: Mutex<VecDeque<Instant>>,
+}
+
+impl Default for SolutionRate {
+    fn default() -> Self {
+        Self::new(SOLUTION_RATE_WINDOW)
+    }
+}
+
+impl SolutionRate {
+    /// A rate over the last `window`
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            times: Mutex::new(VecDeque::with_capacity(SOLUTION_RATE_CAPACITY)),
+        }
+    }
+
+    /// Record a solution found at `at`, forgetting the oldest one if the buffer is full
+    pub fn record(&self, at: Instant) {
+        let mut times = self
+            .times
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if times.len() == SOLUTION_RATE_CAPACITY {
+            times.pop_front();
+        }
+        times.push_back(at);
+    }
+
+    /// Solutions per hour over the window ending at `now`.
+    ///
+    /// If more solutions fell within the window than the buffer holds, the rate is taken
+    /// over the span of the ones it still holds instead, so it is never underestimated.
+    pub fn per_hour(&self, now: Instant) -> f64 {
+        let times = self
+            .times
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let recent: Vec<Duration> = times
+            .iter()
+            .map(|&at| now.saturating_duration_since(at))
+            .filter(|&age| age <= self.window)
+            .collect();
+        let span = match recent.first() {
+            Some(&oldest)
+                if times.len() == SOLUTION_RATE_CAPACITY && recent.len() == times.len() =>
+            {
+                oldest.max(Duration::from_secs(1))
+            }
+            _ => self.window,
+        };
+        if span.is_zero() {
+            return 0.0;
+        }
+        recent.len() as f64 * 3600.0 / span.as_secs_f64()
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solutions_outside_window_drop_out() {
+        let rate = SolutionRate::new(Duration::from_secs(2 * 60 * 60));
+        let start = Instant::now();
+        let hours = |h: u64| start + Duration::from_secs(h * 60 * 60);
+        assert_eq!(rate.per_hour(start), 0.0);
+
+        rate.record(start);
+        rate.record(hours(1));
+        rate.record(hours(2));
+        // All three within the last two hours
+        assert_eq!(rate.per_hour(hours(2)), 1.5);
+        // The first has aged out
+        assert_eq!(rate.per_hour(hours(3)), 1.0);
+        assert_eq!(rate.per_hour(hours(4)), 0.5);
+        // Gone cold
+        assert_eq!(rate.per_hour(hours(5)), 0.0);
+
+        assert_eq!(SolutionRate::new(Duration::ZERO).per_hour(start), 0.0);
+    }
+
+    #[test]
+    fn test_full_buffer_measures_its_own_span() {
+        let rate = SolutionRate::new(Duration::from_secs(60 * 60));
+        let start = Instant::now();
+        // One solution a second for longer than the buffer holds
+        for second in 0..SOLUTION_RATE_CAPACITY as u64 * 2 {
+            rate.record(start + Duration::from_secs(second));
+        }
+        let now = start + Duration::from_secs(SOLUTION_RATE_CAPACITY as u64 * 2);
+        assert_eq!(rate.per_hour(now), 3600.0);
+    }
+}
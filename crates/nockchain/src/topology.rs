@@ -0,0 +1,1179 @@
+//! CPU and NUMA topology detection for the miners.
+//!
+//! Topology is read from sysfs. Every reader takes the sysfs root as a parameter so
+//! tests can point it at a synthetic tree instead of the host's `/sys`.
+//! [`bind_process_to_numa_node`] confines a whole process to one NUMA domain, for running
+//! one miner process per node; [`interleave_memory`] instead spreads its pages over
+//! several, optionally weighted towards nodes with more or faster memory.
+//! [`probe_pinning`] checks that pinning a thread actually keeps it on its CPU, which some
+//! hypervisors accept without honouring.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use std::{fmt, fs, io};
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+/// Default sysfs mount point
+pub const SYSFS_ROOT: &str = "/sys";
+
+/// A logical CPU number as used by `sched_setaffinity` and sysfs
+pub type CpuId = usize;
+
+/// A single logical CPU as seen by the kernel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CpuInfo {
+    pub cpu: usize,
+    pub package: usize,
+    pub core: usize,
+    pub numa_node: usize,
+}
+
+/// A NUMA domain and the logical CPUs local to it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NumaNode {
+    pub id: usize,
+    pub cpus: Vec<usize>,
+}
+
+/// One cache level as reported for cpu0
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CacheInfo {
+    pub level: u8,
+    pub kind: String,
+    pub size_bytes: usize,
+    /// Number of logical CPUs sharing one instance of this cache
+    pub shared_cpus: usize,
+}
+
+/// AMD EPYC "NUMA nodes per socket" BIOS setting, inferred from the domain count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NpsMode {
+    /// One NUMA domain spans every socket
+    Nps0,
+    Nps1,
+    Nps2,
+    Nps4,
+    /// Any other split, e.g. from a non-EPYC machine
+    Other(usize),
+}
+
+impl fmt::Display for NpsMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NpsMode::Nps0 => write!(f, "NPS0"),
+            NpsMode::Nps1 => write!(f, "NPS1"),
+            NpsMode::Nps2 => write!(f, "NPS2"),
+            NpsMode::Nps4 => write!(f, "NPS4"),
+            NpsMode::Other(n) => write!(f, "{} nodes per socket", n),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Topology {
+    pub cpus: Vec<CpuInfo>,
+    pub numa_nodes: Vec<NumaNode>,
+    pub caches: Vec<CacheInfo>,
+}
+
+impl Topology {
+    /// Detect the host topology from `/sys`
+    pub fn detect() -> io::Result<Self> {
+        Self::from_sysfs(Path::new(SYSFS_ROOT))
+    }
+
+    /// Read the topology from a sysfs tree rooted at `root`
+    pub fn from_sysfs(root: &Path) -> io::Result<Self> {
+        let cpu_dir = root.join("devices/system/cpu");
+        let node_dir = root.join("devices/system/node");
+
+        let online = parse_cpu_list(read_trimmed(&cpu_dir.join("online"))?.as_str())?;
+
+        // Machines without NUMA support have no node directory; treat them as one node.
+        let mut numa_nodes = Vec::new();
+        if node_dir.is_dir() {
+            for entry in fs::read_dir(&node_dir)? {
+                let entry = entry?;
+                let name = entry.file_name();
+                let Some(id) = name
+                    .to_str()
+                    .and_then(|n| n.strip_prefix("node"))
+                    .and_then(|n| n.parse::<usize>().ok())
+                else {
+                    continue;
+                };
+                let cpus = parse_cpu_list(&read_trimmed(&entry.path().join("cpulist"))?)?;
+                numa_nodes.push(NumaNode { id, cpus });
+            }
+        }
+        if numa_nodes.is_empty() {
+            numa_nodes.push(NumaNode {
+                id: 0,
+                cpus: online.clone(),
+            });
+        }
+        numa_nodes.sort_by_key(|node| node.id);
+
+        let mut cpus = Vec::with_capacity(online.len());
+        for &cpu in &online {
+            let topo = cpu_dir.join(format!("cpu{}/topology", cpu));
+            let package = read_usize(&topo.join("physical_package_id")).unwrap_or(0);
+            let core = read_usize(&topo.join("core_id")).unwrap_or(cpu);
+            let numa_node = numa_nodes
+                .iter()
+                .find(|node| node.cpus.contains(&cpu))
+                .map(|node| node.id)
+                .unwrap_or(0);
+            cpus.push(CpuInfo {
+                cpu,
+                package,
+                core,
+                numa_node,
+            });
+        }
+
+        let caches = match online.first() {
+            Some(&cpu) => read_caches(&cpu_dir.join(format!("cpu{}/cache", cpu)))?,
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            cpus,
+            numa_nodes,
+            caches,
+        })
+    }
+
+    /// In-memory layout for tests and CI: `nodes` NUMA domains of `cores_per_node` cores
+    /// with two SMT threads each, spread evenly over `sockets` packages.
+    ///
+    /// CPUs are numbered the way Linux does on EPYC: every core's first thread, then
+    /// every sibling, so node `n` owns `n*c..(n+1)*c` plus the same range offset by the
+    /// core count.
+    pub fn synthetic(sockets: usize, nodes: usize, cores_per_node: usize) -> Self {
+        let cores = nodes * cores_per_node;
+        let nodes_per_socket = (nodes / sockets.max(1)).max(1);
+        let cpus: Vec<CpuInfo> = (0..cores * 2)
+            .map(|cpu| {
+                let core = cpu % cores;
+                let numa_node = core / cores_per_node;
+                CpuInfo {
+                    cpu,
+                    package: numa_node / nodes_per_socket,
+                    core,
+                    numa_node,
+                }
+            })
+            .collect();
+        let numa_nodes = (0..nodes)
+            .map(|id| NumaNode {
+                id,
+                cpus: cpus
+                    .iter()
+                    .filter(|cpu| cpu.numa_node == id)
+                    .map(|cpu| cpu.cpu)
+                    .collect(),
+            })
+            .collect();
+        Self {
+            cpus,
+            numa_nodes,
+            caches: Vec::new(),
+        }
+    }
+
+    pub fn logical_cpus(&self) -> usize {
+        self.cpus.len()
+    }
+
+    pub fn physical_cores(&self) -> usize {
+        self.cpus
+            .iter()
+            .map(|cpu| (cpu.package, cpu.core))
+            .collect::<BTreeSet<_>>()
+            .len()
+    }
+
+    pub fn sockets(&self) -> usize {
+        self.cpus
+            .iter()
+            .map(|cpu| cpu.package)
+            .collect::<BTreeSet<_>>()
+            .len()
+    }
+
+    pub fn smt_threads_per_core(&self) -> usize {
+        let cores = self.physical_cores();
+        if cores == 0 {
+            1
+        } else {
+            self.logical_cpus() / cores
+        }
+    }
+
+    /// NUMA domains per socket, or 0 when one domain spans several sockets (NPS0).
+    ///
+    /// Memory-only nodes carry no CPUs and are not counted.
+    pub fn nodes_per_socket(&self) -> usize {
+        let domains = self
+            .numa_nodes
+            .iter()
+            .filter(|node| !node.cpus.is_empty())
+            .count();
+        domains / self.sockets().max(1)
+    }
+
+    pub fn nps_mode(&self) -> NpsMode {
+        match self.nodes_per_socket() {
+            0 => NpsMode::Nps0,
+            1 => NpsMode::Nps1,
+            2 => NpsMode::Nps2,
+            4 => NpsMode::Nps4,
+            n => NpsMode::Other(n),
+        }
+    }
+
+    /// NUMA domains local to `package`, each narrowed to that package's CPUs.
+    ///
+    /// Under NPS0 the single domain is split per socket so workers still stay on one die.
+    pub fn socket_domains(&self, package: usize) -> Vec<NumaNode> {
+        self.numa_nodes
+            .iter()
+            .map(|node| NumaNode {
+                id: node.id,
+                cpus: self
+                    .cpus
+                    .iter()
+                    .filter(|cpu| cpu.numa_node == node.id && cpu.package == package)
+                    .map(|cpu| cpu.cpu)
+                    .collect(),
+            })
+            .filter(|node| !node.cpus.is_empty())
+            .collect()
+    }
+
+    /// Every (domain, socket) pair that has CPUs, in socket order
+    pub fn cpu_domains(&self) -> Vec<NumaNode> {
+        self.cpus
+            .iter()
+            .map(|cpu| cpu.package)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .flat_map(|package| self.socket_domains(package))
+            .collect()
+    }
+
+    /// Logical CPU for mining worker `worker`, dealing workers round-robin over the
+    /// NUMA domains so each worker's memory is local whatever the NPS setting.
+    /// Same as `assign_workers(self, n)[worker]` for any `n > worker`.
+    pub fn cpu_for_worker(&self, worker: usize) -> usize {
+        cpu_in_domains(&self.cpu_domains(), worker)
+            .map(|(cpu, _)| cpu)
+            .unwrap_or(worker)
+    }
+
+    /// This topology less the `reserved` CPUs, so that no worker is assigned to them,
+    /// e.g. to leave the cores servicing a NIC's interrupts to the network stack.
+    /// NUMA nodes keep their ids even if every one of their CPUs is reserved.
+    pub fn without_cpus(&self, reserved: &[CpuId]) -> Topology {
+        let kept = |cpu: &usize| !reserved.contains(cpu);
+        Topology {
+            cpus: self
+                .cpus
+                .iter()
+                .filter(|info| kept(&info.cpu))
+                .cloned()
+                .collect(),
+            numa_nodes: self
+                .numa_nodes
+                .iter()
+                .map(|node| NumaNode {
+                    id: node.id,
+                    cpus: node.cpus.iter().copied().filter(kept).collect(),
+                })
+                .collect(),
+            caches: self.caches.clone(),
+        }
+    }
+
+    /// CPUs of NUMA node `node`; an error if the node doesn't exist or has no CPUs
+    pub fn node_cpus(&self, node: usize) -> io::Result<Vec<CpuId>> {
+        match self
+            .numa_nodes
+            .iter()
+            .find(|candidate| candidate.id == node)
+        {
+            Some(found) if !found.cpus.is_empty() => Ok(found.cpus.clone()),
+            Some(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("NUMA node {} has no CPUs", node),
+            )),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "no NUMA node {}; this host has {:?}",
+                    node,
+                    self.numa_nodes.iter().map(|n| n.id).collect::<Vec<_>>()
+                ),
+            )),
+        }
+    }
+
+    /// Machine-readable summary of the topology and the chosen worker placement
+    pub fn report(&self, thread_assignment: &[usize]) -> TopologyReport {
+        TopologyReport {
+            logical_cpus: self.logical_cpus(),
+            physical_cores: self.physical_cores(),
+            sockets: self.sockets(),
+            smt_threads_per_core: self.smt_threads_per_core(),
+            numa_nodes: self.numa_nodes.clone(),
+            caches: self.caches.clone(),
+            thread_assignment: thread_assignment.to_vec(),
+            pinning_effective: None,
+        }
+    }
+}
+
+/// Topology summary emitted once at boot for fleet provisioning checks
+#[derive(Debug, Clone, Serialize)]
+pub struct TopologyReport {
+    pub logical_cpus: usize,
+    pub physical_cores: usize,
+    pub sockets: usize,
+    pub smt_threads_per_core: usize,
+    pub numa_nodes: Vec<NumaNode>,
+    pub caches: Vec<CacheInfo>,
+    /// Logical CPU assigned to each mining worker, indexed by worker id
+    pub thread_assignment: Vec<usize>,
+    /// Whether threads pinned to the assigned CPUs stayed there; `None` if not probed
+    pub pinning_effective: Option<bool>,
+}
+
+impl TopologyReport {
+    /// Include the outcome of [`probe_pinning`], if it ran
+    pub fn with_pinning(mut self, probe: Option<&PinningProbe>) -> Self {
+        self.pinning_effective = probe.map(PinningProbe::effective);
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Topology report is always serializable")
+    }
+
+    /// Write the report as JSON to `path`, replacing any previous report
+    pub fn write_to(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_json())?;
+        info!("Wrote topology report to {}", path.display());
+        Ok(())
+    }
+}
+
+/// Detect the host topology and write its report to `path`
+pub fn export_topology_report(
+    path: &Path,
+    thread_assignment: &[usize],
+    pinning: Option<&PinningProbe>,
+) -> io::Result<()> {
+    Topology::detect()?
+        .report(thread_assignment)
+        .with_pinning(pinning)
+        .write_to(path)
+}
+
+/// Balanced, deterministic placement of `n_workers` mining workers, indexed by worker id.
+///
+/// Workers are dealt round-robin over [`Topology::cpu_domains`], so the worker counts of
+/// any two NUMA domains differ by at most one, and each domain is filled in cpulist order
+/// before any of its CPUs is reused.
+pub fn assign_workers(topology: &Topology, n_workers: usize) -> Vec<CpuId> {
+    assign_workers_strided(topology, n_workers, 1)
+}
+
+/// [`assign_workers`], but walking each domain's CPUs `stride` apart as in
+/// [`strided_index`]. Per-domain counts are the same; only the CPUs within a domain change.
+pub fn assign_workers_strided(topology: &Topology, n_workers: usize, stride: usize) -> Vec<CpuId> {
+    let domains = topology.cpu_domains();
+    (0..n_workers)
+        .map(|worker| {
+            if domains.is_empty() {
+                return worker;
+            }
+            let domain = &domains[worker % domains.len()];
+            let slot = worker / domains.len();
+            domain
+                .cpus
+                .get(strided_index(slot, domain.cpus.len(), stride))
+                .copied()
+                .unwrap_or(worker)
+        })
+        .collect()
+}
+
+/// Pick the `slot`th CPU from `domains`, dealing slots round-robin across domains and
+/// filling each domain in cpulist order. Returns the CPU and its NUMA node.
+pub fn cpu_in_domains(domains: &[NumaNode], slot: usize) -> Option<(usize, usize)> {
+    if domains.is_empty() {
+        return None;
+    }
+    let domain = &domains[slot % domains.len()];
+    let index = (slot / domains.len()) % domain.cpus.len().max(1);
+    domain.cpus.get(index).map(|&cpu| (cpu, domain.id))
+}
+
+/// Index of the `slot`th pick when walking `len` items `stride` apart: 0, s, 2s, ...,
+/// then 1, 1+s, ..., so every item is picked once per `len` slots. A stride of the CCX
+/// size spreads consecutive workers over CCXs instead of packing them into one.
+pub fn strided_index(slot: usize, len: usize, stride: usize) -> usize {
+    if len == 0 {
+        return 0;
+    }
+    let stride = stride.clamp(1, len);
+    let mut slot = slot % len;
+    for offset in 0..stride {
+        let count = (len - offset).div_ceil(stride);
+        if slot < count {
+            return offset + slot * stride;
+        }
+        slot -= count;
+    }
+    unreachable!("strided passes cover all {} items", len)
+}
+
+/// Host topology, detected once and shared by the miners. `None` if sysfs is unreadable.
+pub fn host_topology() -> Option<&'static Topology> {
+    static HOST: OnceLock<Option<Topology>> = OnceLock::new();
+    HOST.get_or_init(|| match Topology::detect() {
+        Ok(topology) => {
+            info!(
+                "Detected {} NUMA domains on {} sockets ({})",
+                topology.numa_nodes.len(),
+                topology.sockets(),
+                topology.nps_mode()
+            );
+            Some(topology)
+        }
+        Err(e) => {
+            warn!("Could not read CPU topology from sysfs: {}", e);
+            None
+        }
+    })
+    .as_ref()
+}
+
+/// Confine this process to NUMA node `node`: it may only run on that node's CPUs and only
+/// allocate that node's memory. Returns the CPUs it is now limited to.
+///
+/// Both settings apply to the calling thread and are inherited by threads it spawns
+/// afterwards, so call this before starting the async runtime or any miner. Memory is
+/// bound strictly (`MPOL_BIND`), so the process hits OOM rather than spilling onto
+/// another node when this one is full.
+pub fn bind_process_to_numa_node(topology: &Topology, node: usize) -> io::Result<Vec<CpuId>> {
+    let cpus = topology.node_cpus(node)?;
+    set_affinity(&cpus)?;
+    bind_memory(node)?;
+    info!(
+        "Bound to NUMA node {} with {} CPUs: {:?}",
+        node,
+        cpus.len(),
+        cpus
+    );
+    Ok(cpus)
+}
+
+#[cfg(target_os = "linux")]
+fn set_affinity(cpus: &[CpuId]) -> io::Result<()> {
+    // SAFETY: cpu_set_t is plain data, and every CPU is checked against its size
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        for &cpu in cpus {
+            if cpu >= libc::CPU_SETSIZE as usize {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("CPU {} is beyond CPU_SETSIZE", cpu),
+                ));
+            }
+            libc::CPU_SET(cpu, &mut set);
+        }
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn bind_memory(node: usize) -> io::Result<()> {
+    set_mempolicy(libc::MPOL_BIND, &[node])
+}
+
+#[cfg(target_os = "linux")]
+fn set_mempolicy(mode: libc::c_int, nodes: &[usize]) -> io::Result<()> {
+    const BITS: usize = libc::c_ulong::BITS as usize;
+    let highest = nodes.iter().copied().max().unwrap_or(0);
+    let mut mask = vec![0 as libc::c_ulong; highest / BITS + 1];
+    for &node in nodes {
+        mask[node / BITS] |= 1 << (node % BITS);
+    }
+    // The kernel reads one bit fewer than `maxnode`
+    let max_node = mask.len() * BITS + 1;
+    // SAFETY: `mask` holds `max_node - 1` bits and outlives the call
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_set_mempolicy,
+            mode,
+            mask.as_ptr(),
+            max_node as libc::c_ulong,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_affinity(_cpus: &[CpuId]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "NUMA binding needs Linux",
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn bind_memory(_node: usize) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "NUMA binding needs Linux",
+    ))
+}
+
+/// `MPOL_WEIGHTED_INTERLEAVE` from `linux/mempolicy.h`, new in Linux 6.9 and not yet in libc
+#[cfg(target_os = "linux")]
+const MPOL_WEIGHTED_INTERLEAVE: libc::c_int = 6;
+
+/// How [`interleave_memory`] spreads the process's pages over NUMA nodes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterleavePolicy {
+    /// Evenly across these nodes (`MPOL_INTERLEAVE`)
+    Uniform(Vec<usize>),
+    /// In proportion to these `(node, weight)` pairs (`MPOL_WEIGHTED_INTERLEAVE`)
+    Weighted(Vec<(usize, u8)>),
+}
+
+impl InterleavePolicy {
+    /// The policy for `nodes` given `weights`, indexed by node id
+    ///
+    /// No weights, or equal weights, mean [`InterleavePolicy::Uniform`] over every node.
+    /// Nodes weighted 0 get no pages. Fails if a node has no weight or none is positive.
+    pub fn for_nodes(nodes: &[usize], weights: &[u8]) -> io::Result<Self> {
+        if weights.is_empty() {
+            return Ok(InterleavePolicy::Uniform(nodes.to_vec()));
+        }
+        let mut weighted = Vec::with_capacity(nodes.len());
+        for &node in nodes {
+            match weights.get(node) {
+                Some(0) => {}
+                Some(&weight) => weighted.push((node, weight)),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("no interleave weight for NUMA node {}", node),
+                    ))
+                }
+            }
+        }
+        if weighted.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "every NUMA node has interleave weight 0",
+            ));
+        }
+        if weighted.iter().all(|&(_, weight)| weight == weighted[0].1) {
+            return Ok(InterleavePolicy::Uniform(
+                weighted.into_iter().map(|(node, _)| node).collect(),
+            ));
+        }
+        Ok(InterleavePolicy::Weighted(weighted))
+    }
+
+    /// The nodes that get pages
+    pub fn nodes(&self) -> Vec<usize> {
+        match self {
+            InterleavePolicy::Uniform(nodes) => nodes.clone(),
+            InterleavePolicy::Weighted(weights) => weights.iter().map(|&(node, _)| node).collect(),
+        }
+    }
+}
+
+impl fmt::Display for InterleavePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterleavePolicy::Uniform(nodes) => write!(f, "interleave across nodes {:?}", nodes),
+            InterleavePolicy::Weighted(weights) => {
+                write!(f, "weighted interleave (")?;
+                for (i, (node, weight)) in weights.iter().enumerate() {
+                    let sep = if i == 0 { "" } else { ", " };
+                    write!(f, "{}node {}: {}", sep, node, weight)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Spread this process's future allocations over `nodes`, weighted by `weights` (indexed
+/// by node id; see [`InterleavePolicy::for_nodes`]). Returns the policy put in place.
+///
+/// Like [`bind_process_to_numa_node`] this applies to the calling thread and the threads
+/// it spawns afterwards. The kernel keeps weighted-interleave weights system-wide in
+/// sysfs, so setting them needs root and changes them for every process using the
+/// policy. Where that fails, or the kernel predates Linux 6.9, this falls back to plain
+/// interleave across the same nodes.
+pub fn interleave_memory(nodes: &[usize], weights: &[u8]) -> io::Result<InterleavePolicy> {
+    let policy = InterleavePolicy::for_nodes(nodes, weights)?;
+    let applied = match &policy {
+        InterleavePolicy::Uniform(nodes) => interleave(nodes, false).map(|()| policy.clone()),
+        InterleavePolicy::Weighted(pairs) => {
+            match write_interleave_weights(Path::new(SYSFS_ROOT), pairs)
+                .and_then(|()| interleave(&policy.nodes(), true))
+            {
+                Ok(()) => Ok(policy.clone()),
+                Err(e) => {
+                    warn!(
+                        "Weighted interleave unavailable ({}), interleaving evenly instead",
+                        e
+                    );
+                    let nodes = policy.nodes();
+                    interleave(&nodes, false).map(|()| InterleavePolicy::Uniform(nodes))
+                }
+            }
+        }
+    }?;
+    info!("NUMA memory policy: {}", applied);
+    Ok(applied)
+}
+
+/// Set the system-wide weighted-interleave weight of each node
+fn write_interleave_weights(root: &Path, weights: &[(usize, u8)]) -> io::Result<()> {
+    let dir = root.join("kernel/mm/mempolicy/weighted_interleave");
+    for &(node, weight) in weights {
+        fs::write(dir.join(format!("node{}", node)), weight.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn interleave(nodes: &[usize], weighted: bool) -> io::Result<()> {
+    let mode = if weighted {
+        MPOL_WEIGHTED_INTERLEAVE
+    } else {
+        libc::MPOL_INTERLEAVE
+    };
+    set_mempolicy(mode, nodes)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn interleave(_nodes: &[usize], _weighted: bool) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "NUMA interleaving needs Linux",
+    ))
+}
+
+/// How long each probe thread watches which CPU it runs on
+const PINNING_PROBE_TIME: Duration = Duration::from_millis(50);
+
+/// Whether pinning held on each CPU [`probe_pinning`] tried
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PinningProbe {
+    /// CPUs whose probe thread never ran anywhere else
+    pub held: Vec<CpuId>,
+    /// CPUs whose probe thread was also seen on the listed CPUs despite being pinned
+    pub escaped: Vec<(CpuId, Vec<CpuId>)>,
+    /// CPUs the probe thread could not be pinned to at all
+    pub unpinnable: Vec<CpuId>,
+}
+
+impl PinningProbe {
+    /// Every probed CPU kept its thread
+    pub fn effective(&self) -> bool {
+        self.escaped.is_empty() && self.unpinnable.is_empty()
+    }
+}
+
+impl fmt::Display for PinningProbe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.effective() {
+            return write!(f, "pinning held on all {} CPUs", self.held.len());
+        }
+        write!(
+            f,
+            "pinning held on {} of {} CPUs",
+            self.held.len(),
+            self.held.len() + self.escaped.len() + self.unpinnable.len()
+        )?;
+        for (cpu, seen) in &self.escaped {
+            write!(f, "; CPU {}'s thread also ran on {:?}", cpu, seen)?;
+        }
+        if !self.unpinnable.is_empty() {
+            write!(f, "; could not pin to {:?}", self.unpinnable)?;
+        }
+        Ok(())
+    }
+}
+
+/// Pin a probe thread to each of `cpus`, all at once, and watch with `sched_getcpu`
+/// whether it stays there. On some VMs `sched_setaffinity` succeeds but is not honoured,
+/// so this is the only way to tell real pinning from a no-op. Logs a warning when any
+/// thread strayed or could not be pinned.
+///
+/// The probe spins every thread for a fraction of a second, so run it at startup, before
+/// the miners take the CPUs.
+pub fn probe_pinning(cpus: &[CpuId]) -> PinningProbe {
+    let seen: Vec<io::Result<BTreeSet<CpuId>>> = std::thread::scope(|scope| {
+        let probes: Vec<_> = cpus
+            .iter()
+            .map(|&cpu| scope.spawn(move || probe_cpu(cpu)))
+            .collect();
+        probes
+            .into_iter()
+            .map(|probe| probe.join().expect("pinning probe panicked"))
+            .collect()
+    });
+    let mut probe = PinningProbe::default();
+    for (&cpu, seen) in cpus.iter().zip(seen) {
+        match seen {
+            Ok(seen) if seen.iter().all(|&other| other == cpu) => probe.held.push(cpu),
+            Ok(seen) => probe.escaped.push((cpu, seen.into_iter().collect())),
+            Err(_) => probe.unpinnable.push(cpu),
+        }
+    }
+    if probe.effective() {
+        info!("Thread pinning verified: {}", probe);
+    } else {
+        warn!(
+            "Thread pinning is not effective, workers may not stay on their CPUs: {}",
+            probe
+        );
+    }
+    probe
+}
+
+/// Pin the calling thread to `cpu` and return every CPU it was seen on afterwards
+fn probe_cpu(cpu: CpuId) -> io::Result<BTreeSet<CpuId>> {
+    set_affinity(&[cpu])?;
+    let mut seen = BTreeSet::new();
+    let started = Instant::now();
+    while started.elapsed() < PINNING_PROBE_TIME {
+        for _ in 0..64 {
+            seen.insert(current_cpu()?);
+            std::hint::spin_loop();
+        }
+        // Give the scheduler a chance to move the thread if it is going to
+        std::thread::yield_now();
+    }
+    Ok(seen)
+}
+
+#[cfg(target_os = "linux")]
+fn current_cpu() -> io::Result<CpuId> {
+    // SAFETY: sched_getcpu has no preconditions
+    let cpu = unsafe { libc::sched_getcpu() };
+    CpuId::try_from(cpu).map_err(|_| io::Error::last_os_error())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn current_cpu() -> io::Result<CpuId> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "sched_getcpu needs Linux",
+    ))
+}
+
+/// Parse a kernel cpu list such as `0-3,8,10-11`
+pub fn parse_cpu_list(list: &str) -> io::Result<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for part in list.trim().split(',').filter(|part| !part.is_empty()) {
+        let parse = |s: &str| {
+            s.trim()
+                .parse::<usize>()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        };
+        match part.split_once('-') {
+            Some((start, end)) => cpus.extend(parse(start)?..=parse(end)?),
+            None => cpus.push(parse(part)?),
+        }
+    }
+    Ok(cpus)
+}
+
+fn read_caches(cache_dir: &Path) -> io::Result<Vec<CacheInfo>> {
+    let mut caches = Vec::new();
+    if !cache_dir.is_dir() {
+        return Ok(caches);
+    }
+    for entry in fs::read_dir(cache_dir)? {
+        let path = entry?.path();
+        let is_index = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with("index"));
+        if !is_index {
+            continue;
+        }
+        let level = read_usize(&path.join("level"))? as u8;
+        let kind = read_trimmed(&path.join("type"))?;
+        let size_bytes = parse_cache_size(&read_trimmed(&path.join("size"))?)?;
+        let shared_cpus = read_trimmed(&path.join("shared_cpu_list"))
+            .and_then(|list| parse_cpu_list(&list))
+            .map(|cpus| cpus.len())
+            .unwrap_or(1);
+        caches.push(CacheInfo {
+            level,
+            kind,
+            size_bytes,
+            shared_cpus,
+        });
+    }
+    caches.sort_by(|a, b| (a.level, &a.kind).cmp(&(b.level, &b.kind)));
+    Ok(caches)
+}
+
+/// Parse a sysfs cache size such as `32K` or `32768K`
+fn parse_cache_size(size: &str) -> io::Result<usize> {
+    let (digits, multiplier) = match size.chars().last() {
+        Some('K') => (&size[..size.len() - 1], 1 << 10),
+        Some('M') => (&size[..size.len() - 1], 1 << 20),
+        Some('G') => (&size[..size.len() - 1], 1 << 30),
+        _ => (size, 1),
+    };
+    digits
+        .parse::<usize>()
+        .map(|n| n * multiplier)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn read_trimmed(path: &Path) -> io::Result<String> {
+    Ok(fs::read_to_string(path)?.trim().to_string())
+}
+
+fn read_usize(path: &Path) -> io::Result<usize> {
+    read_trimmed(path)?
+        .parse::<usize>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Build a synthetic sysfs tree: `nodes` NUMA nodes with `cores_per_node` physical
+    /// cores each, two SMT threads per core, spread over `sockets` packages.
+    pub(crate) fn fake_sysfs(
+        sockets: usize,
+        nodes: usize,
+        cores_per_node: usize,
+    ) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cpu_dir = dir.path().join("devices/system/cpu");
+        let node_dir = dir.path().join("devices/system/node");
+        let cores = nodes * cores_per_node;
+        let logical = cores * 2;
+        fs::create_dir_all(&cpu_dir).unwrap();
+        fs::write(cpu_dir.join("online"), format!("0-{}\n", logical - 1)).unwrap();
+
+        let nodes_per_socket = nodes / sockets;
+        for node in 0..nodes {
+            let first = node * cores_per_node;
+            let last = first + cores_per_node - 1;
+            let path = node_dir.join(format!("node{}", node));
+            fs::create_dir_all(&path).unwrap();
+            fs::write(
+                path.join("cpulist"),
+                format!("{}-{},{}-{}\n", first, last, first + cores, last + cores),
+            )
+            .unwrap();
+        }
+        for cpu in 0..logical {
+            let core = cpu % cores;
+            let package = core / cores_per_node / nodes_per_socket;
+            let topo = cpu_dir.join(format!("cpu{}/topology", cpu));
+            fs::create_dir_all(&topo).unwrap();
+            fs::write(topo.join("physical_package_id"), format!("{}\n", package)).unwrap();
+            fs::write(topo.join("core_id"), format!("{}\n", core)).unwrap();
+        }
+        let cache = cpu_dir.join("cpu0/cache");
+        for (idx, (level, kind, size, shared)) in [
+            (1, "Data", "32K", "0,16"),
+            (2, "Unified", "1024K", "0,16"),
+            (3, "Unified", "32768K", "0-7,16-23"),
+        ]
+        .iter()
+        .enumerate()
+        {
+            let path = cache.join(format!("index{}", idx));
+            fs::create_dir_all(&path).unwrap();
+            fs::write(path.join("level"), format!("{}\n", level)).unwrap();
+            fs::write(path.join("type"), format!("{}\n", kind)).unwrap();
+            fs::write(path.join("size"), format!("{}\n", size)).unwrap();
+            fs::write(path.join("shared_cpu_list"), format!("{}\n", shared)).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(
+            parse_cpu_list("0-3,8,10-11\n").unwrap(),
+            vec![0, 1, 2, 3, 8, 10, 11]
+        );
+        assert_eq!(parse_cpu_list("").unwrap(), Vec::<usize>::new());
+        assert!(parse_cpu_list("0-x").is_err());
+    }
+
+    #[test]
+    fn test_from_sysfs() {
+        let sysfs = fake_sysfs(1, 2, 4);
+        let topology = Topology::from_sysfs(sysfs.path()).unwrap();
+        assert_eq!(topology.logical_cpus(), 16);
+        assert_eq!(topology.physical_cores(), 8);
+        assert_eq!(topology.sockets(), 1);
+        assert_eq!(topology.smt_threads_per_core(), 2);
+        assert_eq!(topology.numa_nodes.len(), 2);
+        assert_eq!(
+            topology.numa_nodes[1].cpus,
+            vec![4, 5, 6, 7, 12, 13, 14, 15]
+        );
+        assert_eq!(topology.cpus[13].numa_node, 1);
+        assert_eq!(topology.caches.len(), 3);
+        assert_eq!(topology.caches[2].size_bytes, 32 << 20);
+        assert_eq!(topology.caches[2].shared_cpus, 16);
+    }
+
+    #[test]
+    fn test_nps4_affinity_per_domain() {
+        // One socket split into four NUMA domains of four cores each
+        let sysfs = fake_sysfs(1, 4, 4);
+        let topology = Topology::from_sysfs(sysfs.path()).unwrap();
+        assert_eq!(topology.sockets(), 1);
+        assert_eq!(topology.nodes_per_socket(), 4);
+        assert_eq!(topology.nps_mode(), NpsMode::Nps4);
+        assert_eq!(topology.socket_domains(0).len(), 4);
+
+        let assignment: Vec<usize> = (0..8).map(|w| topology.cpu_for_worker(w)).collect();
+        assert_eq!(assignment, vec![0, 4, 8, 12, 1, 5, 9, 13]);
+        for (worker, &cpu) in assignment.iter().enumerate() {
+            assert_eq!(topology.cpus[cpu].numa_node, worker % 4);
+        }
+
+        // Every logical CPU is used exactly once before any repeats
+        let mut all: Vec<usize> = (0..32).map(|w| topology.cpu_for_worker(w)).collect();
+        all.sort_unstable();
+        assert_eq!(all, (0..32).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_nps_mode_dual_socket() {
+        let topology = Topology::from_sysfs(fake_sysfs(2, 2, 2).path()).unwrap();
+        assert_eq!(topology.nps_mode(), NpsMode::Nps1);
+        assert_eq!(topology.socket_domains(1)[0].id, 1);
+
+        let topology = Topology::from_sysfs(fake_sysfs(2, 4, 2).path()).unwrap();
+        assert_eq!(topology.nps_mode(), NpsMode::Nps2);
+        assert_eq!(topology.cpu_for_worker(2), 4);
+        assert_eq!(topology.cpus[topology.cpu_for_worker(2)].package, 1);
+    }
+
+    #[test]
+    fn test_reserved_cpus_never_assigned() {
+        // Two nodes of 4 cores: node 0 owns 0-3 and 8-11
+        let topology = Topology::synthetic(1, 2, 4);
+        let reserved = [0, 1, 8];
+        let mining = topology.without_cpus(&reserved);
+        assert_eq!(mining.logical_cpus(), 13);
+        assert_eq!(mining.node_cpus(0).unwrap(), vec![2, 3, 9, 10, 11]);
+        assert_eq!(mining.node_cpus(1).unwrap(), topology.node_cpus(1).unwrap());
+        // More workers than CPUs left wrap around the remaining ones
+        for cpu in assign_workers(&mining, 32) {
+            assert!(!reserved.contains(&cpu), "worker on reserved CPU {}", cpu);
+        }
+        assert!((0..32).all(|worker| !reserved.contains(&mining.cpu_for_worker(worker))));
+
+        let none_left = topology.without_cpus(&topology.node_cpus(0).unwrap());
+        assert_eq!(none_left.numa_nodes.len(), 2);
+        assert_eq!(none_left.cpu_domains().len(), 1);
+    }
+
+    #[test]
+    fn test_node_cpus() {
+        let topology = Topology::synthetic(1, 4, 2);
+        assert_eq!(topology.node_cpus(2).unwrap(), vec![4, 5, 12, 13]);
+        assert_eq!(
+            topology.node_cpus(4).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        let mut memory_only = topology.clone();
+        memory_only.numa_nodes.push(NumaNode {
+            id: 4,
+            cpus: Vec::new(),
+        });
+        assert_eq!(
+            memory_only.node_cpus(4).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn test_interleave_policy_for_nodes() {
+        let nodes = [0, 1, 2];
+        assert_eq!(
+            InterleavePolicy::for_nodes(&nodes, &[]).unwrap(),
+            InterleavePolicy::Uniform(vec![0, 1, 2])
+        );
+        let weighted = InterleavePolicy::for_nodes(&nodes, &[3, 0, 1]).unwrap();
+        assert_eq!(weighted, InterleavePolicy::Weighted(vec![(0, 3), (2, 1)]));
+        assert_eq!(
+            weighted.to_string(),
+            "weighted interleave (node 0: 3, node 2: 1)"
+        );
+        // Equal weights need no kernel support beyond plain interleave
+        assert_eq!(
+            InterleavePolicy::for_nodes(&nodes, &[2, 2, 2]).unwrap(),
+            InterleavePolicy::Uniform(vec![0, 1, 2])
+        );
+        for weights in [&[1, 1][..], &[0, 0, 0]] {
+            assert_eq!(
+                InterleavePolicy::for_nodes(&nodes, weights)
+                    .unwrap_err()
+                    .kind(),
+                io::ErrorKind::InvalidInput
+            );
+        }
+
+        let sysfs = tempfile::tempdir().unwrap();
+        let dir = sysfs.path().join("kernel/mm/mempolicy/weighted_interleave");
+        // Kernels before 6.9 have no weights to set
+        assert!(write_interleave_weights(sysfs.path(), &[(0, 3)]).is_err());
+        fs::create_dir_all(&dir).unwrap();
+        write_interleave_weights(sysfs.path(), &[(0, 3), (2, 1)]).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("node0")).unwrap(), "3");
+        assert_eq!(fs::read_to_string(dir.join("node2")).unwrap(), "1");
+    }
+
+    #[test]
+    fn test_strided_index() {
+        let order: Vec<usize> = (0..8).map(|slot| strided_index(slot, 8, 4)).collect();
+        assert_eq!(order, vec![0, 4, 1, 5, 2, 6, 3, 7]);
+        assert_eq!(strided_index(3, 8, 1), 3);
+        assert_eq!(strided_index(9, 8, 1), 1);
+
+        // Uneven passes still visit every item exactly once
+        let mut order: Vec<usize> = (0..10).map(|slot| strided_index(slot, 10, 3)).collect();
+        assert_eq!(&order[..4], &[0, 3, 6, 9]);
+        order.sort_unstable();
+        assert_eq!(order, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_assign_workers_balanced() {
+        let mut uneven = Topology::synthetic(1, 3, 4);
+        uneven
+            .cpus
+            .retain(|cpu| cpu.numa_node != 2 || cpu.core % 4 < 2);
+        let layouts = [
+            Topology::synthetic(1, 4, 4),
+            // Dual EPYC 7K62 under NPS4: 8 domains of 12 cores
+            Topology::synthetic(2, 8, 12),
+            Topology::synthetic(2, 2, 2),
+            uneven,
+        ];
+        for topology in &layouts {
+            for n_workers in [1, 7, 30, 188, 400] {
+                let assignment = assign_workers(topology, n_workers);
+                assert_eq!(assignment.len(), n_workers);
+                assert_eq!(assignment, assign_workers(topology, n_workers));
+
+                let mut per_node = vec![0usize; topology.numa_nodes.len()];
+                for &cpu in &assignment {
+                    per_node[topology
+                        .cpus
+                        .iter()
+                        .find(|c| c.cpu == cpu)
+                        .unwrap()
+                        .numa_node] += 1;
+                }
+                let (min, max) = (
+                    per_node.iter().min().unwrap(),
+                    per_node.iter().max().unwrap(),
+                );
+                assert!(
+                    max - min <= 1,
+                    "{n_workers} workers unbalanced: {per_node:?}"
+                );
+
+                // No CPU is shared while some are still free
+                let distinct: BTreeSet<CpuId> = assignment.iter().copied().collect();
+                if n_workers <= topology.logical_cpus() / topology.numa_nodes.len() {
+                    assert_eq!(distinct.len(), n_workers);
+                }
+                for (worker, &cpu) in assignment.iter().enumerate() {
+                    assert_eq!(topology.cpu_for_worker(worker), cpu);
+                }
+            }
+        }
+
+        let spread = assign_workers_strided(&Topology::synthetic(1, 1, 8), 4, 4);
+        assert_eq!(spread, vec![0, 4, 8, 12]);
+    }
+
+    #[test]
+    fn test_synthetic_matches_sysfs() {
+        for (sockets, nodes, cores) in [(1, 2, 4), (2, 2, 2), (1, 4, 4), (2, 8, 3)] {
+            let from_sysfs =
+                Topology::from_sysfs(fake_sysfs(sockets, nodes, cores).path()).unwrap();
+            let synthetic = Topology::synthetic(sockets, nodes, cores);
+            assert_eq!(synthetic.cpus, from_sysfs.cpus);
+            assert_eq!(synthetic.numa_nodes, from_sysfs.numa_nodes);
+        }
+    }
+
+    #[test]
+    fn test_report_json() {
+        let sysfs = fake_sysfs(2, 2, 2);
+        let topology = Topology::from_sysfs(sysfs.path()).unwrap();
+        let report = topology.report(&[0, 1, 2]);
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["sockets"], 2);
+        assert_eq!(json["logical_cpus"], 8);
+        assert_eq!(json["thread_assignment"], serde_json::json!([0, 1, 2]));
+        assert_eq!(json["pinning_effective"], serde_json::Value::Null);
+
+        let out = tempfile::NamedTempFile::new().unwrap();
+        report.write_to(out.path()).unwrap();
+        assert_eq!(fs::read_to_string(out.path()).unwrap(), report.to_json());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_probe_pinning() {
+        // The CPU this test runs on is certainly in its allowed set
+        let cpu = unsafe { libc::sched_getcpu() } as usize;
+        let probe = probe_pinning(&[cpu]);
+        assert_eq!(probe.held, vec![cpu]);
+        assert!(probe.effective());
+
+        let probe = probe_pinning(&[cpu, usize::MAX]);
+        assert_eq!(probe.unpinnable, vec![usize::MAX]);
+        assert!(!probe.effective());
+        let report = Topology::synthetic(1, 1, 2)
+            .report(&[cpu])
+            .with_pinning(Some(&probe));
+        assert_eq!(report.pinning_effective, Some(false));
+    }
+}
@@ -0,0 +1,66 @@
+// Known (candidate, nonce, expected result) vectors run through the real miner kernel.
+//
+// This guards the hashing path against consensus-breaking changes: every vector in
+// tests/vectors/mining.json must still be classified as a solution or a miss exactly as
+// recorded. Targets are chosen so the classification does not depend on the hash value.
+mod test {
+    use nockapp::utils::NOCK_STACK_SIZE_TINY;
+    use nockchain::hash_backend::{Candidate, CpuSerfBackend, HashBackend, HashResult, Nonce};
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    struct Vector {
+        name: String,
+        version: u64,
+        header: [u64; 5],
+        /// u32 bignum limbs, least significant first
+        target: Vec<u32>,
+        pow_len: u64,
+        nonce: [u64; 5],
+        solution: bool,
+    }
+
+    fn load_vectors() -> Vec<Vector> {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/vectors/mining.json");
+        let json = std::fs::read_to_string(path).expect("Could not read mining test vectors");
+        serde_json::from_str(&json).expect("Malformed mining test vectors")
+    }
+
+    #[test]
+    fn test_vectors_parse() {
+        let vectors = load_vectors();
+        assert!(vectors.iter().any(|v| v.solution));
+        assert!(vectors.iter().any(|v| !v.solution));
+    }
+
+    #[ignore = "Proves every vector with the miner kernel, which is slow outside release builds"]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_mining_vectors() -> Result<(), Box<dyn std::error::Error>> {
+        let backend = CpuSerfBackend::new(
+            zkvm_jetpack::hot::produce_prover_hot_state(),
+            NOCK_STACK_SIZE_TINY,
+            Vec::new(),
+        )
+        .await?;
+
+        for vector in load_vectors() {
+            let candidate = Candidate::from_parts(
+                vector.version, vector.header, &vector.target, vector.pow_len,
+            );
+            let nonces = [Nonce::from_belts(vector.nonce)];
+            let results =
+                tokio::task::block_in_place(|| backend.hash_candidates(&candidate, &nonces));
+            let found = match results.first() {
+                Some(HashResult::Found { .. }) => true,
+                Some(HashResult::Miss { .. }) => false,
+                Some(HashResult::Unexpected { head }) => {
+                    panic!("{}: unexpected effect {}", vector.name, head)
+                }
+                Some(HashResult::Failed(e)) => panic!("{}: kernel failed: {e:?}", vector.name),
+                Some(HashResult::Cancelled) | None => panic!("{}: no result", vector.name),
+            };
+            assert_eq!(found, vector.solution, "{}", vector.name);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,12 @@
+//! Compare field kernel throughput across the backends available on this host.
+//!
+//! Usage: `field-bench [--json]`
+
+fn main() {
+    let report = zkvm_jetpack::field::bench_all();
+    if std::env::args().skip(1).any(|arg| arg == "--json") {
+        println!("{}", report.to_json());
+    } else {
+        println!("{report}");
+    }
+}
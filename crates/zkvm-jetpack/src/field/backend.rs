@@ -0,0 +1,287 @@
+//! Runtime choice of field arithmetic backend.
+//!
+//! By default every entry point in [`super`] picks the fastest implementation the host
+//! supports. [`set_backend`] pins them to one backend instead, e.g. to benchmark the same
+//! workload on each backend without recompiling:
+//!
+//! - [`FieldBackend::Scalar`] keeps [`super::batch`] off the SIMD kernels and OpenCL
+//!   batch multiplies on the CPU
+//! - [`FieldBackend::Avx2`] keeps [`super::batch`] on the 4-lane AVX2 kernels even where
+//!   AVX-512 is available, and OpenCL batch multiplies on the CPU
+//! - [`FieldBackend::Avx512`] keeps OpenCL batch multiplies on the CPU
+//! - [`FieldBackend::OpenCl`] sends OpenCL batch multiplies to the device; the
+//!   [`super::batch`] operations run on the CPU as usual
+//!
+//! Widest is not always fastest. Intel's first AVX-512 server cores (Skylake-SP, Cascade
+//! Lake, Cooper Lake) drop the whole core to a lower frequency license while 512-bit
+//! instructions are in flight, and take a while to climb back, so the scalar and hashing
+//! code between batch calls runs slower too. [`avx512_downclocks`] recognises those parts
+//! and [`avoid_avx512_downclock`] pins [`FieldBackend::Avx2`] on them. Newer Intel cores
+//! and AMD Zen 4/5 keep their clocks, and the AVX-512 kernels win there. The field
+//! microbenchmark times the kernels alone, so it tends to flatter AVX-512 on the affected
+//! parts; compare whole-miner hash rate with each backend pinned before settling on one.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// A family of implementations for the base field operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldBackend {
+    /// Portable 64-bit arithmetic from [`crate::form::math::base`]
+    Scalar,
+    /// 4-lane AVX2 kernels from [`crate::form::math::base_optimized`], which run at full
+    /// clock on CPUs that downclock for AVX-512
+    Avx2,
+    /// 8-lane AVX-512F kernels from [`crate::form::math::base_optimized`]
+    Avx512,
+    /// An OpenCL device, with the `opencl` feature
+    OpenCl,
+}
+
+impl FieldBackend {
+    const ALL: [FieldBackend; 4] = [
+        FieldBackend::Scalar,
+        FieldBackend::Avx2,
+        FieldBackend::Avx512,
+        FieldBackend::OpenCl,
+    ];
+
+    /// Whether this build and host can run the backend
+    pub fn is_supported(self) -> bool {
+        match self {
+            FieldBackend::Scalar => true,
+            FieldBackend::Avx2 => avx2_detected(),
+            FieldBackend::Avx512 => avx512_detected(),
+            FieldBackend::OpenCl => opencl_detected(),
+        }
+    }
+
+    fn tag(self) -> u8 {
+        match self {
+            FieldBackend::Scalar => 1,
+            FieldBackend::Avx512 => 2,
+            FieldBackend::OpenCl => 3,
+            FieldBackend::Avx2 => 4,
+        }
+    }
+}
+
+impl fmt::Display for FieldBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldBackend::Scalar => write!(f, "scalar"),
+            FieldBackend::Avx2 => write!(f, "avx2"),
+            FieldBackend::Avx512 => write!(f, "avx512"),
+            FieldBackend::OpenCl => write!(f, "opencl"),
+        }
+    }
+}
+
+impl FromStr for FieldBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FieldBackend::ALL
+            .into_iter()
+            .find(|backend| backend.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!("unknown field backend: {s} (expected scalar, avx2, avx512 or opencl)")
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendError {
+    /// The backend is not compiled in or the hardware cannot run it
+    Unsupported(FieldBackend),
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendError::Unsupported(backend) => {
+                write!(f, "field backend {backend} is not supported on this host")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+/// Tag of the pinned backend, or `AUTO`
+static SELECTED: AtomicU8 = AtomicU8::new(AUTO);
+const AUTO: u8 = 0;
+
+/// Backends this build can run on this host, slowest first
+pub fn available_backends() -> Vec<FieldBackend> {
+    FieldBackend::ALL
+        .into_iter()
+        .filter(|backend| backend.is_supported())
+        .collect()
+}
+
+/// Pin the field operations to `backend` for the rest of the process.
+///
+/// Fails without changing anything if the backend is unavailable.
+pub fn set_backend(backend: FieldBackend) -> Result<(), BackendError> {
+    if !backend.is_supported() {
+        return Err(BackendError::Unsupported(backend));
+    }
+    SELECTED.store(backend.tag(), Ordering::Relaxed);
+    Ok(())
+}
+
+/// Go back to picking the fastest backend per operation
+pub fn clear_backend() {
+    SELECTED.store(AUTO, Ordering::Relaxed);
+}
+
+/// The backend pinned with [`set_backend`], if any
+pub fn selected_backend() -> Option<FieldBackend> {
+    let tag = SELECTED.load(Ordering::Relaxed);
+    FieldBackend::ALL
+        .into_iter()
+        .find(|backend| backend.tag() == tag)
+}
+
+/// Whether this CPU is one whose AVX-512 frequency license is known to cost more than
+/// the wider kernels gain: Intel family 6 model 0x55, i.e. Skylake-SP, Cascade Lake and
+/// Cooper Lake
+pub fn avx512_downclocks() -> bool {
+    avx512_detected() && downclocking_model()
+}
+
+/// Pin [`FieldBackend::Avx2`] if [`avx512_downclocks`] and nothing else is pinned yet.
+/// Returns whether it did.
+pub fn avoid_avx512_downclock() -> bool {
+    selected_backend().is_none() && avx512_downclocks() && set_backend(FieldBackend::Avx2).is_ok()
+}
+
+/// Whether batch operations may use the AVX-512 kernels
+pub(crate) fn use_avx512() -> bool {
+    !matches!(
+        selected_backend(),
+        Some(FieldBackend::Scalar | FieldBackend::Avx2)
+    ) && avx512_detected()
+}
+
+/// Whether batch operations may use the AVX2 kernels, for whatever the AVX-512 kernels
+/// leave over or in their place
+pub(crate) fn use_avx2() -> bool {
+    selected_backend() != Some(FieldBackend::Scalar) && avx2_detected()
+}
+
+/// Whether OpenCL batch multiplies may go to the device
+#[cfg_attr(not(feature = "opencl"), allow(dead_code))]
+pub(crate) fn use_opencl() -> bool {
+    matches!(selected_backend(), None | Some(FieldBackend::OpenCl))
+}
+
+#[cfg(target_arch = "x86_64")]
+fn avx512_detected() -> bool {
+    std::arch::is_x86_feature_detected!("avx512f")
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn avx512_detected() -> bool {
+    false
+}
+
+#[cfg(target_arch = "x86_64")]
+fn avx2_detected() -> bool {
+    std::arch::is_x86_feature_detected!("avx2")
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn avx2_detected() -> bool {
+    false
+}
+
+#[cfg(target_arch = "x86_64")]
+fn downclocking_model() -> bool {
+    use std::arch::x86_64::__cpuid;
+
+    // SAFETY: cpuid is available on every x86_64 CPU
+    let vendor = unsafe { __cpuid(0) };
+    let is_intel = [vendor.ebx, vendor.edx, vendor.ecx]
+        .iter()
+        .flat_map(|reg| reg.to_le_bytes())
+        .eq(*b"GenuineIntel");
+    if !is_intel {
+        return false;
+    }
+    let signature = unsafe { __cpuid(1) }.eax;
+    let family = (signature >> 8) & 0xf;
+    let model = ((signature >> 4) & 0xf) | (((signature >> 16) & 0xf) << 4);
+    family == 6 && model == 0x55
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn downclocking_model() -> bool {
+    false
+}
+
+#[cfg(feature = "opencl")]
+fn opencl_detected() -> bool {
+    super::opencl::opencl_available()
+}
+
+#[cfg(not(feature = "opencl"))]
+fn opencl_detected() -> bool {
+    false
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Held by tests that change the process-wide selection
+    pub(crate) static SELECTION_LOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn test_available_backends_can_be_selected() {
+        let _guard = SELECTION_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let available = available_backends();
+        assert_eq!(available.first(), Some(&FieldBackend::Scalar));
+        for backend in FieldBackend::ALL {
+            match set_backend(backend) {
+                Ok(()) => {
+                    assert!(available.contains(&backend));
+                    assert_eq!(selected_backend(), Some(backend));
+                }
+                Err(e) => {
+                    assert!(!available.contains(&backend));
+                    assert_eq!(e, BackendError::Unsupported(backend));
+                }
+            }
+        }
+        set_backend(FieldBackend::Scalar).unwrap();
+        assert!(!use_avx512());
+        assert!(!use_avx2());
+        if set_backend(FieldBackend::Avx2).is_ok() {
+            assert!(!use_avx512());
+            assert!(use_avx2());
+            // Something is already pinned, so the downclock check leaves it alone
+            assert!(!avoid_avx512_downclock());
+            assert_eq!(selected_backend(), Some(FieldBackend::Avx2));
+        }
+        clear_backend();
+        assert_eq!(selected_backend(), None);
+        assert_eq!(use_avx512(), available.contains(&FieldBackend::Avx512));
+    }
+
+    #[test]
+    fn test_parse_backend() {
+        for backend in FieldBackend::ALL {
+            assert_eq!(backend.to_string().parse::<FieldBackend>(), Ok(backend));
+        }
+        assert_eq!("AVX512".parse::<FieldBackend>(), Ok(FieldBackend::Avx512));
+        assert_eq!("avx2".parse::<FieldBackend>(), Ok(FieldBackend::Avx2));
+        assert!("neon".parse::<FieldBackend>().is_err());
+    }
+}
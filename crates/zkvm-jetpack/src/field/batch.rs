@@ -0,0 +1,502 @@
+//! Element-wise batch operations over base field elements.
+//!
+//! Inputs must be canonical field elements (`< PRIME`). On x86_64 with AVX-512F
+//! available, full 8-lane chunks go through the SIMD kernels in
+//! [`crate::form::math::base_optimized`], a 4-lane remainder through the AVX2 kernels,
+//! and the last few elements through the scalar path. [`super::backend::set_backend`]
+//! can pin the AVX2 kernels for everything, or the scalar path, and
+//! [`super::verify::set_verify_mode`] can check the SIMD results against the scalar path.
+//!
+//! [`is_all_zero`] and [`bfirst_nonzero`] scan a vector for nonzero elements the same way,
+//! using the AVX-512 test masks where available.
+
+use super::verify;
+use crate::form::math::base::{badd, bmul, reduce};
+
+const SIMD_WIDTH: usize = 8;
+const AVX2_WIDTH: usize = 4;
+
+/// Element-wise field addition.
+///
+/// # Panics
+///
+/// Panics if `a` and `b` have different lengths.
+pub fn add(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let mut result = vec![0u64; a.len()];
+    add_into(a, b, &mut result);
+    result
+}
+
+/// Element-wise field multiplication.
+///
+/// # Panics
+///
+/// Panics if `a` and `b` have different lengths.
+pub fn mul(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let mut result = vec![0u64; a.len()];
+    mul_into(a, b, &mut result);
+    result
+}
+
+/// Element-wise field squaring.
+pub fn square(a: &[u64]) -> Vec<u64> {
+    let mut result = vec![0u64; a.len()];
+    square_into(a, &mut result);
+    result
+}
+
+/// Element-wise field addition into a caller-provided buffer.
+///
+/// # Panics
+///
+/// Panics if `a`, `b` and `result` do not all have the same length.
+pub fn add_into(a: &[u64], b: &[u64], result: &mut [u64]) {
+    check_lengths(a, b, result);
+    let done = simd_add(a, b, result);
+    verify::check("add", &mut result[..done], |i| badd(a[i], b[i]));
+    for i in done..a.len() {
+        result[i] = badd(a[i], b[i]);
+    }
+}
+
+/// Element-wise field multiplication into a caller-provided buffer.
+///
+/// # Panics
+///
+/// Panics if `a`, `b` and `result` do not all have the same length.
+pub fn mul_into(a: &[u64], b: &[u64], result: &mut [u64]) {
+    check_lengths(a, b, result);
+    let done = simd_mul(a, b, result);
+    verify::check("mul", &mut result[..done], |i| bmul(a[i], b[i]));
+    for i in done..a.len() {
+        result[i] = bmul(a[i], b[i]);
+    }
+}
+
+/// Element-wise field squaring into a caller-provided buffer.
+///
+/// Cheaper than `mul_into(a, a, result)`: the SIMD path needs three partial products
+/// per lane instead of four.
+///
+/// # Panics
+///
+/// Panics if `a` and `result` have different lengths.
+pub fn square_into(a: &[u64], result: &mut [u64]) {
+    check_lengths(a, a, result);
+    let done = simd_square(a, result);
+    verify::check("square", &mut result[..done], |i| bmul(a[i], a[i]));
+    for i in done..a.len() {
+        result[i] = bmul(a[i], a[i]);
+    }
+}
+
+/// Reduce 128-bit values, e.g. products from a widening multiply, into field elements.
+///
+/// Unlike the other operations this accepts any `u128`, not just products of canonical
+/// elements.
+///
+/// # Panics
+///
+/// Panics if `products` and `result` have different lengths.
+pub fn reduce_128(products: &[u128], result: &mut [u64]) {
+    assert_eq!(
+        products.len(),
+        result.len(),
+        "batch result must match operand length"
+    );
+    let done = simd_reduce_128(products, result);
+    verify::check("reduce_128", &mut result[..done], |i| reduce(products[i]));
+    for i in done..products.len() {
+        result[i] = reduce(products[i]);
+    }
+}
+
+/// Whether every element is zero, e.g. a residual after subtraction.
+pub fn is_all_zero(a: &[u64]) -> bool {
+    bfirst_nonzero(a).is_none()
+}
+
+/// Index of the first nonzero element, or `None` if every element is zero.
+///
+/// Elements are compared as words, so a non-canonical `PRIME` counts as nonzero.
+pub fn bfirst_nonzero(a: &[u64]) -> Option<usize> {
+    let (done, found) = simd_first_nonzero(a);
+    found.or_else(|| a[done..].iter().position(|&x| x != 0).map(|i| done + i))
+}
+
+fn check_lengths(a: &[u64], b: &[u64], result: &[u64]) {
+    assert_eq!(a.len(), b.len(), "batch operands must have the same length");
+    assert_eq!(
+        a.len(),
+        result.len(),
+        "batch result must match operand length"
+    );
+}
+
+/// Runs the SIMD kernels over as much of the input as they cover and returns how many
+/// elements were processed: AVX-512 over the largest multiple of `SIMD_WIDTH`, then AVX2
+/// over the largest multiple of `AVX2_WIDTH` of what is left (all of it, when the AVX2
+/// backend is pinned).
+#[cfg(target_arch = "x86_64")]
+fn simd_add(a: &[u64], b: &[u64], result: &mut [u64]) -> usize {
+    use crate::form::math::base_optimized::{badd_batch_avx2, badd_batch_avx512};
+
+    let (wide, narrow) = simd_split(a.len());
+    if wide > 0 {
+        // SAFETY: avx512f support was checked by `simd_split` and all slices are `wide`
+        // long, a multiple of SIMD_WIDTH.
+        unsafe { badd_batch_avx512(&a[..wide], &b[..wide], &mut result[..wide]) };
+    }
+    if narrow > wide {
+        // SAFETY: avx2 support was checked by `simd_split` and all slices are
+        // `narrow - wide` long, a multiple of AVX2_WIDTH.
+        unsafe {
+            badd_batch_avx2(
+                &a[wide..narrow],
+                &b[wide..narrow],
+                &mut result[wide..narrow],
+            )
+        };
+    }
+    narrow
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn simd_add(_a: &[u64], _b: &[u64], _result: &mut [u64]) -> usize {
+    0
+}
+
+#[cfg(target_arch = "x86_64")]
+fn simd_mul(a: &[u64], b: &[u64], result: &mut [u64]) -> usize {
+    use crate::form::math::base_optimized::{bmul_batch_avx2, bmul_batch_avx512};
+
+    let (wide, narrow) = simd_split(a.len());
+    if wide > 0 {
+        // SAFETY: as in `simd_add`
+        unsafe { bmul_batch_avx512(&a[..wide], &b[..wide], &mut result[..wide]) };
+    }
+    if narrow > wide {
+        // SAFETY: as in `simd_add`
+        unsafe {
+            bmul_batch_avx2(
+                &a[wide..narrow],
+                &b[wide..narrow],
+                &mut result[wide..narrow],
+            )
+        };
+    }
+    narrow
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn simd_mul(_a: &[u64], _b: &[u64], _result: &mut [u64]) -> usize {
+    0
+}
+
+#[cfg(target_arch = "x86_64")]
+fn simd_square(a: &[u64], result: &mut [u64]) -> usize {
+    use crate::form::math::base_optimized::{bmul_batch_avx2, bsquare_batch_avx512};
+
+    let (wide, narrow) = simd_split(a.len());
+    if wide > 0 {
+        // SAFETY: as in `simd_add`
+        unsafe { bsquare_batch_avx512(&a[..wide], &mut result[..wide]) };
+    }
+    if narrow > wide {
+        // There is no AVX2 squaring kernel; the multiply is only one partial product worse
+        // SAFETY: as in `simd_add`
+        unsafe {
+            bmul_batch_avx2(
+                &a[wide..narrow],
+                &a[wide..narrow],
+                &mut result[wide..narrow],
+            )
+        };
+    }
+    narrow
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn simd_square(_a: &[u64], _result: &mut [u64]) -> usize {
+    0
+}
+
+#[cfg(target_arch = "x86_64")]
+fn simd_reduce_128(products: &[u128], result: &mut [u64]) -> usize {
+    use crate::form::math::base_optimized::{reduce_128_batch_avx2, reduce_128_batch_avx512};
+
+    let (wide, narrow) = simd_split(products.len());
+    if wide > 0 {
+        // SAFETY: as in `simd_add`
+        unsafe { reduce_128_batch_avx512(&products[..wide], &mut result[..wide]) };
+    }
+    if narrow > wide {
+        // SAFETY: as in `simd_add`
+        unsafe { reduce_128_batch_avx2(&products[wide..narrow], &mut result[wide..narrow]) };
+    }
+    narrow
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn simd_reduce_128(_products: &[u128], _result: &mut [u64]) -> usize {
+    0
+}
+
+/// Searches as much of the input as the SIMD kernels cover, returning how many elements
+/// that was and the first nonzero one among them
+#[cfg(target_arch = "x86_64")]
+fn simd_first_nonzero(a: &[u64]) -> (usize, Option<usize>) {
+    use crate::form::math::base_optimized::{bfirst_nonzero_avx2, bfirst_nonzero_avx512};
+
+    let (wide, narrow) = simd_split(a.len());
+    if wide > 0 {
+        // SAFETY: as in `simd_add`
+        if let Some(i) = unsafe { bfirst_nonzero_avx512(&a[..wide]) } {
+            return (wide, Some(i));
+        }
+    }
+    if narrow > wide {
+        // SAFETY: as in `simd_add`
+        if let Some(i) = unsafe { bfirst_nonzero_avx2(&a[wide..narrow]) } {
+            return (narrow, Some(wide + i));
+        }
+    }
+    (narrow, None)
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn simd_first_nonzero(_a: &[u64]) -> (usize, Option<usize>) {
+    (0, None)
+}
+
+/// Where the AVX-512 kernels stop and where the AVX2 kernels stop, for `len` elements
+/// under the current backend selection
+#[cfg(target_arch = "x86_64")]
+fn simd_split(len: usize) -> (usize, usize) {
+    let wide = if super::backend::use_avx512() {
+        len - len % SIMD_WIDTH
+    } else {
+        0
+    };
+    let narrow = if super::backend::use_avx2() {
+        wide + (len - wide) / AVX2_WIDTH * AVX2_WIDTH
+    } else {
+        wide
+    };
+    (wide, narrow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::form::math::base::PRIME;
+
+    fn sample(len: usize, seed: u64) -> Vec<u64> {
+        let mut x = seed;
+        (0..len)
+            .map(|_| {
+                x = x
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                x % PRIME
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_add_matches_scalar() {
+        for len in [0, 1, 7, 8, 9, 64, 67] {
+            let a = sample(len, 1);
+            let b = sample(len, 2);
+            let expected: Vec<u64> = a.iter().zip(&b).map(|(&x, &y)| badd(x, y)).collect();
+            assert_eq!(add(&a, &b), expected, "len {len}");
+        }
+    }
+
+    #[test]
+    fn test_mul_matches_scalar() {
+        for len in [0, 1, 7, 8, 9, 64, 67] {
+            let a = sample(len, 3);
+            let b = sample(len, 4);
+            let expected: Vec<u64> = a.iter().zip(&b).map(|(&x, &y)| bmul(x, y)).collect();
+            assert_eq!(mul(&a, &b), expected, "len {len}");
+        }
+    }
+
+    #[test]
+    fn test_square_matches_scalar() {
+        for len in [0, 1, 7, 8, 9, 64, 67] {
+            let a = sample(len, 5);
+            let expected: Vec<u64> = a.iter().map(|&x| bmul(x, x)).collect();
+            assert_eq!(square(&a), expected, "len {len}");
+        }
+    }
+
+    #[test]
+    fn test_reduce_128_matches_scalar() {
+        for len in [0, 1, 7, 8, 9, 64, 67] {
+            let a = sample(len, 7);
+            let b = sample(len, 8);
+            let mut products: Vec<u128> = a
+                .iter()
+                .zip(&b)
+                .map(|(&x, &y)| x as u128 * y as u128)
+                .collect();
+            if let Some(last) = products.last_mut() {
+                *last = u128::MAX;
+            }
+            let mut result = vec![0u64; len];
+            reduce_128(&products, &mut result);
+            let expected: Vec<u64> = products.iter().map(|&n| reduce(n)).collect();
+            assert_eq!(result, expected, "len {len}");
+        }
+    }
+
+    #[test]
+    fn test_edge_values() {
+        let edges = [0, 1, 2, PRIME - 1, PRIME - 2, 0xFFFF_FFFF, 1 << 32, PRIME >> 1];
+        let a: Vec<u64> = edges.iter().cycle().take(64).copied().collect();
+        let b: Vec<u64> = edges.iter().rev().cycle().take(64).copied().collect();
+        let add_expected: Vec<u64> = a.iter().zip(&b).map(|(&x, &y)| badd(x, y)).collect();
+        let mul_expected: Vec<u64> = a.iter().zip(&b).map(|(&x, &y)| bmul(x, y)).collect();
+        assert_eq!(add(&a, &b), add_expected);
+        assert_eq!(mul(&a, &b), mul_expected);
+        assert_eq!(
+            square(&a),
+            a.iter().map(|&x| bmul(x, x)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_avx512_kernels_called_from_generic_code() {
+        use crate::form::math::base_optimized::{
+            badd_batch_avx512, bmul_batch_avx512, bsquare_batch_avx512, reduce_128_batch_avx512,
+        };
+
+        if !std::arch::is_x86_feature_detected!("avx512f") {
+            return;
+        }
+        // Every pair of edge values, so each one lands in every lane. This function is
+        // compiled without avx512f, like the dispatchers, and tests build with LTO.
+        let edges = [0, 1, 0xFFFF_FFFF, 1 << 32, PRIME >> 1, 1 << 63, PRIME - 2, PRIME - 1];
+        let a: Vec<u64> = edges.iter().flat_map(|&x| [x; 8]).collect();
+        let b: Vec<u64> = edges.iter().cycle().take(64).copied().collect();
+        let products: Vec<u128> = a
+            .iter()
+            .zip(&b)
+            .map(|(&x, &y)| x as u128 * y as u128)
+            .collect();
+
+        let add_expected: Vec<u64> = a.iter().zip(&b).map(|(&x, &y)| badd(x, y)).collect();
+        let mul_expected: Vec<u64> = a.iter().zip(&b).map(|(&x, &y)| bmul(x, y)).collect();
+        let square_expected: Vec<u64> = a.iter().map(|&x| bmul(x, x)).collect();
+        let reduce_expected: Vec<u64> = products.iter().map(|&n| reduce(n)).collect();
+
+        let mut result = vec![0u64; a.len()];
+        unsafe { badd_batch_avx512(&a, &b, &mut result) };
+        assert_eq!(result, add_expected);
+        unsafe { bmul_batch_avx512(&a, &b, &mut result) };
+        assert_eq!(result, mul_expected);
+        unsafe { bsquare_batch_avx512(&a, &mut result) };
+        assert_eq!(result, square_expected);
+        unsafe { reduce_128_batch_avx512(&products, &mut result) };
+        assert_eq!(result, reduce_expected);
+
+        // The same through the dispatching entry points
+        assert_eq!(add(&a, &b), add_expected);
+        assert_eq!(mul(&a, &b), mul_expected);
+        assert_eq!(square(&a), square_expected);
+        reduce_128(&products, &mut result);
+        assert_eq!(result, reduce_expected);
+    }
+
+    #[test]
+    fn test_pinned_backends_match_scalar() {
+        use super::super::backend::tests::SELECTION_LOCK;
+        use super::super::backend::{clear_backend, set_backend, FieldBackend};
+
+        let _guard = SELECTION_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for backend in [FieldBackend::Scalar, FieldBackend::Avx2, FieldBackend::Avx512] {
+            if set_backend(backend).is_err() {
+                continue;
+            }
+            for len in [0, 3, 4, 5, 12, 13, 15, 67] {
+                let a = sample(len, 9);
+                let b = sample(len, 10);
+                let products: Vec<u128> = a
+                    .iter()
+                    .zip(&b)
+                    .map(|(&x, &y)| x as u128 * y as u128)
+                    .collect();
+                let mut reduced = vec![0u64; len];
+                reduce_128(&products, &mut reduced);
+
+                let expected: Vec<u64> = a.iter().zip(&b).map(|(&x, &y)| bmul(x, y)).collect();
+                assert_eq!(mul(&a, &b), expected, "{backend} len {len}");
+                assert_eq!(reduced, expected, "{backend} len {len}");
+                assert_eq!(
+                    add(&a, &b),
+                    a.iter()
+                        .zip(&b)
+                        .map(|(&x, &y)| badd(x, y))
+                        .collect::<Vec<_>>(),
+                    "{backend} len {len}"
+                );
+                assert_eq!(
+                    square(&a),
+                    a.iter().map(|&x| bmul(x, x)).collect::<Vec<_>>(),
+                    "{backend} len {len}"
+                );
+            }
+        }
+        clear_backend();
+    }
+
+    #[test]
+    fn test_first_nonzero_matches_naive() {
+        use super::super::backend::tests::SELECTION_LOCK;
+        use super::super::backend::{clear_backend, set_backend, FieldBackend};
+
+        let naive = |a: &[u64]| a.iter().position(|&x| x != 0);
+        let _guard = SELECTION_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for backend in [FieldBackend::Scalar, FieldBackend::Avx2, FieldBackend::Avx512] {
+            if set_backend(backend).is_err() {
+                continue;
+            }
+            for len in [0, 1, 3, 4, 5, 8, 12, 13, 16, 67] {
+                let zeros = vec![0u64; len];
+                assert_eq!(bfirst_nonzero(&zeros), None, "{backend} len {len}");
+                assert!(is_all_zero(&zeros), "{backend} len {len}");
+                // A single nonzero element in every position, including every lane and tail
+                for i in 0..len {
+                    for value in [1, PRIME - 1, PRIME, 1 << 63, u64::MAX] {
+                        let mut a = zeros.clone();
+                        a[i] = value;
+                        assert_eq!(bfirst_nonzero(&a), Some(i), "{backend} len {len} at {i}");
+                        assert!(!is_all_zero(&a), "{backend} len {len} at {i}");
+                    }
+                }
+                let a = sample(len, 11);
+                assert_eq!(bfirst_nonzero(&a), naive(&a), "{backend} len {len}");
+                // Several nonzero elements, the first one past the AVX-512 part
+                let mut a = zeros.clone();
+                for i in (len * 3 / 4..len).step_by(2) {
+                    a[i] = i as u64;
+                }
+                assert_eq!(bfirst_nonzero(&a), naive(&a), "{backend} len {len}");
+            }
+        }
+        clear_backend();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_length_mismatch_panics() {
+        add(&[1, 2], &[1]);
+    }
+}
@@ -0,0 +1,242 @@
+//! Throughput comparison of the field kernels across backends.
+//!
+//! [`bench_all`] times add, multiply and 128-bit reduction on every backend
+//! [`super::backend::available_backends`] reports, pinning each in turn with
+//! [`super::backend::set_backend`]. The `field-bench` binary prints the result as a table
+//! or, with `--json`, as JSON.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use super::backend::{self, FieldBackend};
+use super::batch;
+use crate::form::math::base::PRIME;
+
+/// Elements per operand in [`bench_all`]
+pub const BENCH_ELEMENTS: usize = 1 << 16;
+/// Timed runs per operation in [`bench_all`], after one warm-up run
+pub const BENCH_ITERATIONS: usize = 32;
+
+/// Field operations timed per backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchOp {
+    Add,
+    Mul,
+    Reduce128,
+}
+
+impl BenchOp {
+    const ALL: [BenchOp; 3] = [BenchOp::Add, BenchOp::Mul, BenchOp::Reduce128];
+}
+
+impl fmt::Display for BenchOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BenchOp::Add => write!(f, "badd"),
+            BenchOp::Mul => write!(f, "bmul"),
+            BenchOp::Reduce128 => write!(f, "reduce_128"),
+        }
+    }
+}
+
+/// Throughput of one operation on one backend
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    pub backend: FieldBackend,
+    pub op: BenchOp,
+    pub elements_per_sec: f64,
+}
+
+/// Every timed (backend, operation) pair. Operations a backend does not implement
+/// are left out, e.g. OpenCL only offloads multiplication.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendBenchReport {
+    pub elements: usize,
+    pub iterations: usize,
+    pub results: Vec<BenchResult>,
+}
+
+impl BackendBenchReport {
+    /// Throughput of `op` on `backend`, if it was timed
+    pub fn get(&self, backend: FieldBackend, op: BenchOp) -> Option<f64> {
+        self.results
+            .iter()
+            .find(|result| result.backend == backend && result.op == op)
+            .map(|result| result.elements_per_sec)
+    }
+
+    /// Backends that appear in the report, in timing order
+    pub fn backends(&self) -> Vec<FieldBackend> {
+        let mut backends: Vec<FieldBackend> = Vec::new();
+        for result in &self.results {
+            if !backends.contains(&result.backend) {
+                backends.push(result.backend);
+            }
+        }
+        backends
+    }
+
+    pub fn to_json(&self) -> String {
+        let results: Vec<String> = self
+            .results
+            .iter()
+            .map(|result| {
+                format!(
+                    "{{\"backend\":\"{}\",\"op\":\"{}\",\"elements_per_sec\":{:.0}}}",
+                    result.backend, result.op, result.elements_per_sec
+                )
+            })
+            .collect();
+        format!(
+            "{{\"elements\":{},\"iterations\":{},\"results\":[{}]}}",
+            self.elements,
+            self.iterations,
+            results.join(",")
+        )
+    }
+}
+
+/// Table of Melem/s with one row per backend and one column per operation
+impl fmt::Display for BackendBenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<10}", "backend")?;
+        for op in BenchOp::ALL {
+            write!(f, "{:>14}", op.to_string())?;
+        }
+        writeln!(f)?;
+        for backend in self.backends() {
+            write!(f, "{:<10}", backend.to_string())?;
+            for op in BenchOp::ALL {
+                match self.get(backend, op) {
+                    Some(rate) => write!(f, "{:>14.1}", rate / 1e6)?,
+                    None => write!(f, "{:>14}", "-")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        write!(
+            f,
+            "(million elements/s, {} elements x {} runs)",
+            self.elements, self.iterations
+        )
+    }
+}
+
+/// Benchmark every available backend with the default sizes
+pub fn bench_all() -> BackendBenchReport {
+    bench_all_with(BENCH_ELEMENTS, BENCH_ITERATIONS)
+}
+
+/// Benchmark every available backend on `elements`-long operands, `iterations` runs each.
+///
+/// The backend selection in effect beforehand is restored afterwards.
+pub fn bench_all_with(elements: usize, iterations: usize) -> BackendBenchReport {
+    let previous = backend::selected_backend();
+    let a = sample(elements, 1);
+    let b = sample(elements, 2);
+    let products: Vec<u128> = a
+        .iter()
+        .zip(&b)
+        .map(|(&x, &y)| x as u128 * y as u128)
+        .collect();
+    let mut out = vec![0u64; elements];
+
+    let mut results = Vec::new();
+    for backend in backend::available_backends() {
+        if backend::set_backend(backend).is_err() {
+            continue;
+        }
+        for op in BenchOp::ALL {
+            let elapsed = match (backend, op) {
+                (FieldBackend::OpenCl, BenchOp::Mul) => opencl_mul(&a, &b, iterations),
+                (FieldBackend::OpenCl, _) => None,
+                (_, BenchOp::Add) => Some(time(iterations, || batch::add_into(&a, &b, &mut out))),
+                (_, BenchOp::Mul) => Some(time(iterations, || batch::mul_into(&a, &b, &mut out))),
+                (_, BenchOp::Reduce128) => {
+                    Some(time(iterations, || batch::reduce_128(&products, &mut out)))
+                }
+            };
+            if let Some(elapsed) = elapsed {
+                let total = (elements * iterations) as f64;
+                results.push(BenchResult {
+                    backend,
+                    op,
+                    elements_per_sec: total / elapsed.as_secs_f64().max(f64::MIN_POSITIVE),
+                });
+            }
+        }
+    }
+
+    match previous {
+        Some(backend) => {
+            let _ = backend::set_backend(backend);
+        }
+        None => backend::clear_backend(),
+    }
+    BackendBenchReport {
+        elements,
+        iterations,
+        results,
+    }
+}
+
+/// Time `iterations` runs of `run` after one untimed warm-up run
+fn time(iterations: usize, mut run: impl FnMut()) -> Duration {
+    run();
+    let started = Instant::now();
+    for _ in 0..iterations {
+        run();
+    }
+    started.elapsed()
+}
+
+#[cfg(feature = "opencl")]
+fn opencl_mul(a: &[u64], b: &[u64], iterations: usize) -> Option<Duration> {
+    Some(time(iterations, || {
+        std::hint::black_box(super::opencl::bmul_batch_opencl(a, b));
+    }))
+}
+
+#[cfg(not(feature = "opencl"))]
+fn opencl_mul(_a: &[u64], _b: &[u64], _iterations: usize) -> Option<Duration> {
+    None
+}
+
+/// Deterministic canonical field elements
+fn sample(len: usize, seed: u64) -> Vec<u64> {
+    let mut x = seed;
+    (0..len)
+        .map(|_| {
+            x = x
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            x % PRIME
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_covers_available_backends() {
+        let _guard = backend::tests::SELECTION_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let report = bench_all_with(64, 1);
+        for backend in backend::available_backends() {
+            if backend == FieldBackend::OpenCl {
+                assert!(report.get(backend, BenchOp::Mul).is_some());
+                continue;
+            }
+            for op in BenchOp::ALL {
+                let rate = report.get(backend, op).expect("every CPU backend is timed");
+                assert!(rate > 0.0);
+            }
+        }
+        let json = report.to_json();
+        assert!(json.starts_with("{\"elements\":64,\"iterations\":1,\"results\":[{"));
+        assert!(report.to_string().starts_with("backend"));
+    }
+}
@@ -0,0 +1,310 @@
+//! A dedicated thread pool for the batch operations in [`super::batch`].
+//!
+//! The batch operations run on whichever thread calls them. In a process that also mines,
+//! that thread competes with the mining workers for cores. A [`FieldExecutor`] owns its
+//! own worker threads, optionally pinned one per CPU, so field work can be fenced onto
+//! cores the miner doesn't use. Each call splits its input into one chunk per worker and
+//! blocks until every chunk is done.
+//!
+//! Chunks are copied into and out of the workers, which costs a pass over memory. That is
+//! cheap next to a large multiply, but for inputs of a few hundred elements calling
+//! [`super::batch`] directly is faster.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::{fmt, panic};
+
+use super::batch;
+
+/// Chunks are whole numbers of AVX-512 vectors so no worker falls back to scalar code
+/// except on the very end of the input
+const CHUNK_ALIGN: usize = 8;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Failure to start a [`FieldExecutor`]
+#[derive(Debug)]
+pub enum ExecutorError {
+    /// A pool needs at least one thread
+    NoThreads,
+    /// Pinning a worker to `cpu` failed
+    Pin {
+        cpu: usize,
+        source: std::io::Error,
+    },
+    Spawn(std::io::Error),
+}
+
+impl fmt::Display for ExecutorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutorError::NoThreads => write!(f, "a field executor needs at least one thread"),
+            ExecutorError::Pin { cpu, source } => {
+                write!(f, "could not pin field worker to CPU {cpu}: {source}")
+            }
+            ExecutorError::Spawn(e) => write!(f, "could not spawn field worker: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ExecutorError {}
+
+/// Runs [`super::batch`] operations on threads of its own
+pub struct FieldExecutor {
+    jobs: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+    cpus: Vec<usize>,
+}
+
+impl FieldExecutor {
+    /// A pool of `threads` unpinned workers, scheduled wherever the OS likes
+    pub fn new(threads: usize) -> Result<Self, ExecutorError> {
+        Self::spawn(vec![None; threads])
+    }
+
+    /// A pool with one worker pinned to each of `cpus`
+    ///
+    /// Pinning needs Linux; elsewhere this fails with [`ExecutorError::Pin`].
+    pub fn pinned(cpus: &[usize]) -> Result<Self, ExecutorError> {
+        Self::spawn(cpus.iter().copied().map(Some).collect())
+    }
+
+    fn spawn(placement: Vec<Option<usize>>) -> Result<Self, ExecutorError> {
+        if placement.is_empty() {
+            return Err(ExecutorError::NoThreads);
+        }
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        let mut executor = Self {
+            jobs: Some(jobs),
+            workers: Vec::with_capacity(placement.len()),
+            cpus: placement.iter().flatten().copied().collect(),
+        };
+        for (index, cpu) in placement.into_iter().enumerate() {
+            let queue = queue.clone();
+            let (started, start_result) = mpsc::channel();
+            let worker = thread::Builder::new()
+                .name(format!("field-worker-{index}"))
+                .spawn(move || {
+                    let pinned = match cpu {
+                        Some(cpu) => pin_current_thread(cpu)
+                            .map_err(|source| ExecutorError::Pin { cpu, source }),
+                        None => Ok(()),
+                    };
+                    let ok = pinned.is_ok();
+                    let _ = started.send(pinned);
+                    if ok {
+                        work(&queue);
+                    }
+                })
+                .map_err(ExecutorError::Spawn)?;
+            executor.workers.push(worker);
+            // Dropping `executor` on error shuts down the workers already running
+            start_result.recv().unwrap_or_else(|_| {
+                Err(ExecutorError::Spawn(std::io::Error::other(
+                    "field worker died while starting",
+                )))
+            })?;
+        }
+        Ok(executor)
+    }
+
+    /// CPUs the workers are pinned to; empty for an unpinned pool
+    pub fn cpus(&self) -> &[usize] {
+        &self.cpus
+    }
+
+    /// Number of worker threads
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// [`batch::add`] on the pool
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` and `b` have different lengths.
+    pub fn add(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        assert_eq!(a.len(), b.len());
+        self.run(a, b, batch::add_into)
+    }
+
+    /// [`batch::mul`] on the pool
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` and `b` have different lengths.
+    pub fn mul(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        assert_eq!(a.len(), b.len());
+        self.run(a, b, batch::mul_into)
+    }
+
+    fn run(&self, a: &[u64], b: &[u64], op: fn(&[u64], &[u64], &mut [u64])) -> Vec<u64> {
+        let mut result = vec![0u64; a.len()];
+        if a.is_empty() {
+            return result;
+        }
+        let chunk = a.len().div_ceil(self.threads()).div_ceil(CHUNK_ALIGN) * CHUNK_ALIGN;
+        let jobs = self
+            .jobs
+            .as_ref()
+            .expect("executor is running until dropped");
+        let (done, finished) = mpsc::channel();
+        let mut pending = 0;
+        for (index, (a, b)) in a.chunks(chunk).zip(b.chunks(chunk)).enumerate() {
+            let (a, b, done) = (a.to_vec(), b.to_vec(), done.clone());
+            jobs.send(Box::new(move || {
+                // A panic goes back to the caller instead of leaving it waiting on `done`
+                let out = panic::catch_unwind(|| {
+                    let mut out = vec![0u64; a.len()];
+                    op(&a, &b, &mut out);
+                    out
+                });
+                let _ = done.send((index, out));
+            }))
+            .expect("field workers outlive the executor");
+            pending += 1;
+        }
+        drop(done);
+        let mut failure = None;
+        for _ in 0..pending {
+            let (index, out) = finished.recv().expect("every job reports back");
+            match out {
+                Ok(out) => result[index * chunk..index * chunk + out.len()].copy_from_slice(&out),
+                Err(payload) => failure = failure.or(Some(payload)),
+            }
+        }
+        // Only once every chunk is in, so no job is left running on the caller's inputs
+        if let Some(payload) = failure {
+            panic::resume_unwind(payload);
+        }
+        result
+    }
+}
+
+impl Drop for FieldExecutor {
+    fn drop(&mut self) {
+        // Closing the queue lets every worker's `recv` fail and the thread exit
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn work(queue: &Mutex<Receiver<Job>>) {
+    loop {
+        let job = match queue.lock() {
+            Ok(queue) => queue.recv(),
+            Err(_) => return,
+        };
+        match job {
+            // The worker outlives a panicking job; `run`'s jobs report their own panics
+            Ok(job) => {
+                let _ = panic::catch_unwind(panic::AssertUnwindSafe(job));
+            }
+            Err(_) => return,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn pin_current_thread(cpu: usize) -> std::io::Result<()> {
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "CPU number out of range",
+        ));
+    }
+    // SAFETY: cpu_set_t is plain data and `cpu` fits in it
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        libc::CPU_SET(cpu, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_current_thread(_cpu: usize) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "thread pinning needs Linux",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::form::math::base::PRIME;
+
+    fn elements(len: usize, seed: u64) -> Vec<u64> {
+        (0..len as u64)
+            .map(|i| i.wrapping_mul(0x9e3779b97f4a7c15).wrapping_add(seed) % PRIME)
+            .collect()
+    }
+
+    #[test]
+    fn test_executor_matches_batch() {
+        let executor = FieldExecutor::new(3).unwrap();
+        assert_eq!(executor.threads(), 3);
+        assert!(executor.cpus().is_empty());
+        // Lengths that leave ragged, short and empty chunks
+        for len in [0, 1, 7, 25, 1000] {
+            let (a, b) = (elements(len, 1), elements(len, 2));
+            assert_eq!(executor.mul(&a, &b), batch::mul(&a, &b), "len {len}");
+            assert_eq!(executor.add(&a, &b), batch::add(&a, &b), "len {len}");
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_pinned_workers_stay_on_their_cpus() {
+        // The CPU this test runs on is certainly in its allowed set
+        let cpu = unsafe { libc::sched_getcpu() } as usize;
+        let executor = FieldExecutor::pinned(&[cpu]).unwrap();
+        assert_eq!(executor.cpus(), &[cpu]);
+        let (tx, rx) = mpsc::channel();
+        executor
+            .jobs
+            .as_ref()
+            .unwrap()
+            .send(Box::new(move || {
+                let _ = tx.send(unsafe { libc::sched_getcpu() });
+            }))
+            .unwrap();
+        assert_eq!(rx.recv().unwrap() as usize, cpu);
+    }
+
+    #[test]
+    fn test_executor_rejects_bad_placement() {
+        assert!(matches!(
+            FieldExecutor::new(0),
+            Err(ExecutorError::NoThreads)
+        ));
+        assert!(matches!(
+            FieldExecutor::pinned(&[]),
+            Err(ExecutorError::NoThreads)
+        ));
+        assert!(matches!(
+            FieldExecutor::pinned(&[usize::MAX]),
+            Err(ExecutorError::Pin { .. })
+        ));
+    }
+    #[test]
+    fn test_panicking_job_reaches_the_caller_and_spares_the_worker() {
+        let executor = FieldExecutor::new(1).unwrap();
+        let (a, b) = (elements(25, 1), elements(25, 2));
+        let caught = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            executor.run(&a, &b, |_, _, _| panic!("field op failed"))
+        }));
+        let payload = caught.unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"field op failed"));
+        // The only worker is still there to take the next call
+        assert_eq!(executor.mul(&a, &b), batch::mul(&a, &b));
+    }
+}
@@ -0,0 +1,139 @@
+//! Field arithmetic over any Goldilocks-shaped prime `2^(2K) - 2^K + 1`.
+//!
+//! The reduction only relies on `2^(2K) = 2^K - 1` and `2^(3K) = -1`, which hold for
+//! every such prime. Instantiating it with [`Goldilocks`] gives the production field;
+//! small instantiations such as `K = 4` (`PRIME = 241`) are small enough for tests to
+//! check every input.
+
+/// A prime of the form `2^(2K) - 2^K + 1`, with `K <= 32`
+pub trait Field {
+    const K: u32;
+    const PRIME: u64 = ((1u128 << (2 * Self::K)) - (1u128 << Self::K) + 1) as u64;
+}
+
+/// The base field, `2^64 - 2^32 + 1`
+pub struct Goldilocks;
+
+impl Field for Goldilocks {
+    const K: u32 = 32;
+}
+
+/// Mask of the low `2K` bits, the width field elements are stored in
+#[inline(always)]
+fn word_mask<F: Field>() -> u64 {
+    ((1u128 << (2 * F::K)) - 1) as u64
+}
+
+/// Reduce a value below `2^(4K)` modulo `F::PRIME`.
+///
+/// Arithmetic wraps at `2K` bits, so for [`Goldilocks`] this is plain `u64` arithmetic.
+#[inline(always)]
+pub fn reduce<F: Field>(n: u128) -> u64 {
+    let k = F::K;
+    let mask = word_mask::<F>();
+    let low = (n as u64) & mask;
+    let mid = ((n >> (2 * k)) as u64) & ((1u64 << k) - 1);
+    let high = (n >> (3 * k)) as u64;
+
+    // Subtract the 2^(3K) part, adding PRIME back on borrow
+    let borrow = low < high;
+    let mut result = low.wrapping_sub(high) & mask;
+    if borrow {
+        result = result.wrapping_add(F::PRIME) & mask;
+    }
+
+    // Add mid * (2^K - 1), subtracting PRIME on carry out of 2K bits
+    let temp = (mid << k) - mid;
+    let sum = result as u128 + temp as u128;
+    let carry = (sum >> (2 * k)) != 0;
+    let sum = (sum as u64) & mask;
+    if carry || sum >= F::PRIME {
+        sum.wrapping_sub(F::PRIME) & mask
+    } else {
+        sum
+    }
+}
+
+/// `a + b` for canonical `a`, `b`
+#[inline(always)]
+pub fn badd<F: Field>(a: u64, b: u64) -> u64 {
+    debug_assert!(a < F::PRIME && b < F::PRIME);
+    let sum = a as u128 + b as u128;
+    if sum >= F::PRIME as u128 {
+        (sum - F::PRIME as u128) as u64
+    } else {
+        sum as u64
+    }
+}
+
+/// `a * b` for canonical `a`, `b`
+#[inline(always)]
+pub fn bmul<F: Field>(a: u64, b: u64) -> u64 {
+    debug_assert!(a < F::PRIME && b < F::PRIME);
+    reduce::<F>(a as u128 * b as u128)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::form::math::base;
+
+    /// 2^8 - 2^4 + 1
+    struct Tiny;
+
+    impl Field for Tiny {
+        const K: u32 = 4;
+    }
+
+    /// 2^4 - 2^2 + 1
+    struct Micro;
+
+    impl Field for Micro {
+        const K: u32 = 2;
+    }
+
+    fn exhaustive<F: Field>() {
+        let p = F::PRIME;
+        for n in 0..(1u128 << (4 * F::K)) {
+            assert_eq!(reduce::<F>(n) as u128, n % p as u128, "reduce({n}) mod {p}");
+        }
+        for a in 0..p {
+            for b in 0..p {
+                assert_eq!(badd::<F>(a, b), (a + b) % p, "{a} + {b} mod {p}");
+                assert_eq!(bmul::<F>(a, b), a * b % p, "{a} * {b} mod {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_fields_exhaustive() {
+        assert_eq!(Tiny::PRIME, 241);
+        assert_eq!(Micro::PRIME, 13);
+        exhaustive::<Tiny>();
+        exhaustive::<Micro>();
+    }
+
+    #[test]
+    fn test_goldilocks_matches_base() {
+        assert_eq!(Goldilocks::PRIME, base::PRIME);
+        let edges = [
+            0,
+            1,
+            2,
+            0xFFFF_FFFF,
+            1 << 32,
+            base::PRIME >> 1,
+            base::PRIME - 2,
+            base::PRIME - 1,
+        ];
+        for &a in &edges {
+            for &b in &edges {
+                assert_eq!(badd::<Goldilocks>(a, b), base::badd(a, b));
+                assert_eq!(bmul::<Goldilocks>(a, b), base::bmul(a, b));
+            }
+        }
+        for n in [u128::MAX, u64::MAX as u128, (u64::MAX as u128) << 64, base::PRIME_128 * 7] {
+            assert_eq!(reduce::<Goldilocks>(n) as u128, n % base::PRIME_128);
+        }
+    }
+}
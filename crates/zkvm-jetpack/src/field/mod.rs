@@ -0,0 +1,23 @@
+//! Safe entry points for base field arithmetic.
+//!
+//! Everything here detects CPU support at runtime and falls back to the scalar
+//! implementations in [`crate::form::math::base`], so callers never need `unsafe`.
+//! [`generic`] holds the reduction logic over any Goldilocks-shaped prime,
+//! [`backend`] lets callers pin the implementation at runtime, [`executor`] runs the batch
+//! operations on dedicated (optionally pinned) threads, [`verify`] cross-checks the SIMD
+//! kernels against the scalar code, [`tables`] shares the Tip5 constants and NTT twiddles
+//! between threads, and [`bench_all`] compares the backends on this host.
+
+pub mod backend;
+pub mod batch;
+pub mod bench;
+pub mod executor;
+pub mod generic;
+#[cfg(feature = "opencl")]
+pub mod opencl;
+pub mod tables;
+pub mod verify;
+
+pub use bench::{bench_all, BackendBenchReport};
+pub use executor::{ExecutorError, FieldExecutor};
+pub use tables::PrecomputedTables;
@@ -0,0 +1,548 @@
+//! OpenCL offload for batch base field multiplication.
+//!
+//! Talks to the system OpenCL ICD loader directly, so building with the `opencl` feature
+//! only needs `libOpenCL` at link time. The first GPU (or any OpenCL device, if there is
+//! no GPU) is set up lazily on first use; when none is available every call falls back
+//! to [`super::batch::mul`].
+
+use std::ffi::{c_char, c_void, CString};
+use std::sync::{Mutex, OnceLock};
+use std::{fmt, ptr};
+
+use tracing::{debug, warn};
+
+use super::batch;
+
+/// 64x64 -> 128-bit multiply followed by the same reduction as `base::reduce_159`
+const BMUL_KERNEL_SOURCE: &str = r#"
+#define PRIME 0xFFFFFFFF00000001UL
+
+inline ulong reduce_128(ulong lo, ulong hi) {
+    ulong mid = hi & 0xFFFFFFFFUL;
+    ulong high = hi >> 32;
+
+    ulong low2 = lo - high;
+    if (lo < high) {
+        low2 += PRIME;
+    }
+
+    ulong product = (mid << 32) - mid;
+    ulong result = product + low2;
+    if (result < product) {
+        result -= PRIME;
+    }
+    if (result >= PRIME) {
+        result -= PRIME;
+    }
+    return result;
+}
+
+__kernel void bmul_batch(__global const ulong* a,
+                         __global const ulong* b,
+                         __global ulong* out,
+                         const ulong n) {
+    size_t i = get_global_id(0);
+    if (i >= n) {
+        return;
+    }
+    ulong x = a[i];
+    ulong y = b[i];
+    out[i] = reduce_128(x * y, mul_hi(x, y));
+}
+"#;
+
+const BMUL_KERNEL_NAME: &str = "bmul_batch";
+
+// Minimal OpenCL 1.2 bindings, just what the batch multiply needs
+#[allow(non_camel_case_types)]
+mod ffi {
+    use std::ffi::{c_char, c_void};
+
+    pub type cl_int = i32;
+    pub type cl_uint = u32;
+    pub type cl_ulong = u64;
+    pub type cl_bitfield = cl_ulong;
+    pub type cl_device_type = cl_bitfield;
+    pub type cl_mem_flags = cl_bitfield;
+    pub type cl_command_queue_properties = cl_bitfield;
+    pub type cl_program_build_info = cl_uint;
+    pub type cl_platform_id = *mut c_void;
+    pub type cl_device_id = *mut c_void;
+    pub type cl_context = *mut c_void;
+    pub type cl_command_queue = *mut c_void;
+    pub type cl_program = *mut c_void;
+    pub type cl_kernel = *mut c_void;
+    pub type cl_mem = *mut c_void;
+    pub type cl_event = *mut c_void;
+    pub type cl_bool = cl_uint;
+
+    pub const CL_SUCCESS: cl_int = 0;
+    pub const CL_DEVICE_NOT_FOUND: cl_int = -1;
+    pub const CL_BUILD_PROGRAM_FAILURE: cl_int = -11;
+    pub const CL_DEVICE_TYPE_GPU: cl_device_type = 1 << 2;
+    pub const CL_DEVICE_TYPE_ALL: cl_device_type = 0xFFFF_FFFF;
+    pub const CL_MEM_READ_ONLY: cl_mem_flags = 1 << 2;
+    pub const CL_MEM_WRITE_ONLY: cl_mem_flags = 1 << 1;
+    pub const CL_MEM_COPY_HOST_PTR: cl_mem_flags = 1 << 5;
+    pub const CL_PROGRAM_BUILD_LOG: cl_program_build_info = 0x1183;
+    pub const CL_TRUE: cl_bool = 1;
+
+    #[link(name = "OpenCL")]
+    extern "C" {
+        pub fn clGetPlatformIDs(
+            num_entries: cl_uint,
+            platforms: *mut cl_platform_id,
+            num_platforms: *mut cl_uint,
+        ) -> cl_int;
+        pub fn clGetDeviceIDs(
+            platform: cl_platform_id,
+            device_type: cl_device_type,
+            num_entries: cl_uint,
+            devices: *mut cl_device_id,
+            num_devices: *mut cl_uint,
+        ) -> cl_int;
+        pub fn clCreateContext(
+            properties: *const isize,
+            num_devices: cl_uint,
+            devices: *const cl_device_id,
+            pfn_notify: *const c_void,
+            user_data: *mut c_void,
+            errcode_ret: *mut cl_int,
+        ) -> cl_context;
+        pub fn clCreateCommandQueue(
+            context: cl_context,
+            device: cl_device_id,
+            properties: cl_command_queue_properties,
+            errcode_ret: *mut cl_int,
+        ) -> cl_command_queue;
+        pub fn clCreateProgramWithSource(
+            context: cl_context,
+            count: cl_uint,
+            strings: *const *const c_char,
+            lengths: *const usize,
+            errcode_ret: *mut cl_int,
+        ) -> cl_program;
+        pub fn clBuildProgram(
+            program: cl_program,
+            num_devices: cl_uint,
+            device_list: *const cl_device_id,
+            options: *const c_char,
+            pfn_notify: *const c_void,
+            user_data: *mut c_void,
+        ) -> cl_int;
+        pub fn clGetProgramBuildInfo(
+            program: cl_program,
+            device: cl_device_id,
+            param_name: cl_program_build_info,
+            param_value_size: usize,
+            param_value: *mut c_void,
+            param_value_size_ret: *mut usize,
+        ) -> cl_int;
+        pub fn clCreateKernel(
+            program: cl_program,
+            kernel_name: *const c_char,
+            errcode_ret: *mut cl_int,
+        ) -> cl_kernel;
+        pub fn clCreateBuffer(
+            context: cl_context,
+            flags: cl_mem_flags,
+            size: usize,
+            host_ptr: *mut c_void,
+            errcode_ret: *mut cl_int,
+        ) -> cl_mem;
+        pub fn clSetKernelArg(
+            kernel: cl_kernel,
+            arg_index: cl_uint,
+            arg_size: usize,
+            arg_value: *const c_void,
+        ) -> cl_int;
+        pub fn clEnqueueNDRangeKernel(
+            command_queue: cl_command_queue,
+            kernel: cl_kernel,
+            work_dim: cl_uint,
+            global_work_offset: *const usize,
+            global_work_size: *const usize,
+            local_work_size: *const usize,
+            num_events_in_wait_list: cl_uint,
+            event_wait_list: *const cl_event,
+            event: *mut cl_event,
+        ) -> cl_int;
+        pub fn clEnqueueReadBuffer(
+            command_queue: cl_command_queue,
+            buffer: cl_mem,
+            blocking_read: cl_bool,
+            offset: usize,
+            size: usize,
+            ptr: *mut c_void,
+            num_events_in_wait_list: cl_uint,
+            event_wait_list: *const cl_event,
+            event: *mut cl_event,
+        ) -> cl_int;
+        pub fn clReleaseMemObject(memobj: cl_mem) -> cl_int;
+        pub fn clReleaseKernel(kernel: cl_kernel) -> cl_int;
+        pub fn clReleaseProgram(program: cl_program) -> cl_int;
+        pub fn clReleaseCommandQueue(command_queue: cl_command_queue) -> cl_int;
+        pub fn clReleaseContext(context: cl_context) -> cl_int;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenClError {
+    /// No OpenCL platform exposes a usable device
+    NoDevice,
+    /// An OpenCL call returned an error code
+    Api { call: &'static str, code: i32 },
+    /// The kernel failed to compile; carries the build log
+    Build(String),
+}
+
+impl fmt::Display for OpenClError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenClError::NoDevice => write!(f, "no OpenCL device available"),
+            OpenClError::Api { call, code } => write!(f, "{call} failed with OpenCL error {code}"),
+            OpenClError::Build(log) => write!(f, "OpenCL kernel build failed: {log}"),
+        }
+    }
+}
+
+impl std::error::Error for OpenClError {}
+
+fn check(call: &'static str, code: ffi::cl_int) -> Result<(), OpenClError> {
+    if code == ffi::CL_SUCCESS {
+        Ok(())
+    } else {
+        Err(OpenClError::Api { call, code })
+    }
+}
+
+/// Device buffer released on drop
+struct Buffer(ffi::cl_mem);
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        unsafe { ffi::clReleaseMemObject(self.0) };
+    }
+}
+
+/// A device with the batch multiply kernel compiled and ready to run
+pub struct OpenClContext {
+    context: ffi::cl_context,
+    queue: ffi::cl_command_queue,
+    program: ffi::cl_program,
+    kernel: ffi::cl_kernel,
+}
+
+// SAFETY: OpenCL objects may be used from any thread. The kernel's arguments are
+// per-object state, which is why callers share an `OpenClContext` behind a mutex.
+unsafe impl Send for OpenClContext {}
+
+impl OpenClContext {
+    /// Pick the first GPU (or any device if there is no GPU) and build the kernel
+    pub fn new() -> Result<Self, OpenClError> {
+        let device = Self::first_device()?;
+        unsafe {
+            let mut err = 0;
+            let context = ffi::clCreateContext(
+                ptr::null(),
+                1,
+                &device,
+                ptr::null(),
+                ptr::null_mut(),
+                &mut err,
+            );
+            check("clCreateContext", err)?;
+            // From here on, `Drop` releases whatever has been created
+            let mut ctx = Self {
+                context,
+                queue: ptr::null_mut(),
+                program: ptr::null_mut(),
+                kernel: ptr::null_mut(),
+            };
+
+            ctx.queue = ffi::clCreateCommandQueue(context, device, 0, &mut err);
+            check("clCreateCommandQueue", err)?;
+
+            let source = BMUL_KERNEL_SOURCE.as_ptr() as *const c_char;
+            let length = BMUL_KERNEL_SOURCE.len();
+            ctx.program = ffi::clCreateProgramWithSource(context, 1, &source, &length, &mut err);
+            check("clCreateProgramWithSource", err)?;
+
+            let code = ffi::clBuildProgram(
+                ctx.program,
+                1,
+                &device,
+                ptr::null(),
+                ptr::null(),
+                ptr::null_mut(),
+            );
+            if code == ffi::CL_BUILD_PROGRAM_FAILURE {
+                return Err(OpenClError::Build(Self::build_log(ctx.program, device)));
+            }
+            check("clBuildProgram", code)?;
+
+            let name = CString::new(BMUL_KERNEL_NAME).expect("kernel name has no NUL bytes");
+            ctx.kernel = ffi::clCreateKernel(ctx.program, name.as_ptr(), &mut err);
+            check("clCreateKernel", err)?;
+
+            Ok(ctx)
+        }
+    }
+
+    fn first_device() -> Result<ffi::cl_device_id, OpenClError> {
+        unsafe {
+            let mut num_platforms = 0;
+            if ffi::clGetPlatformIDs(0, ptr::null_mut(), &mut num_platforms) != ffi::CL_SUCCESS
+                || num_platforms == 0
+            {
+                return Err(OpenClError::NoDevice);
+            }
+            let mut platforms = vec![ptr::null_mut(); num_platforms as usize];
+            check(
+                "clGetPlatformIDs",
+                ffi::clGetPlatformIDs(num_platforms, platforms.as_mut_ptr(), ptr::null_mut()),
+            )?;
+
+            for device_type in [ffi::CL_DEVICE_TYPE_GPU, ffi::CL_DEVICE_TYPE_ALL] {
+                for &platform in &platforms {
+                    let mut device = ptr::null_mut();
+                    let code =
+                        ffi::clGetDeviceIDs(platform, device_type, 1, &mut device, ptr::null_mut());
+                    match code {
+                        ffi::CL_SUCCESS => return Ok(device),
+                        ffi::CL_DEVICE_NOT_FOUND => continue,
+                        code => {
+                            return Err(OpenClError::Api {
+                                call: "clGetDeviceIDs",
+                                code,
+                            })
+                        }
+                    }
+                }
+            }
+            Err(OpenClError::NoDevice)
+        }
+    }
+
+    fn build_log(program: ffi::cl_program, device: ffi::cl_device_id) -> String {
+        unsafe {
+            let mut size = 0;
+            ffi::clGetProgramBuildInfo(
+                program,
+                device,
+                ffi::CL_PROGRAM_BUILD_LOG,
+                0,
+                ptr::null_mut(),
+                &mut size,
+            );
+            let mut log = vec![0u8; size];
+            ffi::clGetProgramBuildInfo(
+                program,
+                device,
+                ffi::CL_PROGRAM_BUILD_LOG,
+                size,
+                log.as_mut_ptr() as *mut c_void,
+                ptr::null_mut(),
+            );
+            String::from_utf8_lossy(&log)
+                .trim_end_matches('\0')
+                .to_string()
+        }
+    }
+
+    fn input_buffer(&self, data: &[u64]) -> Result<Buffer, OpenClError> {
+        let mut err = 0;
+        let mem = unsafe {
+            ffi::clCreateBuffer(
+                self.context,
+                ffi::CL_MEM_READ_ONLY | ffi::CL_MEM_COPY_HOST_PTR,
+                std::mem::size_of_val(data),
+                data.as_ptr() as *mut c_void,
+                &mut err,
+            )
+        };
+        check("clCreateBuffer", err)?;
+        Ok(Buffer(mem))
+    }
+
+    /// Element-wise field multiplication on the device.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` and `b` have different lengths.
+    pub fn bmul(&mut self, a: &[u64], b: &[u64]) -> Result<Vec<u64>, OpenClError> {
+        assert_eq!(a.len(), b.len(), "batch operands must have the same length");
+        let mut result = vec![0u64; a.len()];
+        if a.is_empty() {
+            return Ok(result);
+        }
+
+        let a_buf = self.input_buffer(a)?;
+        let b_buf = self.input_buffer(b)?;
+        let mut err = 0;
+        let out_buf = Buffer(unsafe {
+            ffi::clCreateBuffer(
+                self.context,
+                ffi::CL_MEM_WRITE_ONLY,
+                std::mem::size_of_val(result.as_slice()),
+                ptr::null_mut(),
+                &mut err,
+            )
+        });
+        check("clCreateBuffer", err)?;
+
+        let n = a.len() as ffi::cl_ulong;
+        let global_size = a.len();
+        unsafe {
+            let mem_size = std::mem::size_of::<ffi::cl_mem>();
+            let args: [(usize, *const c_void); 4] = [
+                (mem_size, &a_buf.0 as *const _ as *const c_void),
+                (mem_size, &b_buf.0 as *const _ as *const c_void),
+                (mem_size, &out_buf.0 as *const _ as *const c_void),
+                (
+                    std::mem::size_of::<ffi::cl_ulong>(),
+                    &n as *const _ as *const c_void,
+                ),
+            ];
+            for (index, (size, value)) in args.into_iter().enumerate() {
+                check(
+                    "clSetKernelArg",
+                    ffi::clSetKernelArg(self.kernel, index as u32, size, value),
+                )?;
+            }
+
+            check(
+                "clEnqueueNDRangeKernel",
+                ffi::clEnqueueNDRangeKernel(
+                    self.queue,
+                    self.kernel,
+                    1,
+                    ptr::null(),
+                    &global_size,
+                    ptr::null(),
+                    0,
+                    ptr::null(),
+                    ptr::null_mut(),
+                ),
+            )?;
+            check(
+                "clEnqueueReadBuffer",
+                ffi::clEnqueueReadBuffer(
+                    self.queue,
+                    out_buf.0,
+                    ffi::CL_TRUE,
+                    0,
+                    std::mem::size_of_val(result.as_slice()),
+                    result.as_mut_ptr() as *mut c_void,
+                    0,
+                    ptr::null(),
+                    ptr::null_mut(),
+                ),
+            )?;
+        }
+        Ok(result)
+    }
+}
+
+impl Drop for OpenClContext {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.kernel.is_null() {
+                ffi::clReleaseKernel(self.kernel);
+            }
+            if !self.program.is_null() {
+                ffi::clReleaseProgram(self.program);
+            }
+            if !self.queue.is_null() {
+                ffi::clReleaseCommandQueue(self.queue);
+            }
+            ffi::clReleaseContext(self.context);
+        }
+    }
+}
+
+/// Process-wide device, set up on first use; `None` when OpenCL is unavailable
+fn shared_context() -> Option<&'static Mutex<OpenClContext>> {
+    static CONTEXT: OnceLock<Option<Mutex<OpenClContext>>> = OnceLock::new();
+    CONTEXT
+        .get_or_init(|| match OpenClContext::new() {
+            Ok(ctx) => {
+                debug!("OpenCL field multiply backend initialized");
+                Some(Mutex::new(ctx))
+            }
+            Err(e) => {
+                debug!("OpenCL unavailable, batch multiply stays on the CPU: {e}");
+                None
+            }
+        })
+        .as_ref()
+}
+
+/// Whether batch multiplies will actually run on an OpenCL device
+pub fn opencl_available() -> bool {
+    shared_context().is_some()
+}
+
+/// Element-wise field multiplication, offloaded to OpenCL when a device is present.
+///
+/// Falls back to [`batch::mul`] when there is no device, the device call fails, or a
+/// CPU backend was pinned with [`super::backend::set_backend`].
+///
+/// # Panics
+///
+/// Panics if `a` and `b` have different lengths.
+pub fn bmul_batch_opencl(a: &[u64], b: &[u64]) -> Vec<u64> {
+    if let Some(ctx) = super::backend::use_opencl().then(shared_context).flatten() {
+        let mut ctx = ctx.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match ctx.bmul(a, b) {
+            Ok(result) => return result,
+            Err(e) => warn!("OpenCL batch multiply failed, falling back to CPU: {e}"),
+        }
+    }
+    batch::mul(a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::form::math::base::{bmul, PRIME};
+
+    fn sample(len: usize, seed: u64) -> Vec<u64> {
+        let mut x = seed;
+        (0..len)
+            .map(|_| {
+                x = x
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                x % PRIME
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bmul_batch_opencl_matches_scalar() {
+        for len in [0, 1, 7, 1000, 65_537, 1 << 20] {
+            let a = sample(len, 5);
+            let b = sample(len, 6);
+            let result = bmul_batch_opencl(&a, &b);
+            assert_eq!(result.len(), len);
+            for i in 0..len {
+                assert_eq!(result[i], bmul(a[i], b[i]), "len {len}, index {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_bmul_batch_opencl_edges() {
+        let edges = [0, 1, 0xFFFF_FFFF, 1 << 32, 1 << 63, PRIME - (1 << 32), PRIME - 1];
+        let a: Vec<u64> = edges
+            .iter()
+            .flat_map(|&x| edges.iter().map(move |_| x))
+            .collect();
+        let b: Vec<u64> = edges.iter().flat_map(|_| edges.iter().copied()).collect();
+        let result = bmul_batch_opencl(&a, &b);
+        for i in 0..a.len() {
+            assert_eq!(result[i], bmul(a[i], b[i]));
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Constants the hash and NTT kernels read on every call, computed once per process.
+//!
+//! Tip5's round constants and MDS matrix, and the twiddle factors of an NTT, are the same
+//! for every worker. [`PrecomputedTables`] holds them in one immutable block, each table
+//! starting on a cache line so SIMD kernels can use aligned vector loads, and [`shared`]
+//! builds it on first use and hands every caller the same [`Arc`]. A miner with hundreds
+//! of workers keeps one copy instead of one per thread, and pays for building it once.
+//!
+//! The round constants are stored in the Montgomery form [`tip5::permute`] adds them in.
+//! One twiddle table serves every NTT size up to its own: the stage that combines halves
+//! of length `m` reads [`PrecomputedTables::twiddles`]`(m)`, the powers of a primitive
+//! `2m`-th root of unity, which do not depend on the size of the whole transform.
+
+use std::sync::{Arc, OnceLock};
+
+use crate::form::math::base::{badd, bmul, FieldError, PRIME_128};
+use crate::form::math::tip5::{self, MDS_MATRIX_I64, NUM_ROUNDS, R, ROUND_CONSTANTS, STATE_SIZE};
+use crate::form::poly::Belt;
+
+/// [`shared`] has twiddles for NTTs of up to 2^20 elements, which take 8 MiB
+pub const TWIDDLE_LOG_SIZE: u32 = 20;
+
+/// Largest twiddle table [`PrecomputedTables::new`] can build: the field has roots of
+/// unity of order up to 2^32
+pub const MAX_TWIDDLE_LOG_SIZE: u32 = 32;
+
+/// Field elements per cache line, and per AVX-512 vector
+const LINE_WORDS: usize = 8;
+
+/// One cache line of field elements
+#[derive(Clone, Copy)]
+#[repr(C, align(64))]
+struct Line([u64; LINE_WORDS]);
+
+/// A table of `len` field elements starting on a cache line
+struct Table(Vec<Line>);
+
+impl Table {
+    fn new(len: usize) -> Self {
+        Table(vec![Line([0; LINE_WORDS]); len.div_ceil(LINE_WORDS)])
+    }
+
+    fn words(&self) -> &[u64] {
+        // SAFETY: `Line` is a `repr(C)` wrapper of `[u64; LINE_WORDS]` whose alignment
+        // equals its size, so the lines are that many contiguous, initialized u64s
+        unsafe { std::slice::from_raw_parts(self.0.as_ptr().cast(), self.0.len() * LINE_WORDS) }
+    }
+
+    fn words_mut(&mut self) -> &mut [u64] {
+        // SAFETY: as in `words`, and the slice borrows the table mutably
+        unsafe {
+            std::slice::from_raw_parts_mut(self.0.as_mut_ptr().cast(), self.0.len() * LINE_WORDS)
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        std::mem::size_of_val(self.0.as_slice())
+    }
+}
+
+/// Tip5 constants and NTT twiddles, shared read-only by every worker
+pub struct PrecomputedTables {
+    /// Round-major, each round's constants on their own two cache lines
+    round_constants: Table,
+    /// Row-major, each row on its own two cache lines
+    mds: Table,
+    /// `twiddles[m + j]` is the `j`th power of a primitive `2m`-th root of unity, for
+    /// each power of two `m` below `1 << twiddle_log_size`
+    twiddles: Table,
+    twiddle_log_size: u32,
+}
+
+impl PrecomputedTables {
+    /// Tables with twiddles for NTTs of up to `2^twiddle_log_size` elements.
+    ///
+    /// # Panics
+    ///
+    /// If `twiddle_log_size` is above [`MAX_TWIDDLE_LOG_SIZE`].
+    pub fn new(twiddle_log_size: u32) -> Self {
+        assert!(
+            twiddle_log_size <= MAX_TWIDDLE_LOG_SIZE,
+            "no roots of unity of order 2^{twiddle_log_size}"
+        );
+        let mut round_constants = Table::new(NUM_ROUNDS * STATE_SIZE);
+        for (constant, &raw) in round_constants.words_mut().iter_mut().zip(&ROUND_CONSTANTS) {
+            *constant = ((raw as u128 * R) % PRIME_128) as u64;
+        }
+
+        let mut mds = Table::new(STATE_SIZE * STATE_SIZE);
+        for (element, &raw) in mds
+            .words_mut()
+            .iter_mut()
+            .zip(MDS_MATRIX_I64.iter().flatten())
+        {
+            *element = raw as u64;
+        }
+
+        let mut twiddles = Table::new(1 << twiddle_log_size);
+        let words = twiddles.words_mut();
+        for log_m in 0..twiddle_log_size {
+            let m = 1usize << log_m;
+            let root = Belt(2 << log_m)
+                .ordered_root()
+                .expect("orders up to 2^32 have roots")
+                .0;
+            let mut power = 1;
+            for twiddle in &mut words[m..2 * m] {
+                *twiddle = power;
+                power = bmul(power, root);
+            }
+        }
+
+        Self {
+            round_constants,
+            mds,
+            twiddles,
+            twiddle_log_size,
+        }
+    }
+
+    /// The constants added in `round`, in Montgomery form
+    pub fn round_constants(&self, round: usize) -> &[u64; STATE_SIZE] {
+        self.round_constants.words()[round * STATE_SIZE..][..STATE_SIZE]
+            .try_into()
+            .expect("rows are STATE_SIZE long")
+    }
+
+    pub fn mds_row(&self, row: usize) -> &[u64; STATE_SIZE] {
+        self.mds.words()[row * STATE_SIZE..][..STATE_SIZE]
+            .try_into()
+            .expect("rows are STATE_SIZE long")
+    }
+
+    /// The twiddles of the NTT stage combining halves of length `m`: the first `m` powers
+    /// of a primitive `2m`-th root of unity. From `m = 8` on they start on a cache line.
+    ///
+    /// # Panics
+    ///
+    /// If `m` is not a power of two below [`Self::max_ntt_len`].
+    pub fn twiddles(&self, m: usize) -> &[u64] {
+        assert!(
+            m.is_power_of_two() && m < self.max_ntt_len(),
+            "no twiddles for a stage of {m}"
+        );
+        &self.twiddles.words()[m..2 * m]
+    }
+
+    /// Longest NTT the twiddles cover
+    pub fn max_ntt_len(&self) -> usize {
+        1 << self.twiddle_log_size
+    }
+
+    /// Memory the tables take
+    pub fn bytes(&self) -> usize {
+        self.round_constants.bytes() + self.mds.bytes() + self.twiddles.bytes()
+    }
+
+    /// [`tip5::permute`] reading the constants from these tables
+    pub fn permute(&self, sponge: &mut [u64; STATE_SIZE]) {
+        for round in 0..NUM_ROUNDS {
+            let a = tip5::sbox_layer(sponge);
+            let constants = self.round_constants(round);
+            for (i, element) in sponge.iter_mut().enumerate() {
+                let row = self.mds_row(i);
+                let mixed = row
+                    .iter()
+                    .zip(&a)
+                    .fold(0, |acc, (&m, &x)| badd(acc, bmul(m, x)));
+                *element = badd(constants[i], mixed);
+            }
+        }
+    }
+
+    /// [`crate::form::math::bpoly::bp_fft`] reading the twiddles from these tables.
+    /// Fails like it for lengths that are not a power of two, and for those above
+    /// [`Self::max_ntt_len`].
+    pub fn ntt(&self, bp: &[Belt]) -> Result<Vec<Belt>, FieldError> {
+        let n = bp.len();
+        if n <= 1 {
+            return Ok(bp.to_vec());
+        }
+        if !n.is_power_of_two() || n > self.max_ntt_len() {
+            return Err(FieldError::OrderedRootError);
+        }
+        let log_n = n.ilog2();
+        let mut x = bp.to_vec();
+        for k in 0..n {
+            let rk = k.reverse_bits() >> (usize::BITS - log_n);
+            if k < rk {
+                x.swap(k, rk);
+            }
+        }
+
+        let mut m = 1;
+        while m < n {
+            let twiddles = self.twiddles(m);
+            for k in (0..n).step_by(2 * m) {
+                for (j, &w) in twiddles.iter().enumerate() {
+                    let u = x[k + j];
+                    let v = x[k + j + m] * Belt(w);
+                    x[k + j] = u + v;
+                    x[k + j + m] = u - v;
+                }
+            }
+            m *= 2;
+        }
+        Ok(x)
+    }
+}
+
+static SHARED: OnceLock<Arc<PrecomputedTables>> = OnceLock::new();
+
+/// The process's tables, with twiddles up to 2^[`TWIDDLE_LOG_SIZE`], built by the first
+/// caller. Call it once at startup so no worker pays for building them.
+pub fn shared() -> Arc<PrecomputedTables> {
+    SHARED
+        .get_or_init(|| Arc::new(PrecomputedTables::new(TWIDDLE_LOG_SIZE)))
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::form::math::base::PRIME;
+    use crate::form::math::bpoly::bp_fft;
+
+    #[test]
+    fn test_permute_matches_tip5() {
+        let tables = PrecomputedTables::new(4);
+        let mut expected: [u64; STATE_SIZE] =
+            std::array::from_fn(|i| (i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) % PRIME);
+        let mut sponge = expected;
+        tip5::permute(&mut expected);
+        tables.permute(&mut sponge);
+        assert_eq!(sponge, expected);
+    }
+
+    #[test]
+    fn test_ntt_matches_bp_fft() {
+        let tables = PrecomputedTables::new(8);
+        for log_n in 0..=8 {
+            let bp: Vec<Belt> = (0..1u64 << log_n)
+                .map(|i| Belt((i * 0x1234_5678_9abc + 7) % PRIME))
+                .collect();
+            assert_eq!(tables.ntt(&bp).unwrap(), bp_fft(&bp).unwrap(), "2^{log_n}");
+        }
+        assert!(tables.ntt(&[Belt(1); 512]).is_err());
+        assert!(tables.ntt(&[Belt(1); 12]).is_err());
+    }
+
+    #[test]
+    fn test_tables_are_line_aligned_and_shared() {
+        let tables = shared();
+        assert!(Arc::ptr_eq(&tables, &shared()));
+        assert_eq!(tables.max_ntt_len(), 1 << TWIDDLE_LOG_SIZE);
+        assert_eq!(tables.round_constants(0).as_ptr() as usize % 64, 0);
+        assert_eq!(tables.mds_row(1).as_ptr() as usize % 64, 0);
+        assert_eq!(tables.twiddles(8).as_ptr() as usize % 64, 0);
+        assert_eq!(tables.twiddles(1), &[1]);
+        assert!(tables.bytes() >= 8 << TWIDDLE_LOG_SIZE);
+    }
+}
@@ -0,0 +1,232 @@
+//! Cross-checking the SIMD kernels against the scalar reference.
+//!
+//! A miscompiled kernel or a marginal vector unit gives wrong field elements without any
+//! other symptom. With [`set_verify_mode`] on, every [`super::batch`] operation recomputes
+//! the elements its SIMD kernels produced with the scalar code in
+//! [`crate::form::math::base`] and compares them. That roughly doubles the cost of the
+//! batch operations, so it is off by default and meant for burn-in and high-assurance
+//! runs; while it is off the only cost is one relaxed load per call.
+//!
+//! Elements the batch operations compute with the scalar code anyway (the tail after the
+//! last full vector, or everything under [`super::backend::FieldBackend::Scalar`]) are not
+//! checked again.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::Mutex;
+
+use tracing::error;
+
+/// What the batch operations do about their SIMD results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VerifyMode {
+    /// Trust the kernels
+    #[default]
+    Off,
+    /// Log and count each divergence, and return the scalar result in its place
+    Report,
+    /// Panic on the first divergence
+    Panic,
+}
+
+impl fmt::Display for VerifyMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyMode::Off => write!(f, "off"),
+            VerifyMode::Report => write!(f, "report"),
+            VerifyMode::Panic => write!(f, "panic"),
+        }
+    }
+}
+
+/// A SIMD result that disagreed with the scalar reference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// The batch operation, e.g. `"mul"`
+    pub op: &'static str,
+    /// Position of the element in the batch
+    pub index: usize,
+    pub simd: u64,
+    pub scalar: u64,
+    /// CPU the check ran on, to tell a faulty core from a faulty build
+    pub cpu: Option<usize>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "field {} diverged from the scalar reference at element {}: simd {:#x}, scalar {:#x}",
+            self.op, self.index, self.simd, self.scalar
+        )?;
+        match self.cpu {
+            Some(cpu) => write!(f, " on CPU {cpu}"),
+            None => Ok(()),
+        }
+    }
+}
+
+static MODE: AtomicU8 = AtomicU8::new(0);
+static DIVERGENCES: AtomicU64 = AtomicU64::new(0);
+static LAST_DIVERGENCE: Mutex<Option<Divergence>> = Mutex::new(None);
+
+/// Check the batch operations' SIMD results for the rest of the process
+pub fn set_verify_mode(mode: VerifyMode) {
+    let tag = match mode {
+        VerifyMode::Off => 0,
+        VerifyMode::Report => 1,
+        VerifyMode::Panic => 2,
+    };
+    MODE.store(tag, Ordering::Relaxed);
+}
+
+pub fn verify_mode() -> VerifyMode {
+    match MODE.load(Ordering::Relaxed) {
+        1 => VerifyMode::Report,
+        2 => VerifyMode::Panic,
+        _ => VerifyMode::Off,
+    }
+}
+
+/// Divergences seen in [`VerifyMode::Report`] since the process started
+pub fn divergences() -> u64 {
+    DIVERGENCES.load(Ordering::Relaxed)
+}
+
+/// The most recent divergence seen in [`VerifyMode::Report`]
+pub fn last_divergence() -> Option<Divergence> {
+    LAST_DIVERGENCE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Compare `simd`, the first elements of a batch `op`'s result, with `scalar(i)` for each
+/// index, handling any divergence as the current mode says
+pub(crate) fn check(op: &'static str, simd: &mut [u64], scalar: impl Fn(usize) -> u64) {
+    let mode = verify_mode();
+    if mode == VerifyMode::Off {
+        return;
+    }
+    for (index, value) in simd.iter_mut().enumerate() {
+        let expected = scalar(index);
+        if *value == expected {
+            continue;
+        }
+        let divergence = Divergence {
+            op,
+            index,
+            simd: *value,
+            scalar: expected,
+            cpu: current_cpu(),
+        };
+        if mode == VerifyMode::Panic {
+            panic!("{divergence}");
+        }
+        error!("{divergence}");
+        DIVERGENCES.fetch_add(1, Ordering::Relaxed);
+        *value = expected;
+        *LAST_DIVERGENCE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(divergence);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn current_cpu() -> Option<usize> {
+    // SAFETY: sched_getcpu has no preconditions
+    let cpu = unsafe { libc::sched_getcpu() };
+    usize::try_from(cpu).ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn current_cpu() -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::form::math::base::{bmul, PRIME};
+
+    /// Held by tests that change the process-wide mode
+    static MODE_LOCK: Mutex<()> = Mutex::new(());
+
+    fn faulty_batch() -> (Vec<u64>, Vec<u64>) {
+        let a: Vec<u64> = (0..16).map(|i| (i * 0x1234_5678_9abc) % PRIME).collect();
+        let mut result: Vec<u64> = a.iter().map(|&x| bmul(x, x)).collect();
+        result[5] ^= 1 << 17;
+        (a, result)
+    }
+
+    #[test]
+    fn test_report_mode_counts_and_repairs() {
+        let _guard = MODE_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (a, mut result) = faulty_batch();
+        let before = divergences();
+
+        set_verify_mode(VerifyMode::Off);
+        check("square", &mut result, |i| bmul(a[i], a[i]));
+        assert_eq!(divergences(), before);
+
+        set_verify_mode(VerifyMode::Report);
+        let faulty = result[5];
+        check("square", &mut result, |i| bmul(a[i], a[i]));
+        set_verify_mode(VerifyMode::Off);
+        assert_eq!(divergences(), before + 1);
+        assert_eq!(result[5], bmul(a[5], a[5]));
+        let divergence = last_divergence().unwrap();
+        assert_eq!((divergence.op, divergence.index), ("square", 5));
+        assert_eq!(divergence.simd, faulty);
+        assert_eq!(divergence.scalar, result[5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "diverged from the scalar reference at element 5")]
+    fn test_panic_mode_panics() {
+        let _guard = MODE_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (a, mut result) = faulty_batch();
+        set_verify_mode(VerifyMode::Panic);
+        // Turned off again before the lock is released, even though the check panics
+        struct Reset;
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                set_verify_mode(VerifyMode::Off);
+            }
+        }
+        let _reset = Reset;
+        check("square", &mut result, |i| bmul(a[i], a[i]));
+    }
+
+    #[test]
+    fn test_batch_operations_pass_verification() {
+        use crate::field::batch;
+        use crate::form::math::base_optimized::BatchProcessor;
+
+        let _guard = MODE_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        set_verify_mode(VerifyMode::Panic);
+        let a: Vec<u64> = (0..67).map(|i| (i * 0x9e37_79b9_7f4a) % PRIME).collect();
+        let b: Vec<u64> = a.iter().rev().copied().collect();
+        let products: Vec<u128> = a
+            .iter()
+            .zip(&b)
+            .map(|(&x, &y)| x as u128 * y as u128)
+            .collect();
+        let mut reduced = vec![0u64; a.len()];
+        batch::add(&a, &b);
+        batch::mul(&a, &b);
+        batch::square(&a);
+        batch::reduce_128(&products, &mut reduced);
+        // Spread over several chunks, the last with a scalar tail
+        let mut processor = BatchProcessor::new(a.len()).with_chunk_size(16);
+        processor.process_batch_add(&a, &b);
+        processor.process_batch_mul(&a, &b);
+        set_verify_mode(VerifyMode::Off);
+    }
+}
@@ -9,6 +9,8 @@ pub const ORDER: u64 = 2_u64.pow(32);
 #[derive(Debug)]
 pub enum FieldError {
     OrderedRootError,
+    NonCanonical,
+    ByteLength,
 }
 
 pub fn based_check(a: u64) -> bool {
@@ -131,7 +133,98 @@ pub fn binv(a: u64) -> u64 {
     bpow(a, PRIME - 2)
 }
 
+/// Width in bytes of a serialized base field element
+pub const BELT_BYTES: usize = 8;
+
+/// Serialize a base field element as little-endian bytes.
+///
+/// The byte order is fixed regardless of host endianness so serialized field
+/// elements can be moved between architectures.
+#[inline(always)]
+pub fn belt_to_le_bytes(a: u64) -> [u8; BELT_BYTES] {
+    based!(a);
+    a.to_le_bytes()
+}
+
+/// Deserialize a base field element from little-endian bytes, rejecting
+/// values outside the field.
+#[inline(always)]
+pub fn belt_from_le_bytes(bytes: [u8; BELT_BYTES]) -> Result<u64, FieldError> {
+    let a = u64::from_le_bytes(bytes);
+    if based_check(a) {
+        Ok(a)
+    } else {
+        Err(FieldError::NonCanonical)
+    }
+}
+
+/// Serialize a slice of base field elements as concatenated little-endian bytes.
+pub fn belts_to_le_bytes(a: &[u64]) -> Vec<u8> {
+    let mut res = Vec::with_capacity(a.len() * BELT_BYTES);
+    for &x in a {
+        res.extend_from_slice(&belt_to_le_bytes(x));
+    }
+    res
+}
+
+/// Deserialize concatenated little-endian base field elements.
+///
+/// The input length must be a multiple of [`BELT_BYTES`].
+pub fn belts_from_le_bytes(bytes: &[u8]) -> Result<Vec<u64>, FieldError> {
+    if bytes.len() % BELT_BYTES != 0 {
+        return Err(FieldError::ByteLength);
+    }
+    bytes
+        .chunks_exact(BELT_BYTES)
+        .map(|chunk| belt_from_le_bytes(*array_ref![chunk, 0, BELT_BYTES]))
+        .collect()
+}
+
 #[test]
 fn test_binv() {
     assert_eq!(bmul(binv(888), 888), 1);
 }
+
+#[test]
+fn test_belt_le_bytes_layout() {
+    // The serialized form is defined by arithmetic, not by host memory layout,
+    // so this holds on big-endian targets too.
+    let a: u64 = 0x0102_0304_0506_0708;
+    let bytes = belt_to_le_bytes(a);
+    for (i, byte) in bytes.iter().enumerate() {
+        assert_eq!(*byte as u64, (a >> (8 * i)) & 0xff);
+    }
+    assert_eq!(bytes, [0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01]);
+}
+
+#[test]
+fn test_belt_le_bytes_roundtrip() {
+    let belts = [0, 1, 888, PRIME - 1, H, ORDER];
+    let bytes = belts_to_le_bytes(&belts);
+    assert_eq!(bytes.len(), belts.len() * BELT_BYTES);
+    assert_eq!(belts_from_le_bytes(&bytes).unwrap(), belts.to_vec());
+}
+
+#[test]
+fn test_belt_from_le_bytes_rejects() {
+    assert!(matches!(
+        belt_from_le_bytes(PRIME.to_le_bytes()),
+        Err(FieldError::NonCanonical)
+    ));
+    assert!(matches!(
+        belt_from_le_bytes([0xff; BELT_BYTES]),
+        Err(FieldError::NonCanonical)
+    ));
+    assert!(matches!(
+        belts_from_le_bytes(&[0u8; 7]),
+        Err(FieldError::ByteLength)
+    ));
+}
+
+#[cfg(target_endian = "big")]
+#[test]
+fn test_belt_le_bytes_big_endian_host() {
+    let a: u64 = 0x0102_0304_0506_0708;
+    assert_ne!(belt_to_le_bytes(a), a.to_ne_bytes());
+    assert_eq!(belt_from_le_bytes(belt_to_le_bytes(a)).unwrap(), a);
+}
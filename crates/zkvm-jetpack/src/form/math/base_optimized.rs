@@ -1,231 +1,741 @@
 // Optimized base field arithmetic for AMD EPYC 9654
 // Utilizes AVX-512 instructions and EPYC-specific optimizations
 
-use crate::form::math::base::{PRIME, PRIME_128, PRIME_PRIME};
-
 #[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::*;
 
+use crate::form::math::base::PRIME;
+#[cfg(test)]
+use crate::form::math::base::PRIME_128;
+
 // AVX-512 optimized constants
 const SIMD_WIDTH: usize = 8; // 512-bit / 64-bit = 8 elements
-const CACHE_LINE_SIZE: usize = 64;
+const AVX2_WIDTH: usize = 4; // 256-bit / 64-bit = 4 elements, for tails and the AVX2 backend
+
+// The public kernels below are `#[inline(never)]`: callers such as `field::batch` are
+// compiled without avx512f, and a real call keeps the feature boundary intact under LTO
+// instead of relying on the optimizer to decline inlining across it.
+//
+// Every kernel asserts its slices have equal lengths that are a whole number of vectors,
+// so each load and store of lanes `i..i + width` stays in bounds. The `debug_assert!` at
+// the top of each loop restates that for the lanes about to be touched, so a change to the
+// length checks or the stride trips it in debug builds instead of reading past the slice.
+// `test_kernels_stay_in_bounds` runs every kernel on every valid length with guard words
+// around the output; CI also runs the tests under AddressSanitizer, which catches stray
+// reads too, and under Miri for the AVX2 kernels (Miri doesn't implement AVX-512).
 
 /// Optimized batch field addition using AVX-512
+///
+/// # Safety
+///
+/// The CPU must support AVX-512F. Prefer [`crate::field::batch`], which checks this
+/// at runtime and falls back to scalar code.
 #[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "avx512f")]
+#[inline(never)]
 pub unsafe fn badd_batch_avx512(a: &[u64], b: &[u64], result: &mut [u64]) {
     assert_eq!(a.len(), b.len());
     assert_eq!(a.len(), result.len());
     assert!(a.len() % SIMD_WIDTH == 0);
-    
+
     let prime_vec = _mm512_set1_epi64(PRIME as i64);
-    
+
     for i in (0..a.len()).step_by(SIMD_WIDTH) {
+        debug_assert!(
+            i + SIMD_WIDTH <= a.len(),
+            "lanes {}..{} past the end",
+            i,
+            i + SIMD_WIDTH
+        );
         // Load 8 elements from each array
         let a_vec = _mm512_loadu_epi64(a.as_ptr().add(i) as *const i64);
         let b_vec = _mm512_loadu_epi64(b.as_ptr().add(i) as *const i64);
-        
-        // Perform modular addition
+
+        // Perform modular addition: a + b - PRIME == a - (PRIME - b), adding PRIME back
+        // when a < PRIME - b. For canonical inputs this never leaves [0, PRIME).
         let neg_b = _mm512_sub_epi64(prime_vec, b_vec);
         let diff = _mm512_sub_epi64(a_vec, neg_b);
-        
+
         // Handle overflow correction
         let underflow_mask = _mm512_cmplt_epu64_mask(a_vec, neg_b);
-        let correction = _mm512_mask_set1_epi64(_mm512_setzero_epi64(), underflow_mask, PRIME as i64);
+        let correction =
+            _mm512_mask_set1_epi64(_mm512_setzero_si512(), underflow_mask, PRIME as i64);
         let final_result = _mm512_add_epi64(diff, correction);
-        
+
         // Store result
         _mm512_storeu_epi64(result.as_mut_ptr().add(i) as *mut i64, final_result);
     }
 }
 
 /// Optimized batch field multiplication using AVX-512
+///
+/// # Safety
+///
+/// The CPU must support AVX-512F. Prefer [`crate::field::batch`], which checks this
+/// at runtime and falls back to scalar code.
 #[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "avx512f")]
+#[inline(never)]
 pub unsafe fn bmul_batch_avx512(a: &[u64], b: &[u64], result: &mut [u64]) {
     assert_eq!(a.len(), b.len());
     assert_eq!(a.len(), result.len());
     assert!(a.len() % SIMD_WIDTH == 0);
-    
+
     for i in (0..a.len()).step_by(SIMD_WIDTH) {
+        debug_assert!(
+            i + SIMD_WIDTH <= a.len(),
+            "lanes {}..{} past the end",
+            i,
+            i + SIMD_WIDTH
+        );
         // Load elements
         let a_vec = _mm512_loadu_epi64(a.as_ptr().add(i) as *const i64);
         let b_vec = _mm512_loadu_epi64(b.as_ptr().add(i) as *const i64);
-        
-        // Perform 64x64 -> 128-bit multiplication
-        let prod_lo = _mm512_mullo_epi64(a_vec, b_vec);
-        let prod_hi = _mm512_mulhi_epu64(a_vec, b_vec);
-        
-        // Reduce each 128-bit product modulo PRIME
-        for j in 0..SIMD_WIDTH {
-            let lo = _mm512_extract_epi64(prod_lo, j) as u64;
-            let hi = _mm512_extract_epi64(prod_hi, j) as u64;
-            let product = ((hi as u128) << 64) | (lo as u128);
-            result[i + j] = reduce_128_optimized(product);
+
+        // Perform 64x64 -> 128-bit multiplication and reduce modulo PRIME
+        let (prod_hi, prod_lo) = mul_64x64_avx512(a_vec, b_vec);
+        let reduced = reduce_128_avx512(prod_hi, prod_lo);
+
+        _mm512_storeu_epi64(result.as_mut_ptr().add(i) as *mut i64, reduced);
+    }
+}
+
+/// Optimized batch field squaring using AVX-512
+///
+/// # Safety
+///
+/// The CPU must support AVX-512F. Prefer [`crate::field::batch`], which checks this
+/// at runtime and falls back to scalar code.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+#[inline(never)]
+pub unsafe fn bsquare_batch_avx512(a: &[u64], result: &mut [u64]) {
+    assert_eq!(a.len(), result.len());
+    assert!(a.len() % SIMD_WIDTH == 0);
+
+    for i in (0..a.len()).step_by(SIMD_WIDTH) {
+        debug_assert!(
+            i + SIMD_WIDTH <= a.len(),
+            "lanes {}..{} past the end",
+            i,
+            i + SIMD_WIDTH
+        );
+        let a_vec = _mm512_loadu_epi64(a.as_ptr().add(i) as *const i64);
+        let (sq_hi, sq_lo) = square_64_avx512(a_vec);
+        let reduced = reduce_128_avx512(sq_hi, sq_lo);
+        _mm512_storeu_epi64(result.as_mut_ptr().add(i) as *mut i64, reduced);
+    }
+}
+
+/// Optimized batch reduction of 128-bit values, e.g. products from a widening multiply
+///
+/// Each group of 8 values is split into low and high 64-bit halves in registers and
+/// reduced lane-wise, matching `base::reduce` for every input.
+///
+/// # Safety
+///
+/// The CPU must support AVX-512F. Prefer [`crate::field::batch::reduce_128`], which
+/// checks this at runtime and falls back to scalar code.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+#[inline(never)]
+pub unsafe fn reduce_128_batch_avx512(products: &[u128], result: &mut [u64]) {
+    assert_eq!(products.len(), result.len());
+    assert!(products.len() % SIMD_WIDTH == 0);
+
+    // Little-endian u128s interleave as lo0, hi0, lo1, hi1, ...
+    let lo_index = _mm512_set_epi64(14, 12, 10, 8, 6, 4, 2, 0);
+    let hi_index = _mm512_set_epi64(15, 13, 11, 9, 7, 5, 3, 1);
+
+    for i in (0..products.len()).step_by(SIMD_WIDTH) {
+        debug_assert!(
+            i + SIMD_WIDTH <= products.len(),
+            "lanes {}..{} past the end",
+            i,
+            i + SIMD_WIDTH
+        );
+        let words = products.as_ptr().add(i) as *const i64;
+        let first = _mm512_loadu_epi64(words);
+        let second = _mm512_loadu_epi64(words.add(SIMD_WIDTH));
+
+        let lo = _mm512_permutex2var_epi64(first, lo_index, second);
+        let hi = _mm512_permutex2var_epi64(first, hi_index, second);
+        let reduced = reduce_128_avx512(hi, lo);
+
+        _mm512_storeu_epi64(result.as_mut_ptr().add(i) as *mut i64, reduced);
+    }
+}
+
+/// Full 64x64 -> 128-bit product of each lane, returned as (high, low) halves.
+///
+/// AVX-512F has no 64-bit high multiply, so the product is assembled from four
+/// 32x32 -> 64-bit partial products.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+#[inline]
+unsafe fn mul_64x64_avx512(a: __m512i, b: __m512i) -> (__m512i, __m512i) {
+    let mask_lo32 = _mm512_set1_epi64(0xFFFF_FFFF);
+    let a_hi = _mm512_srli_epi64::<32>(a);
+    let b_hi = _mm512_srli_epi64::<32>(b);
+
+    // _mm512_mul_epu32 multiplies the low 32 bits of each 64-bit lane
+    let ll = _mm512_mul_epu32(a, b);
+    let lh = _mm512_mul_epu32(a, b_hi);
+    let hl = _mm512_mul_epu32(a_hi, b);
+    let hh = _mm512_mul_epu32(a_hi, b_hi);
+
+    // Sum of the middle 32-bit columns; at most 3 * (2^32 - 1), so no overflow
+    let mid = _mm512_add_epi64(
+        _mm512_add_epi64(_mm512_srli_epi64::<32>(ll), _mm512_and_si512(lh, mask_lo32)),
+        _mm512_and_si512(hl, mask_lo32),
+    );
+    let lo = _mm512_or_si512(
+        _mm512_and_si512(ll, mask_lo32),
+        _mm512_slli_epi64::<32>(mid),
+    );
+    let hi = _mm512_add_epi64(
+        _mm512_add_epi64(hh, _mm512_srli_epi64::<32>(mid)),
+        _mm512_add_epi64(_mm512_srli_epi64::<32>(lh), _mm512_srli_epi64::<32>(hl)),
+    );
+    (hi, lo)
+}
+
+/// Square of each lane as (high, low) halves.
+///
+/// Like `mul_64x64_avx512`, but the two cross products are equal, so only three
+/// 32x32 -> 64-bit multiplies are needed.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+#[inline]
+unsafe fn square_64_avx512(a: __m512i) -> (__m512i, __m512i) {
+    let mask_lo32 = _mm512_set1_epi64(0xFFFF_FFFF);
+    let a_hi = _mm512_srli_epi64::<32>(a);
+
+    let ll = _mm512_mul_epu32(a, a);
+    let lh = _mm512_mul_epu32(a, a_hi);
+    let hh = _mm512_mul_epu32(a_hi, a_hi);
+
+    // Middle column holds the cross product twice; still at most 3 * (2^32 - 1)
+    let lh_lo = _mm512_and_si512(lh, mask_lo32);
+    let mid = _mm512_add_epi64(_mm512_srli_epi64::<32>(ll), _mm512_add_epi64(lh_lo, lh_lo));
+    let lo = _mm512_or_si512(
+        _mm512_and_si512(ll, mask_lo32),
+        _mm512_slli_epi64::<32>(mid),
+    );
+    let lh_hi = _mm512_srli_epi64::<32>(lh);
+    let hi = _mm512_add_epi64(
+        _mm512_add_epi64(hh, _mm512_srli_epi64::<32>(mid)),
+        _mm512_add_epi64(lh_hi, lh_hi),
+    );
+    (hi, lo)
+}
+
+/// Lane-wise reduction of a 128-bit value given as (high, low) halves.
+///
+/// Mirrors `base::reduce_159` step for step so results match the scalar path exactly.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+#[inline]
+unsafe fn reduce_128_avx512(hi: __m512i, lo: __m512i) -> __m512i {
+    let prime_vec = _mm512_set1_epi64(PRIME as i64);
+    let mid = _mm512_and_si512(hi, _mm512_set1_epi64(0xFFFF_FFFF));
+    let high = _mm512_srli_epi64::<32>(hi);
+
+    // low - high, adding PRIME back on borrow
+    let borrow = _mm512_cmplt_epu64_mask(lo, high);
+    let low2 = _mm512_sub_epi64(lo, high);
+    let low2 = _mm512_mask_add_epi64(low2, borrow, low2, prime_vec);
+
+    // mid * (2^32 - 1)
+    let product = _mm512_sub_epi64(_mm512_slli_epi64::<32>(mid), mid);
+
+    // product + low2, subtracting PRIME on carry
+    let sum = _mm512_add_epi64(product, low2);
+    let carry = _mm512_cmplt_epu64_mask(sum, product);
+    let sum = _mm512_mask_sub_epi64(sum, carry, sum, prime_vec);
+
+    // Final canonicalization
+    let ge = _mm512_cmpge_epu64_mask(sum, prime_vec);
+    _mm512_mask_sub_epi64(sum, ge, sum, prime_vec)
+}
+
+/// Batch field addition using AVX2, four lanes at a time
+///
+/// Used for tails too short for a full AVX-512 vector, and for everything when the
+/// AVX2 field backend is selected.
+///
+/// # Safety
+///
+/// The CPU must support AVX2, and every slice must be a multiple of 4 long.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[inline(never)]
+pub unsafe fn badd_batch_avx2(a: &[u64], b: &[u64], result: &mut [u64]) {
+    assert_eq!(a.len(), b.len());
+    assert_eq!(a.len(), result.len());
+    assert!(a.len() % AVX2_WIDTH == 0);
+
+    let prime_vec = _mm256_set1_epi64x(PRIME as i64);
+
+    for i in (0..a.len()).step_by(AVX2_WIDTH) {
+        debug_assert!(
+            i + AVX2_WIDTH <= a.len(),
+            "lanes {}..{} past the end",
+            i,
+            i + AVX2_WIDTH
+        );
+        let a_vec = _mm256_loadu_si256(a.as_ptr().add(i) as *const __m256i);
+        let b_vec = _mm256_loadu_si256(b.as_ptr().add(i) as *const __m256i);
+
+        // Same as the AVX-512 kernel: a - (PRIME - b), adding PRIME back on borrow
+        let neg_b = _mm256_sub_epi64(prime_vec, b_vec);
+        let diff = _mm256_sub_epi64(a_vec, neg_b);
+        let underflow = cmplt_epu64_avx2(a_vec, neg_b);
+        let final_result = _mm256_add_epi64(diff, _mm256_and_si256(underflow, prime_vec));
+
+        _mm256_storeu_si256(result.as_mut_ptr().add(i) as *mut __m256i, final_result);
+    }
+}
+
+/// Batch field multiplication using AVX2, four lanes at a time
+///
+/// Used for tails too short for a full AVX-512 vector, and for everything when the
+/// AVX2 field backend is selected.
+///
+/// # Safety
+///
+/// The CPU must support AVX2, and every slice must be a multiple of 4 long.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[inline(never)]
+pub unsafe fn bmul_batch_avx2(a: &[u64], b: &[u64], result: &mut [u64]) {
+    assert_eq!(a.len(), b.len());
+    assert_eq!(a.len(), result.len());
+    assert!(a.len() % AVX2_WIDTH == 0);
+
+    for i in (0..a.len()).step_by(AVX2_WIDTH) {
+        debug_assert!(
+            i + AVX2_WIDTH <= a.len(),
+            "lanes {}..{} past the end",
+            i,
+            i + AVX2_WIDTH
+        );
+        let a_vec = _mm256_loadu_si256(a.as_ptr().add(i) as *const __m256i);
+        let b_vec = _mm256_loadu_si256(b.as_ptr().add(i) as *const __m256i);
+
+        let (prod_hi, prod_lo) = mul_64x64_avx2(a_vec, b_vec);
+        let reduced = reduce_128_avx2(prod_hi, prod_lo);
+
+        _mm256_storeu_si256(result.as_mut_ptr().add(i) as *mut __m256i, reduced);
+    }
+}
+
+/// Batch 128-bit reduction using AVX2, four values at a time
+///
+/// # Safety
+///
+/// The CPU must support AVX2, and both slices must be a multiple of 4 long.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[inline(never)]
+pub unsafe fn reduce_128_batch_avx2(products: &[u128], result: &mut [u64]) {
+    assert_eq!(products.len(), result.len());
+    assert!(products.len() % AVX2_WIDTH == 0);
+
+    for i in (0..products.len()).step_by(AVX2_WIDTH) {
+        debug_assert!(
+            i + AVX2_WIDTH <= products.len(),
+            "lanes {}..{} past the end",
+            i,
+            i + AVX2_WIDTH
+        );
+        let words = products.as_ptr().add(i) as *const __m256i;
+        let first = _mm256_loadu_si256(words);
+        let second = _mm256_loadu_si256(words.add(1));
+
+        // Unpacking works within 128-bit halves, leaving lanes in 0, 2, 1, 3 order
+        let lo = _mm256_permute4x64_epi64::<0b11_01_10_00>(_mm256_unpacklo_epi64(first, second));
+        let hi = _mm256_permute4x64_epi64::<0b11_01_10_00>(_mm256_unpackhi_epi64(first, second));
+        let reduced = reduce_128_avx2(hi, lo);
+
+        _mm256_storeu_si256(result.as_mut_ptr().add(i) as *mut __m256i, reduced);
+    }
+}
+
+/// Index of the first nonzero element using AVX-512 test masks, or `None` if every
+/// element is zero
+///
+/// # Safety
+///
+/// The CPU must support AVX-512F, and `a` must be a multiple of 8 long. Prefer
+/// [`crate::field::batch::bfirst_nonzero`], which checks this at runtime and falls back
+/// to scalar code.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+#[inline(never)]
+pub unsafe fn bfirst_nonzero_avx512(a: &[u64]) -> Option<usize> {
+    assert!(a.len() % SIMD_WIDTH == 0);
+
+    for i in (0..a.len()).step_by(SIMD_WIDTH) {
+        debug_assert!(
+            i + SIMD_WIDTH <= a.len(),
+            "lanes {}..{} past the end",
+            i,
+            i + SIMD_WIDTH
+        );
+        let a_vec = _mm512_loadu_epi64(a.as_ptr().add(i) as *const i64);
+        // One bit per lane with any bit set
+        let nonzero = _mm512_test_epi64_mask(a_vec, a_vec);
+        if nonzero != 0 {
+            return Some(i + nonzero.trailing_zeros() as usize);
         }
     }
+    None
+}
+
+/// Index of the first nonzero element using AVX2, four lanes at a time, or `None` if
+/// every element is zero
+///
+/// # Safety
+///
+/// The CPU must support AVX2, and `a` must be a multiple of 4 long.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[inline(never)]
+pub unsafe fn bfirst_nonzero_avx2(a: &[u64]) -> Option<usize> {
+    assert!(a.len() % AVX2_WIDTH == 0);
+
+    for i in (0..a.len()).step_by(AVX2_WIDTH) {
+        debug_assert!(
+            i + AVX2_WIDTH <= a.len(),
+            "lanes {}..{} past the end",
+            i,
+            i + AVX2_WIDTH
+        );
+        let a_vec = _mm256_loadu_si256(a.as_ptr().add(i) as *const __m256i);
+        if _mm256_testz_si256(a_vec, a_vec) == 0 {
+            // AVX2 has no per-lane test mask, so find the lane by comparing with zero
+            let zero_lanes = _mm256_cmpeq_epi64(a_vec, _mm256_setzero_si256());
+            let zero_bits = _mm256_movemask_pd(_mm256_castsi256_pd(zero_lanes)) as u32;
+            return Some(i + (!zero_bits).trailing_zeros() as usize);
+        }
+    }
+    None
+}
+
+/// Unsigned `a < b` per 64-bit lane, as an all-ones mask. AVX2 only compares signed,
+/// so both sides are shifted by 2^63 first.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[inline]
+unsafe fn cmplt_epu64_avx2(a: __m256i, b: __m256i) -> __m256i {
+    let sign = _mm256_set1_epi64x(i64::MIN);
+    _mm256_cmpgt_epi64(_mm256_xor_si256(b, sign), _mm256_xor_si256(a, sign))
+}
+
+/// Four-lane version of `mul_64x64_avx512`
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[inline]
+unsafe fn mul_64x64_avx2(a: __m256i, b: __m256i) -> (__m256i, __m256i) {
+    let mask_lo32 = _mm256_set1_epi64x(0xFFFF_FFFF);
+    let a_hi = _mm256_srli_epi64::<32>(a);
+    let b_hi = _mm256_srli_epi64::<32>(b);
+
+    let ll = _mm256_mul_epu32(a, b);
+    let lh = _mm256_mul_epu32(a, b_hi);
+    let hl = _mm256_mul_epu32(a_hi, b);
+    let hh = _mm256_mul_epu32(a_hi, b_hi);
+
+    let mid = _mm256_add_epi64(
+        _mm256_add_epi64(_mm256_srli_epi64::<32>(ll), _mm256_and_si256(lh, mask_lo32)),
+        _mm256_and_si256(hl, mask_lo32),
+    );
+    let lo = _mm256_or_si256(
+        _mm256_and_si256(ll, mask_lo32),
+        _mm256_slli_epi64::<32>(mid),
+    );
+    let hi = _mm256_add_epi64(
+        _mm256_add_epi64(hh, _mm256_srli_epi64::<32>(mid)),
+        _mm256_add_epi64(_mm256_srli_epi64::<32>(lh), _mm256_srli_epi64::<32>(hl)),
+    );
+    (hi, lo)
+}
+
+/// Four-lane version of `reduce_128_avx512`, with masks in place of mask registers
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[inline]
+unsafe fn reduce_128_avx2(hi: __m256i, lo: __m256i) -> __m256i {
+    let prime_vec = _mm256_set1_epi64x(PRIME as i64);
+    let mid = _mm256_and_si256(hi, _mm256_set1_epi64x(0xFFFF_FFFF));
+    let high = _mm256_srli_epi64::<32>(hi);
+
+    // low - high, adding PRIME back on borrow
+    let borrow = cmplt_epu64_avx2(lo, high);
+    let low2 = _mm256_sub_epi64(lo, high);
+    let low2 = _mm256_add_epi64(low2, _mm256_and_si256(borrow, prime_vec));
+
+    // mid * (2^32 - 1)
+    let product = _mm256_sub_epi64(_mm256_slli_epi64::<32>(mid), mid);
+
+    // product + low2, subtracting PRIME on carry
+    let sum = _mm256_add_epi64(product, low2);
+    let carry = cmplt_epu64_avx2(sum, product);
+    let sum = _mm256_sub_epi64(sum, _mm256_and_si256(carry, prime_vec));
+
+    // Final canonicalization
+    let below = cmplt_epu64_avx2(sum, prime_vec);
+    _mm256_sub_epi64(sum, _mm256_andnot_si256(below, prime_vec))
 }
 
 /// Highly optimized 128-bit modular reduction for EPYC 9654
 #[inline(always)]
 pub fn reduce_128_optimized(n: u128) -> u64 {
     // Use the specific prime structure for faster reduction
-    // PRIME = 2^64 - 2^32 + 1
-    let low = n as u64;
-    let high = (n >> 64) as u64;
-    
-    // First reduction step
-    let temp = high.wrapping_mul(0xFFFFFFFF); // (2^32 - 1)
-    let (mut result, carry1) = low.overflowing_sub(temp);
-    
-    // Handle carry
-    if carry1 {
-        result = result.wrapping_add(PRIME);
-    }
-    
-    // Second reduction step
-    let (final_result, carry2) = result.overflowing_add(high);
-    if carry2 || final_result >= PRIME {
-        final_result.wrapping_sub(PRIME)
-    } else {
-        final_result
-    }
+    // PRIME = 2^64 - 2^32 + 1, so 2^64 = 2^32 - 1 and 2^96 = -1 (mod PRIME).
+    // The same code is checked exhaustively over small primes of this shape.
+    crate::field::generic::reduce::<crate::field::generic::Goldilocks>(n)
+}
+
+/// Batch form of [`reduce_128_optimized`] for arrays of products
+///
+/// Groups of 8 are reduced with AVX-512 when the CPU supports it, the rest one at a time.
+pub fn reduce_128_batch(products: &[u128], out: &mut [u64]) {
+    crate::field::batch::reduce_128(products, out)
 }
 
 /// Cache-optimized batch operations for large datasets
+///
+/// Inputs are processed in chunks of `batch_size` elements staged through a scratch
+/// buffer holding one chunk each of `a`, `b` and the result. The buffer is allocated on
+/// first use and reused afterwards, so it costs `3 * 8 * batch_size` bytes per processor.
 pub struct BatchProcessor {
-    cache_aligned_buffer: Vec<u64>,
+    scratch: Vec<u64>,
     batch_size: usize,
 }
 
+/// Bytes of scratch needed per element of batch size: one u64 each for a, b and result
+const SCRATCH_BYTES_PER_ELEMENT: usize = 3 * std::mem::size_of::<u64>();
+
 impl BatchProcessor {
     pub fn new(max_elements: usize) -> Self {
-        // Align to cache line boundaries and ensure AVX-512 alignment
-        let batch_size = ((max_elements + SIMD_WIDTH - 1) / SIMD_WIDTH) * SIMD_WIDTH;
-        let mut buffer = Vec::with_capacity(batch_size * 3); // Space for a, b, result
-        
-        // Ensure cache line alignment
-        let alignment_offset = (CACHE_LINE_SIZE - (buffer.as_ptr() as usize % CACHE_LINE_SIZE)) % CACHE_LINE_SIZE;
-        for _ in 0..(alignment_offset / 8) {
-            buffer.push(0);
-        }
-        
+        // Align to cache line boundaries and ensure AVX-512 alignment; at least one vector
+        let batch_size = max_elements.max(1).div_ceil(SIMD_WIDTH) * SIMD_WIDTH;
+
         Self {
-            cache_aligned_buffer: buffer,
+            scratch: Vec::new(),
             batch_size,
         }
     }
-    
+
+    /// Like [`BatchProcessor::new`], but shrinks the chunk size so the scratch buffer
+    /// stays within `max_scratch_bytes`, however large `max_elements` is.
+    ///
+    /// Smaller chunks mean more loop iterations and copies per call, so a tight budget
+    /// costs some throughput; the chunk size never drops below one SIMD vector
+    /// (`SIMD_WIDTH` elements, 192 bytes of scratch).
+    pub fn with_max_scratch_bytes(max_elements: usize, max_scratch_bytes: usize) -> Self {
+        let mut processor = Self::new(max_elements);
+        let budget_elements =
+            max_scratch_bytes / SCRATCH_BYTES_PER_ELEMENT / SIMD_WIDTH * SIMD_WIDTH;
+        processor.batch_size = processor.batch_size.min(budget_elements.max(SIMD_WIDTH));
+        processor
+    }
+
+    /// Use chunks of `chunk_size` elements, rounded up to a whole number of SIMD vectors,
+    /// regardless of how many elements the processor was created for.
+    ///
+    /// The working set per chunk is `scratch_bytes()`, so this is the knob for fitting
+    /// chunks in L2 on one CPU and L3 on another. Any scratch already allocated is dropped.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.batch_size = chunk_size.max(1).div_ceil(SIMD_WIDTH) * SIMD_WIDTH;
+        self.scratch = Vec::new();
+        self
+    }
+
+    /// Number of elements processed per chunk
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// Scratch memory this processor uses once it has processed a batch
+    pub fn scratch_bytes(&self) -> usize {
+        self.batch_size * SCRATCH_BYTES_PER_ELEMENT
+    }
+
     /// Process large batches with optimal memory access patterns
+    ///
+    /// Only the first `min(a.len(), b.len())` elements are added.
     pub fn process_batch_add(&mut self, a: &[u64], b: &[u64]) -> Vec<u64> {
         let len = a.len().min(b.len());
         let mut result = vec![0u64; len];
-        
-        // Process in cache-friendly chunks
-        let chunk_size = std::cmp::min(self.batch_size, len);
-        
-        for chunk_start in (0..len).step_by(chunk_size) {
-            let chunk_end = std::cmp::min(chunk_start + chunk_size, len);
-            let chunk_len = chunk_end - chunk_start;
-            
-            // Pad to SIMD width
-            let padded_len = ((chunk_len + SIMD_WIDTH - 1) / SIMD_WIDTH) * SIMD_WIDTH;
-            
-            // Copy to aligned buffer
-            let mut a_chunk = vec![0u64; padded_len];
-            let mut b_chunk = vec![0u64; padded_len];
-            let mut result_chunk = vec![0u64; padded_len];
-            
-            a_chunk[..chunk_len].copy_from_slice(&a[chunk_start..chunk_end]);
-            b_chunk[..chunk_len].copy_from_slice(&b[chunk_start..chunk_end]);
-            
-            // Perform optimized batch operation
-            #[cfg(target_arch = "x86_64")]
-            unsafe {
-                if is_x86_feature_detected!("avx512f") {
-                    badd_batch_avx512(&a_chunk, &b_chunk, &mut result_chunk);
-                } else {
-                    // Fallback to scalar
-                    for i in 0..chunk_len {
-                        result_chunk[i] = crate::form::math::base::badd(a_chunk[i], b_chunk[i]);
-                    }
-                }
-            }
-            
-            #[cfg(not(target_arch = "x86_64"))]
-            {
-                for i in 0..chunk_len {
-                    result_chunk[i] = crate::form::math::base::badd(a_chunk[i], b_chunk[i]);
-                }
-            }
-            
-            result[chunk_start..chunk_end].copy_from_slice(&result_chunk[..chunk_len]);
-        }
-        
+        self.process_batch_add_into(&a[..len], &b[..len], &mut result);
         result
     }
-    
+
     /// Process large batches with optimal memory access patterns for multiplication
+    ///
+    /// Only the first `min(a.len(), b.len())` elements are multiplied.
     pub fn process_batch_mul(&mut self, a: &[u64], b: &[u64]) -> Vec<u64> {
         let len = a.len().min(b.len());
         let mut result = vec![0u64; len];
-        
-        let chunk_size = std::cmp::min(self.batch_size, len);
-        
-        for chunk_start in (0..len).step_by(chunk_size) {
-            let chunk_end = std::cmp::min(chunk_start + chunk_size, len);
+        self.process_batch_mul_into(&a[..len], &b[..len], &mut result);
+        result
+    }
+
+    /// [`BatchProcessor::process_batch_add`] writing into `out` instead of allocating
+    ///
+    /// # Panics
+    ///
+    /// If `a`, `b` and `out` are not all the same length.
+    pub fn process_batch_add_into(&mut self, a: &[u64], b: &[u64], out: &mut [u64]) {
+        #[cfg(target_arch = "x86_64")]
+        let kernels = BatchKernels::detect(badd_batch_avx512, badd_batch_avx2);
+        #[cfg(not(target_arch = "x86_64"))]
+        let kernels = BatchKernels::default();
+
+        self.process_batch("add", a, b, out, kernels, crate::form::math::base::badd)
+    }
+
+    /// [`BatchProcessor::process_batch_mul`] writing into `out` instead of allocating
+    ///
+    /// # Panics
+    ///
+    /// If `a`, `b` and `out` are not all the same length.
+    pub fn process_batch_mul_into(&mut self, a: &[u64], b: &[u64], out: &mut [u64]) {
+        #[cfg(target_arch = "x86_64")]
+        let kernels = BatchKernels::detect(bmul_batch_avx512, bmul_batch_avx2);
+        #[cfg(not(target_arch = "x86_64"))]
+        let kernels = BatchKernels::default();
+
+        self.process_batch("mul", a, b, out, kernels, crate::form::math::base::bmul)
+    }
+
+    /// Stage `a` and `b` chunk by chunk through the scratch buffer. Each chunk goes
+    /// through the widest kernel that fits what is left of it, with no padding: 8-lane
+    /// AVX-512 vectors, then one 4-lane AVX2 vector, then scalar for the last few. The
+    /// vector results are checked against `scalar` as [`crate::field::verify`] is set to.
+    fn process_batch(
+        &mut self,
+        op: &'static str,
+        a: &[u64],
+        b: &[u64],
+        result: &mut [u64],
+        kernels: BatchKernels,
+        scalar: fn(u64, u64) -> u64,
+    ) {
+        assert_eq!(a.len(), b.len(), "batch operands must have the same length");
+        assert_eq!(
+            a.len(),
+            result.len(),
+            "batch result must match operand length"
+        );
+        let len = a.len();
+        if len == 0 {
+            return;
+        }
+
+        if self.scratch.is_empty() {
+            self.scratch = vec![0u64; self.batch_size * 3];
+        }
+        let (a_chunk, rest) = self.scratch.split_at_mut(self.batch_size);
+        let (b_chunk, result_chunk) = rest.split_at_mut(self.batch_size);
+
+        // Process in cache-friendly chunks
+        for chunk_start in (0..len).step_by(self.batch_size) {
+            let chunk_end = std::cmp::min(chunk_start + self.batch_size, len);
             let chunk_len = chunk_end - chunk_start;
-            
-            let padded_len = ((chunk_len + SIMD_WIDTH - 1) / SIMD_WIDTH) * SIMD_WIDTH;
-            
-            let mut a_chunk = vec![0u64; padded_len];
-            let mut b_chunk = vec![0u64; padded_len];
-            let mut result_chunk = vec![0u64; padded_len];
-            
+
             a_chunk[..chunk_len].copy_from_slice(&a[chunk_start..chunk_end]);
             b_chunk[..chunk_len].copy_from_slice(&b[chunk_start..chunk_end]);
-            
-            #[cfg(target_arch = "x86_64")]
-            unsafe {
-                if is_x86_feature_detected!("avx512f") {
-                    bmul_batch_avx512(&a_chunk, &b_chunk, &mut result_chunk);
-                } else {
-                    for i in 0..chunk_len {
-                        result_chunk[i] = crate::form::math::base::bmul(a_chunk[i], b_chunk[i]);
-                    }
-                }
+
+            let (wide, narrow) = kernels.split(chunk_len);
+            let vector_end = wide + narrow;
+            // SAFETY: kernels are only present after checking CPU support, and each
+            // range is a multiple of its kernel's width
+            if let (Some(kernel), true) = (kernels.avx512, wide > 0) {
+                unsafe {
+                    kernel(
+                        &a_chunk[..wide],
+                        &b_chunk[..wide],
+                        &mut result_chunk[..wide],
+                    )
+                };
             }
-            
-            #[cfg(not(target_arch = "x86_64"))]
-            {
-                for i in 0..chunk_len {
-                    result_chunk[i] = crate::form::math::base::bmul(a_chunk[i], b_chunk[i]);
-                }
+            if let (Some(kernel), true) = (kernels.avx2, narrow > 0) {
+                unsafe {
+                    kernel(
+                        &a_chunk[wide..vector_end],
+                        &b_chunk[wide..vector_end],
+                        &mut result_chunk[wide..vector_end],
+                    )
+                };
+            }
+            crate::field::verify::check(op, &mut result_chunk[..vector_end], |i| {
+                scalar(a_chunk[i], b_chunk[i])
+            });
+            for i in vector_end..chunk_len {
+                result_chunk[i] = scalar(a_chunk[i], b_chunk[i]);
             }
-            
+
             result[chunk_start..chunk_end].copy_from_slice(&result_chunk[..chunk_len]);
         }
-        
-        result
+    }
+}
+
+/// Signature shared by the AVX-512 and AVX2 batch kernels
+type BatchKernel = unsafe fn(&[u64], &[u64], &mut [u64]);
+
+/// The SIMD kernels for one operation that this CPU can run and the selected field
+/// backend allows
+#[derive(Clone, Copy, Default)]
+struct BatchKernels {
+    avx512: Option<BatchKernel>,
+    avx2: Option<BatchKernel>,
+}
+
+impl BatchKernels {
+    #[cfg(target_arch = "x86_64")]
+    fn detect(avx512: BatchKernel, avx2: BatchKernel) -> Self {
+        Self {
+            avx512: crate::field::backend::use_avx512().then_some(avx512),
+            avx2: crate::field::backend::use_avx2().then_some(avx2),
+        }
+    }
+
+    /// How many of `len` elements go through AVX-512 and how many through AVX2 after
+    /// them; the rest are scalar. Without AVX-512, AVX2 takes every whole 4-lane vector.
+    fn split(&self, len: usize) -> (usize, usize) {
+        let wide = if self.avx512.is_some() {
+            len / SIMD_WIDTH * SIMD_WIDTH
+        } else {
+            0
+        };
+        let narrow = if self.avx2.is_some() {
+            (len - wide) / AVX2_WIDTH * AVX2_WIDTH
+        } else {
+            0
+        };
+        (wide, narrow)
     }
 }
 
 /// EPYC-optimized polynomial evaluation using Horner's method with SIMD
+///
+/// `coeffs` runs from the constant term up: `coeffs[i]` is the coefficient of `x^i`.
 pub fn poly_eval_optimized(coeffs: &[u64], x: u64) -> u64 {
     if coeffs.is_empty() {
         return 0;
     }
-    
+
     let mut result = coeffs[coeffs.len() - 1];
-    
+
     // Process remaining coefficients in reverse order
     for &coeff in coeffs.iter().rev().skip(1) {
-        result = crate::form::math::base::badd(
-            crate::form::math::base::bmul(result, x),
-            coeff
-        );
+        result = crate::form::math::base::badd(crate::form::math::base::bmul(result, x), coeff);
     }
-    
+
     result
 }
 
@@ -235,17 +745,11 @@ pub fn prefetch_for_mining(data: &[u64], offset: usize) {
     unsafe {
         if offset < data.len() {
             // Prefetch into L2 cache (PREFETCH_T1)
-            _mm_prefetch(
-                data.as_ptr().add(offset) as *const i8,
-                _MM_HINT_T1
-            );
-            
+            _mm_prefetch(data.as_ptr().add(offset) as *const i8, _MM_HINT_T1);
+
             // Prefetch next cache line into L3 (PREFETCH_T2)
             if offset + 8 < data.len() {
-                _mm_prefetch(
-                    data.as_ptr().add(offset + 8) as *const i8,
-                    _MM_HINT_T2
-                );
+                _mm_prefetch(data.as_ptr().add(offset + 8) as *const i8, _MM_HINT_T2);
             }
         }
     }
@@ -254,21 +758,97 @@ pub fn prefetch_for_mining(data: &[u64], offset: usize) {
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
     #[test]
     fn test_batch_operations() {
         let a = vec![1, 2, 3, 4, 5, 6, 7, 8];
         let b = vec![8, 7, 6, 5, 4, 3, 2, 1];
-        
+
         let mut processor = BatchProcessor::new(16);
         let result = processor.process_batch_add(&a, &b);
-        
+
         // Verify results
         for i in 0..a.len() {
             assert_eq!(result[i], crate::form::math::base::badd(a[i], b[i]));
         }
     }
-    
+
+    #[test]
+    fn test_batch_processor_scratch_cap() {
+        let a: Vec<u64> = (0..1000).map(|i| i * 7919).collect();
+        let b: Vec<u64> = (0..1000).map(|i| PRIME - 1 - i).collect();
+
+        // 1024 bytes fits 42 elements of scratch, rounded down to 40
+        let mut processor = BatchProcessor::with_max_scratch_bytes(1 << 20, 1024);
+        assert_eq!(processor.batch_size(), 40);
+        assert!(processor.scratch_bytes() <= 1024);
+
+        let sum = processor.process_batch_add(&a, &b);
+        let product = processor.process_batch_mul(&a, &b);
+        for i in 0..a.len() {
+            assert_eq!(sum[i], crate::form::math::base::badd(a[i], b[i]));
+            assert_eq!(product[i], crate::form::math::base::bmul(a[i], b[i]));
+        }
+
+        // A budget below one vector still processes a full vector per chunk
+        let processor = BatchProcessor::with_max_scratch_bytes(1 << 20, 1);
+        assert_eq!(processor.batch_size(), SIMD_WIDTH);
+        // A generous budget leaves the requested size alone
+        let processor = BatchProcessor::with_max_scratch_bytes(100, usize::MAX);
+        assert_eq!(processor.batch_size(), 104);
+    }
+
+    #[test]
+    fn test_batch_processor_chunk_size() {
+        let a: Vec<u64> = (0..1000).map(|i| (i * 7919) % PRIME).collect();
+        let b: Vec<u64> = (0..1000).map(|i| (i * 104729) % PRIME).collect();
+        let expected = BatchProcessor::new(1000).process_batch_mul(&a, &b);
+
+        for (requested, chunk) in
+            [(0, SIMD_WIDTH), (1, SIMD_WIDTH), (8, 8), (100, 104), (4096, 4096)]
+        {
+            let mut processor = BatchProcessor::new(1000).with_chunk_size(requested);
+            assert_eq!(processor.batch_size(), chunk);
+            assert_eq!(processor.scratch_bytes(), chunk * SCRATCH_BYTES_PER_ELEMENT);
+            assert_eq!(
+                processor.process_batch_mul(&a, &b),
+                expected,
+                "chunk size {}",
+                chunk
+            );
+        }
+
+        // A processor sized for nothing still works in whole vectors
+        let mut processor = BatchProcessor::new(0);
+        assert_eq!(processor.batch_size(), SIMD_WIDTH);
+        assert_eq!(processor.process_batch_mul(&a, &b), expected);
+    }
+
+    #[test]
+    fn test_batch_processor_into() {
+        let a: Vec<u64> = (0..100).map(|i| (i * 7919) % PRIME).collect();
+        let b: Vec<u64> = (0..100).map(|i| PRIME - 1 - i).collect();
+        let mut processor = BatchProcessor::new(32);
+
+        // Leftovers from an earlier batch are overwritten
+        let mut out = vec![u64::MAX; a.len()];
+        processor.process_batch_add_into(&a, &b, &mut out);
+        assert_eq!(out, processor.process_batch_add(&a, &b));
+        processor.process_batch_mul_into(&a, &b, &mut out);
+        assert_eq!(out, processor.process_batch_mul(&a, &b));
+
+        processor.process_batch_mul_into(&[], &[], &mut []);
+        // The allocating forms still stop at the shorter operand
+        assert_eq!(processor.process_batch_add(&a, &b[..10]).len(), 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "batch result must match operand length")]
+    fn test_batch_processor_into_checks_lengths() {
+        let mut out = [0u64; 3];
+        BatchProcessor::new(8).process_batch_add_into(&[1, 2, 3, 4], &[5, 6, 7, 8], &mut out);
+    }
+
     #[test]
     fn test_reduce_128_optimized() {
         let test_cases = [
@@ -279,11 +859,489 @@ mod tests {
             u64::MAX as u128,
             (u64::MAX as u128) * (u64::MAX as u128),
         ];
-        
+
         for &test_val in &test_cases {
             let optimized = reduce_128_optimized(test_val);
             let reference = crate::form::math::base::reduce(test_val);
             assert_eq!(optimized, reference, "Mismatch for input {}", test_val);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_reduce_128_boundary_products() {
+        const P: u64 = PRIME;
+        // Products a 64x64 multiply can actually produce, at the edges of each fold step
+        let pairs: [(u64, u64); 14] = [
+            (P - 1, P - 1),
+            (P - 1, P - 2),
+            (u64::MAX, u64::MAX),
+            // Straddling 2^64
+            (1 << 32, 1 << 32),
+            ((1 << 32) - 1, (1 << 32) + 1),
+            ((1 << 32) + 1, (1 << 32) + 1),
+            (u64::MAX, 1),
+            (u64::MAX, 2),
+            // Straddling 2^96
+            (1 << 48, 1 << 48),
+            ((1 << 48) - 1, (1 << 48) + 1),
+            ((1 << 48) + 1, 1 << 48),
+            (1 << 32, u64::MAX),
+            (1 << 33, u64::MAX >> 1),
+            (P - 1, 1 << 32),
+        ];
+        let mut products: Vec<u128> = pairs
+            .iter()
+            .map(|&(a, b)| (a as u128) * (b as u128))
+            .collect();
+        products.extend([
+            u128::MAX,
+            (1 << 96) - 1,
+            1 << 96,
+            (1 << 96) + 1,
+            (1 << 64) - 1,
+            1 << 64,
+            (1 << 64) + 1,
+            PRIME_128 << 32,
+        ]);
+
+        // 22 products: two vectors through AVX-512 when available, six in the scalar tail
+        let mut batch = vec![0u64; products.len()];
+        reduce_128_batch(&products, &mut batch);
+        for (i, &n) in products.iter().enumerate() {
+            let expected = (n % PRIME_128) as u64;
+            assert_eq!(
+                reduce_128_optimized(n),
+                expected,
+                "reduce_128_optimized({:#x})",
+                n
+            );
+            assert_eq!(batch[i], expected, "reduce_128_batch at {} ({:#x})", i, n);
+        }
+    }
+
+    // Values around every carry/borrow boundary of the vector add and multiply
+    #[cfg(target_arch = "x86_64")]
+    const EDGE_VALUES: [u64; 12] = [
+        0,
+        1,
+        2,
+        0xFFFF_FFFF,
+        1 << 32,
+        (1 << 32) + 1,
+        PRIME >> 1,
+        (PRIME >> 1) + 1,
+        1 << 63,
+        PRIME - (1 << 32),
+        PRIME - 2,
+        PRIME - 1,
+    ];
+
+    /// Run a vector kernel and compare it lane-by-lane to its scalar reference
+    #[cfg(target_arch = "x86_64")]
+    fn check_lanes(
+        a: &[u64],
+        b: &[u64],
+        kernel: unsafe fn(&[u64], &[u64], &mut [u64]),
+        scalar: fn(u64, u64) -> u64,
+    ) -> bool {
+        let len = a.len().min(b.len()) / SIMD_WIDTH * SIMD_WIDTH;
+        let mut result = vec![0u64; len];
+        unsafe { kernel(&a[..len], &b[..len], &mut result) };
+        (0..len).all(|i| result[i] == scalar(a[i], b[i]))
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn edge_pairs() -> (Vec<u64>, Vec<u64>) {
+        let mut a = Vec::new();
+        let mut b = Vec::new();
+        for &x in &EDGE_VALUES {
+            for &y in &EDGE_VALUES {
+                a.push(x);
+                b.push(y);
+            }
+        }
+        (a, b)
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_badd_batch_avx512_edges() {
+        if !is_x86_feature_detected!("avx512f") {
+            return;
+        }
+        let (a, b) = edge_pairs();
+        assert_eq!(a.len() % SIMD_WIDTH, 0);
+        assert!(check_lanes(
+            &a,
+            &b,
+            badd_batch_avx512,
+            crate::form::math::base::badd
+        ));
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_bmul_batch_avx512_edges() {
+        if !is_x86_feature_detected!("avx512f") {
+            return;
+        }
+        let (a, b) = edge_pairs();
+        assert!(check_lanes(
+            &a,
+            &b,
+            bmul_batch_avx512,
+            crate::form::math::base::bmul
+        ));
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_bsquare_batch_avx512_edges() {
+        if !is_x86_feature_detected!("avx512f") {
+            return;
+        }
+        let (a, _) = edge_pairs();
+        assert!(check_lanes(
+            &a,
+            &a,
+            |a, _, out| unsafe { bsquare_batch_avx512(a, out) },
+            crate::form::math::base::bmul
+        ));
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_batch_avx2_edges() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+        let (a, b) = edge_pairs();
+        assert!(check_lanes(
+            &a,
+            &b,
+            badd_batch_avx2,
+            crate::form::math::base::badd
+        ));
+        assert!(check_lanes(
+            &a,
+            &b,
+            bmul_batch_avx2,
+            crate::form::math::base::bmul
+        ));
+        // A single 4-lane vector, the shape the tail path uses
+        let mut result = [0u64; 4];
+        unsafe { bmul_batch_avx2(&a[140..], &b[140..], &mut result) };
+        for i in 0..4 {
+            assert_eq!(
+                result[i],
+                crate::form::math::base::bmul(a[140 + i], b[140 + i])
+            );
+        }
+    }
+
+    /// Run `kernel` over `len` lanes, writing into the middle of a buffer fenced by guard
+    /// words, and check the guards survive. Inputs are exact-size allocations so
+    /// AddressSanitizer flags any read past them.
+    #[cfg(target_arch = "x86_64")]
+    fn check_in_bounds(len: usize, kernel: impl Fn(&[u64], &[u64], &[u128], &mut [u64])) {
+        const GUARD: u64 = 0xDEAD_BEEF_DEAD_BEEF;
+        const PAD: usize = 2 * SIMD_WIDTH;
+        let a: Vec<u64> = (0..len as u64).map(|i| PRIME - 1 - i * 7919).collect();
+        let b: Vec<u64> = (0..len as u64).map(|i| (i * 104729) % PRIME).collect();
+        let products: Vec<u128> = (0..len as u128)
+            .map(|i| u128::MAX - i * 0x1_0000_0001)
+            .collect();
+        let mut fenced = vec![GUARD; len + 2 * PAD];
+        kernel(&a, &b, &products, &mut fenced[PAD..PAD + len]);
+        assert!(
+            fenced[..PAD].iter().all(|&word| word == GUARD),
+            "write before the output, len {}",
+            len
+        );
+        assert!(
+            fenced[PAD + len..].iter().all(|&word| word == GUARD),
+            "write past the output, len {}",
+            len
+        );
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_kernels_stay_in_bounds() {
+        // SAFETY: each kernel runs only when its feature is detected, on lengths that are
+        // a whole number of its vectors
+        if is_x86_feature_detected!("avx512f") {
+            for len in (0..=64).step_by(SIMD_WIDTH) {
+                check_in_bounds(len, |a, b, _, out| unsafe { badd_batch_avx512(a, b, out) });
+                check_in_bounds(len, |a, b, _, out| unsafe { bmul_batch_avx512(a, b, out) });
+                check_in_bounds(len, |a, _, _, out| unsafe { bsquare_batch_avx512(a, out) });
+                check_in_bounds(len, |_, _, products, out| unsafe {
+                    reduce_128_batch_avx512(products, out)
+                });
+            }
+        }
+        if is_x86_feature_detected!("avx2") {
+            for len in (0..=64).step_by(AVX2_WIDTH) {
+                check_in_bounds(len, |a, b, _, out| unsafe { badd_batch_avx2(a, b, out) });
+                check_in_bounds(len, |a, b, _, out| unsafe { bmul_batch_avx2(a, b, out) });
+                check_in_bounds(len, |_, _, products, out| unsafe {
+                    reduce_128_batch_avx2(products, out)
+                });
+            }
+        }
+        // The safe entry points pick kernels and tails for any length
+        for len in 0..=64 {
+            check_in_bounds(len, |a, b, _, out| crate::field::batch::add_into(a, b, out));
+            check_in_bounds(len, |a, b, _, out| crate::field::batch::mul_into(a, b, out));
+            check_in_bounds(len, |a, _, _, out| crate::field::batch::square_into(a, out));
+            check_in_bounds(len, |_, _, products, out| {
+                crate::field::batch::reduce_128(products, out)
+            });
+        }
+    }
+
+    #[test]
+    fn test_batch_kernels_split() {
+        fn kernel(_: &[u64], _: &[u64], _: &mut [u64]) {}
+        let both = BatchKernels {
+            avx512: Some(kernel),
+            avx2: Some(kernel),
+        };
+        let avx2_only = BatchKernels {
+            avx512: None,
+            avx2: Some(kernel),
+        };
+        let scalar = BatchKernels::default();
+
+        for (len, split) in [
+            (0, (0, 0)),
+            (3, (0, 0)),
+            (5, (0, 4)),
+            (8, (8, 0)),
+            (13, (8, 4)),
+            (15, (8, 4)),
+            (16, (16, 0)),
+        ] {
+            assert_eq!(both.split(len), split, "len {}", len);
+        }
+        assert_eq!(avx2_only.split(13), (0, 12));
+        assert_eq!(scalar.split(13), (0, 0));
+    }
+
+    #[test]
+    fn test_batch_processor_odd_lengths() {
+        let a: Vec<u64> = (0..200).map(|i| PRIME - 1 - i * 7919).collect();
+        let b: Vec<u64> = (0..200).map(|i| (i * 104729) % PRIME).collect();
+        for chunk in [8, 16, 64] {
+            let mut processor = BatchProcessor::new(200).with_chunk_size(chunk);
+            for len in 1..=70 {
+                let sum = processor.process_batch_add(&a[..len], &b[..len]);
+                let product = processor.process_batch_mul(&a[..len], &b[..len]);
+                for i in 0..len {
+                    assert_eq!(
+                        sum[i],
+                        crate::form::math::base::badd(a[i], b[i]),
+                        "len {} chunk {}",
+                        len,
+                        chunk
+                    );
+                    assert_eq!(
+                        product[i],
+                        crate::form::math::base::bmul(a[i], b[i]),
+                        "len {} chunk {}",
+                        len,
+                        chunk
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_poly_eval_coefficient_order() {
+        use crate::form::math::base::{badd, bmul, bneg, bsub};
+
+        // 1 + 2x + 3x^2 at x = 10; the reversed convention would give 123
+        assert_eq!(poly_eval_optimized(&[1, 2, 3], 10), 321);
+        assert_eq!(poly_eval_optimized(&[], 10), 0);
+        assert_eq!(poly_eval_optimized(&[7], 10), 7);
+
+        // Expand (x - r0)(x - r1)...(x - rn) into coefficients, lowest degree first
+        let roots = [0, 1, 2, 3, 0xFFFF_FFFF, 1 << 32, PRIME >> 1, PRIME - 2, PRIME - 1];
+        let mut coeffs = vec![1u64];
+        for &root in &roots {
+            let mut next = vec![0u64; coeffs.len() + 1];
+            for (i, &c) in coeffs.iter().enumerate() {
+                next[i + 1] = badd(next[i + 1], c);
+                next[i] = badd(next[i], bmul(bneg(root), c));
+            }
+            coeffs = next;
+        }
+
+        for &root in &roots {
+            assert_eq!(poly_eval_optimized(&coeffs, root), 0, "root {:#x}", root);
+        }
+        // Away from the roots, compare against the product form
+        for x in [4, 5, 1000, 1 << 40, PRIME - 3, PRIME / 3] {
+            let product = roots.iter().fold(1, |acc, &root| bmul(acc, bsub(x, root)));
+            assert_ne!(product, 0);
+            assert_eq!(poly_eval_optimized(&coeffs, x), product, "x = {:#x}", x);
+        }
+    }
+
+    /// Times many small odd-length multiplies with the adaptive tail against padding
+    /// every tail to a full AVX-512 vector. Run with `--ignored --nocapture`.
+    #[test]
+    #[ignore = "Benchmark; compare the printed timings"]
+    #[cfg(target_arch = "x86_64")]
+    fn bench_odd_length_tails() {
+        if !is_x86_feature_detected!("avx512f") {
+            return;
+        }
+        let a: Vec<u64> = (0..64).map(|i| PRIME - 1 - i * 7919).collect();
+        let b: Vec<u64> = (0..64).map(|i| (i * 104729) % PRIME).collect();
+        let lengths: Vec<usize> = (1..64).filter(|len| len % SIMD_WIDTH != 0).collect();
+        let rounds = 20_000;
+
+        let mut processor = BatchProcessor::new(64);
+        let started = std::time::Instant::now();
+        for _ in 0..rounds {
+            for &len in &lengths {
+                std::hint::black_box(processor.process_batch_mul(&a[..len], &b[..len]));
+            }
+        }
+        let adaptive = started.elapsed();
+
+        let padded_kernels = BatchKernels {
+            avx512: Some(bmul_batch_avx512),
+            avx2: None,
+        };
+        let started = std::time::Instant::now();
+        for _ in 0..rounds {
+            for &len in &lengths {
+                let padded = len.div_ceil(SIMD_WIDTH) * SIMD_WIDTH;
+                let mut a_pad = a[..len].to_vec();
+                let mut b_pad = b[..len].to_vec();
+                a_pad.resize(padded, 0);
+                b_pad.resize(padded, 0);
+                let mut out = vec![0u64; padded];
+                processor.process_batch(
+                    "mul",
+                    &a_pad,
+                    &b_pad,
+                    &mut out,
+                    padded_kernels,
+                    crate::form::math::base::bmul,
+                );
+                std::hint::black_box(&out[..len]);
+            }
+        }
+        let padded = started.elapsed();
+
+        println!(
+            "{} odd lengths x {} rounds: adaptive tail {:?}, padded to 8 {:?}",
+            lengths.len(),
+            rounds,
+            adaptive,
+            padded
+        );
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_reduce_128_batch_avx512() {
+        if !is_x86_feature_detected!("avx512f") {
+            return;
+        }
+        let (a, b) = edge_pairs();
+        let mut products: Vec<u128> = a
+            .iter()
+            .zip(&b)
+            .map(|(&x, &y)| (x as u128) * (y as u128))
+            .collect();
+        // Values no field product reaches: the reduction must hold for all of u128
+        products.extend([
+            u128::MAX,
+            u128::MAX - 1,
+            PRIME_128 << 64,
+            1 << 127,
+            1 << 96,
+            (1 << 96) - 1,
+            PRIME_128 * PRIME_128,
+            0,
+        ]);
+        assert_eq!(products.len() % SIMD_WIDTH, 0);
+
+        let mut result = vec![0u64; products.len()];
+        unsafe { reduce_128_batch_avx512(&products, &mut result) };
+        for (i, &n) in products.iter().enumerate() {
+            assert_eq!(
+                result[i],
+                crate::form::math::base::reduce(n),
+                "Mismatch for input {}",
+                n
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_reduce_128_batch_avx2() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+        let (a, b) = edge_pairs();
+        let mut products: Vec<u128> = a
+            .iter()
+            .zip(&b)
+            .map(|(&x, &y)| (x as u128) * (y as u128))
+            .collect();
+        products.extend([
+            u128::MAX,
+            u128::MAX - 1,
+            PRIME_128 << 64,
+            1 << 127,
+            1 << 96,
+            (1 << 96) - 1,
+            PRIME_128 * PRIME_128,
+            0,
+        ]);
+        assert_eq!(products.len() % AVX2_WIDTH, 0);
+
+        let mut result = vec![0u64; products.len()];
+        unsafe { reduce_128_batch_avx2(&products, &mut result) };
+        for (i, &n) in products.iter().enumerate() {
+            assert_eq!(
+                result[i],
+                crate::form::math::base::reduce(n),
+                "Mismatch for input {}",
+                n
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_batch_avx512_random() {
+        use crate::form::poly::Belt;
+
+        if !is_x86_feature_detected!("avx512f") {
+            return;
+        }
+        fn prop(pairs: Vec<(Belt, Belt)>) -> bool {
+            let a: Vec<u64> = pairs.iter().map(|(x, _)| x.0).collect();
+            let b: Vec<u64> = pairs.iter().map(|(_, y)| y.0).collect();
+            check_lanes(&a, &b, badd_batch_avx512, crate::form::math::base::badd)
+                && check_lanes(&a, &b, bmul_batch_avx512, crate::form::math::base::bmul)
+                && check_lanes(
+                    &a,
+                    &a,
+                    |a, _, out| unsafe { bsquare_batch_avx512(a, out) },
+                    crate::form::math::base::bmul,
+                )
+        }
+        quickcheck::QuickCheck::new()
+            .tests(1000)
+            .quickcheck(prop as fn(Vec<(Belt, Belt)>) -> bool);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod base;
+pub mod base_optimized;
 pub mod bpoly;
 pub mod fext;
 pub mod fpoly;
@@ -29,7 +29,7 @@ const LOOKUP_TABLE: [u8; 256] = [
     1, 170, 40, 131, 192, 229, 248, 255,
 ];
 
-const ROUND_CONSTANTS: [u64; NUM_ROUNDS * STATE_SIZE] = [
+pub(crate) const ROUND_CONSTANTS: [u64; NUM_ROUNDS * STATE_SIZE] = [
     // 1st round constants
     1332676891236936200, 16607633045354064669, 12746538998793080786, 15240351333789289931,
     10333439796058208418, 986873372968378050, 153505017314310505, 703086547770691416,
@@ -67,7 +67,7 @@ const ROUND_CONSTANTS: [u64; NUM_ROUNDS * STATE_SIZE] = [
     7134876918849821827, 5796994175286958720, 7251651436095127661, 4565856221886323991,
 ];
 
-const MDS_MATRIX_I64: [[i64; STATE_SIZE]; STATE_SIZE] = [
+pub(crate) const MDS_MATRIX_I64: [[i64; STATE_SIZE]; STATE_SIZE] = [
     [
         61402, 17845, 26798, 59689, 12021, 40901, 41351, 27521, 56951, 12034, 53865, 43244, 7454,
         33823, 28750, 1108,
@@ -146,7 +146,7 @@ pub fn permute(sponge: &mut [u64; 16]) {
     }
 }
 
-fn sbox_layer(state: &[u64; STATE_SIZE]) -> [u64; STATE_SIZE] {
+pub(crate) fn sbox_layer(state: &[u64; STATE_SIZE]) -> [u64; STATE_SIZE] {
     let mut res: [u64; STATE_SIZE] = [0; STATE_SIZE];
 
     for i in 0..NUM_SPLIT_AND_LOOKUP {
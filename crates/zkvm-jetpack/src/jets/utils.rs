@@ -12,7 +12,9 @@ pub fn jet_err<T>() -> Result<T, JetErr> {
 impl From<FieldError> for JetErr {
     fn from(e: FieldError) -> Self {
         match e {
-            FieldError::OrderedRootError => Fail(Error::Deterministic(Mote::Exit, D(0))),
+            FieldError::OrderedRootError | FieldError::NonCanonical | FieldError::ByteLength => {
+                Fail(Error::Deterministic(Mote::Exit, D(0)))
+            }
         }
     }
 }
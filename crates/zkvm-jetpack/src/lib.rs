@@ -1,3 +1,7 @@
+#![feature(stdarch_x86_avx512)]
+#![feature(avx512_target_feature)]
+
+pub mod field;
 pub mod form;
 pub mod hand;
 pub mod hot;